@@ -1,48 +1,110 @@
-use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
-use sudoku::{api, solver::Solver};
-use tokio::runtime::Runtime;
-use std::collections::HashMap;
-
-fn solve_benchmark(c: &mut Criterion) {
-    let rt = Runtime::new().unwrap();
-    
-    // Create a benchmark group
-    let mut group = c.benchmark_group("sudoku_solver");
-    group.sample_size(10); // Reduce sample size due to API rate limiting
-    
-    // Fetch some boards for benchmarking
-    let boards = rt.block_on(async {
-        api::prefetch_boards(10).await.unwrap();
-        api::fetch_multiple_boards(10).await.unwrap()
+//! Solver, validator and generator benchmarks.
+//!
+//! Every input comes from the built-in fixtures or a seeded generator, so the
+//! benchmarks never touch the network and runs are comparable across machines.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+use sudoku::{generator::BoardGenerator, providers::fixture, simd::SimdValidator, solver::Solver, Board, Grid};
+
+/// Seed for all generated inputs
+const SEED: u64 = 2024;
+
+/// Fixtures to benchmark, chosen to exercise different solving techniques
+const CORPUS: &[&str] = &["easy", "x_wing", "hard", "sparse", "seventeen_clues"];
+
+fn corpus_grids() -> Vec<(&'static str, Grid)> {
+    CORPUS
+        .iter()
+        .map(|&name| (name, fixture::get(name).unwrap().grid().unwrap()))
+        .collect()
+}
+
+fn generated_grids() -> Vec<Grid> {
+    let mut generator = BoardGenerator::with_seed(SEED);
+    ["Easy", "Medium", "Hard"]
+        .iter()
+        .map(|difficulty| generator.generate_with_difficulty(difficulty).unwrap())
+        .collect()
+}
+
+fn solver_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solver/corpus");
+    for (name, grid) in corpus_grids() {
+        group.bench_with_input(BenchmarkId::new("solve", name), &grid, |b, grid| {
+            b.iter(|| Solver::new(grid.clone()).solve().unwrap())
+        });
+        let puzzle = Board::new(&grid.value);
+        group.bench_with_input(BenchmarkId::new("solve_into", name), &puzzle, |b, puzzle| {
+            b.iter(|| {
+                let mut board = puzzle.clone();
+                Solver::solve_into(&mut board).unwrap();
+                board
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("solver/generated");
+    for grid in generated_grids() {
+        group.bench_with_input(BenchmarkId::new("solve", &grid.difficulty), &grid, |b, grid| {
+            b.iter(|| Solver::new(grid.clone()).solve().unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn validator_benchmarks(c: &mut Criterion) {
+    let solutions: Vec<Board> = corpus_grids()
+        .iter()
+        .filter_map(|(_, grid)| grid.solution_board())
+        .cycle()
+        .take(64)
+        .collect();
+
+    let mut group = c.benchmark_group("validator");
+    group.bench_function("validate_solution", |b| {
+        b.iter(|| SimdValidator::validate_solution(black_box(&solutions[0])))
+    });
+    group.bench_function("validate_many_64", |b| {
+        b.iter(|| SimdValidator::validate_many(black_box(&solutions)))
     });
+    group.finish();
+}
 
-    // Group boards by difficulty to avoid duplicate IDs
-    let mut difficulty_groups: HashMap<String, Vec<_>> = HashMap::new();
-    for board in boards {
-        difficulty_groups
-            .entry(board.difficulty.clone())
-            .or_default()
-            .push(board);
+fn generator_benchmarks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generator");
+    group.sample_size(10);
+    for difficulty in ["Easy", "Medium", "Hard"] {
+        group.bench_function(BenchmarkId::new("generate", difficulty), |b| {
+            // Reseed per iteration so every sample generates the same puzzle
+            b.iter(|| {
+                BoardGenerator::with_seed(SEED)
+                    .generate_with_difficulty(difficulty)
+                    .unwrap()
+            })
+        });
     }
+    group.finish();
 
-    // Benchmark each difficulty level with unique IDs
-    for (difficulty, boards) in difficulty_groups {
-        for (idx, board) in boards.iter().enumerate() {
-            let id = format!("{}_board_{}", difficulty, idx + 1);
-            group.bench_with_input(
-                BenchmarkId::new("solve", id),
-                board,
-                |b, board| {
-                    b.iter(|| {
-                        let mut solver = Solver::new(board.clone());
-                        solver.solve().unwrap()
+    // Generation time varies a lot between seeds, so also time a fixed batch
+    let mut group = c.benchmark_group("generator/batch");
+    group.sample_size(10);
+    for difficulty in ["Easy", "Medium", "Hard"] {
+        group.bench_function(BenchmarkId::new("generate_10", difficulty), |b| {
+            b.iter(|| {
+                (0..10)
+                    .map(|i| {
+                        BoardGenerator::with_seed(SEED + i)
+                            .generate_with_difficulty(difficulty)
+                            .unwrap()
                     })
-                },
-            );
-        }
+                    .collect::<Vec<_>>()
+            })
+        });
     }
     group.finish();
 }
 
-criterion_group!(benches, solve_benchmark);
-criterion_main!(benches); 
\ No newline at end of file
+criterion_group!(benches, solver_benchmarks, validator_benchmarks, generator_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,18 @@
+//! Any input either fails cleanly or solves to a valid grid that keeps its givens.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(solution) = sudoku::parse_and_solve(data) {
+        let puzzle = sudoku::validate_bytes(data).expect("solved input validates");
+        assert!(solution.to_line().bytes().all(|c| c != b'.'));
+        assert!(solution.is_consistent());
+        assert!(puzzle
+            .to_line()
+            .bytes()
+            .zip(solution.to_line().bytes())
+            .all(|(given, value)| given == b'.' || given == value));
+    }
+});
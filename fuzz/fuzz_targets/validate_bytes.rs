@@ -0,0 +1,13 @@
+//! Any input either fails cleanly or yields a consistent board that round-trips.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(board) = sudoku::validate_bytes(data) {
+        assert!(board.is_consistent());
+        let line = board.to_line();
+        assert_eq!(sudoku::validate_bytes(line.as_bytes()).expect("lines round-trip"), board);
+    }
+});
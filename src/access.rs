@@ -0,0 +1,187 @@
+//! API-key authentication and rate limiting for serve mode.
+//!
+//! With no keys configured the server is open and any rate limit applies per
+//! client IP. Once a key is added, every request must carry
+//! `Authorization: Bearer <key>`, and limits are tracked per key.
+
+use crate::{Result, SudokuError};
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+/// Callers whose buckets are kept. Past this the least recently seen one is
+/// dropped, so a flood of addresses can't grow the map without bound; a
+/// caller idle for a minute has a full bucket anyway, the same as a new one.
+const MAX_BUCKETS: usize = 10_000;
+
+/// Why a request was turned away
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Denied {
+    /// Missing or unknown API key
+    Unauthorized,
+    /// The caller is over its rate limit and may retry after the given delay
+    RateLimited { retry_after: Duration },
+}
+
+/// Token bucket holding up to one minute's worth of requests
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Who may call the server, and how often
+#[derive(Debug)]
+pub struct AccessPolicy {
+    /// API keys with their own limit in requests per minute, if any
+    keys: HashMap<String, Option<u32>>,
+    /// Requests per minute for keys without their own limit, or per IP when open
+    default_limit: Option<u32>,
+    /// Buckets of the `MAX_BUCKETS` callers seen most recently
+    buckets: Mutex<LruCache<String, Bucket>>,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        Self {
+            keys: HashMap::new(),
+            default_limit: None,
+            buckets: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_BUCKETS).unwrap())),
+        }
+    }
+}
+
+impl AccessPolicy {
+    /// Allows every request without limits
+    pub fn open() -> Self {
+        Self::default()
+    }
+
+    /// Limits each caller to `per_minute` requests unless its key sets its own limit
+    pub fn with_rate_limit(mut self, per_minute: u32) -> Self {
+        self.default_limit = Some(per_minute);
+        self
+    }
+
+    /// Accepts `key`, optionally with its own limit in requests per minute
+    pub fn with_key(mut self, key: impl Into<String>, per_minute: Option<u32>) -> Self {
+        self.keys.insert(key.into(), per_minute);
+        self
+    }
+
+    /// Parses a key given as `key` or `key:requests-per-minute`
+    pub fn parse_key(spec: &str) -> Result<(String, Option<u32>)> {
+        let invalid = || SudokuError::ParseError(format!("Invalid API key '{}', expected key[:per-minute]", spec));
+        let (key, limit) = match spec.split_once(':') {
+            Some((key, limit)) => (key, Some(limit.parse().map_err(|_| invalid())?)),
+            None => (spec, None),
+        };
+        if key.is_empty() {
+            return Err(invalid());
+        }
+        Ok((key.to_string(), limit))
+    }
+
+    /// Returns true if requests must carry an API key
+    pub fn requires_key(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Admits or rejects one request.
+    ///
+    /// `token` is the bearer token sent with the request and `client` identifies
+    /// the caller (its IP) when the server is open.
+    pub fn check(&self, token: Option<&str>, client: &str) -> std::result::Result<(), Denied> {
+        let (caller, limit) = if self.requires_key() {
+            let token = token.ok_or(Denied::Unauthorized)?;
+            let limit = self.keys.get(token).ok_or(Denied::Unauthorized)?;
+            (token, limit.or(self.default_limit))
+        } else {
+            (client, self.default_limit)
+        };
+        match limit {
+            Some(per_minute) => self.take_token(caller, per_minute),
+            None => Ok(()),
+        }
+    }
+
+    fn take_token(&self, caller: &str, per_minute: u32) -> std::result::Result<(), Denied> {
+        let capacity = f64::from(per_minute);
+        let refill_per_sec = capacity / 60.0;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock();
+        let bucket = buckets.get_or_insert_mut(caller.to_string(), || Bucket { tokens: capacity, updated: now });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let retry_after = if refill_per_sec > 0.0 {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec)
+        } else {
+            Duration::MAX
+        };
+        Err(Denied::RateLimited { retry_after })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_keys() {
+        let policy = AccessPolicy::open().with_key("secret", None);
+        assert_eq!(policy.check(None, "1.2.3.4"), Err(Denied::Unauthorized));
+        assert_eq!(policy.check(Some("wrong"), "1.2.3.4"), Err(Denied::Unauthorized));
+        assert_eq!(policy.check(Some("secret"), "1.2.3.4"), Ok(()));
+        assert_eq!(AccessPolicy::open().check(None, "1.2.3.4"), Ok(()));
+    }
+
+    #[test]
+    fn test_rate_limits() {
+        let policy = AccessPolicy::open()
+            .with_rate_limit(2)
+            .with_key("a", None)
+            .with_key("b", Some(1));
+        assert!(policy.check(Some("a"), "").is_ok());
+        assert!(policy.check(Some("a"), "").is_ok());
+        assert!(matches!(policy.check(Some("a"), ""), Err(Denied::RateLimited { .. })));
+
+        // Each key has its own bucket and limit
+        assert!(policy.check(Some("b"), "").is_ok());
+        match policy.check(Some("b"), "") {
+            Err(Denied::RateLimited { retry_after }) => assert!(retry_after > Duration::from_secs(50)),
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+
+        // Open servers limit per client
+        let open = AccessPolicy::open().with_rate_limit(1);
+        assert!(open.check(None, "1.1.1.1").is_ok());
+        assert!(open.check(None, "2.2.2.2").is_ok());
+        assert!(open.check(None, "1.1.1.1").is_err());
+
+        // A flood of callers only keeps the most recent ones, and a caller
+        // pushed out starts again with a full bucket
+        for i in 0..MAX_BUCKETS {
+            assert!(open.check(None, &format!("10.0.{}.{}", i / 256, i % 256)).is_ok());
+        }
+        assert_eq!(open.buckets.lock().len(), MAX_BUCKETS);
+        assert!(open.check(None, "1.1.1.1").is_ok());
+        assert!(open.check(None, "10.0.39.15").is_err());
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(AccessPolicy::parse_key("abc").unwrap(), ("abc".to_string(), None));
+        assert_eq!(AccessPolicy::parse_key("abc:60").unwrap(), ("abc".to_string(), Some(60)));
+        assert!(AccessPolicy::parse_key("abc:lots").is_err());
+        assert!(AccessPolicy::parse_key(":60").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Bulk solving and rating of puzzle files.
+//!
+//! Puzzles are read one per line (81 cells, as accepted by `Board::parse`,
+//! so `.sdm` files work as-is), solved in parallel a chunk at a time, and
+//! written out in input order, so memory stays bounded however large the file
+//! and `solve_lines` works as a filter in a shell pipeline.
+//! Blank lines and lines starting with `#` are skipped.
+//!
+//! Each puzzle produces exactly one output line: its solution (or rating), or
+//! the input followed by ` # ` and the reason it couldn't be handled.
+
+use crate::{logic::Technique, rating::{self, Rating}, solver::Solver, Board, Result, SudokuError};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+/// How a batch is run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Worker threads
+    pub jobs: usize,
+    /// Puzzles read and solved at a time; bounds memory use
+    pub chunk_size: usize,
+    /// Also measure backdoor and search-tree size when rating; much slower
+    pub hardness: bool,
+    /// Propagate singles on the GPU before solving (see `gpu::solve_all`).
+    /// Only builds with the `gpu` feature have one; others ignore this.
+    pub gpu: bool,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            jobs: num_cpus::get(),
+            chunk_size: 4096,
+            hardness: false,
+            gpu: false,
+        }
+    }
+}
+
+/// Totals for a batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BatchSummary {
+    pub puzzles: usize,
+    pub solved: usize,
+    /// Well-formed puzzles with no solution, including conflicting givens
+    pub unsolvable: usize,
+    /// Lines that aren't puzzles
+    pub invalid: usize,
+    pub elapsed: Duration,
+}
+
+impl BatchSummary {
+    pub fn puzzles_per_sec(&self) -> f64 {
+        self.puzzles as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn print(&self) {
+        println!("\nSolved {}/{} puzzles in {:.2?} ({:.0}/s)", self.solved, self.puzzles, self.elapsed, self.puzzles_per_sec());
+        if self.unsolvable > 0 {
+            println!("  Unsolvable: {}", self.unsolvable);
+        }
+        if self.invalid > 0 {
+            println!("  Invalid:    {}", self.invalid);
+        }
+    }
+}
+
+/// Solves every puzzle in `input`, writing one line per puzzle to `output`.
+///
+/// Input is read on its own thread. Puzzles are solved as soon as they
+/// arrive, batched with whatever else is already waiting, so the output keeps
+/// up with a slow producer on a pipe as well as with a large file. Stops
+/// quietly if `output` is a pipe that was closed.
+pub fn solve_lines(input: impl BufRead + Send, output: impl Write, options: &BatchOptions) -> Result<BatchSummary> {
+    let mut summary = BatchSummary::default();
+    let solve = |lines: &[String]| -> Vec<Result<Board>> {
+        #[cfg(feature = "gpu")]
+        if options.gpu {
+            return solve_on_gpu(lines);
+        }
+        lines.par_iter().map(|line| solve_line(line)).collect()
+    };
+    summary.elapsed = for_each_line(input, output, options, solve, |output, line, result| {
+        summary.puzzles += 1;
+        match result {
+            Ok(solution) => {
+                summary.solved += 1;
+                writeln!(output, "{}", solution.to_line())
+            }
+            Err(e) => {
+                if e.is_invalid_input() {
+                    summary.invalid += 1;
+                } else {
+                    summary.unsolvable += 1;
+                }
+                writeln!(output, "{} # {}", line, e)
+            }
+        }
+    })?;
+    Ok(summary)
+}
+
+/// Rates every puzzle in `input` with `rating::rate`, writing one line per
+/// puzzle to `output`: the puzzle, its difficulty, hardest technique and step
+/// count (then backdoor and search-tree size with `options.hardness`), or
+/// with `json` a JSON object per line. Read and stopped like `solve_lines`.
+pub fn rate_lines(input: impl BufRead + Send, output: impl Write, options: &BatchOptions, json: bool) -> Result<RatingSummary> {
+    let mut summary = RatingSummary::default();
+    let rate = |lines: &[String]| lines.par_iter().map(|line| rate_line(line, options.hardness)).collect();
+    summary.elapsed = for_each_line(input, output, options, rate, |output, line, result| {
+        summary.puzzles += 1;
+        match result {
+            Ok(rating) => {
+                summary.add(&rating);
+                if json {
+                    let rated = RatedPuzzle { puzzle: line, difficulty: rating.difficulty(), rating: &rating };
+                    writeln!(output, "{}", serde_json::to_string(&rated).map_err(std::io::Error::other)?)
+                } else {
+                    let hardest = rating.hardest.map_or("-", |technique| technique.name());
+                    write!(output, "{} {:<6} {:<17} {}", line, rating.difficulty(), hardest, rating.steps)?;
+                    match rating.hardness {
+                        Some(hardness) => {
+                            let backdoor = match hardness.backdoor {
+                                Some(size) => size.to_string(),
+                                None => format!(">{}", rating::MAX_BACKDOOR),
+                            };
+                            writeln!(output, " {} {}", backdoor, hardness.search_nodes)
+                        }
+                        None => writeln!(output),
+                    }
+                }
+            }
+            Err(e) => {
+                summary.invalid += 1;
+                if json {
+                    writeln!(output, "{}", serde_json::json!({ "puzzle": line, "error": e.to_string() }))
+                } else {
+                    writeln!(output, "{} # {}", line, e)
+                }
+            }
+        }
+    })?;
+    Ok(summary)
+}
+
+/// One line of `rate_lines` JSON output
+#[derive(Serialize)]
+struct RatedPuzzle<'a> {
+    puzzle: &'a str,
+    difficulty: &'static str,
+    #[serde(flatten)]
+    rating: &'a Rating,
+}
+
+/// Distribution of ratings over a batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RatingSummary {
+    pub puzzles: usize,
+    /// Lines that aren't puzzles, or whose givens conflict
+    pub invalid: usize,
+    /// Puzzles per difficulty label
+    pub difficulties: BTreeMap<&'static str, usize>,
+    /// Puzzles per hardest technique needed; logic-free puzzles aren't counted
+    pub hardest: BTreeMap<Technique, usize>,
+    /// Puzzles logic alone couldn't finish
+    pub unsolved_by_logic: usize,
+    pub elapsed: Duration,
+}
+
+impl RatingSummary {
+    fn add(&mut self, rating: &Rating) {
+        *self.difficulties.entry(rating.difficulty()).or_insert(0) += 1;
+        if let Some(technique) = rating.hardest {
+            *self.hardest.entry(technique).or_insert(0) += 1;
+        }
+        if !rating.solved {
+            self.unsolved_by_logic += 1;
+        }
+    }
+
+    /// Number of puzzles that were rated
+    pub fn rated(&self) -> usize {
+        self.puzzles - self.invalid
+    }
+
+    pub fn print(&self) {
+        let rated = self.rated();
+        let percent = |count: usize| 100.0 * count as f64 / rated.max(1) as f64;
+        println!("\nRated {} puzzles in {:.2?}", rated, self.elapsed);
+        println!("  By difficulty:");
+        for label in ["Easy", "Medium", "Hard", "Expert"] {
+            let count = self.difficulties.get(label).copied().unwrap_or(0);
+            println!("    {:<18} {:>7} ({:5.1}%)", label, count, percent(count));
+        }
+        println!("  By hardest technique:");
+        for (technique, &count) in &self.hardest {
+            println!("    {:<18} {:>7} ({:5.1}%)", technique.name(), count, percent(count));
+        }
+        println!("  Unsolved by logic:   {:>7} ({:5.1}%)", self.unsolved_by_logic, percent(self.unsolved_by_logic));
+        if self.invalid > 0 {
+            println!("  Invalid:             {:>7}", self.invalid);
+        }
+    }
+}
+
+/// Runs `work` over the puzzle lines of `input` a chunk at a time on the
+/// batch's thread pool, passing each line and its result to `emit` in input
+/// order and flushing `output` after every chunk. Returns the time taken.
+fn for_each_line<O, T, W, E>(input: impl BufRead + Send, mut output: O, options: &BatchOptions, work: W, mut emit: E) -> Result<Duration>
+where
+    O: Write,
+    T: Send,
+    W: Fn(&[String]) -> Vec<T> + Sync,
+    E: FnMut(&mut O, &str, T) -> std::io::Result<()>,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs.max(1))
+        .build()?;
+    let chunk_size = options.chunk_size.max(1);
+
+    let start = Instant::now();
+    std::thread::scope(|scope| -> Result<()> {
+        // Bounded, so a fast reader can't run ahead of the workers
+        let (sender, receiver) = crossbeam::channel::bounded::<std::io::Result<String>>(chunk_size);
+        scope.spawn(move || {
+            for line in input.lines() {
+                let line = line.map(|line| line.trim().to_string());
+                if matches!(&line, Ok(puzzle) if puzzle.is_empty() || puzzle.starts_with('#')) {
+                    continue;
+                }
+                let failed = line.is_err();
+                // The receiver is gone if writing failed; stop reading then too
+                if sender.send(line).is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        let mut chunk = Vec::with_capacity(chunk_size);
+        while let Ok(first) = receiver.recv() {
+            chunk.clear();
+            chunk.push(first?);
+            while chunk.len() < chunk_size {
+                match receiver.try_recv() {
+                    Ok(line) => chunk.push(line?),
+                    Err(_) => break,
+                }
+            }
+
+            let results = pool.install(|| work(&chunk));
+            let written = chunk.iter().zip(results).try_for_each(|(line, result)| emit(&mut output, line, result));
+            match written.and_then(|()| output.flush()) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    })?;
+    Ok(start.elapsed())
+}
+
+fn solve_line(line: &str) -> Result<Board> {
+    let mut board = Board::parse(line)?;
+    Solver::solve_into(&mut board)?;
+    Ok(board)
+}
+
+/// Parses `lines` and solves the puzzles with `gpu::solve_all`
+#[cfg(feature = "gpu")]
+fn solve_on_gpu(lines: &[String]) -> Vec<Result<Board>> {
+    let parsed: Vec<Result<Board>> = lines.par_iter().map(|line| Board::parse(line)).collect();
+    let boards: Vec<Board> = parsed.iter().filter_map(|board| board.as_ref().ok()).cloned().collect();
+    let mut solved = crate::gpu::solve_all(&boards).into_iter();
+    parsed
+        .into_iter()
+        .map(|board| board.and_then(|_| solved.next().unwrap_or(Err(SudokuError::InvalidBoard))))
+        .collect()
+}
+
+fn rate_line(line: &str, hardness: bool) -> Result<Rating> {
+    let board = Board::parse(line)?;
+    if !board.is_consistent() {
+        return Err(SudokuError::InvalidBoard);
+    }
+    Ok(if hardness { rating::rate_with_hardness(&board) } else { rating::rate(&board) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture::{self, FixtureKind};
+
+    #[test]
+    fn test_solve_lines() {
+        let puzzles: Vec<&str> = fixture::of_kind(FixtureKind::Unique).map(|f| f.puzzle).collect();
+        let input = format!(
+            "# comment\n{}\n\n{}\nnot a puzzle\n{}\n",
+            puzzles.join("\n"),
+            fixture::get("no_candidates").unwrap().puzzle,
+            fixture::get("duplicate_in_row").unwrap().puzzle,
+        );
+        // Without the `gpu` feature, or without an adapter, both runs are on the CPU
+        for gpu in [false, true] {
+            let options = BatchOptions { jobs: 2, chunk_size: 2, hardness: false, gpu };
+            let mut output = Vec::new();
+            let summary = solve_lines(input.as_bytes(), &mut output, &options).unwrap();
+
+            assert_eq!((summary.puzzles, summary.solved, summary.unsolvable, summary.invalid), (puzzles.len() + 3, puzzles.len(), 2, 1));
+            let output = String::from_utf8(output).unwrap();
+            let lines: Vec<&str> = output.lines().collect();
+            assert_eq!(lines.len(), summary.puzzles);
+            for (puzzle, line) in puzzles.iter().zip(&lines) {
+                let solved = Board::parse(line).unwrap();
+                assert!(crate::simd::SimdValidator::validate_solution(&solved));
+                assert!(Board::parse(puzzle).unwrap().cells.iter().zip(&solved.cells).all(|(&g, &v)| g == 0 || g == v));
+            }
+            assert!(lines[puzzles.len() + 1].starts_with("not a puzzle # "));
+        }
+    }
+
+    #[test]
+    fn test_rate_lines() {
+        let input = format!(
+            "{}\n{}\nnot a puzzle\n{}\n",
+            fixture::get("easy").unwrap().puzzle,
+            fixture::get("duplicate_in_row").unwrap().puzzle,
+            "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5",
+        );
+        let options = BatchOptions { jobs: 2, chunk_size: 2, hardness: false, gpu: false };
+        let mut output = Vec::new();
+        let summary = rate_lines(input.as_bytes(), &mut output, &options, false).unwrap();
+
+        assert_eq!((summary.puzzles, summary.rated(), summary.invalid), (4, 2, 2));
+        assert_eq!(summary.difficulties.get("Hard"), Some(&1));
+        assert_eq!(summary.hardest.get(&Technique::XWing), Some(&1));
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[3].contains(" Hard   X-Wing "));
+
+        let mut output = Vec::new();
+        rate_lines(input.as_bytes(), &mut output, &options, true).unwrap();
+        let first: serde_json::Value = serde_json::from_slice(output.split(|&b| b == b'\n').next().unwrap()).unwrap();
+        assert_eq!(first["difficulty"], "Easy");
+        assert_eq!(first["solved"], true);
+        assert!(first.get("hardness").is_none());
+
+        let options = BatchOptions { hardness: true, ..options };
+        let mut output = Vec::new();
+        rate_lines(input.as_bytes(), &mut output, &options, false).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let easy = output.lines().next().unwrap();
+        assert_eq!(easy.split_whitespace().rev().nth(1), Some("0"), "{}", easy);
+    }
+}
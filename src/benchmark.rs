@@ -1,9 +1,58 @@
-use crate::{api, solver::Solver, Result, SudokuError};
+use crate::{api::{self, ApiClient, BoardProvider, FetchSource, FetchTrace}, generator::BoardGenerator, logic::Technique, rating, simd::SimdValidator, solver::{Parallelism, Solver}, Board, Grid, PuzzleSource, Result, SudokuError};
+use futures_util::future::join_all;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+mod compare;
+mod report;
+mod usage;
+
+pub use compare::{compare_runs, MetricDelta, RunComparison};
+pub use report::render_html;
+pub use usage::{Allocations, CpuTime};
+#[cfg(feature = "alloc-stats")]
+pub use usage::CountingAllocator;
+
+/// Number of slowest boards listed by `print_results`
+const SLOWEST_SHOWN: usize = 5;
+
+/// Directory where named baselines are stored
+const BASELINE_DIR: &str = "target/benchmark-baselines";
+
+/// Default relative change that counts as a regression (10%)
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 0.10;
+
+/// Outcome of solving a single benchmark board
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardResult {
+    /// The puzzle as an 81-character line
+    pub puzzle: String,
+    pub difficulty: String,
+    pub solve_time: Duration,
+    /// Search nodes visited by the solver
+    pub nodes: u64,
+    pub solved: bool,
+    pub unique: bool,
+    /// True if the solver hit the per-board timeout (did not finish)
+    #[serde(default)]
+    pub dnf: bool,
+    /// CPU time the process used during the solve, on every thread
+    #[serde(default)]
+    pub cpu_time: CpuTime,
+    /// Allocations the process made during the solve, on every thread, with
+    /// the `alloc-stats` feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<Allocations>,
+}
 
 /// Results from a benchmark run
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BenchmarkResults {
     pub total_duration: Duration,
     pub average_duration: Duration,
@@ -14,18 +63,253 @@ pub struct BenchmarkResults {
     pub unique_solutions: usize,
     pub multiple_solutions: usize,
     pub difficulty_stats: DifficultyStats,
+    /// Per-board results, in the order the boards were solved
+    pub boards: Vec<BoardResult>,
+    /// Time limit applied to each board, if any
+    #[serde(default)]
+    pub per_board_timeout: Option<Duration>,
+    /// Boards that hit the per-board timeout
+    #[serde(default)]
+    pub dnf_boards: usize,
+    /// Times each technique fired when the logic solver worked through the
+    /// boards, by difficulty ("easy", "medium", "hard" or "unknown")
+    #[serde(default)]
+    pub techniques: BTreeMap<String, BTreeMap<Technique, usize>>,
+}
+
+/// Snapshot of a benchmark run in progress, passed to the progress callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkProgress {
+    /// Boards finished so far, solved or not
+    pub completed: usize,
+    pub total: usize,
+    /// Boards that could not be solved, including DNFs
+    pub failures: usize,
+    /// Average solve time of the boards solved so far
+    pub average: Duration,
+    /// Wall-clock time since solving started
+    pub elapsed: Duration,
+}
+
+impl BenchmarkProgress {
+    /// Estimated time left, extrapolated from the pace so far
+    pub fn eta(&self) -> Option<Duration> {
+        if self.completed == 0 {
+            return None;
+        }
+        let remaining = self.total.saturating_sub(self.completed) as u32;
+        Some(self.elapsed / self.completed as u32 * remaining)
+    }
+
+    /// Renders a one-line progress bar `width` characters wide
+    pub fn bar(&self, width: usize) -> String {
+        let filled = (self.completed * width).checked_div(self.total).unwrap_or(width);
+        let eta = self.eta().map_or_else(|| "?".to_string(), |eta| format!("{:.1}s", eta.as_secs_f64()));
+        format!(
+            "[{}{}] {}/{} avg {:?} failed {} eta {}",
+            "#".repeat(filled),
+            "-".repeat(width - filled),
+            self.completed,
+            self.total,
+            self.average,
+            self.failures,
+            eta
+        )
+    }
+}
+
+/// How one metric moved between a baseline and the current run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricChange {
+    pub name: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+    /// Relative change, e.g. 0.25 for 25% higher than the baseline
+    pub change: f64,
+    /// True if the metric got worse by more than the threshold
+    pub regression: bool,
+}
+
+/// Comparison of a benchmark run against a stored baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub threshold: f64,
+    pub metrics: Vec<MetricChange>,
+}
+
+impl Comparison {
+    pub fn has_regressions(&self) -> bool {
+        self.metrics.iter().any(|m| m.regression)
+    }
+
+    /// Pretty prints the comparison as a table
+    pub fn print(&self) {
+        println!("\n=== Baseline Comparison (threshold {:.0}%) ===", self.threshold * 100.0);
+        println!("{:<22} {:>12} {:>12} {:>9}", "Metric", "Baseline", "Current", "Change");
+        for m in &self.metrics {
+            println!("{:<22} {:>12.3} {:>12.3} {:>+8.1}%{}",
+                m.name,
+                m.baseline,
+                m.current,
+                m.change * 100.0,
+                if m.regression { "  REGRESSION" } else { "" }
+            );
+        }
+    }
+}
+
+/// Solve-time distribution for a group of boards
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimingStats {
+    /// Number of solved boards the timings cover
+    pub count: usize,
+    pub min: Duration,
+    pub average: Duration,
+    pub max: Duration,
+    /// 95th percentile (nearest rank)
+    pub p95: Duration,
+}
+
+impl TimingStats {
+    /// Summarizes a set of solve times; all fields are zero when `times` is empty
+    pub fn from_durations(times: &[Duration]) -> Self {
+        if times.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = times.to_vec();
+        sorted.sort_unstable();
+        let rank = (sorted.len() * 95).div_ceil(100).max(1);
+        Self {
+            count: sorted.len(),
+            min: sorted[0],
+            average: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            max: sorted[sorted.len() - 1],
+            p95: sorted[rank - 1],
+        }
+    }
 }
 
 /// Statistics about puzzle difficulties
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct DifficultyStats {
     pub easy: usize,
     pub medium: usize,
     pub hard: usize,
     pub unknown: usize,
+    /// Solve times of the solved boards in each difficulty
+    #[serde(default)]
+    pub easy_times: TimingStats,
+    #[serde(default)]
+    pub medium_times: TimingStats,
+    #[serde(default)]
+    pub hard_times: TimingStats,
+    #[serde(default)]
+    pub unknown_times: TimingStats,
+}
+
+impl DifficultyStats {
+    /// Fills in the per-difficulty timings from per-board results
+    fn record_timings(&mut self, boards: &[BoardResult]) {
+        let times = |label: Option<&str>| -> Vec<Duration> {
+            boards
+                .iter()
+                .filter(|b| b.solved && difficulty_label(&b.difficulty) == label)
+                .map(|b| b.solve_time)
+                .collect()
+        };
+        self.easy_times = TimingStats::from_durations(&times(Some("easy")));
+        self.medium_times = TimingStats::from_durations(&times(Some("medium")));
+        self.hard_times = TimingStats::from_durations(&times(Some("hard")));
+        self.unknown_times = TimingStats::from_durations(&times(None));
+    }
+
+    /// Prints solve times per difficulty as a table
+    pub fn print_timings(&self) {
+        println!("{:<10} {:>6} {:>12} {:>12} {:>12} {:>12}", "Difficulty", "Solved", "Min", "Avg", "Max", "P95");
+        let rows = [
+            ("Easy", &self.easy_times),
+            ("Medium", &self.medium_times),
+            ("Hard", &self.hard_times),
+            ("Unknown", &self.unknown_times),
+        ];
+        for (name, t) in rows {
+            if t.count == 0 {
+                continue;
+            }
+            println!("{:<10} {:>6} {:>12?} {:>12?} {:>12?} {:>12?}", name, t.count, t.min, t.average, t.max, t.p95);
+        }
+    }
+}
+
+/// Tallies the techniques the logic solver applies to each board, by
+/// difficulty label. Boards whose givens clash are left out.
+fn technique_histogram(boards: &[Grid]) -> BTreeMap<String, BTreeMap<Technique, usize>> {
+    let ratings: Vec<(&str, rating::Rating)> = boards
+        .par_iter()
+        .filter_map(|grid| {
+            let board = Board::new(&grid.value);
+            let label = difficulty_label(&grid.difficulty).unwrap_or("unknown");
+            board.is_consistent().then(|| (label, rating::rate(&board)))
+        })
+        .collect();
+    let mut histogram: BTreeMap<String, BTreeMap<Technique, usize>> = BTreeMap::new();
+    for (label, rating) in ratings {
+        let counts = histogram.entry(label.to_string()).or_default();
+        for (technique, count) in rating.technique_counts {
+            *counts.entry(technique).or_insert(0) += count;
+        }
+    }
+    histogram
+}
+
+/// Maps a difficulty string to "easy", "medium", or "hard", or None if unrecognized
+fn difficulty_label(difficulty: &str) -> Option<&'static str> {
+    match difficulty.to_lowercase().as_str() {
+        "easy" => Some("easy"),
+        "medium" => Some("medium"),
+        "hard" => Some("hard"),
+        _ => None,
+    }
 }
 
 impl BenchmarkResults {
+    /// Aggregates per-board results into a run's totals. Timings and the
+    /// per-difficulty stats cover the solved boards, and the average spreads
+    /// their time over every board. `techniques` is left empty.
+    pub fn from_boards(boards: Vec<BoardResult>, total_duration: Duration, per_board_timeout: Option<Duration>) -> Self {
+        let solved = || boards.iter().filter(|b| b.solved);
+        let solve_time: Duration = solved().map(|b| b.solve_time).sum();
+        let solved_boards = solved().count();
+        let unique_solutions = solved().filter(|b| b.unique).count();
+
+        let mut difficulty_stats = DifficultyStats::default();
+        for board in &boards {
+            match difficulty_label(&board.difficulty) {
+                Some("easy") => difficulty_stats.easy += 1,
+                Some("medium") => difficulty_stats.medium += 1,
+                Some("hard") => difficulty_stats.hard += 1,
+                _ => difficulty_stats.unknown += 1,
+            }
+        }
+        difficulty_stats.record_timings(&boards);
+
+        Self {
+            total_duration,
+            average_duration: solve_time.checked_div(boards.len() as u32).unwrap_or_default(),
+            min_duration: solved().map(|b| b.solve_time).min().unwrap_or(Duration::from_secs(u64::MAX)),
+            max_duration: solved().map(|b| b.solve_time).max().unwrap_or_default(),
+            total_boards: boards.len(),
+            solved_boards,
+            unique_solutions,
+            multiple_solutions: solved_boards - unique_solutions,
+            difficulty_stats,
+            per_board_timeout,
+            dnf_boards: boards.iter().filter(|b| b.dnf).count(),
+            boards,
+            techniques: BTreeMap::new(),
+        }
+    }
+
     /// Returns the success rate as a percentage (including both unique and multiple solutions)
     pub fn success_rate(&self) -> f64 {
         (self.solved_boards as f64 / self.total_boards as f64) * 100.0
@@ -36,6 +320,124 @@ impl BenchmarkResults {
         (self.unique_solutions as f64 / self.solved_boards as f64) * 100.0
     }
 
+    /// Returns the `n` boards that took longest to solve, slowest first
+    pub fn slowest(&self, n: usize) -> Vec<&BoardResult> {
+        let mut boards: Vec<&BoardResult> = self.boards.iter().collect();
+        boards.sort_by_key(|b| std::cmp::Reverse(b.solve_time));
+        boards.truncate(n);
+        boards
+    }
+
+    /// Boards that did not finish within the per-board timeout
+    pub fn dnf(&self) -> impl Iterator<Item = &BoardResult> {
+        self.boards.iter().filter(|b| b.dnf)
+    }
+
+    /// Serializes the results, including per-board results, as JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Writes the results as JSON to `path`
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Reads results written by `save_json`
+    pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the results as a self-contained HTML page with charts to
+    /// `path`, comparing them with the named baseline when given
+    pub fn save_report<P: AsRef<Path>>(&self, path: P, comparison: Option<(&str, &Comparison)>) -> Result<()> {
+        fs::write(path, render_html(self, comparison))?;
+        Ok(())
+    }
+
+    /// CPU time used by all the solves together
+    pub fn cpu_time(&self) -> CpuTime {
+        self.boards.iter().fold(CpuTime::default(), |sum, b| CpuTime {
+            user: sum.user + b.cpu_time.user,
+            system: sum.system + b.cpu_time.system,
+        })
+    }
+
+    /// CPU time over wall time across the solves: about 1 for a solver on one
+    /// core, more when the work spreads over several
+    pub fn cores_used(&self) -> f64 {
+        let wall: Duration = self.boards.iter().map(|b| b.solve_time).sum();
+        if wall.is_zero() {
+            return 0.0;
+        }
+        self.cpu_time().total().as_secs_f64() / wall.as_secs_f64()
+    }
+
+    /// Allocations made by all the solves together; None without the
+    /// `alloc-stats` feature
+    pub fn allocations(&self) -> Option<Allocations> {
+        self.boards.iter().filter_map(|b| b.allocations).reduce(|sum, a| Allocations {
+            count: sum.count + a.count,
+            bytes: sum.bytes + a.bytes,
+        })
+    }
+
+    /// Average search nodes per solved board
+    pub fn average_nodes(&self) -> f64 {
+        let solved: Vec<&BoardResult> = self.boards.iter().filter(|b| b.solved).collect();
+        if solved.is_empty() {
+            return 0.0;
+        }
+        solved.iter().map(|b| b.nodes as f64).sum::<f64>() / solved.len() as f64
+    }
+
+    /// Stores the results as the baseline `name`, returning the file written
+    pub fn save_baseline(&self, name: &str) -> Result<PathBuf> {
+        let path = baseline_path(name);
+        fs::create_dir_all(BASELINE_DIR)?;
+        self.save_json(&path)?;
+        Ok(path)
+    }
+
+    /// Loads the baseline previously saved as `name`
+    pub fn load_baseline(name: &str) -> Result<Self> {
+        Self::load_json(baseline_path(name))
+    }
+
+    /// Compares these results against `baseline`.
+    ///
+    /// A metric regresses when it moves in the bad direction by more than
+    /// `threshold`, given as a fraction (0.1 = 10%).
+    pub fn compare(&self, baseline: &BenchmarkResults, threshold: f64) -> Comparison {
+        let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+        // (name, baseline, current, higher is better)
+        let metrics = [
+            ("average_duration_ms", millis(baseline.average_duration), millis(self.average_duration), false),
+            ("max_duration_ms", millis(baseline.max_duration), millis(self.max_duration), false),
+            ("min_duration_ms", millis(baseline.min_duration), millis(self.min_duration), false),
+            ("average_nodes", baseline.average_nodes(), self.average_nodes(), false),
+            ("success_rate_pct", baseline.success_rate(), self.success_rate(), true),
+        ];
+
+        let metrics = metrics
+            .into_iter()
+            .map(|(name, before, after, higher_is_better)| {
+                let change = if before == 0.0 { 0.0 } else { (after - before) / before };
+                let worse = if higher_is_better { -change } else { change };
+                MetricChange {
+                    name,
+                    baseline: before,
+                    current: after,
+                    change,
+                    regression: worse > threshold,
+                }
+            })
+            .collect();
+
+        Comparison { threshold, metrics }
+    }
+
     /// Pretty prints the benchmark results
     pub fn print_results(&self) {
         println!("\n=== Benchmark Results ===");
@@ -43,6 +445,19 @@ impl BenchmarkResults {
         println!("Average Duration: {:?}", self.average_duration);
         println!("Min Duration: {:?}", self.min_duration);
         println!("Max Duration: {:?}", self.max_duration);
+        let cpu = self.cpu_time();
+        println!("CPU Time: {:?} user, {:?} system ({:.2} cores busy while solving)",
+            cpu.user,
+            cpu.system,
+            self.cores_used()
+        );
+        if let Some(allocations) = self.allocations() {
+            println!("Allocations: {} ({} bytes), {:.0} per board",
+                allocations.count,
+                allocations.bytes,
+                allocations.count as f64 / self.total_boards as f64
+            );
+        }
         println!("Total Boards: {}", self.total_boards);
         println!("Successfully Solved: {} ({:.1}%)", self.solved_boards, self.success_rate());
         println!("Unique Solutions: {} ({:.1}%)", self.unique_solutions, self.unique_solution_rate());
@@ -70,11 +485,460 @@ impl BenchmarkResults {
                 (self.difficulty_stats.unknown as f64 / self.total_boards as f64) * 100.0
             );
         }
+
+        if let Some(timeout) = self.per_board_timeout {
+            println!("Did Not Finish: {} (limit {:?} per board)", self.dnf_boards, timeout);
+        }
+
+        println!("\nSolve Times by Difficulty:");
+        self.difficulty_stats.print_timings();
+
+        if !self.techniques.is_empty() {
+            println!("\nTechniques by Difficulty:");
+            self.print_techniques();
+        }
+
+        let slowest = self.slowest(SLOWEST_SHOWN);
+        if !slowest.is_empty() {
+            println!("\nSlowest Boards:");
+            for board in slowest {
+                println!("  {:>12?}  {:>12?} cpu  {:>8} nodes  {:<6}  {}{}",
+                    board.solve_time,
+                    board.cpu_time.total(),
+                    board.nodes,
+                    board.difficulty,
+                    board.puzzle,
+                    if board.dnf { "  (DNF)" } else if board.solved { "" } else { "  (unsolved)" }
+                );
+            }
+        }
+
+        if self.dnf_boards > 0 {
+            println!("\nDid Not Finish:");
+            for board in self.dnf() {
+                println!("  {:<6}  {}", board.difficulty, board.puzzle);
+            }
+        }
+    }
+
+    /// Prints how often each technique fired, one column per difficulty
+    pub fn print_techniques(&self) {
+        let labels: Vec<&str> = ["easy", "medium", "hard", "unknown"]
+            .into_iter()
+            .filter(|label| self.techniques.contains_key(*label))
+            .collect();
+        print!("{:<18}", "Technique");
+        for label in &labels {
+            print!(" {:>8}", label);
+        }
+        println!();
+        for technique in Technique::ALL {
+            let counts: Vec<usize> = labels
+                .iter()
+                .map(|label| self.techniques[*label].get(&technique).copied().unwrap_or(0))
+                .collect();
+            if counts.iter().all(|&count| count == 0) {
+                continue;
+            }
+            print!("{:<18}", technique.name());
+            for count in counts {
+                print!(" {:>8}", count);
+            }
+            println!();
+        }
+    }
+}
+
+fn baseline_path(name: &str) -> PathBuf {
+    Path::new(BASELINE_DIR).join(format!("{}.json", name))
+}
+
+/// Throughput of one thread-pool size in a scaling run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScalingResult {
+    pub threads: usize,
+    pub duration: Duration,
+    pub boards_per_sec: f64,
+    /// Throughput relative to the first (single-thread) configuration
+    pub speedup: f64,
+}
+
+/// Prints scaling results as a table
+pub fn print_scaling(results: &[ScalingResult]) {
+    println!("\n=== Thread Scaling ===");
+    println!("{:>7} {:>14} {:>12} {:>9}", "Threads", "Duration", "Boards/sec", "Speedup");
+    for r in results {
+        println!("{:>7} {:>14?} {:>12.1} {:>8.2}x", r.threads, r.duration, r.boards_per_sec, r.speedup);
+    }
+}
+
+/// Difficulty labels in the order they appear in a calibration matrix
+pub const CALIBRATION_LABELS: [&str; 4] = ["Easy", "Medium", "Hard", "Expert"];
+
+/// Cross-tabulation of API difficulty labels against the internal rating
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// `matrix[api][rated]` counts boards by API label (row) and internal
+    /// rating (column), both indexed by `CALIBRATION_LABELS`
+    pub matrix: [[usize; 4]; 4],
+    /// Boards whose API label isn't one of `CALIBRATION_LABELS`
+    pub unlabelled: usize,
+    /// Boards left out by `from_api_boards` because they didn't come from
+    /// the API
+    #[serde(default)]
+    pub not_from_api: usize,
+}
+
+impl Calibration {
+    /// Rates every grid and tallies it against its API label
+    pub fn from_grids(grids: &[Grid]) -> Self {
+        let mut calibration = Self::default();
+        for grid in grids {
+            let Some(api) = calibration_index(&grid.difficulty) else {
+                calibration.unlabelled += 1;
+                continue;
+            };
+            let rated = rating::rate(&Board::new(&grid.value)).difficulty();
+            // `difficulty` only returns calibration labels
+            if let Some(rated) = calibration_index(rated) {
+                calibration.matrix[api][rated] += 1;
+            }
+        }
+        calibration
+    }
+
+    /// Like `from_grids` for boards from the API client, leaving out those
+    /// it generated instead, as a fallback or in its cache warm-up, whose
+    /// labels are the generator's rather than the API's
+    pub fn from_api_boards(grids: &[Grid]) -> Self {
+        let (from_api, others): (Vec<Grid>, Vec<Grid>) =
+            grids.iter().cloned().partition(|grid| grid.metadata.source == Some(PuzzleSource::Api));
+        Self { not_from_api: others.len(), ..Self::from_grids(&from_api) }
+    }
+
+    /// Number of labelled boards
+    pub fn total(&self) -> usize {
+        self.matrix.iter().flatten().sum()
+    }
+
+    /// Percentage of labelled boards where the rating agrees with the API
+    pub fn agreement_rate(&self) -> f64 {
+        let agreed: usize = (0..CALIBRATION_LABELS.len()).map(|i| self.matrix[i][i]).sum();
+        match self.total() {
+            0 => 0.0,
+            total => agreed as f64 / total as f64 * 100.0,
+        }
+    }
+
+    /// Prints the confusion matrix with API labels down the side
+    pub fn print(&self) {
+        println!("\n=== Rating Calibration ===");
+        print!("{:<12}", "API \\ Rated");
+        for label in CALIBRATION_LABELS {
+            print!(" {:>7}", label);
+        }
+        println!();
+        for (label, row) in CALIBRATION_LABELS.iter().zip(&self.matrix) {
+            print!("{:<12}", label);
+            for count in row {
+                print!(" {:>7}", count);
+            }
+            println!();
+        }
+        println!("Agreement: {:.1}% of {} boards", self.agreement_rate(), self.total());
+        if self.unlabelled > 0 {
+            println!("Unlabelled boards skipped: {}", self.unlabelled);
+        }
+        if self.not_from_api > 0 {
+            println!("Boards not from the API skipped: {}", self.not_from_api);
+        }
     }
 }
 
+fn calibration_index(difficulty: &str) -> Option<usize> {
+    CALIBRATION_LABELS.iter().position(|label| label.eq_ignore_ascii_case(difficulty))
+}
+
+/// Fetches `board_count` boards and compares their API labels with the
+/// internal rating; see `Calibration::from_api_boards`
+pub async fn run_calibration(board_count: usize) -> Result<Calibration> {
+    if board_count == 0 {
+        return Err(SudokuError::BenchmarkError("Board count must be greater than 0".to_string()));
+    }
+    let boards = api::fetch_multiple_boards(board_count).await?;
+    info!("Rating {} boards...", boards.len());
+    let calibration = tokio::task::spawn_blocking(move || Calibration::from_api_boards(&boards))
+        .await
+        .map_err(|e| SudokuError::BenchmarkError(e.to_string()))?;
+    if calibration.not_from_api > 0 {
+        warn!("{} of {} boards didn't come from the API and were left out", calibration.not_from_api, board_count);
+    }
+    Ok(calibration)
+}
+
+/// Generation times for one difficulty
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratorTiming {
+    pub difficulty: String,
+    pub times: TimingStats,
+    /// Average number of givens in the generated puzzles
+    pub average_clues: f64,
+}
+
+/// Difficulties measured by the generator benchmark
+const GENERATOR_DIFFICULTIES: [&str; 3] = ["Easy", "Medium", "Hard"];
+
+/// Times puzzle generation, `count` puzzles per difficulty.
+///
+/// With a `seed` the same puzzles are generated on every run, which makes
+/// runs comparable.
+pub fn run_generator_benchmark(count: usize, seed: Option<u64>) -> Result<Vec<GeneratorTiming>> {
+    if count == 0 {
+        return Err(SudokuError::BenchmarkError("Board count must be greater than 0".to_string()));
+    }
+
+    let mut generator = seed.map_or_else(BoardGenerator::new, BoardGenerator::with_seed);
+    GENERATOR_DIFFICULTIES
+        .iter()
+        .map(|&difficulty| {
+            info!("Generating {} {} puzzles...", count, difficulty);
+            let mut times = Vec::with_capacity(count);
+            let mut clues = 0;
+            for _ in 0..count {
+                let start = Instant::now();
+                let grid = generator.generate_with_difficulty(difficulty)?;
+                times.push(start.elapsed());
+                clues += Board::new(&grid.value).cells.iter().filter(|&&v| v != 0).count();
+            }
+            Ok(GeneratorTiming {
+                difficulty: difficulty.to_string(),
+                times: TimingStats::from_durations(&times),
+                average_clues: clues as f64 / count as f64,
+            })
+        })
+        .collect()
+}
+
+/// Prints generator timings as a table
+pub fn print_generator_results(results: &[GeneratorTiming]) {
+    println!("\n=== Generator Benchmark ===");
+    println!(
+        "{:<10} {:>6} {:>12} {:>12} {:>12} {:>12} {:>7}",
+        "Difficulty", "Count", "Min", "Avg", "Max", "P95", "Clues"
+    );
+    for r in results {
+        println!(
+            "{:<10} {:>6} {:>12?} {:>12?} {:>12?} {:>12?} {:>7.1}",
+            r.difficulty, r.times.count, r.times.min, r.times.average, r.times.max, r.times.p95, r.average_clues
+        );
+    }
+}
+
+/// Requests made by `run_api_benchmark`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiWorkload {
+    pub requests: usize,
+    /// Requests in flight at once
+    pub concurrency: usize,
+    /// Pause after each request before the same client makes its next one
+    pub think_time: Duration,
+}
+
+impl Default for ApiWorkload {
+    fn default() -> Self {
+        Self { requests: 100, concurrency: 4, think_time: Duration::ZERO }
+    }
+}
+
+/// How the API layer served a workload, kept apart from solve times so slow
+/// fetching can be told from slow solving
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiBenchmarkResults {
+    pub requests: usize,
+    /// Requests that got no board at all
+    pub failures: usize,
+    /// Latency of requests served from the cache
+    pub cache_hits: TimingStats,
+    /// Latency of requests served by the provider, rate limiting included
+    pub provider_hits: TimingStats,
+    /// Latency of requests answered by the local generator
+    pub fallbacks: TimingStats,
+    /// Time spent in each provider fetch, failed ones included
+    pub api_fetches: TimingStats,
+    pub total_duration: Duration,
+}
+
+impl ApiBenchmarkResults {
+    /// Percentage of requests served from the cache
+    pub fn cache_hit_rate(&self) -> f64 {
+        self.cache_hits.count as f64 / self.requests.max(1) as f64 * 100.0
+    }
+
+    /// Percentage of requests that fell back to the generator
+    pub fn fallback_rate(&self) -> f64 {
+        self.fallbacks.count as f64 / self.requests.max(1) as f64 * 100.0
+    }
+
+    pub fn print(&self) {
+        println!("\n=== API Benchmark ===");
+        println!("{} requests in {:?} ({} failed)", self.requests, self.total_duration, self.failures);
+        println!(
+            "Cache hit rate: {:.1}%, generator fallback rate: {:.1}%",
+            self.cache_hit_rate(),
+            self.fallback_rate()
+        );
+        println!("{:<14} {:>6} {:>12} {:>12} {:>12} {:>12}", "Served by", "Count", "Min", "Avg", "Max", "P95");
+        let rows = [
+            ("Cache", &self.cache_hits),
+            ("Provider", &self.provider_hits),
+            ("Generator", &self.fallbacks),
+            ("API fetch", &self.api_fetches),
+        ];
+        for (label, times) in rows {
+            println!(
+                "{:<14} {:>6} {:>12?} {:>12?} {:>12?} {:>12?}",
+                label, times.count, times.min, times.average, times.max, times.p95
+            );
+        }
+    }
+}
+
+/// Fetches `workload.requests` boards through `client` and times each by
+/// where it was served from. Nothing is solved.
+pub async fn run_api_benchmark<P: BoardProvider>(
+    client: &ApiClient<P>,
+    workload: &ApiWorkload,
+) -> Result<ApiBenchmarkResults> {
+    if workload.requests == 0 {
+        return Err(SudokuError::BenchmarkError("Request count must be greater than 0".to_string()));
+    }
+    info!("Making {} board requests, {} at a time...", workload.requests, workload.concurrency.max(1));
+    let start = Instant::now();
+    let next = AtomicUsize::new(0);
+    let client_loop = || async {
+        let mut samples = Vec::new();
+        while next.fetch_add(1, Ordering::Relaxed) < workload.requests {
+            let request_start = Instant::now();
+            let trace = client.fetch_new_board_traced().await.map(|(_, trace)| trace).ok();
+            samples.push((request_start.elapsed(), trace));
+            if !workload.think_time.is_zero() {
+                tokio::time::sleep(workload.think_time).await;
+            }
+        }
+        samples
+    };
+    let samples: Vec<(Duration, Option<FetchTrace>)> =
+        join_all((0..workload.concurrency.max(1)).map(|_| client_loop())).await.into_iter().flatten().collect();
+
+    let served = |source: FetchSource| {
+        let times: Vec<Duration> = samples
+            .iter()
+            .filter(|(_, trace)| trace.is_some_and(|trace| trace.source == source))
+            .map(|&(time, _)| time)
+            .collect();
+        TimingStats::from_durations(&times)
+    };
+    let api_fetches: Vec<Duration> = samples.iter().filter_map(|&(_, trace)| trace?.provider_time).collect();
+    Ok(ApiBenchmarkResults {
+        requests: samples.len(),
+        failures: samples.iter().filter(|(_, trace)| trace.is_none()).count(),
+        cache_hits: served(FetchSource::Cache),
+        provider_hits: served(FetchSource::Provider),
+        fallbacks: served(FetchSource::Fallback),
+        api_fetches: TimingStats::from_durations(&api_fetches),
+        total_duration: start.elapsed(),
+    })
+}
+
+/// Thread counts to try: powers of two below `max`, then `max` itself
+pub fn thread_counts(max: usize) -> Vec<usize> {
+    let max = max.max(1);
+    let mut counts: Vec<usize> = std::iter::successors(Some(1usize), |&n| Some(n * 2))
+        .take_while(|&n| n < max)
+        .collect();
+    counts.push(max);
+    counts
+}
+
+/// Solves `boards` once per thread count, each solver running on a pool of
+/// that many threads whatever `solver::set_parallelism` says
+pub fn measure_scaling(boards: &[Grid], thread_counts: &[usize]) -> Result<Vec<ScalingResult>> {
+    let mut results: Vec<ScalingResult> = Vec::with_capacity(thread_counts.len());
+    for &threads in thread_counts {
+        let start = Instant::now();
+        for board in boards {
+            // Failures are reported by the regular benchmark; only time matters here
+            let _ = Solver::new(board.clone()).with_parallelism(Parallelism::threads(threads)).solve();
+        }
+        let duration = start.elapsed();
+
+        let boards_per_sec = boards.len() as f64 / duration.as_secs_f64().max(f64::EPSILON);
+        let speedup = results.first().map_or(1.0, |first| boards_per_sec / first.boards_per_sec);
+        debug!("{} threads: {:.1} boards/sec", threads, boards_per_sec);
+        results.push(ScalingResult { threads, duration, boards_per_sec, speedup });
+    }
+    Ok(results)
+}
+
+/// Runs the throughput benchmark with 1, 2, 4, ... up to `max_threads` threads
+pub async fn run_scaling_benchmark(board_count: usize, max_threads: usize) -> Result<Vec<ScalingResult>> {
+    if board_count == 0 {
+        return Err(SudokuError::BenchmarkError("Board count must be greater than 0".to_string()));
+    }
+
+    let boards = api::fetch_multiple_boards(board_count).await?;
+    let counts = thread_counts(max_threads);
+    info!("Measuring throughput of {} boards with {:?} threads...", boards.len(), counts);
+
+    // The solves are CPU-bound, so keep them off the async workers
+    tokio::task::spawn_blocking(move || measure_scaling(&boards, &counts))
+        .await
+        .map_err(|e| SudokuError::BenchmarkError(e.to_string()))?
+}
+
+/// Parses a duration such as `500ms`, `2s` or `1.5s`; a bare number is read as milliseconds
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1e-3)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1.0)
+    } else {
+        (s, 1e-3)
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|n| Duration::try_from_secs_f64(n * scale).ok())
+        .ok_or_else(|| SudokuError::ParseError(format!("Invalid duration '{}'", s)))
+}
+
 /// Runs a benchmark solving the specified number of boards
 pub async fn run_benchmark(board_count: usize, prefetch: bool) -> Result<BenchmarkResults> {
+    run_benchmark_with_timeout(board_count, prefetch, None).await
+}
+
+/// Runs a benchmark, giving up on any board that takes longer than `per_board_timeout`.
+///
+/// Boards that time out are recorded as DNF with their puzzle string and the
+/// run moves on to the next board.
+pub async fn run_benchmark_with_timeout(
+    board_count: usize,
+    prefetch: bool,
+    per_board_timeout: Option<Duration>,
+) -> Result<BenchmarkResults> {
+    run_benchmark_with_progress(board_count, prefetch, per_board_timeout, |_| {}).await
+}
+
+/// Runs a benchmark, calling `on_progress` after each board is solved or given up on
+#[tracing::instrument(name = "benchmark", skip(on_progress))]
+pub async fn run_benchmark_with_progress(
+    board_count: usize,
+    prefetch: bool,
+    per_board_timeout: Option<Duration>,
+    on_progress: impl FnMut(&BenchmarkProgress) + Send,
+) -> Result<BenchmarkResults> {
     if board_count == 0 {
         return Err(SudokuError::BenchmarkError("Board count must be greater than 0".to_string()));
     }
@@ -87,69 +951,116 @@ pub async fn run_benchmark(board_count: usize, prefetch: bool) -> Result<Benchma
 
     info!("Starting benchmark with {} boards...", board_count);
     let start = Instant::now();
-    let mut min_duration = Duration::from_secs(u64::MAX);
-    let mut max_duration = Duration::from_secs(0);
-    let mut total_duration = Duration::from_secs(0);
-    let mut solved_boards = 0;
-    let mut unique_solutions = 0;
-    let mut multiple_solutions = 0;
-    let mut difficulty_stats = DifficultyStats::default();
-
-    // Fetch all boards
     let boards = api::fetch_multiple_boards(board_count).await?;
+    Ok(solve_boards(&boards, start, per_board_timeout, on_progress))
+}
+
+/// Benchmarks the given boards, such as a public collection read with
+/// `formats::read`, calling `on_progress` after each
+pub fn run_benchmark_on(
+    boards: &[Grid],
+    per_board_timeout: Option<Duration>,
+    on_progress: impl FnMut(&BenchmarkProgress),
+) -> Result<BenchmarkResults> {
+    if boards.is_empty() {
+        return Err(SudokuError::BenchmarkError("No boards to benchmark".to_string()));
+    }
+    info!("Starting benchmark with {} boards...", boards.len());
+    Ok(solve_boards(boards, Instant::now(), per_board_timeout, on_progress))
+}
+
+/// Solves each board in turn and collects the results; `start` is when the
+/// run began, including any time spent fetching
+fn solve_boards(
+    boards: &[Grid],
+    start: Instant,
+    per_board_timeout: Option<Duration>,
+    mut on_progress: impl FnMut(&BenchmarkProgress),
+) -> BenchmarkResults {
+    let board_count = boards.len();
+    let mut solve_time = Duration::ZERO;
+    let mut solutions = Vec::with_capacity(board_count);
+    let mut board_results = Vec::with_capacity(board_count);
+    let mut failures = 0;
+    let solving_start = Instant::now();
     
     // Process each board
     for (i, board) in boards.iter().cloned().enumerate() {
         debug!("Solving board {}/{}", i + 1, board_count);
-        
-        // Update difficulty stats
-        match board.difficulty.to_lowercase().as_str() {
-            "easy" => difficulty_stats.easy += 1,
-            "medium" => difficulty_stats.medium += 1,
-            "hard" => difficulty_stats.hard += 1,
-            _ => difficulty_stats.unknown += 1,
-        }
 
-        // Solve the board and measure time
+        let mut result = BoardResult {
+            puzzle: Board::new(&board.value).to_line(),
+            difficulty: board.difficulty.clone(),
+            solve_time: Duration::ZERO,
+            nodes: 0,
+            solved: false,
+            unique: false,
+            dnf: false,
+            cpu_time: CpuTime::default(),
+            allocations: None,
+        };
+
+        // Solve the board and measure time; the usage readings are taken
+        // outside the timed span and are process-wide, since the solver's
+        // rayon workers share the work (see `usage`)
+        let (cpu_start, allocations_start) = (CpuTime::now(), Allocations::now());
         let solve_start = Instant::now();
-        let mut solver = Solver::new(board);
-        match solver.solve() {
-            Ok(_) => {
-                solved_boards += 1;
-                if solver.has_unique_solution() {
-                    unique_solutions += 1;
-                } else {
-                    multiple_solutions += 1;
-                }
-                let duration = solve_start.elapsed();
-                min_duration = min_duration.min(duration);
-                max_duration = max_duration.max(duration);
-                total_duration += duration;
+        let mut solver = Solver::new(board).with_uniqueness_check();
+        if let Some(timeout) = per_board_timeout {
+            solver = solver.with_timeout(timeout);
+        }
+        let span = tracing::info_span!("benchmark_board", index = i, difficulty = %result.difficulty);
+        let outcome = span.in_scope(|| solver.solve());
+        let duration = solve_start.elapsed();
+        result.cpu_time = CpuTime::now().since(cpu_start);
+        result.allocations = Allocations::now().zip(allocations_start).map(|(now, start)| now.since(start));
+        result.solve_time = duration;
+        match outcome {
+            Ok(solution) => {
+                solve_time += duration;
+                result.nodes = solution.stats.nodes;
+                result.unique = solution.unique == Some(true);
+                result.solved = true;
+                solutions.push(solution.board);
             }
             Err(e) => {
+                result.dnf = matches!(e, SudokuError::SolveTimeout);
+                failures += 1;
                 debug!("Failed to solve board {}: {}", i + 1, e);
             }
         }
+        board_results.push(result);
+
+        let solved = board_results.len() - failures;
+        on_progress(&BenchmarkProgress {
+            completed: board_results.len(),
+            total: boards.len(),
+            failures,
+            average: solve_time.checked_div(solved as u32).unwrap_or_default(),
+            elapsed: solving_start.elapsed(),
+        });
     }
 
-    let results = BenchmarkResults {
-        total_duration: start.elapsed(),
-        average_duration: total_duration / board_count as u32,
-        min_duration,
-        max_duration,
-        total_boards: board_count,
-        solved_boards,
-        unique_solutions,
-        multiple_solutions,
-        difficulty_stats,
-    };
+    // Double-check every reported solution in one batch. One that fails
+    // validation doesn't count as solved.
+    let valid = SimdValidator::validate_many(&solutions);
+    for (result, &ok) in board_results.iter_mut().filter(|r| r.solved).zip(&valid) {
+        result.solved = ok;
+    }
+    // The logic solver's pass isn't part of the run's time
+    let elapsed = start.elapsed();
 
-    Ok(results)
+    BenchmarkResults {
+        techniques: technique_histogram(boards),
+        ..BenchmarkResults::from_boards(board_results, elapsed, per_board_timeout)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::{fault::{Fault, FaultPlan, FaultyProvider}, fixture::FixtureProvider};
+    use crate::GridBuilder;
     use tokio::time::timeout;
 
     #[tokio::test]
@@ -166,6 +1077,221 @@ mod tests {
         }
     }
 
+    /// A board solved uniquely in `solve_time`, for building results in tests
+    pub(super) fn solved_board(puzzle: &str, difficulty: &str, solve_time: Duration) -> BoardResult {
+        BoardResult {
+            puzzle: puzzle.to_string(),
+            difficulty: difficulty.to_string(),
+            solve_time,
+            nodes: 0,
+            solved: true,
+            unique: true,
+            dnf: false,
+            cpu_time: CpuTime::default(),
+            allocations: None,
+        }
+    }
+
+    fn sample_results(times_ms: &[u64]) -> BenchmarkResults {
+        let boards: Vec<BoardResult> = times_ms
+            .iter()
+            .enumerate()
+            .map(|(i, &millis)| BoardResult {
+                nodes: millis * 10,
+                cpu_time: CpuTime { user: Duration::from_millis(millis * 2), system: Duration::ZERO },
+                ..solved_board(&((b'a' + i as u8) as char).to_string(), "Hard", Duration::from_millis(millis))
+            })
+            .collect();
+        let total = boards.iter().map(|b| b.solve_time).sum();
+        BenchmarkResults::from_boards(boards, total, None)
+    }
+
+    #[test]
+    fn test_slowest_and_json() {
+        let mut results = sample_results(&[15, 40, 5]);
+        results.techniques.insert("hard".to_string(), BTreeMap::from([(Technique::XWing, 2)]));
+
+        let slowest: Vec<&str> = results.slowest(2).iter().map(|b| b.puzzle.as_str()).collect();
+        assert_eq!(slowest, ["b", "a"]);
+
+        let json = results.to_json().unwrap();
+        let decoded: BenchmarkResults = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.boards.len(), 3);
+        assert_eq!(decoded.boards[1].nodes, 400);
+        assert_eq!(decoded.techniques["hard"][&Technique::XWing], 2);
+        assert_eq!(decoded.boards[1].cpu_time.user, Duration::from_millis(80));
+        // Every sample solve used twice its wall time in CPU
+        assert!((results.cores_used() - 2.0).abs() < 1e-9);
+        assert_eq!(results.cpu_time().total(), Duration::from_millis(120));
+        assert_eq!(results.allocations(), None);
+    }
+
+    #[test]
+    fn test_timing_stats() {
+        let times: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+        let stats = TimingStats::from_durations(&times);
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(20));
+        assert_eq!(stats.p95, Duration::from_millis(19));
+        assert_eq!(stats.average, Duration::from_micros(10_500));
+        assert_eq!(TimingStats::from_durations(&[]), TimingStats::default());
+
+        let mut difficulty = DifficultyStats::default();
+        difficulty.record_timings(&sample_results(&[10, 30]).boards);
+        assert_eq!(difficulty.hard_times.count, 2);
+        assert_eq!(difficulty.hard_times.average, Duration::from_millis(20));
+        assert_eq!(difficulty.easy_times.count, 0);
+    }
+
+    #[test]
+    fn test_compare_flags_regressions() {
+        let baseline = sample_results(&[10, 20, 30]);
+        let current = sample_results(&[10, 20, 60]);
+
+        let comparison = current.compare(&baseline, DEFAULT_REGRESSION_THRESHOLD);
+        assert!(comparison.has_regressions());
+        let max = comparison.metrics.iter().find(|m| m.name == "max_duration_ms").unwrap();
+        assert!(max.regression);
+        assert!((max.change - 1.0).abs() < 1e-9);
+        let min = comparison.metrics.iter().find(|m| m.name == "min_duration_ms").unwrap();
+        assert!(!min.regression);
+
+        // Faster runs are never regressions
+        assert!(!baseline.compare(&current, DEFAULT_REGRESSION_THRESHOLD).has_regressions());
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_progress() {
+        let client = ApiClient::new(FixtureProvider::default());
+        let boards = client.fetch_multiple_boards(3).await.unwrap();
+        let mut updates = Vec::new();
+        let results = run_benchmark_on(&boards, None, |p| updates.push(p.clone())).unwrap();
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates.iter().map(|p| p.completed).collect::<Vec<_>>(), [1, 2, 3]);
+        let last = updates.last().unwrap();
+        assert_eq!(last.total, 3);
+        assert_eq!(last.failures, results.total_boards - results.solved_boards);
+        assert_eq!(last.eta(), Some(Duration::ZERO));
+        assert!(last.bar(10).starts_with("[##########] 3/3"));
+    }
+
+    #[test]
+    fn test_benchmark_collection() {
+        let input: String = ["easy", "hard", "no_candidates"]
+            .iter()
+            .map(|name| format!("{}\n", crate::providers::fixture::get(name).unwrap().puzzle))
+            .collect();
+        let boards = crate::formats::parse(&input, None).unwrap();
+        let results = run_benchmark_on(&boards, None, |_| {}).unwrap();
+        assert_eq!((results.total_boards, results.solved_boards, results.unique_solutions), (3, 2, 2));
+        assert_eq!(results.difficulty_stats.unknown, 3);
+        let tracked = results.boards.iter().all(|b| b.allocations.is_some());
+        assert_eq!(tracked, cfg!(feature = "alloc-stats"));
+
+        // Every board is unlabelled, so all the techniques land under "unknown"
+        assert_eq!(results.techniques.keys().collect::<Vec<_>>(), ["unknown"]);
+        let singles: usize = boards
+            .iter()
+            .map(|grid| rating::rate(&Board::new(&grid.value)).technique_counts.get(&Technique::NakedSingle).copied())
+            .map(Option::unwrap_or_default)
+            .sum();
+        assert_eq!(results.techniques["unknown"][&Technique::NakedSingle], singles);
+        assert!(run_benchmark_on(&[], None, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("250").unwrap(), Duration::from_millis(250));
+        assert!(parse_duration("fast").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("inf").is_err());
+        assert!(matches!(parse_duration("1e20s"), Err(SudokuError::ParseError(_))));
+        assert!(matches!(parse_duration("1e30ms"), Err(SudokuError::ParseError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_per_board_timeout() {
+        let client = ApiClient::new(FixtureProvider::default());
+        let boards = client.fetch_multiple_boards(2).await.unwrap();
+        let results = run_benchmark_on(&boards, Some(Duration::ZERO), |_| {}).unwrap();
+        assert_eq!(results.per_board_timeout, Some(Duration::ZERO));
+        assert!(results.dnf_boards > 0);
+        assert_eq!(results.dnf_boards, results.dnf().count());
+        assert!(results.dnf().all(|b| !b.solved && b.puzzle.len() == 81));
+    }
+
+    #[test]
+    fn test_generator_benchmark() {
+        let results = run_generator_benchmark(2, Some(3)).unwrap();
+        assert_eq!(results.len(), GENERATOR_DIFFICULTIES.len());
+        assert!(results.iter().all(|r| r.times.count == 2 && r.average_clues > 0.0));
+        // Fewer clues are left as the difficulty rises
+        assert!(results[0].average_clues > results[2].average_clues);
+        assert!(run_generator_benchmark(0, None).is_err());
+    }
+
+    #[test]
+    fn test_calibration_matrix() {
+        let grid = |line: &str, difficulty: &str| {
+            GridBuilder::from_board(&Board::parse(line).unwrap()).with_difficulty(difficulty).build().unwrap()
+        };
+        let easy = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        let x_wing = "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5";
+        let calibration = Calibration::from_grids(&[
+            grid(easy, "easy"),
+            grid(x_wing, "Hard"),
+            grid(easy, "Hard"),
+            grid(easy, "Fiendish"),
+        ]);
+
+        assert_eq!(calibration.matrix[0][0], 1);
+        assert_eq!(calibration.matrix[2][2], 1);
+        assert_eq!(calibration.matrix[2][0], 1);
+        assert_eq!(calibration.unlabelled, 1);
+        assert_eq!(calibration.total(), 3);
+        assert!((calibration.agreement_rate() - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(calibration.not_from_api, 0);
+
+        // Only boards the API sent count; generated ones carry the generator's labels
+        let from = |source| {
+            let mut board = grid(easy, "Hard");
+            board.metadata.source = Some(source);
+            board
+        };
+        let boards = [from(PuzzleSource::Api), from(PuzzleSource::Generator), grid(easy, "Hard")];
+        let calibration = Calibration::from_api_boards(&boards);
+        assert_eq!((calibration.total(), calibration.matrix[2][0], calibration.not_from_api), (1, 1, 2));
+    }
+
+    #[test]
+    fn test_thread_counts() {
+        assert_eq!(thread_counts(1), [1]);
+        assert_eq!(thread_counts(4), [1, 2, 4]);
+        assert_eq!(thread_counts(6), [1, 2, 4, 6]);
+    }
+
+    #[test]
+    fn test_measure_scaling() {
+        let puzzle =
+            Board::parse("53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79").unwrap();
+        let grid = GridBuilder::from_board(&puzzle).with_difficulty("Easy").build().unwrap();
+        let results = measure_scaling(&[grid.clone(), grid.clone()], &[1, 2]).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].speedup, 1.0);
+        assert!(results.iter().all(|r| r.boards_per_sec > 0.0));
+
+        // A process-wide setting doesn't override the thread count of each run
+        crate::solver::set_parallelism(Parallelism::Threads(3));
+        let results = measure_scaling(&[grid], &[5, 6]);
+        crate::solver::set_parallelism(Parallelism::Global);
+        assert_eq!(results.unwrap().len(), 2);
+        assert!(crate::solver::has_pool(5) && crate::solver::has_pool(6));
+    }
+
     #[tokio::test]
     async fn test_benchmark_invalid_count() {
         match run_benchmark(0, false).await {
@@ -187,4 +1313,28 @@ mod tests {
             Err(_) => panic!("Benchmark timed out"),
         }
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_api_benchmark() {
+        // Every fetch takes 5ms and every other one fails
+        let delay = Duration::from_millis(5);
+        let plan = FaultPlan::Script([Fault::Delay(delay), Fault::Timeout(delay)].repeat(2));
+        let client = ApiClient::new(FaultyProvider::new(FixtureProvider::default(), plan))
+            .with_warm_up(0)
+            .with_min_request_interval(Duration::ZERO);
+        // One request at a time: provider (cached), cache hit, failed fetch and fallback, and again
+        let workload = ApiWorkload { requests: 6, concurrency: 1, ..ApiWorkload::default() };
+        let results = timeout(Duration::from_secs(30), run_api_benchmark(&client, &workload)).await.unwrap().unwrap();
+
+        assert_eq!((results.requests, results.failures), (6, 0));
+        let counts = (results.cache_hits.count, results.provider_hits.count, results.fallbacks.count);
+        assert_eq!(counts, (2, 2, 2));
+        assert_eq!(results.api_fetches.count, 4);
+        assert!(results.api_fetches.min >= Duration::from_millis(5));
+        assert!((results.fallback_rate() - 100.0 / 3.0).abs() < 1e-9);
+        assert!(results.provider_hits.min >= results.api_fetches.min);
+
+        let workload = ApiWorkload { requests: 0, ..ApiWorkload::default() };
+        assert!(run_api_benchmark(&client, &workload).await.is_err());
+    }
+}
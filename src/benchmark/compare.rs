@@ -0,0 +1,303 @@
+//! Before/after comparison of two saved benchmark runs.
+//!
+//! `compare_runs` lines up the per-metric averages of two `--json` exports
+//! and says how likely each change in the per-board numbers is to be real.
+//! When both runs solved the same puzzles, the boards are paired up and
+//! tested with the Wilcoxon signed-rank test; otherwise the two samples are
+//! compared with the Mann-Whitney U test. Both are rank tests, so a handful of
+//! very slow boards can't swamp the result, and both use the normal
+//! approximation, which wants at least `MIN_SAMPLES` boards.
+
+use super::{BenchmarkResults, BoardResult};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Fewest boards on each side for a significance hint
+pub const MIN_SAMPLES: usize = 8;
+
+/// How one metric moved from the old run to the new one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricDelta {
+    pub name: &'static str,
+    pub old: f64,
+    pub new: f64,
+    /// Relative change, e.g. -0.2 for 20% lower than the old run
+    pub change: f64,
+    /// Two-sided p-value of the per-board numbers; None for run-level
+    /// metrics and when either run has fewer than `MIN_SAMPLES` boards
+    pub p_value: Option<f64>,
+}
+
+impl MetricDelta {
+    /// A word on how far to trust the change
+    pub fn hint(&self) -> &'static str {
+        match self.p_value {
+            None => "",
+            Some(p) if p < 0.01 => "significant",
+            Some(p) if p < 0.05 => "probably real",
+            Some(_) => "noise",
+        }
+    }
+}
+
+/// Two benchmark runs side by side
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunComparison {
+    /// True if both runs solved the same puzzles, which were tested in pairs
+    pub paired: bool,
+    /// Boards each run solved, compared per board
+    pub old_boards: usize,
+    pub new_boards: usize,
+    pub metrics: Vec<MetricDelta>,
+}
+
+impl RunComparison {
+    /// Pretty prints the comparison as a table
+    pub fn print(&self) {
+        let test = if self.paired { "paired, Wilcoxon signed-rank" } else { "unpaired, Mann-Whitney U" };
+        println!("\n=== Benchmark Comparison ({} vs {} boards, {}) ===", self.old_boards, self.new_boards, test);
+        println!("{:<20} {:>12} {:>12} {:>9} {:>9}  Hint", "Metric", "Old", "New", "Change", "p");
+        for m in &self.metrics {
+            let p = m.p_value.map_or_else(|| "-".to_string(), |p| format!("{:.4}", p));
+            let line = format!(
+                "{:<20} {:>12.3} {:>12.3} {:>+8.1}% {:>9}  {}",
+                m.name,
+                m.old,
+                m.new,
+                m.change * 100.0,
+                p,
+                m.hint()
+            );
+            println!("{}", line.trim_end());
+        }
+        if self.old_boards.min(self.new_boards) < MIN_SAMPLES {
+            println!("Fewer than {} solved boards on a side; no significance hints", MIN_SAMPLES);
+        }
+    }
+}
+
+/// A per-board number compared between runs
+struct BoardMetric {
+    name: &'static str,
+    value: fn(&BoardResult) -> f64,
+}
+
+const BOARD_METRICS: [BoardMetric; 3] = [
+    BoardMetric { name: "average_solve_ms", value: |b| b.solve_time.as_secs_f64() * 1000.0 },
+    BoardMetric { name: "average_nodes", value: |b| b.nodes as f64 },
+    BoardMetric { name: "average_cpu_ms", value: |b| b.cpu_time.total().as_secs_f64() * 1000.0 },
+];
+
+/// Compares `new` with `old`, metric by metric
+pub fn compare_runs(old: &BenchmarkResults, new: &BenchmarkResults) -> RunComparison {
+    let solved = |results: &BenchmarkResults| -> Vec<BoardResult> {
+        results.boards.iter().filter(|b| b.solved).cloned().collect()
+    };
+    let (old_solved, new_solved) = (solved(old), solved(new));
+    let pairs = pair_boards(old, new);
+    let testable = old_solved.len().min(new_solved.len()) >= MIN_SAMPLES;
+
+    let mut metrics: Vec<MetricDelta> = BOARD_METRICS
+        .iter()
+        .map(|metric| {
+            let values = |boards: &[BoardResult]| boards.iter().map(metric.value).collect::<Vec<f64>>();
+            let (before, after) = (values(&old_solved), values(&new_solved));
+            let p_value = match &pairs {
+                Some(pairs) if pairs.len() >= MIN_SAMPLES => {
+                    let differences: Vec<f64> =
+                        pairs.iter().map(|(old, new)| (metric.value)(new) - (metric.value)(old)).collect();
+                    Some(signed_rank_test(&differences))
+                }
+                Some(_) => None,
+                None => testable.then(|| rank_sum_test(&before, &after)),
+            };
+            delta(metric.name, mean(&before), mean(&after), p_value)
+        })
+        .collect();
+    metrics.push(delta("success_rate_pct", old.success_rate(), new.success_rate(), None));
+    metrics.push(delta("cores_used", old.cores_used(), new.cores_used(), None));
+
+    RunComparison { paired: pairs.is_some(), old_boards: old_solved.len(), new_boards: new_solved.len(), metrics }
+}
+
+fn delta(name: &'static str, old: f64, new: f64, p_value: Option<f64>) -> MetricDelta {
+    let change = if old == 0.0 { 0.0 } else { (new - old) / old };
+    MetricDelta { name, old, new, change, p_value }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The boards both runs solved, matched by puzzle, if the runs covered the
+/// same puzzles
+fn pair_boards<'a>(
+    old: &'a BenchmarkResults,
+    new: &'a BenchmarkResults,
+) -> Option<Vec<(&'a BoardResult, &'a BoardResult)>> {
+    let mut old_puzzles: Vec<&str> = old.boards.iter().map(|b| b.puzzle.as_str()).collect();
+    let mut new_puzzles: Vec<&str> = new.boards.iter().map(|b| b.puzzle.as_str()).collect();
+    old_puzzles.sort_unstable();
+    new_puzzles.sort_unstable();
+    if old_puzzles != new_puzzles {
+        return None;
+    }
+    let by_puzzle: HashMap<&str, &BoardResult> = old.boards.iter().map(|b| (b.puzzle.as_str(), b)).collect();
+    Some(
+        new.boards
+            .iter()
+            .filter_map(|b| by_puzzle.get(b.puzzle.as_str()).map(|&old| (old, b)))
+            .filter(|(old, new)| old.solved && new.solved)
+            .collect(),
+    )
+}
+
+/// Ranks from 1, ties sharing the average of their ranks, along with the
+/// sum of t³ - t over groups of t ties for the variance correction
+fn ranks(values: &[f64]) -> (Vec<f64>, f64) {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut ties = 0.0;
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        let t = (end - start) as f64;
+        ties += t * t * t - t;
+        start = end;
+    }
+    (ranks, ties)
+}
+
+/// Two-sided p-value of the Mann-Whitney U test that `a` and `b` come from
+/// the same distribution
+fn rank_sum_test(a: &[f64], b: &[f64]) -> f64 {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let n = n1 + n2;
+    let combined: Vec<f64> = a.iter().chain(b).copied().collect();
+    let (ranks, ties) = ranks(&combined);
+    let u = ranks[..a.len()].iter().sum::<f64>() - n1 * (n1 + 1.0) / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    two_sided(u - n1 * n2 / 2.0, variance)
+}
+
+/// Two-sided p-value of the Wilcoxon signed-rank test that paired
+/// `differences` are centred on zero; zero differences are dropped
+fn signed_rank_test(differences: &[f64]) -> f64 {
+    let nonzero: Vec<f64> = differences.iter().copied().filter(|&d| d != 0.0).collect();
+    let n = nonzero.len() as f64;
+    let magnitudes: Vec<f64> = nonzero.iter().map(|d| d.abs()).collect();
+    let (ranks, ties) = ranks(&magnitudes);
+    let positive: f64 = ranks.iter().zip(&nonzero).filter(|(_, &d)| d > 0.0).map(|(rank, _)| rank).sum();
+    let variance = n * (n + 1.0) * (2.0 * n + 1.0) / 24.0 - ties / 48.0;
+    two_sided(positive - n * (n + 1.0) / 4.0, variance)
+}
+
+/// Two-sided tail probability of a normal deviation from the mean
+fn two_sided(deviation: f64, variance: f64) -> f64 {
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = deviation.abs() / variance.sqrt();
+    (1.0 - erf(z / std::f64::consts::SQRT_2)).clamp(0.0, 1.0)
+}
+
+/// The error function, to within 1.5e-7 (Abramowitz and Stegun 7.1.26)
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly =
+        t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let value = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 { -value } else { value }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{tests::solved_board, CpuTime};
+    use std::time::Duration;
+
+    fn run(puzzles: &[&str], times_us: &[u64]) -> BenchmarkResults {
+        let boards: Vec<BoardResult> = puzzles
+            .iter()
+            .zip(times_us)
+            .map(|(puzzle, &micros)| BoardResult {
+                nodes: 100,
+                cpu_time: CpuTime { user: Duration::from_micros(micros), system: Duration::ZERO },
+                ..solved_board(puzzle, "Easy", Duration::from_micros(micros))
+            })
+            .collect();
+        let total = boards.iter().map(|b| b.solve_time).sum();
+        BenchmarkResults::from_boards(boards, total, None)
+    }
+
+    fn metric<'a>(comparison: &'a RunComparison, name: &str) -> &'a MetricDelta {
+        comparison.metrics.iter().find(|m| m.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_paired_runs() {
+        let puzzles: Vec<String> = (0..12).map(|i| format!("p{}", i)).collect();
+        let puzzles: Vec<&str> = puzzles.iter().map(String::as_str).collect();
+        let old_times: Vec<u64> = (0..12).map(|i| 1000 + i * 500).collect();
+        let old = run(&puzzles, &old_times);
+
+        // Every board a little faster, listed in another order
+        let mut faster: Vec<(&str, u64)> = puzzles.iter().zip(&old_times).map(|(&p, &t)| (p, t * 9 / 10)).collect();
+        faster.reverse();
+        let (reversed, times): (Vec<&str>, Vec<u64>) = faster.into_iter().unzip();
+        let comparison = compare_runs(&old, &run(&reversed, &times));
+        assert!(comparison.paired);
+        let solve = metric(&comparison, "average_solve_ms");
+        assert!((solve.change + 0.1).abs() < 1e-9);
+        assert!(solve.p_value.unwrap() < 0.01, "{:?}", solve);
+        assert_eq!(solve.hint(), "significant");
+        // Nodes didn't move at all
+        let nodes = metric(&comparison, "average_nodes");
+        assert_eq!((nodes.change, nodes.p_value, nodes.hint()), (0.0, Some(1.0), "noise"));
+        assert_eq!(metric(&comparison, "success_rate_pct").p_value, None);
+    }
+
+    #[test]
+    fn test_unpaired_runs() {
+        let puzzles: Vec<String> = (0..20).map(|i| format!("p{}", i)).collect();
+        let puzzles: Vec<&str> = puzzles.iter().map(String::as_str).collect();
+        let times: Vec<u64> = (0..10).map(|i| 1000 + (i * 7919) % 2000).collect();
+        let old = run(&puzzles[..10], &times);
+        // Other puzzles taking the same times in another order
+        let shuffled: Vec<u64> = times.iter().rev().copied().collect();
+        let comparison = compare_runs(&old, &run(&puzzles[10..], &shuffled));
+        assert!(!comparison.paired);
+        let solve = metric(&comparison, "average_solve_ms");
+        assert_eq!((solve.change, solve.hint()), (0.0, "noise"));
+
+        let slower: Vec<u64> = times.iter().map(|t| t * 3).collect();
+        let comparison = compare_runs(&old, &run(&puzzles[10..], &slower));
+        assert!(metric(&comparison, "average_solve_ms").p_value.unwrap() < 0.01);
+
+        // Too few boards for a hint
+        let comparison = compare_runs(&run(&puzzles[..3], &times[..3]), &run(&puzzles[..3], &slower[..3]));
+        assert_eq!(metric(&comparison, "average_solve_ms").p_value, None);
+        assert_eq!(metric(&comparison, "average_solve_ms").hint(), "");
+    }
+
+    #[test]
+    fn test_erf_and_ranks() {
+        assert!(erf(0.0).abs() < 1e-7);
+        assert!((erf(1.0) - 0.842_700_79).abs() < 1e-6);
+        assert!((erf(-2.0) + 0.995_322_27).abs() < 1e-6);
+        let (ranks, ties) = ranks(&[3.0, 1.0, 3.0, 2.0]);
+        assert_eq!(ranks, [3.5, 1.0, 3.5, 2.0]);
+        assert_eq!(ties, 6.0);
+    }
+}
@@ -0,0 +1,405 @@
+//! Self-contained HTML reports of benchmark runs.
+//!
+//! `render_html` lays a run out as a single page with inline SVG charts, so
+//! the file opens anywhere without scripts or network access: the summary, a
+//! histogram of solve times, boards and timings per difficulty, the technique
+//! histogram, the slowest boards and, given a `Comparison`, each metric next
+//! to its baseline.
+
+use super::{BenchmarkResults, Comparison, TimingStats, SLOWEST_SHOWN};
+use crate::logic::Technique;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Bars in the solve-time histogram
+const BINS: usize = 24;
+/// Width of every chart, in pixels
+const CHART_WIDTH: usize = 720;
+/// Room left of horizontal bars for their labels
+const LABEL_WIDTH: usize = 170;
+/// Room right of horizontal bars for their values
+const VALUE_WIDTH: usize = 110;
+const ROW_HEIGHT: usize = 22;
+const HISTOGRAM_HEIGHT: usize = 180;
+
+const STYLE: &str = concat!(
+    "body{font-family:sans-serif;max-width:760px;margin:2em auto;color:#222}",
+    "h2{margin-top:1.6em;border-bottom:1px solid #ddd}",
+    "table{border-collapse:collapse}td,th{padding:.2em .8em;text-align:right}",
+    "td:first-child,th:first-child{text-align:left}tr:nth-child(even){background:#f4f4f4}",
+    "svg text{font-size:12px}.bar{fill:#4a7bd0}.baseline{fill:#b0b0b0}.regression{fill:#d04a4a}",
+    ".dnf{color:#d04a4a}code{font-size:11px}"
+);
+
+/// One bar of a horizontal bar chart
+struct Bar {
+    label: String,
+    value: f64,
+    /// Shown after the bar
+    text: String,
+    class: &'static str,
+}
+
+/// Renders `results` as an HTML page, adding the metrics of `comparison`
+/// against the named baseline when given
+pub fn render_html(results: &BenchmarkResults, comparison: Option<(&str, &Comparison)>) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Sudoku benchmark</title>\
+         <style>{}</style></head>\n<body>",
+        STYLE
+    );
+    html.push_str("<h1>Sudoku benchmark</h1>\n");
+    summary(&mut html, results);
+
+    html.push_str("<h2>Solve times</h2>\n");
+    let times: Vec<Duration> = results.boards.iter().filter(|b| b.solved).map(|b| b.solve_time).collect();
+    html.push_str(&histogram(&times));
+
+    html.push_str("<h2>Difficulty</h2>\n");
+    difficulties(&mut html, results);
+
+    if !results.techniques.is_empty() {
+        html.push_str("<h2>Techniques</h2>\n");
+        techniques(&mut html, results);
+    }
+
+    if let Some((name, comparison)) = comparison {
+        let _ = writeln!(
+            html,
+            "<h2>Against baseline &lsquo;{}&rsquo;</h2>\n<p>Each metric is drawn on its own scale; a regression, \
+             a change for the worse of more than {:.0}%, is in red.</p>",
+            escape(name),
+            comparison.threshold * 100.0
+        );
+        html.push_str(&compared(comparison));
+    }
+
+    slowest(&mut html, results);
+    html.push_str("</body></html>\n");
+    html
+}
+
+fn summary(html: &mut String, results: &BenchmarkResults) {
+    let rows = [
+        ("Boards", results.total_boards.to_string()),
+        ("Solved", format!("{} ({:.1}%)", results.solved_boards, results.success_rate())),
+        ("Unique solutions", results.unique_solutions.to_string()),
+        ("Multiple solutions", results.multiple_solutions.to_string()),
+        ("Total duration", format!("{:?}", results.total_duration)),
+        ("Average solve", format!("{:?}", results.average_duration)),
+        ("Fastest solve", format!("{:?}", results.min_duration)),
+        ("Slowest solve", format!("{:?}", results.max_duration)),
+        ("Average nodes", format!("{:.0}", results.average_nodes())),
+        ("CPU time", cpu_time(results)),
+    ];
+    html.push_str("<table>");
+    for (name, value) in rows {
+        let _ = write!(html, "<tr><td>{}</td><td>{}</td></tr>", name, value);
+    }
+    if let Some(allocations) = results.allocations() {
+        let (count, bytes) = (allocations.count, allocations.bytes);
+        let _ = write!(html, "<tr><td>Allocations</td><td>{} ({} bytes)</td></tr>", count, bytes);
+    }
+    if let Some(timeout) = results.per_board_timeout {
+        let _ = write!(html, "<tr><td>Did not finish (limit {:?})</td><td>{}</td></tr>", timeout, results.dnf_boards);
+    }
+    html.push_str("</table>\n");
+}
+
+fn cpu_time(results: &BenchmarkResults) -> String {
+    let cpu = results.cpu_time();
+    format!("{:?} user, {:?} system ({:.2} cores)", cpu.user, cpu.system, results.cores_used())
+}
+
+/// Boards per difficulty as bars, then their solve times as a table
+fn difficulties(html: &mut String, results: &BenchmarkResults) {
+    let stats = &results.difficulty_stats;
+    let groups = [
+        ("Easy", stats.easy, &stats.easy_times),
+        ("Medium", stats.medium, &stats.medium_times),
+        ("Hard", stats.hard, &stats.hard_times),
+        ("Unknown", stats.unknown, &stats.unknown_times),
+    ];
+    let total = results.total_boards.max(1) as f64;
+    let bars: Vec<Bar> = groups
+        .iter()
+        .filter(|(_, boards, _)| *boards > 0)
+        .map(|&(name, boards, _)| Bar {
+            label: name.to_string(),
+            value: boards as f64,
+            text: format!("{} ({:.1}%)", boards, boards as f64 / total * 100.0),
+            class: "bar",
+        })
+        .collect();
+    html.push_str(&bar_chart(&bars));
+
+    html.push_str("<table><tr><th>Difficulty</th><th>Solved</th><th>Min</th><th>Avg</th><th>P95</th><th>Max</th></tr>");
+    for (name, _, times) in groups {
+        let TimingStats { count, min, average, max, p95 } = *times;
+        if count > 0 {
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{:?}</td><td>{:?}</td><td>{:?}</td><td>{:?}</td></tr>",
+                name, count, min, average, p95, max
+            );
+        }
+    }
+    html.push_str("</table>\n");
+}
+
+/// A bar chart per difficulty of how often each technique fired
+fn techniques(html: &mut String, results: &BenchmarkResults) {
+    for (difficulty, counts) in &results.techniques {
+        let bars: Vec<Bar> = Technique::ALL
+            .iter()
+            .filter_map(|technique| counts.get(technique).map(|&count| (technique, count)))
+            .map(|(technique, count)| Bar {
+                label: technique.name().to_string(),
+                value: count as f64,
+                text: count.to_string(),
+                class: "bar",
+            })
+            .collect();
+        // Keys are lowercase labels such as "easy"
+        let mut title = difficulty.clone();
+        if let Some(first) = title.get_mut(..1) {
+            first.make_ascii_uppercase();
+        }
+        let _ = writeln!(html, "<h3>{}</h3>", escape(&title));
+        html.push_str(&bar_chart(&bars));
+    }
+}
+
+/// Each metric as a baseline bar over the current run's, both scaled to
+/// the larger of the two
+fn compared(comparison: &Comparison) -> String {
+    let rows = comparison.metrics.len() * 2;
+    let mut svg = svg_open(rows * ROW_HEIGHT + ROW_HEIGHT / 2);
+    let span = (CHART_WIDTH - LABEL_WIDTH - VALUE_WIDTH) as f64;
+    for (i, metric) in comparison.metrics.iter().enumerate() {
+        let top = i * 2 * ROW_HEIGHT;
+        let scale = metric.baseline.max(metric.current);
+        let _ = write!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            LABEL_WIDTH - 8,
+            top + ROW_HEIGHT + 4,
+            metric.name
+        );
+        let current_class = if metric.regression { "regression" } else { "bar" };
+        let pairs = [
+            (metric.baseline, "baseline", String::new()),
+            (metric.current, current_class, change(metric.change)),
+        ];
+        for (j, (value, class, note)) in pairs.into_iter().enumerate() {
+            let y = top + j * ROW_HEIGHT + 3;
+            let width = if scale > 0.0 { value / scale * span } else { 0.0 };
+            let _ = write!(
+                svg,
+                "<rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{:.1}\" height=\"{}\"/>\
+                 <text x=\"{:.1}\" y=\"{}\">{:.3}{}</text>",
+                class,
+                LABEL_WIDTH,
+                y,
+                width,
+                ROW_HEIGHT - 6,
+                LABEL_WIDTH as f64 + width + 4.0,
+                y + ROW_HEIGHT - 10,
+                value,
+                note
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn change(change: f64) -> String {
+    format!(" ({:+.1}%)", change * 100.0)
+}
+
+fn slowest(html: &mut String, results: &BenchmarkResults) {
+    let boards = results.slowest(SLOWEST_SHOWN);
+    if boards.is_empty() {
+        return;
+    }
+    html.push_str("<h2>Slowest boards</h2>\n<table>");
+    html.push_str("<tr><th>Puzzle</th><th>Difficulty</th><th>Time</th><th>Nodes</th></tr>");
+    for board in boards {
+        let outcome = match (board.dnf, board.solved) {
+            (true, _) => " <span class=\"dnf\">DNF</span>",
+            (false, true) => "",
+            (false, false) => " <span class=\"dnf\">unsolved</span>",
+        };
+        let _ = write!(
+            html,
+            "<tr><td><code>{}</code></td><td>{}</td><td>{:?}{}</td><td>{}</td></tr>",
+            escape(&board.puzzle),
+            escape(&board.difficulty),
+            board.solve_time,
+            outcome,
+            board.nodes
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+/// Solve times in `BINS` bins spaced evenly on a log scale between the
+/// fastest and slowest, each bar titled with its range and count
+fn histogram(times: &[Duration]) -> String {
+    if times.is_empty() {
+        return "<p>No boards were solved.</p>\n".to_string();
+    }
+    let logs: Vec<f64> = times.iter().map(|t| (t.as_secs_f64() * 1e6).max(1.0).ln()).collect();
+    let low = logs.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let width = (high - low) / BINS as f64;
+    let mut counts = [0usize; BINS];
+    for log in logs {
+        let bin = if width > 0.0 { ((log - low) / width) as usize } else { 0 };
+        counts[bin.min(BINS - 1)] += 1;
+    }
+
+    let tallest = counts.iter().copied().max().unwrap_or(1) as f64;
+    let bar_width = CHART_WIDTH as f64 / BINS as f64;
+    let edge = |bin: usize| Duration::from_micros((low + width * bin as f64).exp() as u64);
+    let mut svg = svg_open(HISTOGRAM_HEIGHT + 36);
+    for (bin, &count) in counts.iter().enumerate() {
+        let height = count as f64 / tallest * HISTOGRAM_HEIGHT as f64;
+        let _ = write!(
+            svg,
+            "<rect class=\"bar\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\">\
+             <title>{:?} to {:?}: {} boards</title></rect>",
+            bin as f64 * bar_width + 1.0,
+            HISTOGRAM_HEIGHT as f64 - height,
+            bar_width - 2.0,
+            height,
+            edge(bin),
+            edge(bin + 1),
+            count
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<line x1=\"0\" y1=\"{0}\" x2=\"{1}\" y2=\"{0}\" stroke=\"#888\"/>\
+         <text x=\"0\" y=\"{2}\">{3:?}</text><text x=\"{1}\" y=\"{2}\" text-anchor=\"end\">{4:?}</text>\
+         <text x=\"{5}\" y=\"{2}\" text-anchor=\"middle\">solve time (log scale), {6} boards</text></svg>",
+        HISTOGRAM_HEIGHT,
+        CHART_WIDTH,
+        HISTOGRAM_HEIGHT + 18,
+        edge(0),
+        edge(BINS),
+        CHART_WIDTH / 2,
+        times.len()
+    );
+    svg
+}
+
+/// Horizontal bars scaled to the largest value
+fn bar_chart(bars: &[Bar]) -> String {
+    let largest = bars.iter().map(|bar| bar.value).fold(0.0, f64::max);
+    let span = (CHART_WIDTH - LABEL_WIDTH - VALUE_WIDTH) as f64;
+    let mut svg = svg_open(bars.len() * ROW_HEIGHT + 4);
+    for (i, bar) in bars.iter().enumerate() {
+        let y = i * ROW_HEIGHT + 3;
+        let width = if largest > 0.0 { bar.value / largest * span } else { 0.0 };
+        let _ = write!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\
+             <rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{:.1}\" height=\"{}\"/>\
+             <text x=\"{:.1}\" y=\"{}\">{}</text>",
+            LABEL_WIDTH - 8,
+            y + ROW_HEIGHT - 10,
+            escape(&bar.label),
+            bar.class,
+            LABEL_WIDTH,
+            y,
+            width,
+            ROW_HEIGHT - 6,
+            LABEL_WIDTH as f64 + width + 4.0,
+            y + ROW_HEIGHT - 10,
+            escape(&bar.text)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn svg_open(height: usize) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {0} {1}\" width=\"{0}\" height=\"{1}\">",
+        CHART_WIDTH, height
+    )
+}
+
+/// Escapes text for HTML; puzzle lines and labels come from user files
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{tests::solved_board, Allocations, BoardResult, DEFAULT_REGRESSION_THRESHOLD};
+    use std::collections::BTreeMap;
+
+    fn results(times_ms: &[u64]) -> BenchmarkResults {
+        let boards: Vec<BoardResult> = times_ms
+            .iter()
+            .map(|&millis| BoardResult {
+                nodes: millis,
+                allocations: Some(Allocations { count: 3, bytes: 100 }),
+                ..solved_board("<script>", "Easy", Duration::from_millis(millis))
+            })
+            .collect();
+        let total = boards.iter().map(|b| b.solve_time).sum();
+        BenchmarkResults {
+            techniques: BTreeMap::from([("easy".to_string(), BTreeMap::from([(Technique::HiddenSingle, 7)]))]),
+            ..BenchmarkResults::from_boards(boards, total, None)
+        }
+    }
+
+    #[test]
+    fn test_render_html() {
+        let baseline = results(&[1, 2, 3]);
+        let current = results(&[1, 2, 30]);
+        let comparison = current.compare(&baseline, DEFAULT_REGRESSION_THRESHOLD);
+        let html = render_html(&current, Some(("main", &comparison)));
+
+        assert!(html.starts_with("<!DOCTYPE html>") && html.ends_with("</html>\n"));
+        // Nothing is loaded from elsewhere
+        assert!(!html.contains("<script") && !html.contains("src="));
+        assert!(html.contains("&lt;script&gt;"));
+        assert_eq!(html.matches("<svg").count(), html.matches("</svg>").count());
+        assert!(html.contains("<h3>Easy</h3>") && html.contains("Hidden Single"));
+        assert!(html.contains("<td>Allocations</td><td>9 (300 bytes)</td>"));
+        assert!(html.contains("Against baseline &lsquo;main&rsquo;"));
+        assert!(html.contains("class=\"regression\""));
+        // Three boards spread over the histogram's bins
+        assert_eq!(html.matches("boards</title>").count(), BINS);
+        assert!(html.contains(": 1 boards</title>"));
+
+        let html = render_html(&baseline, None);
+        assert!(!html.contains("Against baseline") && !html.contains("regression\""));
+    }
+
+    #[test]
+    fn test_histogram_edges() {
+        assert!(histogram(&[]).contains("No boards were solved"));
+        // Equal times all land in the first bin
+        let same = histogram(&[Duration::from_millis(4); 3]);
+        assert!(same.contains(": 3 boards</title>"));
+    }
+}
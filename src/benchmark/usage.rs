@@ -0,0 +1,175 @@
+//! What a solve costs besides wall time.
+//!
+//! `CpuTime::now` reads the process's user and system CPU time with
+//! `getrusage`, so a parallel solve that keeps several cores busy shows up as
+//! more CPU than wall time; it reads zero where there is no `getrusage`. With
+//! the `alloc-stats` feature a binary can install `CountingAllocator` as its
+//! global allocator, and `Allocations::now` then reads its counters.
+//!
+//! Both readings are process-wide rather than per thread, because a solve
+//! runs on the solver's rayon workers as well as the calling thread. Anything
+//! else the process does at the same time, such as the shared client's cache
+//! warm-up, lands in the same totals, so take them with nothing else running;
+//! the `benchmark` command starts no warm-up for this reason.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// CPU time the process has used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuTime {
+    pub user: Duration,
+    pub system: Duration,
+}
+
+impl CpuTime {
+    #[cfg(unix)]
+    pub fn now() -> Self {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+        // SAFETY: getrusage only writes to the struct it is given, and a
+        // zeroed rusage is a valid value even if it writes nothing
+        let usage = unsafe {
+            libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr());
+            usage.assume_init()
+        };
+        let duration = |time: libc::timeval| {
+            Duration::from_secs(time.tv_sec.max(0) as u64) + Duration::from_micros(time.tv_usec.max(0) as u64)
+        };
+        Self { user: duration(usage.ru_utime), system: duration(usage.ru_stime) }
+    }
+
+    #[cfg(not(unix))]
+    pub fn now() -> Self {
+        Self::default()
+    }
+
+    pub fn total(&self) -> Duration {
+        self.user + self.system
+    }
+
+    /// CPU time used between `earlier` and this reading
+    pub fn since(&self, earlier: CpuTime) -> CpuTime {
+        CpuTime { user: self.user.saturating_sub(earlier.user), system: self.system.saturating_sub(earlier.system) }
+    }
+}
+
+/// Heap allocations the process has made
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Allocations {
+    /// Calls to allocate or grow a block
+    pub count: u64,
+    /// Bytes asked for in those calls
+    pub bytes: u64,
+}
+
+impl Allocations {
+    /// The counts so far; None unless built with the `alloc-stats` feature
+    /// and `CountingAllocator` is the global allocator
+    pub fn now() -> Option<Self> {
+        #[cfg(feature = "alloc-stats")]
+        {
+            use std::sync::atomic::Ordering;
+            // The runtime allocates before `main`, so no count means the
+            // allocator isn't installed
+            let count = counting::ALLOCATIONS.load(Ordering::Relaxed);
+            (count > 0).then(|| Self { count, bytes: counting::ALLOCATED_BYTES.load(Ordering::Relaxed) })
+        }
+        #[cfg(not(feature = "alloc-stats"))]
+        None
+    }
+
+    /// Allocations made between `earlier` and this reading
+    pub fn since(&self, earlier: Allocations) -> Allocations {
+        Allocations { count: self.count.saturating_sub(earlier.count), bytes: self.bytes.saturating_sub(earlier.bytes) }
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+pub use counting::CountingAllocator;
+
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    pub(super) static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+    pub(super) static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+    /// The system allocator, counting allocations and the bytes asked for.
+    ///
+    /// The library doesn't install it; a binary that wants allocation counts
+    /// declares it as its own global allocator:
+    ///
+    /// ```ignore
+    /// #[global_allocator]
+    /// static GLOBAL: sudoku::benchmark::CountingAllocator = sudoku::benchmark::CountingAllocator;
+    /// ```
+    pub struct CountingAllocator;
+
+    fn count(bytes: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    // SAFETY: every call is passed straight on to `System`, which upholds
+    // the `GlobalAlloc` contract; the counters are only atomics
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            System.alloc(layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            System.alloc_zeroed(layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count(new_size);
+            System.realloc(ptr, layout, new_size)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "alloc-stats")]
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn test_cpu_time() {
+        let start = CpuTime::now();
+        // Busy the thread long enough for the clock to tick
+        let spin = std::time::Instant::now();
+        let mut x = 0u64;
+        while spin.elapsed() < Duration::from_millis(30) {
+            x = std::hint::black_box(x.wrapping_mul(31).wrapping_add(7));
+        }
+        let used = CpuTime::now().since(start);
+        if cfg!(unix) {
+            assert!(used.total() >= Duration::from_millis(10), "{:?}", used);
+        }
+        assert_eq!(start.since(CpuTime::now()), CpuTime::default());
+    }
+
+    #[test]
+    fn test_allocations() {
+        let start = Allocations::now();
+        assert_eq!(start.is_some(), cfg!(feature = "alloc-stats"));
+        let Some(start) = start else {
+            return;
+        };
+        let boxes: Vec<Box<[u8; 64]>> = (0..10).map(|_| Box::new([0; 64])).collect();
+        let made = Allocations::now().unwrap().since(start);
+        // Other tests allocate at the same time, so only a lower bound holds
+        assert!(made.count >= 11 && made.bytes >= 640, "{:?}", made);
+        drop(boxes);
+    }
+}
@@ -0,0 +1,254 @@
+//! Per-digit bitboards.
+//!
+//! `BitBoards` keeps two `u128` masks per digit, with bit `row * 9 + col`
+//! standing for a cell: where the digit is placed and where it is still a
+//! candidate. Unit and peer lookups become a mask and a popcount, which is
+//! what fish patterns and other digit-by-digit analysis want.
+
+use crate::{logic::LogicSolver, Board};
+
+/// Every cell of the grid
+pub const ALL_CELLS: u128 = (1 << 81) - 1;
+
+/// Cell `i` of unit `unit`, numbered as in `logic::UNITS`: rows, then
+/// columns, then boxes
+const fn unit_cell(unit: usize, i: usize) -> usize {
+    match unit / 9 {
+        0 => unit * 9 + i,
+        1 => i * 9 + unit % 9,
+        _ => {
+            let b = unit % 9;
+            ((b / 3) * 3 + i / 3) * 9 + (b % 3) * 3 + i % 3
+        }
+    }
+}
+
+const fn unit_masks() -> [u128; 27] {
+    let mut masks = [0; 27];
+    let mut unit = 0;
+    while unit < 27 {
+        let mut i = 0;
+        while i < 9 {
+            masks[unit] |= 1 << unit_cell(unit, i);
+            i += 1;
+        }
+        unit += 1;
+    }
+    masks
+}
+
+const fn peer_masks() -> [u128; 81] {
+    let mut masks = [0; 81];
+    let mut cell = 0;
+    while cell < 81 {
+        let (row, col) = (cell / 9, cell % 9);
+        let b = (row / 3) * 3 + col / 3;
+        masks[cell] = (UNIT_MASKS[row] | UNIT_MASKS[9 + col] | UNIT_MASKS[18 + b]) & !(1 << cell);
+        cell += 1;
+    }
+    masks
+}
+
+/// Cells of every unit, numbered as in `logic::UNITS`
+pub const UNIT_MASKS: [u128; 27] = unit_masks();
+
+/// The 20 peers of every cell
+pub const PEER_MASKS: [u128; 81] = peer_masks();
+
+/// Which of a unit's nine cells are set in `mask`, as bits 0 to 8 in the
+/// unit's own order
+pub fn unit_bits(mask: u128, unit: usize) -> u16 {
+    if unit < 9 {
+        return (mask >> (unit * 9)) as u16 & 0x1FF;
+    }
+    (0..9).filter(|&i| mask & (1 << unit_cell(unit, i)) != 0).fold(0, |bits, i| bits | 1 << i)
+}
+
+/// The cells set in `mask`, in reading order
+pub fn cells(mut mask: u128) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let cell = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(cell)
+    })
+}
+
+/// Placements and candidates of each digit as cell masks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitBoards {
+    /// Cells holding each digit, indexed by digit - 1
+    placed: [u128; 9],
+    /// Empty cells where each digit is still a candidate
+    candidates: [u128; 9],
+}
+
+impl From<&Board> for BitBoards {
+    /// Candidates are every digit not yet placed in a cell's row, column or box
+    fn from(board: &Board) -> Self {
+        let mut placed = [0u128; 9];
+        for (cell, &value) in board.cells.iter().enumerate() {
+            if value != 0 {
+                placed[value as usize - 1] |= 1 << cell;
+            }
+        }
+        let empty = ALL_CELLS & !placed.iter().fold(0, |all, &mask| all | mask);
+        let candidates = placed.map(|mask| {
+            let seen = cells(mask).fold(0, |seen, cell| seen | PEER_MASKS[cell]);
+            empty & !seen
+        });
+        Self { placed, candidates }
+    }
+}
+
+impl From<&LogicSolver> for BitBoards {
+    /// Takes the solver's candidates, including everything its techniques eliminated
+    fn from(solver: &LogicSolver) -> Self {
+        let mut bits = Self::from(solver.board());
+        bits.candidates = [0; 9];
+        for cell in cells(bits.empty()) {
+            for value in solver.candidates(cell / 9, cell % 9).iter_candidates() {
+                bits.candidates[value as usize - 1] |= 1 << cell;
+            }
+        }
+        bits
+    }
+}
+
+impl BitBoards {
+    /// Cells holding `digit` (1 to 9)
+    pub fn positions(&self, digit: u8) -> u128 {
+        self.placed[digit as usize - 1]
+    }
+
+    /// Empty cells where `digit` is still a candidate
+    pub fn candidates(&self, digit: u8) -> u128 {
+        self.candidates[digit as usize - 1]
+    }
+
+    /// Cells holding any digit
+    pub fn occupied(&self) -> u128 {
+        self.placed.iter().fold(0, |all, &mask| all | mask)
+    }
+
+    pub fn empty(&self) -> u128 {
+        ALL_CELLS & !self.occupied()
+    }
+
+    /// Where `digit` is placed in `unit`, as `unit_bits` gives them
+    pub fn positions_in(&self, digit: u8, unit: usize) -> u16 {
+        unit_bits(self.positions(digit), unit)
+    }
+
+    /// Where `digit` is a candidate in `unit`, as `unit_bits` gives them
+    pub fn candidates_in(&self, digit: u8, unit: usize) -> u16 {
+        unit_bits(self.candidates(digit), unit)
+    }
+
+    /// For fish: the columns where `digit` is a candidate in each row, or with
+    /// `by_column` the rows where it is a candidate in each column
+    pub fn candidate_lines(&self, digit: u8, by_column: bool) -> [u16; 9] {
+        let base = if by_column { 9 } else { 0 };
+        std::array::from_fn(|line| self.candidates_in(digit, base + line))
+    }
+
+    /// Places `digit` at `cell`, removing it as a candidate from the cell's peers
+    pub fn place(&mut self, cell: usize, digit: u8) {
+        self.placed[digit as usize - 1] |= 1 << cell;
+        for candidates in &mut self.candidates {
+            *candidates &= !(1 << cell);
+        }
+        self.candidates[digit as usize - 1] &= !PEER_MASKS[cell];
+    }
+
+    /// Removes `digit` as a candidate of `cell`
+    pub fn eliminate(&mut self, cell: usize, digit: u8) {
+        self.candidates[digit as usize - 1] &= !(1 << cell);
+    }
+
+    /// The placed digits as a board
+    pub fn to_board(&self) -> Board {
+        let mut board = Board::empty();
+        for (digit, &mask) in (1..=9).zip(&self.placed) {
+            for cell in cells(mask) {
+                board.cells[cell] = digit;
+            }
+        }
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{logic::UNITS, pencilmarks::PencilMarks, providers::fixture};
+
+    #[test]
+    fn test_masks_match_units() {
+        for (unit, cells_of_unit) in UNITS.iter().enumerate() {
+            // Every unit lists its cells in reading order
+            assert_eq!(cells(UNIT_MASKS[unit]).collect::<Vec<_>>(), cells_of_unit.to_vec());
+            for (i, &cell) in cells_of_unit.iter().enumerate() {
+                assert_eq!(unit_bits(1 << cell, unit), 1 << i);
+            }
+        }
+        assert!(PEER_MASKS.iter().all(|mask| mask.count_ones() == 20));
+        assert_eq!(PEER_MASKS[40] & (1 << 40), 0);
+    }
+
+    #[test]
+    fn test_from_board() {
+        for name in ["easy", "hard", "solved"] {
+            let board = fixture::get(name).unwrap().board();
+            let bits = BitBoards::from(&board);
+            assert_eq!(bits.to_board(), board, "{}", name);
+            let marks = PencilMarks::from_board(&board);
+            for cell in 0..81 {
+                for digit in 1..=9 {
+                    let expected = board.cells[cell] == 0 && marks.get(cell / 9, cell % 9).has_candidate(digit);
+                    let found = bits.candidates(digit) & (1 << cell) != 0;
+                    assert_eq!(found, expected, "{} r{}c{} {}", name, cell / 9 + 1, cell % 9 + 1, digit);
+                }
+            }
+        }
+
+        let easy = fixture::get("easy").unwrap().board();
+        let bits = BitBoards::from(&easy);
+        // Row 1 of the easy puzzle is 53..7....
+        assert_eq!(bits.positions_in(5, 0), 0b1);
+        assert_eq!(bits.positions_in(7, 0), 0b10000);
+        assert_eq!(bits.occupied().count_ones(), 30);
+        assert_eq!(bits.empty().count_ones(), 51);
+    }
+
+    #[test]
+    fn test_place_and_eliminate() {
+        let easy = fixture::get("easy").unwrap().board();
+        let mut bits = BitBoards::from(&easy);
+        let mut board = easy.clone();
+        board.set(0, 2, 4);
+        bits.place(2, 4);
+        assert_eq!(bits, BitBoards::from(&board));
+
+        bits.eliminate(3, 2);
+        assert_eq!(bits.candidates(2) & (1 << 3), 0);
+        let lines = bits.candidate_lines(2, false);
+        assert_eq!(lines[0], bits.candidates_in(2, 0));
+        assert_eq!(bits.candidate_lines(2, true)[3] & 1, 0);
+    }
+
+    #[test]
+    fn test_from_logic_solver() {
+        let board = fixture::get("x_wing").unwrap().board();
+        let mut solver = LogicSolver::new(&board);
+        assert_eq!(BitBoards::from(&solver), BitBoards::from(&board));
+        solver.solve();
+        let bits = BitBoards::from(&solver);
+        for cell in cells(bits.empty()) {
+            let expected = solver.candidates(cell / 9, cell % 9);
+            assert!((1..=9).all(|d| (bits.candidates(d) & (1 << cell) != 0) == expected.has_candidate(d)));
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Bounded board cache with least-recently-used eviction and expiry.
+//!
+//! Boards are handed out freshest first and removed as they are served. When
+//! the cache is full the least recently inserted board is evicted, and boards
+//! older than their time-to-live are dropped instead of being served.
+//! Puzzles equivalent under the Sudoku symmetries to one seen recently are
+//! turned away, so consumers don't get the same puzzle twice in disguise.
+
+use crate::{symmetry, Board, Grid, PuzzleSource};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+/// Canonical hashes remembered for duplicate detection, unless the capacity is larger
+const RECENTLY_SEEN: usize = 4096;
+
+/// Capacity and expiry settings for a `BoardCache`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Most boards held at once
+    pub capacity: NonZeroUsize,
+    /// Age after which any board expires, if at all
+    pub ttl: Option<Duration>,
+    /// Age after which boards fetched from the API expire, so a long-running
+    /// server keeps pulling fresh ones
+    pub api_ttl: Option<Duration>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            capacity: NonZeroUsize::new(1000).unwrap(),
+            ttl: None,
+            api_ttl: Some(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+impl CachePolicy {
+    /// Time-to-live of a board, the shorter of `ttl` and `api_ttl` for API boards
+    fn ttl_for(&self, grid: &Grid) -> Option<Duration> {
+        let api_ttl = self.api_ttl.filter(|_| grid.metadata.source == Some(PuzzleSource::Api));
+        match (self.ttl, api_ttl) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// A board with the canonical hash the cache's duplicate check needs.
+///
+/// Working the hash out is a search over the board's symmetric forms, so
+/// callers sharing a cache behind a lock build this before taking the lock
+/// and pass it to `insert`; a plain `Grid` is hashed inside `insert`.
+#[derive(Debug, Clone)]
+pub struct HashedBoard {
+    grid: Grid,
+    hash: u64,
+}
+
+impl HashedBoard {
+    pub fn new(grid: Grid) -> Self {
+        let hash = symmetry::canonical_hash(&Board::new(&grid.value));
+        Self { grid, hash }
+    }
+}
+
+impl From<Grid> for HashedBoard {
+    fn from(grid: Grid) -> Self {
+        Self::new(grid)
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    grid: Grid,
+    expires: Option<Instant>,
+}
+
+/// Boards waiting to be served
+#[derive(Debug)]
+pub struct BoardCache {
+    policy: CachePolicy,
+    /// Keyed by insertion order, which is also recency since boards are never re-read
+    entries: LruCache<u64, Entry>,
+    next_key: u64,
+    /// Canonical hashes of boards inserted recently, including ones already served
+    seen: LruCache<u64, ()>,
+}
+
+impl Default for BoardCache {
+    fn default() -> Self {
+        Self::new(CachePolicy::default())
+    }
+}
+
+impl BoardCache {
+    pub fn new(policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            entries: LruCache::new(policy.capacity),
+            next_key: 0,
+            seen: LruCache::new(seen_capacity(policy.capacity)),
+        }
+    }
+
+    pub fn policy(&self) -> CachePolicy {
+        self.policy
+    }
+
+    /// Applies a new policy, evicting the oldest boards if the capacity shrank.
+    /// Expiry times of boards already cached are kept.
+    pub fn set_policy(&mut self, policy: CachePolicy) {
+        self.entries.resize(policy.capacity);
+        self.seen.resize(seen_capacity(policy.capacity));
+        self.policy = policy;
+    }
+
+    /// Adds a board, evicting the least recently inserted one if full.
+    ///
+    /// Returns false and drops the board if it is equivalent to one seen recently.
+    pub fn insert(&mut self, board: impl Into<HashedBoard>) -> bool {
+        self.insert_at(board.into(), Instant::now())
+    }
+
+    /// Returns true if `grid` is equivalent to a board inserted recently
+    pub fn seen(&self, grid: &Grid) -> bool {
+        self.seen.contains(&symmetry::canonical_hash(&Board::new(&grid.value)))
+    }
+
+    /// Removes and returns the freshest board that hasn't expired
+    pub fn take(&mut self) -> Option<Grid> {
+        self.take_at(Instant::now())
+    }
+
+    /// Drops every expired board, returning how many were dropped
+    pub fn purge_expired(&mut self) -> usize {
+        self.purge_expired_at(Instant::now())
+    }
+
+    /// Number of boards held, including any that expired but weren't purged yet
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops every board, but keeps remembering them as seen
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn insert_at(&mut self, board: HashedBoard, now: Instant) -> bool {
+        let HashedBoard { grid, hash } = board;
+        if self.seen.put(hash, ()).is_some() {
+            return false;
+        }
+        let expires = self.policy.ttl_for(&grid).map(|ttl| now + ttl);
+        self.entries.push(self.next_key, Entry { grid, expires });
+        self.next_key += 1;
+        true
+    }
+
+    fn take_at(&mut self, now: Instant) -> Option<Grid> {
+        self.purge_expired_at(now);
+        let key = *self.entries.iter().next()?.0;
+        self.entries.pop(&key).map(|entry| entry.grid)
+    }
+
+    fn purge_expired_at(&mut self, now: Instant) -> usize {
+        let expired: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires.is_some_and(|expires| expires <= now))
+            .map(|(&key, _)| key)
+            .collect();
+        for key in &expired {
+            self.entries.pop(key);
+        }
+        expired.len()
+    }
+}
+
+fn seen_capacity(capacity: NonZeroUsize) -> NonZeroUsize {
+    capacity.max(NonZeroUsize::new(RECENTLY_SEEN).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator::BoardGenerator, symmetry::Transform, GridMetadata};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    /// A distinct puzzle per name, labelled with the name as its difficulty
+    fn grid(name: &str, source: Option<PuzzleSource>) -> Grid {
+        let mut grid = BoardGenerator::with_seed(crate::fnv1a(name.as_bytes())).generate().unwrap();
+        grid.difficulty = name.to_string();
+        grid.metadata = GridMetadata { source, ..Default::default() };
+        grid
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let policy = CachePolicy { capacity: NonZeroUsize::new(2).unwrap(), ttl: None, api_ttl: None };
+        let mut cache = BoardCache::new(policy);
+        for name in ["a", "b", "c"] {
+            cache.insert(grid(name, None));
+        }
+        // "a" was evicted; the rest are served freshest first
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.take().unwrap().difficulty, "c");
+        assert_eq!(cache.take().unwrap().difficulty, "b");
+        assert!(cache.take().is_none());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let policy = CachePolicy {
+            capacity: NonZeroUsize::new(10).unwrap(),
+            ttl: Some(Duration::from_secs(60)),
+            api_ttl: Some(Duration::from_secs(10)),
+        };
+        let mut cache = BoardCache::new(policy);
+        let start = Instant::now();
+        cache.insert_at(grid("local", Some(PuzzleSource::Generator)).into(), start);
+        cache.insert_at(grid("api", Some(PuzzleSource::Api)).into(), start);
+
+        // The API board expires first and is never served
+        assert_eq!(cache.purge_expired_at(start + Duration::from_secs(30)), 1);
+        assert_eq!(cache.take_at(start + Duration::from_secs(30)).unwrap().difficulty, "local");
+
+        cache.insert_at(grid("late", None).into(), start);
+        assert!(cache.take_at(start + Duration::from_secs(61)).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_equivalent_boards() {
+        let mut cache = BoardCache::default();
+        let original = grid("original", None);
+        assert!(cache.insert(original.clone()));
+        assert!(!cache.insert(original.clone()));
+
+        let transform = Transform::random(&mut SmallRng::seed_from_u64(3));
+        let mut disguised = original.clone();
+        disguised.value = transform.apply(&Board::new(&original.value)).to_vec();
+        assert!(cache.seen(&disguised));
+        // Hashed up front, as callers holding the cache behind a lock do
+        assert!(!cache.insert(HashedBoard::new(disguised)));
+        assert_eq!(cache.len(), 1);
+
+        // Serving a board doesn't make it welcome again
+        cache.take().unwrap();
+        assert!(!cache.insert(original));
+        assert!(cache.insert(grid("other", None)));
+    }
+}
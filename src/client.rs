@@ -0,0 +1,123 @@
+//! Typed HTTP client for the `serve` endpoints (feature `client`).
+//!
+//! Mirrors the OpenAPI document served at `/openapi.json`; request and
+//! response bodies reuse the server's own types so the two can't drift apart.
+
+use crate::{
+    server::{BatchRequest, BatchResponse, ErrorBody},
+    Grid, Result, SudokuError,
+};
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// Client for a running `sudoku serve` instance
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    http: reqwest::Client,
+    api_key: Option<String>,
+}
+
+impl Client {
+    /// Creates a client for the server at `base_url`, e.g. `http://localhost:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Creates a client that sends requests through `http`
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self { base_url, http, api_key: None }
+    }
+
+    /// Sends `key` as a bearer token with every request
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// `GET /health`: returns true if the server is up
+    pub async fn health(&self) -> Result<bool> {
+        let response = self.request(reqwest::Method::GET, "/health").send().await?;
+        Ok(response.status().is_success())
+    }
+
+    /// `GET /puzzle`: fetches a puzzle
+    pub async fn puzzle(&self) -> Result<Grid> {
+        parse(self.request(reqwest::Method::GET, "/puzzle").send().await?).await
+    }
+
+    /// `GET /daily`: fetches the puzzle of the day, for today if `date` is `None`
+    pub async fn daily(&self, difficulty: &str, date: Option<NaiveDate>) -> Result<Grid> {
+        let mut query = vec![("difficulty", difficulty.to_string())];
+        if let Some(date) = date {
+            query.push(("date", date.format("%Y-%m-%d").to_string()));
+        }
+        parse(self.request(reqwest::Method::GET, "/daily").query(&query).send().await?).await
+    }
+
+    /// `POST /solve/batch`: solves `puzzles` (81-character strings) within `time_budget`
+    pub async fn solve_batch(&self, puzzles: &[String], time_budget: Option<Duration>) -> Result<BatchResponse> {
+        let request = BatchRequest {
+            puzzles: puzzles.to_vec(),
+            time_budget_ms: time_budget.map(|budget| budget.as_millis() as u64),
+        };
+        parse(self.request(reqwest::Method::POST, "/solve/batch").json(&request).send().await?).await
+    }
+
+    /// `GET /openapi.json`: fetches the server's OpenAPI document
+    pub async fn openapi(&self) -> Result<serde_json::Value> {
+        parse(self.request(reqwest::Method::GET, "/openapi.json").send().await?).await
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+}
+
+/// Decodes a JSON body, turning error responses into `SudokuError::ApiError`
+async fn parse<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let status = response.status();
+    let body = response.bytes().await?;
+    if status.is_success() {
+        return Ok(serde_json::from_slice(&body)?);
+    }
+    let message = serde_json::from_slice::<ErrorBody>(&body)
+        .map(|e| e.error)
+        .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+    Err(SudokuError::ApiError(format!("{}: {}", status, message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::handle;
+    use hyper::service::{make_service_fn, service_fn};
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn test_client_against_server() {
+        let make_service = make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle)) });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let client = Client::new(format!("http://{}/", server.local_addr()));
+        tokio::spawn(server);
+
+        assert!(client.health().await.unwrap());
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let grid = client.daily("easy", Some(date)).await.unwrap();
+        assert_eq!(grid, crate::generator::daily(date, "Easy").unwrap());
+
+        let puzzles = vec![crate::Board::new(&grid.value).to_line()];
+        let batch = client.solve_batch(&puzzles, Some(Duration::from_secs(5))).await.unwrap();
+        assert_eq!(batch.solved, 1);
+
+        let doc = client.openapi().await.unwrap();
+        assert!(doc["paths"]["/solve/batch"].is_object());
+    }
+}
@@ -0,0 +1,360 @@
+//! Settings for the binary from `sudoku.toml` and the environment.
+//!
+//! `Config::discover` reads the file named by `SUDOKU_CONFIG`, or
+//! `sudoku.toml` in the working directory if there is one, then applies the
+//! `SUDOKU_*` environment variables on top. Command-line flags override both.
+//! Every setting is optional; anything left out keeps its built-in default.
+//!
+//! ```toml
+//! [api]
+//! url = "https://sudoku-api.vercel.app/api/dosuku"
+//! timeout = "5s"
+//! min_request_interval = "100ms"
+//! warm_up = 100
+//! # Chaos runs only: fail 20% of request attempts and delay each by up to 1s
+//! # fault_rate = 0.2
+//! # fault_delay = "1s"
+//!
+//! [cache]
+//! capacity = 1000
+//! api_ttl = "3600s"
+//!
+//! [[generator.difficulties]]
+//! name = "Easy"
+//! weight = 1
+//! remove = { start = 30, end = 35 }
+//!
+//! [solver]
+//! timeout = "2s"
+//! threads = 4
+//!
+//! [serve]
+//! addr = "0.0.0.0:3000"
+//! rate_limit = 600
+//! api_keys = ["secret:60"]
+//! ```
+
+use crate::{
+    access::AccessPolicy,
+    api::{self, ApiClient, HttpProvider},
+    benchmark::parse_duration,
+    cache::CachePolicy,
+    generator::GeneratorOptions,
+    providers::fault::{FaultInjector, FaultPlan},
+    solver::Parallelism,
+    Result, SudokuError,
+};
+use serde::{Deserialize, Deserializer};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// File read from the working directory when `SUDOKU_CONFIG` isn't set
+pub const DEFAULT_PATH: &str = "sudoku.toml";
+
+/// Everything the binary can be configured with
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api: ApiConfig,
+    pub cache: CacheConfig,
+    /// Difficulty mix and removal ranges of locally generated puzzles
+    pub generator: Option<GeneratorOptions>,
+    pub solver: SolverConfig,
+    pub serve: ServeConfig,
+}
+
+/// Where puzzles are fetched from, and how often
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub url: Option<String>,
+    /// Time allowed for one HTTP request
+    #[serde(deserialize_with = "duration")]
+    pub timeout: Option<Duration>,
+    #[serde(deserialize_with = "duration")]
+    pub min_request_interval: Option<Duration>,
+    /// Boards generated whenever the cache is found empty. Unset, `serve`,
+    /// `watch` and `benchmark` generate `api::DEFAULT_WARM_UP` and other
+    /// commands none.
+    pub warm_up: Option<usize>,
+    /// For chaos runs: the chance, from 0 to 1, that a request attempt fails on purpose
+    pub fault_rate: Option<f64>,
+    /// For chaos runs: the longest delay added to a request attempt
+    #[serde(deserialize_with = "duration")]
+    pub fault_delay: Option<Duration>,
+}
+
+/// Overrides for `CachePolicy`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub capacity: Option<NonZeroUsize>,
+    #[serde(deserialize_with = "duration")]
+    pub ttl: Option<Duration>,
+    #[serde(deserialize_with = "duration")]
+    pub api_ttl: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolverConfig {
+    /// Longest a single solve may take
+    #[serde(deserialize_with = "duration")]
+    pub timeout: Option<Duration>,
+    /// Threads one solve may use, on a pool of its own; 1 searches on the
+    /// calling thread. Rayon's global pool is used when unset.
+    pub threads: Option<NonZeroUsize>,
+}
+
+impl SolverConfig {
+    /// The parallelism to install with `solver::set_parallelism`, if configured
+    pub fn parallelism(&self) -> Option<Parallelism> {
+        self.threads.map(|threads| Parallelism::threads(threads.get()))
+    }
+}
+
+/// Settings for `sudoku serve`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServeConfig {
+    /// Listen address
+    pub addr: Option<String>,
+    /// Requests per minute per caller
+    pub rate_limit: Option<u32>,
+    /// Keys given as `key` or `key:requests-per-minute`
+    pub api_keys: Vec<String>,
+}
+
+impl Config {
+    /// Parses a TOML document and checks it
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let config: Self = toml::from_str(toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a TOML file and checks it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Reads the file named by `SUDOKU_CONFIG`, else `sudoku.toml` if it
+    /// exists, and applies the environment variables on top
+    pub fn discover() -> Result<Self> {
+        let mut config = match std::env::var_os("SUDOKU_CONFIG") {
+            Some(path) => Self::load(path)?,
+            None if Path::new(DEFAULT_PATH).is_file() => Self::load(DEFAULT_PATH)?,
+            None => Self::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Overrides settings from environment variables, looked up with `var`:
+    /// `SUDOKU_API_URL`, `SUDOKU_API_TIMEOUT`, `SUDOKU_API_MIN_REQUEST_INTERVAL`,
+    /// `SUDOKU_API_WARM_UP`, `SUDOKU_API_FAULT_RATE`, `SUDOKU_API_FAULT_DELAY`,
+    /// `SUDOKU_CACHE_CAPACITY`, `SUDOKU_CACHE_TTL`,
+    /// `SUDOKU_CACHE_API_TTL`, `SUDOKU_SOLVER_TIMEOUT`, `SUDOKU_SOLVER_THREADS`,
+    /// `SUDOKU_SERVE_ADDR`, `SUDOKU_SERVE_RATE_LIMIT` and `SUDOKU_SERVE_API_KEYS`
+    /// (comma-separated)
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        let var = &var;
+        let duration = |s: &str| parse_duration(s).ok();
+        self.api.url = env(var, "SUDOKU_API_URL", |s| Some(s.to_string()))?.or(self.api.url.take());
+        self.api.timeout = env(var, "SUDOKU_API_TIMEOUT", duration)?.or(self.api.timeout);
+        self.api.min_request_interval = env(var, "SUDOKU_API_MIN_REQUEST_INTERVAL", duration)?.or(self.api.min_request_interval);
+        self.api.warm_up = env(var, "SUDOKU_API_WARM_UP", |s| s.parse().ok())?.or(self.api.warm_up);
+        self.api.fault_rate = env(var, "SUDOKU_API_FAULT_RATE", |s| s.parse().ok())?.or(self.api.fault_rate);
+        self.api.fault_delay = env(var, "SUDOKU_API_FAULT_DELAY", duration)?.or(self.api.fault_delay);
+        self.cache.capacity = env(var, "SUDOKU_CACHE_CAPACITY", |s| s.parse().ok())?.or(self.cache.capacity);
+        self.cache.ttl = env(var, "SUDOKU_CACHE_TTL", duration)?.or(self.cache.ttl);
+        self.cache.api_ttl = env(var, "SUDOKU_CACHE_API_TTL", duration)?.or(self.cache.api_ttl);
+        self.solver.timeout = env(var, "SUDOKU_SOLVER_TIMEOUT", duration)?.or(self.solver.timeout);
+        self.solver.threads = env(var, "SUDOKU_SOLVER_THREADS", |s| s.parse().ok())?.or(self.solver.threads);
+        self.serve.addr = env(var, "SUDOKU_SERVE_ADDR", |s| Some(s.to_string()))?.or(self.serve.addr.take());
+        self.serve.rate_limit = env(var, "SUDOKU_SERVE_RATE_LIMIT", |s| s.parse().ok())?.or(self.serve.rate_limit);
+        if let Some(keys) = var("SUDOKU_SERVE_API_KEYS") {
+            self.serve.api_keys = keys.split(',').map(str::trim).filter(|k| !k.is_empty()).map(String::from).collect();
+        }
+        self.validate()
+    }
+
+    /// Checks the generator options, fault rate and API keys
+    pub fn validate(&self) -> Result<()> {
+        if self.api.fault_rate.is_some_and(|rate| !(0.0..=1.0).contains(&rate)) {
+            return Err(SudokuError::ParseError("api.fault_rate must be between 0 and 1".to_string()));
+        }
+        if let Some(generator) = &self.generator {
+            generator.validate()?;
+        }
+        self.serve.access_policy().map(|_| ())
+    }
+
+    /// An API client with the configured provider, cache and generator
+    pub fn api_client(&self) -> Result<ApiClient> {
+        let mut provider = HttpProvider::new(
+            self.api.url.as_deref().unwrap_or(api::API_URL),
+            self.api.timeout.unwrap_or(api::REQUEST_TIMEOUT),
+        );
+        if let Some(plan) = self.api.fault_plan() {
+            warn!("Injecting faults into API requests: {:?}", plan);
+            provider = provider.with_faults(Arc::new(FaultInjector::new(plan)));
+        }
+        let mut client = ApiClient::new(provider).with_cache_policy(self.cache.policy());
+        if let Some(interval) = self.api.min_request_interval {
+            client = client.with_min_request_interval(interval);
+        }
+        if let Some(boards) = self.api.warm_up {
+            client = client.with_warm_up(boards);
+        }
+        match &self.generator {
+            Some(options) => client.with_generator_options(options),
+            None => Ok(client),
+        }
+    }
+}
+
+impl ApiConfig {
+    /// Random faults with a fresh seed, when a fault rate or delay is set
+    pub fn fault_plan(&self) -> Option<FaultPlan> {
+        if self.fault_rate.is_none() && self.fault_delay.is_none() {
+            return None;
+        }
+        Some(FaultPlan::Random {
+            failure_rate: self.fault_rate.unwrap_or(0.0),
+            max_delay: self.fault_delay.unwrap_or(Duration::ZERO),
+            seed: rand::random(),
+        })
+    }
+}
+
+impl CacheConfig {
+    /// The default policy with the configured settings applied
+    pub fn policy(&self) -> CachePolicy {
+        let default = CachePolicy::default();
+        CachePolicy {
+            capacity: self.capacity.unwrap_or(default.capacity),
+            ttl: self.ttl.or(default.ttl),
+            api_ttl: self.api_ttl.or(default.api_ttl),
+        }
+    }
+}
+
+impl ServeConfig {
+    /// Access policy with the configured rate limit and keys
+    pub fn access_policy(&self) -> Result<AccessPolicy> {
+        let mut policy = AccessPolicy::open();
+        if let Some(per_minute) = self.rate_limit {
+            policy = policy.with_rate_limit(per_minute);
+        }
+        for spec in &self.api_keys {
+            let (key, per_minute) = AccessPolicy::parse_key(spec)?;
+            policy = policy.with_key(key, per_minute);
+        }
+        Ok(policy)
+    }
+}
+
+/// Parses the environment variable `name` with `parse`, if it is set
+fn env<T>(var: &impl Fn(&str) -> Option<String>, name: &str, parse: impl FnOnce(&str) -> Option<T>) -> Result<Option<T>> {
+    var(name)
+        .map(|value| parse(&value).ok_or_else(|| SudokuError::ParseError(format!("Invalid {}: '{}'", name, value))))
+        .transpose()
+}
+
+/// Deserializes a duration written like `250ms` or `1.5s`
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Duration>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_duration(&s).map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(
+            r#"
+            [api]
+            timeout = "2s"
+            warm_up = 0
+
+            [cache]
+            capacity = 50
+            ttl = "90s"
+
+            [[generator.difficulties]]
+            name = "Hard"
+            weight = 1
+            remove = { start = 51, end = 60 }
+
+            [solver]
+            timeout = "250ms"
+
+            [serve]
+            rate_limit = 10
+            api_keys = ["secret:60"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.api.timeout, Some(Duration::from_secs(2)));
+        assert_eq!(config.api.warm_up, Some(0));
+        assert_eq!(config.api.url, None);
+        assert!(config.api.fault_plan().is_none());
+        assert_eq!(config.cache.policy().capacity.get(), 50);
+        assert_eq!(config.cache.policy().ttl, Some(Duration::from_secs(90)));
+        assert_eq!(config.cache.policy().api_ttl, CachePolicy::default().api_ttl);
+        assert_eq!(config.generator.unwrap().difficulties[0].name, "Hard");
+        assert_eq!(config.solver.timeout, Some(Duration::from_millis(250)));
+        assert!(config.serve.access_policy().unwrap().requires_key());
+
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_invalid_toml() {
+        for toml in [
+            "[api]\nretries = 3",
+            "[solver]\ntimeout = \"soon\"",
+            "[cache]\ncapacity = 0",
+            "[api]\nfault_rate = 1.5",
+            "[serve]\napi_keys = [\":60\"]",
+            "[[generator.difficulties]]\nname = \"Easy\"\nweight = 0\nremove = { start = 30, end = 35 }",
+        ] {
+            assert!(Config::from_toml(toml).is_err(), "accepted {:?}", toml);
+        }
+        let err = Config::from_toml("[solver\n").unwrap_err();
+        assert!(matches!(err, SudokuError::Toml(_)));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let mut config = Config::from_toml("[api]\nwarm_up = 10\nurl = \"http://file\"\n[serve]\napi_keys = [\"a\"]").unwrap();
+        let vars = HashMap::from([
+            ("SUDOKU_API_WARM_UP", "3"),
+            ("SUDOKU_SOLVER_TIMEOUT", "1s"),
+            ("SUDOKU_SOLVER_THREADS", "1"),
+            ("SUDOKU_SERVE_API_KEYS", "b, c:5"),
+            ("SUDOKU_API_FAULT_RATE", "0.25"),
+        ]);
+        config.apply_env(|name| vars.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(config.api.warm_up, Some(3));
+        assert_eq!(config.api.url.as_deref(), Some("http://file"));
+        assert_eq!(config.solver.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(config.solver.parallelism(), Some(Parallelism::Sequential));
+        assert_eq!(config.serve.api_keys, ["b", "c:5"]);
+        let plan = config.api.fault_plan();
+        assert!(matches!(plan, Some(FaultPlan::Random { failure_rate, max_delay, .. })
+            if failure_rate == 0.25 && max_delay == Duration::ZERO));
+
+        let err = config.apply_env(|name| (name == "SUDOKU_CACHE_CAPACITY").then(|| "lots".to_string())).unwrap_err();
+        assert!(err.to_string().contains("SUDOKU_CACHE_CAPACITY"));
+    }
+}
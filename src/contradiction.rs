@@ -0,0 +1,289 @@
+//! Contradiction traces for unsolvable puzzles.
+//!
+//! Runs the logical solver until the puzzle breaks (a cell runs out of
+//! candidates, a digit has nowhere to go in a unit, or a unit repeats a digit),
+//! then walks back through the steps to keep only those the contradiction
+//! actually depends on.
+
+use crate::{
+    explain,
+    logic::{LogicSolver, SolveStep, Technique, Unit, UNITS},
+    solver::count_solutions_up_to,
+    Board,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The way in which a puzzle breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Contradiction {
+    /// An empty cell has no candidates left
+    NoCandidates { row: usize, col: usize },
+    /// A digit cannot be placed anywhere in a unit
+    NoPlaceForDigit { unit: Unit, value: u8 },
+    /// A unit contains the same digit twice
+    DuplicateDigit { unit: Unit, value: u8 },
+    /// Logic stalls without a contradiction, but exhaustive search finds no solution
+    SearchExhausted,
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Contradiction::NoCandidates { row, col } => {
+                write!(f, "r{}c{} has no candidates left", row + 1, col + 1)
+            }
+            Contradiction::NoPlaceForDigit { unit, value } => {
+                write!(f, "{} has no place left in {}", value, unit)
+            }
+            Contradiction::DuplicateDigit { unit, value } => {
+                write!(f, "{} contains {} twice", unit, value)
+            }
+            Contradiction::SearchExhausted => {
+                write!(f, "logic stalls here, and every trial placement leads to a dead end")
+            }
+        }
+    }
+}
+
+/// The deductions leading to a contradiction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContradictionTrace {
+    /// Steps the contradiction depends on, in solving order
+    pub steps: Vec<SolveStep>,
+    pub contradiction: Contradiction,
+}
+
+impl fmt::Display for ContradictionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in explain::explain_all(&self.steps) {
+            writeln!(f, "{}", line)?;
+        }
+        write!(f, "Contradiction: {}", self.contradiction)
+    }
+}
+
+/// Records which step removed each candidate. `None` means it was never a
+/// candidate to begin with, given the puzzle's givens.
+struct Provenance {
+    removed_by: [[Option<usize>; 9]; 81],
+    filled_by: [Option<usize>; 81],
+}
+
+impl Provenance {
+    fn record(&mut self, before: &LogicSolver, after: &LogicSolver, step_idx: usize) {
+        for cell in 0..81 {
+            let (row, col) = (cell / 9, cell % 9);
+            let removed = before.candidates(row, col).0 & !after.candidates(row, col).0;
+            for value in 1..=9u8 {
+                if removed & (1 << (value - 1)) != 0 {
+                    self.removed_by[cell][value as usize - 1] = Some(step_idx);
+                }
+            }
+            if before.board().cells[cell] == 0 && after.board().cells[cell] != 0 {
+                self.filled_by[cell] = Some(step_idx);
+            }
+        }
+    }
+
+    fn removal(&self, cell: usize, value: u8) -> Option<usize> {
+        self.removed_by[cell][value as usize - 1]
+    }
+
+    /// Steps that removed `value` from every cell of `cells` outside `exclude`
+    fn removals_in(&self, cells: &[usize], exclude: &[usize], value: u8) -> Vec<usize> {
+        cells
+            .iter()
+            .filter(|cell| !exclude.contains(cell))
+            .filter_map(|&cell| self.removal(cell, value))
+            .collect()
+    }
+
+    /// Steps whose results the given step relies on
+    fn premises(&self, step: &SolveStep) -> Vec<usize> {
+        let pattern: Vec<usize> = step.cells.iter().map(|&(r, c)| r * 9 + c).collect();
+        match step.technique {
+            Technique::NakedSingle => step
+                .placements
+                .iter()
+                .flat_map(|p| {
+                    let cell = p.row * 9 + p.col;
+                    (1..=9u8).filter(move |&v| v != p.value).filter_map(move |v| self.removal(cell, v))
+                })
+                .collect(),
+            Technique::HiddenSingle => match (step.unit, step.placements.first()) {
+                (Some(unit), Some(p)) => self.removals_in(&UNITS[unit.index()], &pattern, p.value),
+                _ => Vec::new(),
+            },
+            Technique::LockedCandidates => match (step.unit, step.eliminations.first()) {
+                (Some(unit), Some(e)) => self.removals_in(&UNITS[unit.index()], &pattern, e.value),
+                _ => Vec::new(),
+            },
+            // Every other digit was removed from the cells
+            Technique::NakedPair
+            | Technique::NakedTriple
+            | Technique::RemotePair
+            | Technique::XYWing
+            | Technique::XYZWing => pattern
+                .iter()
+                .flat_map(|&cell| {
+                    (1..=9u8)
+                        .filter(|v| !step.digits.contains(v))
+                        .filter_map(move |v| self.removal(cell, v))
+                })
+                .collect(),
+            // The digits were removed from the rest of the unit
+            Technique::HiddenPair | Technique::HiddenTriple | Technique::HiddenQuad => match step.unit {
+                Some(unit) => step
+                    .digits
+                    .iter()
+                    .flat_map(|&value| self.removals_in(&UNITS[unit.index()], &pattern, value))
+                    .collect(),
+                None => Vec::new(),
+            },
+            Technique::XWing => {
+                let Some(e) = step.eliminations.first() else {
+                    return Vec::new();
+                };
+                // Eliminations lie in the cover lines; the base lines are the other orientation
+                let base_is_rows = step.cells.iter().any(|&(_, c)| c == e.col);
+                let mut lines: Vec<usize> = step
+                    .cells
+                    .iter()
+                    .map(|&(r, c)| if base_is_rows { r } else { 9 + c })
+                    .collect();
+                lines.dedup();
+                lines
+                    .into_iter()
+                    .flat_map(|line| self.removals_in(&UNITS[line], &pattern, e.value))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Finds a contradiction in the solver's current state, with the steps it depends on
+fn find_contradiction(solver: &LogicSolver, provenance: &Provenance) -> Option<(Contradiction, Vec<usize>)> {
+    let cells = &solver.board().cells;
+
+    for (unit_idx, unit) in UNITS.iter().enumerate() {
+        for value in 1..=9u8 {
+            let holders: Vec<usize> = unit.iter().copied().filter(|&c| cells[c] == value).collect();
+            if holders.len() > 1 {
+                let deps = holders.iter().filter_map(|&c| provenance.filled_by[c]).collect();
+                return Some((Contradiction::DuplicateDigit { unit: Unit::from_index(unit_idx), value }, deps));
+            }
+        }
+    }
+
+    for cell in 0..81 {
+        if cells[cell] == 0 && solver.candidates(cell / 9, cell % 9).is_empty() {
+            let deps = (1..=9u8).filter_map(|v| provenance.removal(cell, v)).collect();
+            return Some((Contradiction::NoCandidates { row: cell / 9, col: cell % 9 }, deps));
+        }
+    }
+
+    for (unit_idx, unit) in UNITS.iter().enumerate() {
+        for value in 1..=9u8 {
+            let placed = unit.iter().any(|&c| cells[c] == value);
+            let has_spot = unit.iter().any(|&c| solver.candidates(c / 9, c % 9).has_candidate(value));
+            if !placed && !has_spot {
+                let deps = provenance.removals_in(unit, &[], value);
+                return Some((Contradiction::NoPlaceForDigit { unit: Unit::from_index(unit_idx), value }, deps));
+            }
+        }
+    }
+
+    None
+}
+
+/// Explains why `board` has no solution.
+///
+/// Returns `None` if the board is solvable. Otherwise returns the chain of
+/// logical steps that leads to a contradiction, trimmed to the steps the
+/// contradiction depends on. When logic stalls before reaching one, the trace
+/// holds every step taken and ends in `Contradiction::SearchExhausted`.
+pub fn trace(board: &Board) -> Option<ContradictionTrace> {
+    if count_solutions_up_to(board, 1) > 0 {
+        return None;
+    }
+
+    let mut solver = LogicSolver::new(board);
+    let mut provenance = Provenance { removed_by: [[None; 9]; 81], filled_by: [None; 81] };
+    let mut steps = Vec::new();
+
+    let (contradiction, deps) = loop {
+        if let Some(found) = find_contradiction(&solver, &provenance) {
+            break found;
+        }
+        match solver.find_step() {
+            Some(step) => {
+                let before = solver.clone();
+                solver.apply(&step);
+                provenance.record(&before, &solver, steps.len());
+                steps.push(step);
+            }
+            None => return Some(ContradictionTrace { steps, contradiction: Contradiction::SearchExhausted }),
+        }
+    };
+
+    // Walk the dependency graph back from the contradiction
+    let mut needed = vec![false; steps.len()];
+    let mut pending = deps;
+    while let Some(idx) = pending.pop() {
+        if !needed[idx] {
+            needed[idx] = true;
+            pending.extend(provenance.premises(&steps[idx]));
+        }
+    }
+
+    let steps = steps.into_iter().zip(needed).filter(|(_, keep)| *keep).map(|(step, _)| step).collect();
+    Some(ContradictionTrace { steps, contradiction })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solvable_board_has_no_trace() {
+        let board = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        assert!(trace(&board).is_none());
+    }
+
+    #[test]
+    fn test_givens_conflict() {
+        let mut board = Board::empty();
+        for col in 0..8 {
+            board.set(0, col, col as u8 + 1);
+        }
+        board.set(1, 8, 9);
+        let trace = trace(&board).unwrap();
+        assert!(trace.steps.is_empty());
+        assert_eq!(trace.contradiction, Contradiction::NoCandidates { row: 0, col: 8 });
+    }
+
+    #[test]
+    fn test_trace_keeps_only_needed_steps() {
+        // A wrong 2 in r1c3 breaks an otherwise easy puzzle
+        let board = Board::parse(
+            "532.7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let trace = trace(&board).unwrap();
+        let all_steps = LogicSolver::new(&board).solve();
+        assert!(!trace.steps.is_empty());
+        assert!(trace.steps.len() < all_steps.len());
+
+        // The trimmed chain alone still reaches the contradiction
+        let mut solver = LogicSolver::new(&board);
+        for step in &trace.steps {
+            solver.apply(step);
+        }
+        assert!(solver.has_contradiction());
+        assert!(trace.to_string().contains("Contradiction:"));
+    }
+}
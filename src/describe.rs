@@ -0,0 +1,101 @@
+//! Spoken-text descriptions of boards for screen readers.
+//!
+//! Screen readers turn a box-drawing grid into a stream of line characters.
+//! `Board::describe` reads a board out the way a person would over the
+//! phone instead: a summary, each row left to right, then each box, with
+//! runs of empty cells counted rather than read one by one.
+
+use crate::Board;
+
+const BOX_NAMES: [&str; 9] = [
+    "top left",
+    "top middle",
+    "top right",
+    "middle left",
+    "centre",
+    "middle right",
+    "bottom left",
+    "bottom middle",
+    "bottom right",
+];
+
+impl Board {
+    /// A description for screen readers, one sentence per line: how many
+    /// cells are filled, then every row and every box
+    pub fn describe(&self) -> String {
+        let givens = self.cells.iter().filter(|&&v| v != 0).count();
+        let summary = match givens {
+            0 => return "Empty grid.\n".to_string(),
+            81 => "Completed grid, all 81 cells filled.".to_string(),
+            n => format!("Puzzle with {} givens and {} empty cells.", n, 81 - n),
+        };
+        let mut out = summary + "\n";
+        for row in 0..9 {
+            let cells = read_cells((0..9).map(|col| self.get(row, col)));
+            out += &format!("Row {}: {}.\n", row + 1, cells);
+        }
+        for (b, name) in BOX_NAMES.iter().enumerate() {
+            let cells = read_cells((0..9).map(|i| self.get(b / 3 * 3 + i / 3, b % 3 * 3 + i % 3)));
+            out += &format!("Box {}, {}: {}.\n", b + 1, name, cells);
+        }
+        out
+    }
+}
+
+/// Reads digits in order, counting runs of empty cells: "5, 3, 2 blanks, 7, 4 blanks"
+fn read_cells(values: impl Iterator<Item = u8>) -> String {
+    let mut parts = Vec::new();
+    let mut blanks = 0;
+    let count_blanks = |parts: &mut Vec<String>, blanks: &mut usize| {
+        match *blanks {
+            0 => {}
+            1 => parts.push("blank".to_string()),
+            9 => parts.push("all blank".to_string()),
+            n => parts.push(format!("{} blanks", n)),
+        }
+        *blanks = 0;
+    };
+    for value in values {
+        if value == 0 {
+            blanks += 1;
+        } else {
+            count_blanks(&mut parts, &mut blanks);
+            parts.push(value.to_string());
+        }
+    }
+    count_blanks(&mut parts, &mut blanks);
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    #[test]
+    fn test_describe() {
+        let easy = fixture::get("easy").unwrap().board();
+        let description = easy.describe();
+        let lines: Vec<&str> = description.lines().collect();
+        assert_eq!(lines.len(), 19);
+        let givens = easy.cells.iter().filter(|&&v| v != 0).count();
+        assert_eq!(lines[0], format!("Puzzle with {} givens and {} empty cells.", givens, 81 - givens));
+        assert!(lines[1].starts_with("Row 1: ") && lines[10].starts_with("Box 1, top left: "));
+        assert_eq!(description.matches('.').count(), 19);
+
+        let mut board = Board::empty();
+        assert_eq!(board.describe(), "Empty grid.\n");
+        board.set(0, 0, 5);
+        board.set(0, 1, 3);
+        board.set(0, 4, 7);
+        board.set(4, 4, 9);
+        let description = board.describe();
+        assert!(description.contains("Row 1: 5, 3, 2 blanks, 7, 4 blanks.\n"));
+        assert!(description.contains("Row 2: all blank.\n"));
+        assert!(description.contains("Box 2, top middle: blank, 7, 7 blanks.\n"));
+        assert!(description.contains("Box 5, centre: 4 blanks, 9, 4 blanks.\n"));
+
+        let solved = fixture::get("solved").unwrap().board().describe();
+        assert!(solved.starts_with("Completed grid") && !solved.contains("blank"));
+    }
+}
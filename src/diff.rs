@@ -0,0 +1,115 @@
+//! Comparing two boards cell by cell.
+//!
+//! `Board::diff` lists the cells that differ and `SideBySide` prints two
+//! boards next to each other with those cells marked, for reports where a
+//! solution disagrees with a reference or a test's expected board.
+
+use crate::Board;
+use std::fmt;
+
+/// A cell that differs between two boards: row, column, and the value on
+/// each board (0 for empty)
+pub type CellDiff = (usize, usize, u8, u8);
+
+impl Board {
+    /// Cells where `self` and `other` differ, in reading order
+    pub fn diff(&self, other: &Board) -> Vec<CellDiff> {
+        self.cells
+            .iter()
+            .zip(&other.cells)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(cell, (&a, &b))| (cell / 9, cell % 9, a, b))
+            .collect()
+    }
+}
+
+/// Two boards printed side by side, with a `*` before every cell that differs
+#[derive(Debug, Clone, Copy)]
+pub struct SideBySide<'a> {
+    left: &'a Board,
+    right: &'a Board,
+    labels: Option<(&'a str, &'a str)>,
+}
+
+/// Gap between the two grids
+const GAP: &str = "    ";
+
+/// Width of one grid in characters
+const WIDTH: usize = 25;
+
+impl<'a> SideBySide<'a> {
+    pub fn new(left: &'a Board, right: &'a Board) -> Self {
+        Self { left, right, labels: None }
+    }
+
+    /// Prints a heading above each grid
+    pub fn with_labels(mut self, left: &'a str, right: &'a str) -> Self {
+        self.labels = Some((left, right));
+        self
+    }
+
+    /// The grid lines for `board`, marking cells that differ from `other`
+    fn lines(board: &Board, other: &Board) -> Vec<String> {
+        let mut lines = vec!["┌───────┬───────┬───────┐".to_string()];
+        for row in 0..9 {
+            let mut line = String::from("│");
+            for col in 0..9 {
+                let value = board.get(row, col);
+                line.push(if value == other.get(row, col) { ' ' } else { '*' });
+                line.push(if value == 0 { '·' } else { char::from(b'0' + value) });
+                if col % 3 == 2 {
+                    line.push_str(" │");
+                }
+            }
+            lines.push(line);
+            if row % 3 == 2 && row < 8 {
+                lines.push("├───────┼───────┼───────┤".to_string());
+            }
+        }
+        lines.push("└───────┴───────┴───────┘".to_string());
+        lines
+    }
+}
+
+impl fmt::Display for SideBySide<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((left, right)) = self.labels {
+            writeln!(f, "{:<width$}{}{}", left, GAP, right, width = WIDTH)?;
+        }
+        let left = Self::lines(self.left, self.right);
+        let right = Self::lines(self.right, self.left);
+        for (left, right) in left.iter().zip(&right) {
+            writeln!(f, "{}{}{}", left, GAP, right)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    #[test]
+    fn test_diff() {
+        let solved = fixture::get("solved").unwrap().board();
+        assert!(solved.diff(&solved).is_empty());
+
+        let mut changed = solved.clone();
+        changed.set(0, 0, 0);
+        changed.set(4, 7, solved.get(4, 8));
+        let diff = solved.diff(&changed);
+        assert_eq!(diff, vec![(0, 0, solved.get(0, 0), 0), (4, 7, solved.get(4, 7), solved.get(4, 8))]);
+        assert_eq!(changed.diff(&solved)[0], (0, 0, 0, solved.get(0, 0)));
+
+        let printed = SideBySide::new(&solved, &changed).with_labels("Ours", "Theirs").to_string();
+        let lines: Vec<&str> = printed.lines().collect();
+        assert_eq!(lines.len(), 14);
+        assert!(lines[0].starts_with("Ours ") && lines[0].ends_with("    Theirs"));
+        assert_eq!(lines[2].matches('*').count(), 2);
+        assert!(lines[2].contains("│*·"));
+        assert_eq!(printed.matches('*').count(), 4);
+        assert!(lines.iter().skip(1).all(|line| line.chars().count() == WIDTH * 2 + GAP.len()));
+    }
+}
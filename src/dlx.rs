@@ -0,0 +1,238 @@
+//! Dancing-links (Algorithm X) solver.
+//!
+//! Sudoku as exact cover: 324 constraints (each cell filled, each digit once
+//! per row, column and box) and 729 candidate placements covering four
+//! constraints each. It shares no code with the backtracking solvers, which
+//! makes it a useful cross-check for them.
+
+use crate::Board;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Constraint columns, plus the root header at index 0
+const COLUMNS: usize = 324;
+
+/// Toroidal doubly linked node arrays; nodes `1..=COLUMNS` are the column headers
+struct Links {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// Column header of each node
+    column: Vec<usize>,
+    /// Placement (`cell * 9 + digit - 1`) of each node
+    placement: Vec<usize>,
+    /// Nodes left in each column
+    size: Vec<usize>,
+    /// First node of each placement's row
+    row_start: Vec<usize>,
+    /// Placements tried by `search`
+    nodes: u64,
+}
+
+impl Links {
+    fn new() -> Self {
+        let nodes = COLUMNS + 1 + 729 * 4;
+        let mut links = Self {
+            left: Vec::with_capacity(nodes),
+            right: Vec::with_capacity(nodes),
+            up: Vec::with_capacity(nodes),
+            down: Vec::with_capacity(nodes),
+            column: Vec::with_capacity(nodes),
+            placement: Vec::with_capacity(nodes),
+            size: vec![0; COLUMNS + 1],
+            row_start: Vec::with_capacity(729),
+            nodes: 0,
+        };
+        for header in 0..=COLUMNS {
+            links.left.push(if header == 0 { COLUMNS } else { header - 1 });
+            links.right.push(if header == COLUMNS { 0 } else { header + 1 });
+            links.up.push(header);
+            links.down.push(header);
+            links.column.push(header);
+            links.placement.push(usize::MAX);
+        }
+        for placement in 0..729 {
+            let (cell, digit) = (placement / 9, placement % 9);
+            let (row, col) = (cell / 9, cell % 9);
+            let constraints = [
+                cell,
+                81 + row * 9 + digit,
+                162 + col * 9 + digit,
+                243 + Board::get_box_index(row, col) * 9 + digit,
+            ];
+            let first = links.left.len();
+            links.row_start.push(first);
+            for (i, &constraint) in constraints.iter().enumerate() {
+                let node = first + i;
+                let header = constraint + 1;
+                links.left.push(if i == 0 { first + 3 } else { node - 1 });
+                links.right.push(if i == 3 { first } else { node + 1 });
+                links.up.push(links.up[header]);
+                links.down.push(header);
+                let above = links.up[header];
+                links.down[above] = node;
+                links.up[header] = node;
+                links.column.push(header);
+                links.placement.push(placement);
+                links.size[header] += 1;
+            }
+        }
+        links
+    }
+
+    fn cover(&mut self, header: usize) {
+        let (l, r) = (self.left[header], self.right[header]);
+        self.right[l] = r;
+        self.left[r] = l;
+        let mut row = self.down[header];
+        while row != header {
+            let mut node = self.right[row];
+            while node != row {
+                let (u, d) = (self.up[node], self.down[node]);
+                self.down[u] = d;
+                self.up[d] = u;
+                self.size[self.column[node]] -= 1;
+                node = self.right[node];
+            }
+            row = self.down[row];
+        }
+    }
+
+    fn uncover(&mut self, header: usize) {
+        let mut row = self.up[header];
+        while row != header {
+            let mut node = self.left[row];
+            while node != row {
+                let (u, d) = (self.up[node], self.down[node]);
+                self.down[u] = node;
+                self.up[d] = node;
+                self.size[self.column[node]] += 1;
+                node = self.left[node];
+            }
+            row = self.up[row];
+        }
+        let (l, r) = (self.left[header], self.right[header]);
+        self.right[l] = header;
+        self.left[r] = header;
+    }
+
+    /// Covers the other columns of `row`'s placement after its own column was covered
+    fn select(&mut self, row: usize) {
+        let mut node = self.right[row];
+        while node != row {
+            self.cover(self.column[node]);
+            node = self.right[node];
+        }
+    }
+
+    fn deselect(&mut self, row: usize) {
+        let mut node = self.left[row];
+        while node != row {
+            self.uncover(self.column[node]);
+            node = self.left[node];
+        }
+    }
+
+    /// Returns false if a constraint the placement needs is already satisfied
+    fn is_available(&self, row: usize) -> bool {
+        let mut node = row;
+        loop {
+            let header = self.column[node];
+            if self.right[self.left[header]] != header {
+                return false;
+            }
+            node = self.right[node];
+            if node == row {
+                return true;
+            }
+        }
+    }
+
+    fn search(&mut self, chosen: &mut Vec<usize>, found: &mut Vec<Board>, givens: &Board, limit: usize, stop: &AtomicBool) {
+        if found.len() >= limit || stop.load(Ordering::Relaxed) {
+            return;
+        }
+        if self.right[0] == 0 {
+            let mut board = givens.clone();
+            for &placement in chosen.iter() {
+                board.cells[placement / 9] = (placement % 9) as u8 + 1;
+            }
+            found.push(board);
+            return;
+        }
+
+        // Column with the fewest remaining placements
+        let mut header = self.right[0];
+        let mut best = header;
+        while header != 0 {
+            if self.size[header] < self.size[best] {
+                best = header;
+            }
+            header = self.right[header];
+        }
+        if self.size[best] == 0 {
+            return;
+        }
+
+        self.cover(best);
+        let mut row = self.down[best];
+        while row != best && found.len() < limit {
+            self.nodes += 1;
+            chosen.push(self.placement[row]);
+            self.select(row);
+            self.search(chosen, found, givens, limit, stop);
+            self.deselect(row);
+            chosen.pop();
+            row = self.down[row];
+        }
+        self.uncover(best);
+    }
+}
+
+/// Collects up to `limit` solutions of `board`; none if its givens conflict
+pub fn solve(board: &Board, limit: usize) -> Vec<Board> {
+    solve_until(board, limit, &AtomicBool::new(false)).0
+}
+
+/// Like `solve`, but gives up with whatever it has found once `stop` is set.
+/// Also returns the number of placements tried.
+pub(crate) fn solve_until(board: &Board, limit: usize, stop: &AtomicBool) -> (Vec<Board>, u64) {
+    let mut links = Links::new();
+    for (cell, &value) in board.cells.iter().enumerate() {
+        if value == 0 {
+            continue;
+        }
+        let row = links.row_start[cell * 9 + value as usize - 1];
+        if !links.is_available(row) {
+            return (Vec::new(), 0);
+        }
+        links.cover(links.column[row]);
+        links.select(row);
+    }
+
+    let mut found = Vec::new();
+    links.search(&mut Vec::new(), &mut found, board, limit, stop);
+    (found, links.nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture::{FixtureKind, FIXTURES};
+
+    #[test]
+    fn test_dlx_fixtures() {
+        for fixture in FIXTURES {
+            let solutions = solve(&fixture.board(), 2);
+            let expected = match fixture.kind {
+                FixtureKind::Unique | FixtureKind::Solved => 1,
+                FixtureKind::MultipleSolutions => 2,
+                FixtureKind::NoSolution | FixtureKind::Conflicting => 0,
+            };
+            assert_eq!(solutions.len(), expected, "{}", fixture.name);
+            if let Some(grid) = fixture.grid() {
+                assert_eq!(solutions[0], grid.solution_board().unwrap(), "{}", fixture.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Plain-English explanations of logical solve steps.
+//!
+//! Turns the `SolveStep`s produced by `LogicSolver` into sentences such as
+//! "r4c7 must be 6: it is the only cell in box 6 that can hold 6".
+
+use crate::logic::{SolveStep, Technique, Unit};
+use crate::Board;
+
+/// Formats a cell as `r1c1`
+pub(crate) fn cell_name(row: usize, col: usize) -> String {
+    format!("r{}c{}", row + 1, col + 1)
+}
+
+pub(crate) fn cell_list(cells: impl IntoIterator<Item = (usize, usize)>) -> String {
+    cells
+        .into_iter()
+        .map(|(row, col)| cell_name(row, col))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lists up to two distinct line numbers as "2 and 5"
+fn line_pair(mut lines: Vec<usize>) -> String {
+    lines.sort_unstable();
+    lines.dedup();
+    lines.iter().map(|l| (l + 1).to_string()).collect::<Vec<_>>().join(" and ")
+}
+
+/// Lists digits as "4", "4 and 7" or "1, 4 and 7"
+fn digit_list(digits: &[u8]) -> String {
+    let names: Vec<String> = digits.iter().map(u8::to_string).collect();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => names.concat(),
+    }
+}
+
+/// Describes a single step as a sentence
+pub fn explain(step: &SolveStep) -> String {
+    match step.technique {
+        Technique::NakedSingle | Technique::HiddenSingle => {
+            let Some(p) = step.placements.first() else {
+                return step.technique.to_string();
+            };
+            let cell = cell_name(p.row, p.col);
+            match step.unit {
+                Some(unit) if step.technique == Technique::HiddenSingle => format!(
+                    "{} must be {}: it is the only cell in {} that can hold {}",
+                    cell, p.value, unit, p.value
+                ),
+                _ => format!(
+                    "{} must be {}: every other digit already appears in its row, column, or box",
+                    cell, p.value
+                ),
+            }
+        }
+        Technique::LockedCandidates => {
+            let Some(value) = step.eliminations.first().map(|e| e.value) else {
+                return step.technique.to_string();
+            };
+            let (row, col) = step.cells[0];
+            let target = match step.unit {
+                // Pointing: the digit is confined to one line within the box
+                Some(Unit::Box(_)) if step.cells.iter().all(|&(r, _)| r == row) => Unit::Row(row),
+                Some(Unit::Box(_)) => Unit::Column(col),
+                // Claiming: the digit is confined to one box within the line
+                _ => Unit::Box(Board::get_box_index(row, col)),
+            };
+            let unit = step.unit.map_or_else(String::new, |u| u.to_string());
+            format!(
+                "In {}, {} can only go in {} ({}), so it can be removed from the rest of {}: {}",
+                unit,
+                value,
+                target,
+                cell_list(step.cells.iter().copied()),
+                target,
+                cell_list(step.eliminations.iter().map(|e| (e.row, e.col)))
+            )
+        }
+        Technique::NakedPair | Technique::NakedTriple => {
+            let unit = step.unit.map_or_else(String::new, |u| u.to_string());
+            let removals: Vec<String> = step
+                .eliminations
+                .iter()
+                .map(|e| format!("{} from {}", e.value, cell_name(e.row, e.col)))
+                .collect();
+            format!(
+                "In {}, {} can only hold {}, so those digits can be removed from the rest of {}: remove {}",
+                unit,
+                cell_list(step.cells.iter().copied()),
+                digit_list(&step.digits),
+                unit,
+                removals.join(", ")
+            )
+        }
+        Technique::HiddenPair | Technique::HiddenTriple | Technique::HiddenQuad => {
+            let unit = step.unit.map_or_else(String::new, |u| u.to_string());
+            let removals: Vec<String> = step
+                .eliminations
+                .iter()
+                .map(|e| format!("{} from {}", e.value, cell_name(e.row, e.col)))
+                .collect();
+            format!(
+                "In {}, {} can only go in {}, so those cells can't hold anything else: remove {}",
+                unit,
+                digit_list(&step.digits),
+                cell_list(step.cells.iter().copied()),
+                removals.join(", ")
+            )
+        }
+        Technique::XYWing | Technique::XYZWing => {
+            let (Some(&pivot), Some(&z)) = (step.cells.first(), step.digits.last()) else {
+                return step.technique.to_string();
+            };
+            format!(
+                "Whatever {} turns out to be, one of {} must be {}, so no cell seeing them can hold {}: {}",
+                cell_name(pivot.0, pivot.1),
+                cell_list(step.cells[1..].iter().copied()),
+                z,
+                z,
+                cell_list(step.eliminations.iter().map(|e| (e.row, e.col)))
+            )
+        }
+        Technique::RemotePair => {
+            let (Some(&start), Some(&end)) = (step.cells.first(), step.cells.last()) else {
+                return step.technique.to_string();
+            };
+            let removals: Vec<String> = step
+                .eliminations
+                .iter()
+                .map(|e| format!("{} from {}", e.value, cell_name(e.row, e.col)))
+                .collect();
+            format!(
+                "{} each hold only {} and see one another in turn, so {} and {} are different: remove {}",
+                cell_list(step.cells.iter().copied()),
+                digit_list(&step.digits),
+                cell_name(start.0, start.1),
+                cell_name(end.0, end.1),
+                removals.join(", ")
+            )
+        }
+        Technique::XWing => {
+            let Some(value) = step.eliminations.first().map(|e| e.value) else {
+                return step.technique.to_string();
+            };
+            let rows: Vec<usize> = step.cells.iter().map(|&(r, _)| r).collect();
+            let cols: Vec<usize> = step.cells.iter().map(|&(_, c)| c).collect();
+            // Eliminations lie in the cover lines, which tells us the orientation
+            let (base, cover) = if cols.contains(&step.eliminations[0].col) {
+                (format!("rows {}", line_pair(rows)), format!("columns {}", line_pair(cols)))
+            } else {
+                (format!("columns {}", line_pair(cols)), format!("rows {}", line_pair(rows)))
+            };
+            format!(
+                "In {}, {} can only go in {} ({}), so it can be removed from the rest of {}: {}",
+                base,
+                value,
+                cover,
+                cell_list(step.cells.iter().copied()),
+                cover,
+                cell_list(step.eliminations.iter().map(|e| (e.row, e.col)))
+            )
+        }
+    }
+}
+
+/// Describes a sequence of steps, one numbered sentence per step
+pub fn explain_all(steps: &[SolveStep]) -> Vec<String> {
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| format!("{}. {}", i + 1, explain(step)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::{LogicSolver, Placement};
+
+    #[test]
+    fn test_explain_hidden_single() {
+        let step = SolveStep {
+            technique: Technique::HiddenSingle,
+            placements: vec![Placement { row: 3, col: 6, value: 6 }],
+            eliminations: Vec::new(),
+            unit: Some(Unit::Box(5)),
+            cells: vec![(3, 6)],
+            digits: Vec::new(),
+        };
+        assert_eq!(explain(&step), "r4c7 must be 6: it is the only cell in box 6 that can hold 6");
+    }
+
+    #[test]
+    fn test_explain_hidden_pair() {
+        let step = SolveStep {
+            technique: Technique::HiddenPair,
+            placements: Vec::new(),
+            eliminations: vec![Placement { row: 2, col: 0, value: 2 }, Placement { row: 2, col: 4, value: 9 }],
+            unit: Some(Unit::Row(2)),
+            cells: vec![(2, 0), (2, 4)],
+            digits: vec![4, 7],
+        };
+        assert_eq!(
+            explain(&step),
+            "In row 3, 4 and 7 can only go in r3c1, r3c5, so those cells can't hold anything else: remove 2 from r3c1, 9 from r3c5"
+        );
+        assert_eq!(digit_list(&[1, 4, 7]), "1, 4 and 7");
+
+        let step = SolveStep { technique: Technique::NakedPair, ..step };
+        assert!(explain(&step).starts_with("In row 3, r3c1, r3c5 can only hold 4 and 7, so those digits"));
+    }
+
+    #[test]
+    fn test_explain_xy_wing() {
+        let step = SolveStep {
+            technique: Technique::XYWing,
+            placements: Vec::new(),
+            eliminations: vec![Placement { row: 4, col: 4, value: 3 }],
+            unit: None,
+            cells: vec![(0, 0), (0, 4), (4, 0)],
+            digits: vec![1, 2, 3],
+        };
+        assert_eq!(
+            explain(&step),
+            "Whatever r1c1 turns out to be, one of r1c5, r5c1 must be 3, so no cell seeing them can hold 3: r5c5"
+        );
+    }
+
+    #[test]
+    fn test_explain_full_solve() {
+        let board = Board::parse(
+            "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5",
+        )
+        .unwrap();
+        let steps = LogicSolver::new(&board).solve();
+        let lines = explain_all(&steps);
+        assert_eq!(lines.len(), steps.len());
+
+        let x_wing = steps.iter().position(|s| s.technique == Technique::XWing).unwrap();
+        let sentence = &lines[x_wing];
+        assert!(sentence.contains("can only go in"), "{}", sentence);
+        assert!(sentence.contains("rows") && sentence.contains("columns"), "{}", sentence);
+    }
+}
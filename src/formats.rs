@@ -0,0 +1,249 @@
+//! Readers for public puzzle collections.
+//!
+//! - `Format::Lines`: one puzzle per line as read by `Board::parse`, the
+//!   format `solve-file` and `rate` take; blank lines and `#` comments are skipped
+//! - `Format::Euler`: Project Euler problem 96 (`p096_sudoku.txt`), blocks of
+//!   a `Grid NN` heading and nine rows of nine digits, 0 for empty
+//! - `Format::Kaggle`: the Kaggle "1 million Sudoku games" CSV, a
+//!   `quizzes,solutions` header then one puzzle and its solution per line,
+//!   each as 81 digits
+//!
+//! `read` yields one `Grid` per puzzle, with the solution when the format
+//! has one, and reads lazily so million-puzzle files stream.
+
+use crate::{Board, Grid, GridMetadata, PuzzleSource, Result, SudokuError};
+use std::fmt;
+use std::io::BufRead;
+use std::iter::Peekable;
+use std::str::FromStr;
+
+/// A puzzle collection format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Lines,
+    Euler,
+    Kaggle,
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Lines => "lines",
+            Format::Euler => "euler",
+            Format::Kaggle => "kaggle",
+        }
+    }
+
+    /// Guesses the format from the first non-blank line of a file
+    pub fn detect(first_line: &str) -> Self {
+        let line = first_line.trim();
+        if line.starts_with("Grid") {
+            Format::Euler
+        } else if line.contains(',') {
+            Format::Kaggle
+        } else {
+            Format::Lines
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = SudokuError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "lines" => Ok(Format::Lines),
+            "euler" => Ok(Format::Euler),
+            "kaggle" => Ok(Format::Kaggle),
+            _ => Err(SudokuError::ParseError(format!("unknown format '{}' (lines, euler, kaggle)", s))),
+        }
+    }
+}
+
+/// Reads every puzzle in `reader`, guessing the format from the first
+/// non-blank line unless `format` is given. Malformed entries are errors
+/// naming their line; reading carries on after them.
+pub fn read<R: BufRead>(reader: R, format: Option<Format>) -> Records<R> {
+    let mut lines = reader.lines().enumerate().peekable();
+    let format = format.unwrap_or_else(|| {
+        while lines.next_if(|(_, line)| line.as_ref().is_ok_and(|line| line.trim().is_empty())).is_some() {}
+        match lines.peek() {
+            Some((_, Ok(line))) => Format::detect(line),
+            _ => Format::Lines,
+        }
+    });
+    Records { lines, format }
+}
+
+/// Reads every puzzle in `input`, as `read` does, stopping at the first error
+pub fn parse(input: &str, format: Option<Format>) -> Result<Vec<Grid>> {
+    read(input.as_bytes(), format).collect()
+}
+
+type NumberedLines<R> = Peekable<std::iter::Enumerate<std::io::Lines<R>>>;
+
+/// Iterator over the puzzles in a collection, from `read`
+pub struct Records<R: BufRead> {
+    lines: NumberedLines<R>,
+    format: Format,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The next line that isn't blank (or, for `skip_comments`, a `#` comment),
+    /// numbered from 1
+    fn next_line(&mut self, skip_comments: bool) -> Option<Result<(usize, String)>> {
+        for (index, line) in self.lines.by_ref() {
+            match line {
+                Ok(line) if line.trim().is_empty() || (skip_comments && line.trim_start().starts_with('#')) => {}
+                Ok(line) => return Some(Ok((index + 1, line))),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        None
+    }
+
+    fn next_euler(&mut self) -> Option<Result<Grid>> {
+        let (number, heading) = match self.next_line(false)? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+        if !heading.trim_start().starts_with("Grid") {
+            return Some(Err(at_line(number, "expected a 'Grid NN' heading")));
+        }
+        let mut rows = String::with_capacity(81);
+        for row in 1..=9 {
+            match self.lines.next() {
+                Some((_, Ok(line))) if line.trim().len() == 9 => rows.push_str(line.trim()),
+                Some((_, Err(e))) => return Some(Err(e.into())),
+                _ => return Some(Err(at_line(number + row, &format!("expected row {} of 9 digits", row)))),
+            }
+        }
+        Some(Board::parse(&rows).map(|board| grid(&board, None)).map_err(|e| with_line(number, e)))
+    }
+
+    fn next_kaggle(&mut self) -> Option<Result<Grid>> {
+        let (number, line) = match self.next_line(false)? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+        // The header row names the columns
+        if number == 1 && !line.starts_with(|c: char| c.is_ascii_digit()) {
+            return self.next_kaggle();
+        }
+        let Some((puzzle, solution)) = line.trim().split_once(',') else {
+            return Some(Err(at_line(number, "expected 'puzzle,solution'")));
+        };
+        let record = Board::parse(puzzle).and_then(|puzzle| Ok(grid(&puzzle, Some(&Board::parse(solution)?))));
+        Some(record.map_err(|e| with_line(number, e)))
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<Grid>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.format {
+            Format::Lines => {
+                let (number, line) = match self.next_line(true)? {
+                    Ok(line) => line,
+                    Err(e) => return Some(Err(e)),
+                };
+                Some(Board::parse(&line).map(|board| grid(&board, None)).map_err(|e| with_line(number, e)))
+            }
+            Format::Euler => self.next_euler(),
+            Format::Kaggle => self.next_kaggle(),
+        }
+    }
+}
+
+fn grid(puzzle: &Board, solution: Option<&Board>) -> Grid {
+    let value = puzzle.to_vec();
+    Grid {
+        metadata: GridMetadata::new(&value, PuzzleSource::Imported),
+        value,
+        solution: solution.map(Board::to_vec),
+        difficulty: "Unknown".to_string(),
+    }
+}
+
+fn at_line(number: usize, message: &str) -> SudokuError {
+    SudokuError::ParseError(format!("line {}: {}", number, message))
+}
+
+/// Prefixes a parse error's message with the line it came from
+fn with_line(number: usize, error: SudokuError) -> SudokuError {
+    match error {
+        SudokuError::ParseError(message) => at_line(number, &message),
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    fn euler_block(number: usize, board: &Board) -> String {
+        let digits = board.to_line().replace('.', "0");
+        let rows: Vec<&str> = (0..9).map(|row| &digits[row * 9..row * 9 + 9]).collect();
+        format!("Grid {:02}\n{}\n", number, rows.join("\n"))
+    }
+
+    #[test]
+    fn test_euler() {
+        let boards: Vec<Board> = ["easy", "hard"].iter().map(|name| fixture::get(name).unwrap().board()).collect();
+        let input = format!("{}{}", euler_block(1, &boards[0]), euler_block(2, &boards[1]));
+        assert_eq!(read(input.as_bytes(), None).format(), Format::Euler);
+        let grids = parse(&input, None).unwrap();
+        assert_eq!(grids.iter().map(|grid| Board::new(&grid.value)).collect::<Vec<_>>(), boards);
+        assert_eq!(grids[0].metadata.source, Some(PuzzleSource::Imported));
+        assert!(grids[0].solution.is_none());
+
+        let truncated = &input[..input.len() - 20];
+        let err = parse(truncated, Some(Format::Euler)).unwrap_err();
+        assert!(err.to_string().contains("line 19"), "{}", err);
+        assert!(parse("Grid 01\n1234\n", None).is_err());
+        assert!(parse("003020600\n", Some(Format::Euler)).is_err());
+    }
+
+    #[test]
+    fn test_kaggle() {
+        let easy = fixture::get("easy").unwrap();
+        let grid = easy.grid().unwrap();
+        let digits = |board: &Board| board.to_line().replace('.', "0");
+        let input = format!(
+            "quizzes,solutions\r\n{},{}\r\n\r\n{},{}\n",
+            digits(&easy.board()),
+            digits(&grid.solution_board().unwrap()),
+            digits(&easy.board()),
+            "1".repeat(80)
+        );
+        let records: Vec<Result<Grid>> = read(input.as_bytes(), None).collect();
+        assert_eq!(records.len(), 2);
+        let first = records[0].as_ref().unwrap();
+        assert_eq!((&first.value, &first.solution), (&grid.value, &grid.solution));
+        let err = records[1].as_ref().unwrap_err().to_string();
+        assert!(err.contains("line 4") && err.contains("expected 81 cells"), "{}", err);
+    }
+
+    #[test]
+    fn test_lines() {
+        let (easy, hard) = (fixture::get("easy").unwrap(), fixture::get("hard").unwrap());
+        let input = format!("# a comment\n\n{}\n{}\n", easy.puzzle, hard.puzzle);
+        assert_eq!(read(input.as_bytes(), None).format(), Format::Lines);
+        assert_eq!(parse(&input, None).unwrap().len(), 2);
+        assert!(parse("12x\n", None).unwrap_err().to_string().contains("line 1: unexpected character 'x'"));
+        assert_eq!("Kaggle".parse::<Format>().unwrap(), Format::Kaggle);
+        assert!("csv".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Interactive game session state shared by front-ends.
+//!
+//! A `Session` owns the puzzle, the player's current board and pencil marks,
+//! an undo/redo history, and the mistake counter, so front-ends (terminal or
+//! web) don't have to track any of this themselves.
+
+use crate::{
+    pencilmarks::PencilMarks,
+    solver::enumerate_solutions,
+    validation::{self, Conflict},
+    Board, CandidateSet, Grid, Result, SudokuError,
+};
+use std::time::{Duration, Instant};
+
+/// Result of applying a move to a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveOutcome {
+    /// The move was applied and does not contradict the solution
+    Accepted,
+    /// The placed value is wrong; the mistake counter was incremented
+    Mistake,
+    /// The move completed the puzzle
+    Solved,
+}
+
+/// Contents of a single cell: its value and its pencil marks
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CellState {
+    value: u8,
+    marks: CandidateSet,
+}
+
+/// A reversible change to one cell
+#[derive(Debug, Clone, Copy)]
+struct HistoryEntry {
+    row: usize,
+    col: usize,
+    before: CellState,
+    after: CellState,
+}
+
+/// A single game in progress
+#[derive(Debug, Clone)]
+pub struct Session {
+    puzzle: Board,
+    solution: Option<Board>,
+    board: Board,
+    marks: PencilMarks,
+    history: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    mistakes: u32,
+    started: Instant,
+}
+
+impl Session {
+    /// Starts a new session for a puzzle.
+    ///
+    /// Fails with `InvalidBoard` if the puzzle has no solution. Puzzles with
+    /// several solutions are accepted, but mistakes are then only detected
+    /// for placements that conflict with a filled peer.
+    pub fn new(puzzle: Board) -> Result<Self> {
+        let solutions = enumerate_solutions(&puzzle, 2);
+        if solutions.is_empty() {
+            return Err(SudokuError::InvalidBoard);
+        }
+        let solution = if solutions.len() == 1 {
+            solutions.into_iter().next()
+        } else {
+            None
+        };
+
+        Ok(Self {
+            board: puzzle.clone(),
+            puzzle,
+            solution,
+            marks: PencilMarks::empty(),
+            history: Vec::new(),
+            redo_stack: Vec::new(),
+            mistakes: 0,
+            started: Instant::now(),
+        })
+    }
+
+    /// Starts a new session from a fetched or generated grid
+    pub fn from_grid(grid: &Grid) -> Result<Self> {
+        Self::new(Board::new(&grid.value))
+    }
+
+    /// Places `value` at the given cell, or clears it when `value` is 0
+    pub fn apply_move(&mut self, row: usize, col: usize, value: u8) -> Result<MoveOutcome> {
+        self.check_editable(row, col, value as i32)?;
+
+        let before = self.cell(row, col);
+        let after = CellState {
+            value,
+            marks: if value == 0 { before.marks } else { CandidateSet::empty() },
+        };
+        // Clearing an empty cell or re-entering its value changes nothing to undo
+        if after == before {
+            return Ok(MoveOutcome::Accepted);
+        }
+        self.record(row, col, before, after);
+
+        if value == 0 {
+            return Ok(MoveOutcome::Accepted);
+        }
+
+        let wrong = match &self.solution {
+            Some(solution) => solution.get(row, col) != value,
+            None => {
+                let mut board = self.board.clone();
+                board.set(row, col, 0);
+                !board.can_place(row, col, value)
+            }
+        };
+        if wrong {
+            self.mistakes += 1;
+            Ok(MoveOutcome::Mistake)
+        } else if self.is_solved() {
+            Ok(MoveOutcome::Solved)
+        } else {
+            Ok(MoveOutcome::Accepted)
+        }
+    }
+
+    /// Adds or removes a pencil mark in an empty cell
+    pub fn toggle_pencilmark(&mut self, row: usize, col: usize, value: u8) -> Result<()> {
+        self.check_editable(row, col, value as i32)?;
+        if value == 0 || !self.board.is_empty_cell(row, col) {
+            return Err(SudokuError::InvalidValue { row, col, value: value as i32 });
+        }
+
+        let before = self.cell(row, col);
+        let mut after = before;
+        if after.marks.has_candidate(value) {
+            after.marks.remove_candidate(value);
+        } else {
+            after.marks.add_candidate(value);
+        }
+        self.record(row, col, before, after);
+        Ok(())
+    }
+
+    /// Reverts the most recent move. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(entry) => {
+                self.write_cell(entry.row, entry.col, entry.before);
+                self.redo_stack.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-applies the most recently undone move. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(entry) => {
+                self.write_cell(entry.row, entry.col, entry.after);
+                self.history.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if every cell is filled without conflicts
+    pub fn is_solved(&self) -> bool {
+        match &self.solution {
+            Some(solution) => &self.board == solution,
+            None => crate::validate_solution(&self.board),
+        }
+    }
+
+    /// Digits the player has entered more than once in a row, column or box,
+    /// for front-ends to highlight along with the units they break
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        validation::conflicts(&self.board)
+    }
+
+    pub fn puzzle(&self) -> &Board {
+        &self.puzzle
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn pencilmarks(&self) -> &PencilMarks {
+        &self.marks
+    }
+
+    pub fn mistakes(&self) -> u32 {
+        self.mistakes
+    }
+
+    pub fn moves_made(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Time since the session started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn check_editable(&self, row: usize, col: usize, value: i32) -> Result<()> {
+        if row >= 9 || col >= 9 || !(0..=9).contains(&value) {
+            return Err(SudokuError::InvalidValue { row, col, value });
+        }
+        if !self.puzzle.is_empty_cell(row, col) {
+            return Err(SudokuError::InvalidValue { row, col, value });
+        }
+        Ok(())
+    }
+
+    fn cell(&self, row: usize, col: usize) -> CellState {
+        CellState {
+            value: self.board.get(row, col),
+            marks: self.marks.get(row, col),
+        }
+    }
+
+    fn write_cell(&mut self, row: usize, col: usize, state: CellState) {
+        self.board.set(row, col, state.value);
+        self.marks.set(row, col, state.marks);
+    }
+
+    fn record(&mut self, row: usize, col: usize, before: CellState, after: CellState) {
+        self.write_cell(row, col, after);
+        self.history.push(HistoryEntry { row, col, before, after });
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn puzzle() -> Board {
+        Board::new(&[
+            vec![5,3,0,0,7,0,0,0,0],
+            vec![6,0,0,1,9,5,0,0,0],
+            vec![0,9,8,0,0,0,0,6,0],
+            vec![8,0,0,0,6,0,0,0,3],
+            vec![4,0,0,8,0,3,0,0,1],
+            vec![7,0,0,0,2,0,0,0,6],
+            vec![0,6,0,0,0,0,2,8,0],
+            vec![0,0,0,4,1,9,0,0,5],
+            vec![0,0,0,0,8,0,0,7,9],
+        ])
+    }
+
+    #[test]
+    fn test_moves_and_mistakes() {
+        let mut session = Session::new(puzzle()).unwrap();
+        assert_eq!(session.apply_move(0, 2, 4).unwrap(), MoveOutcome::Accepted);
+        assert_eq!(session.apply_move(0, 3, 8).unwrap(), MoveOutcome::Mistake);
+        assert_eq!(session.mistakes(), 1);
+        let conflicts = session.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].value, conflicts[0].cells.as_slice()), (8, &[(0, 3), (4, 3)][..]));
+        assert!(session.apply_move(0, 0, 1).is_err(), "givens are not editable");
+        assert!(session.apply_move(0, 2, 10).is_err());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut session = Session::new(puzzle()).unwrap();
+        session.toggle_pencilmark(0, 2, 1).unwrap();
+        session.toggle_pencilmark(0, 2, 2).unwrap();
+        session.apply_move(0, 2, 4).unwrap();
+        assert!(session.pencilmarks().get(0, 2).is_empty());
+
+        assert!(session.undo());
+        assert_eq!(session.board().get(0, 2), 0);
+        assert_eq!(session.pencilmarks().get(0, 2).count_candidates(), 2);
+
+        assert!(session.redo());
+        assert_eq!(session.board().get(0, 2), 4);
+        assert!(!session.redo());
+
+        // A new move discards the redo history
+        session.undo();
+        session.toggle_pencilmark(0, 2, 3).unwrap();
+        assert!(!session.can_redo());
+    }
+
+    #[test]
+    fn test_unchanged_move_is_not_recorded() {
+        let mut session = Session::new(puzzle()).unwrap();
+        session.apply_move(0, 2, 4).unwrap();
+        assert!(session.undo());
+
+        // Clearing the now empty cell leaves both histories alone
+        assert_eq!(session.apply_move(0, 2, 0).unwrap(), MoveOutcome::Accepted);
+        assert!(!session.can_undo());
+        assert!(session.can_redo());
+
+        assert!(session.redo());
+        session.apply_move(0, 2, 4).unwrap();
+        assert!(session.undo());
+        assert!(!session.can_undo());
+    }
+
+    #[test]
+    fn test_solving_completes_session() {
+        let puzzle = puzzle();
+        let solution = enumerate_solutions(&puzzle, 1).remove(0);
+        let mut session = Session::new(puzzle.clone()).unwrap();
+
+        let mut last = MoveOutcome::Accepted;
+        for row in 0..9 {
+            for col in 0..9 {
+                if puzzle.is_empty_cell(row, col) {
+                    last = session.apply_move(row, col, solution.get(row, col)).unwrap();
+                }
+            }
+        }
+        assert_eq!(last, MoveOutcome::Solved);
+        assert!(session.is_solved());
+        assert_eq!(session.mistakes(), 0);
+    }
+}
@@ -1,49 +1,733 @@
-use crate::{Grid, Result};
-use rand::prelude::*;
-use rand::rngs::SmallRng;
+use crate::{hex::HexBoard, logic::Technique, mini::{MiniBoard, MiniSize}, rating, simd::SimdValidator, solver::{count_solutions_up_to, Solver}, symmetry::{self, Transform}, Board, Grid, GridMetadata, PuzzleSource, Result, SudokuError};
 use std::collections::HashSet;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use chrono::NaiveDate;
+use parking_lot::Mutex;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+mod pool;
+
+pub use pool::{spawn_pool, GeneratorHandle, PoolOptions};
+
+/// Generates the puzzle of the day.
+///
+/// The generator is seeded from the date and difficulty, and its ChaCha8
+/// stream is the same on every platform, so every caller asking for the same
+/// day and difficulty gets the same puzzle. The puzzle is made in a single
+/// attempt rather than by `generate_with_difficulty`, whose label check may
+/// be tuned without changing the puzzles of past days.
+pub fn daily(date: NaiveDate, difficulty: &str) -> Result<Grid> {
+    let difficulty = normalize_difficulty(difficulty);
+    let mut grid = BoardGenerator::with_seed(daily_seed(date, difficulty)).generate_once(difficulty)?;
+    grid.metadata.source = Some(PuzzleSource::Daily);
+    // Dated at midnight so every caller sees identical metadata
+    grid.metadata.created_at = date.and_hms_opt(0, 0, 0).map(|midnight| midnight.and_utc());
+    Ok(grid)
+}
+
+/// Derives a stable seed from a date and difficulty using 64-bit FNV-1a.
+fn daily_seed(date: NaiveDate, difficulty: &str) -> u64 {
+    crate::fnv1a(format!("{}:{}", date.format("%Y-%m-%d"), difficulty).as_bytes())
+}
+
+/// Fresh solutions tried by `generate_with_pattern` before giving up
+const PATTERN_ATTEMPTS: usize = 1000;
+
+/// Parses an 81-character clue pattern; `.` or `0` marks an empty cell, anything else a given
+pub fn parse_pattern(pattern: &str) -> Result<[bool; 81]> {
+    let chars: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.len() != 81 {
+        return Err(SudokuError::ParseError(format!(
+            "expected 81 cells in pattern, found {}",
+            chars.len()
+        )));
+    }
+    let mut mask = [false; 81];
+    for (cell, c) in mask.iter_mut().zip(chars) {
+        *cell = !matches!(c, '.' | '0');
+    }
+    Ok(mask)
+}
+
+/// Settings for `hunt_min_clues_with`
+#[derive(Debug, Clone)]
+pub struct HuntOptions {
+    /// File the best puzzle found so far is written to, as a JSON `HuntReport`
+    pub checkpoint: Option<PathBuf>,
+    /// Minimum time between checkpoint writes
+    pub checkpoint_interval: Duration,
+    /// Seed for reproducible hunts; worker `i` uses `seed + i`
+    pub seed: Option<u64>,
+}
+
+impl Default for HuntOptions {
+    fn default() -> Self {
+        Self {
+            checkpoint: None,
+            checkpoint_interval: Duration::from_secs(30),
+            seed: None,
+        }
+    }
+}
+
+/// Progress of a low-clue hunt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HuntReport {
+    /// Fewest-clue puzzle found, in canonical form
+    pub best: Option<Grid>,
+    /// Clue count of `best` (81 when nothing was found)
+    pub best_clues: usize,
+    /// Non-isomorphic puzzles found with `best_clues` clues
+    pub distinct_at_best: usize,
+    /// Minimal puzzles generated
+    pub attempts: u64,
+    pub elapsed: Duration,
+    pub reached_target: bool,
+}
+
+/// Shared state of the hunt workers
+struct Hunt {
+    target: usize,
+    best_clues: AtomicUsize,
+    attempts: AtomicU64,
+    stop: AtomicBool,
+    start: Instant,
+    found: Mutex<HuntFound>,
+}
+
+struct HuntFound {
+    best: Option<Board>,
+    seen: HashSet<Board>,
+    last_checkpoint: Instant,
+}
+
+impl Hunt {
+    fn report(&self) -> HuntReport {
+        let found = self.found.lock();
+        let best_clues = self.best_clues.load(Ordering::Relaxed);
+        HuntReport {
+            best: found.best.as_ref().map(|puzzle| {
+                let mut solution = puzzle.clone();
+                // Every recorded puzzle has a unique solution
+                let _ = Solver::solve_into(&mut solution);
+                let difficulty = rating::rate(puzzle).difficulty().to_string();
+                let mut metadata = GridMetadata::new(&puzzle.to_vec(), PuzzleSource::Generator);
+                metadata.rating = Some(difficulty.clone());
+                Grid { value: puzzle.to_vec(), solution: Some(solution.to_vec()), difficulty, metadata }
+            }),
+            best_clues,
+            distinct_at_best: found.seen.len(),
+            attempts: self.attempts.load(Ordering::Relaxed),
+            elapsed: self.start.elapsed(),
+            reached_target: best_clues <= self.target,
+        }
+    }
+
+    /// Records a minimal puzzle, canonicalizing it only if it ties or beats the best
+    fn record(&self, puzzle: &Board, options: &HuntOptions) {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        let clues = puzzle.cells.iter().filter(|&&v| v != 0).count();
+        if clues > self.best_clues.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let canonical = symmetry::canonical_form(puzzle);
+        let mut found = self.found.lock();
+        let best_clues = self.best_clues.load(Ordering::Relaxed);
+        if clues < best_clues {
+            info!("Found a {}-clue puzzle", clues);
+            self.best_clues.store(clues, Ordering::Relaxed);
+            found.seen.clear();
+            found.best = Some(canonical.clone());
+        }
+        if clues <= best_clues {
+            found.seen.insert(canonical);
+        }
+        if clues <= self.target {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+
+        let due = found.last_checkpoint.elapsed() >= options.checkpoint_interval;
+        if due && options.checkpoint.is_some() {
+            found.last_checkpoint = Instant::now();
+            drop(found);
+            self.checkpoint(options);
+        }
+    }
+
+    fn checkpoint(&self, options: &HuntOptions) {
+        let Some(path) = &options.checkpoint else {
+            return;
+        };
+        let written = serde_json::to_string_pretty(&self.report())
+            .map_err(SudokuError::from)
+            .and_then(|json| fs::write(path, json).map_err(SudokuError::from));
+        if let Err(e) = written {
+            warn!("Failed to write hunt checkpoint {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Searches for a puzzle with at most `target` clues until `budget` runs out.
+///
+/// See `hunt_min_clues_with`.
+pub fn hunt_min_clues(target: usize, budget: Duration) -> HuntReport {
+    hunt_min_clues_with(target, budget, &HuntOptions::default())
+}
+
+/// Searches for very-low-clue puzzles on every rayon worker.
+///
+/// Each worker repeatedly takes a random solution and removes clues in random
+/// order for as long as the puzzle stays unique, giving a minimal puzzle.
+/// Removals that would empty an unavoidable set (a deadly rectangle) are
+/// skipped without calling the solver. Puzzles that tie or beat the best are
+/// canonicalized, so `distinct_at_best` counts non-isomorphic finds. The hunt
+/// stops at the first puzzle with `target` clues or fewer, or when the budget
+/// is spent, and the best puzzle is checkpointed along the way.
+pub fn hunt_min_clues_with(target: usize, budget: Duration, options: &HuntOptions) -> HuntReport {
+    let hunt = Hunt {
+        target,
+        best_clues: AtomicUsize::new(81),
+        attempts: AtomicU64::new(0),
+        stop: AtomicBool::new(false),
+        start: Instant::now(),
+        found: Mutex::new(HuntFound { best: None, seen: HashSet::new(), last_checkpoint: Instant::now() }),
+    };
+    let base_seed = options.seed.unwrap_or_else(rand::random);
+
+    (0..rayon::current_num_threads()).into_par_iter().for_each(|worker| {
+        let mut generator = BoardGenerator::with_seed(base_seed.wrapping_add(worker as u64));
+        while !hunt.stop.load(Ordering::Relaxed) && hunt.start.elapsed() < budget {
+            let solution = generator.random_solution();
+            let puzzle = generator.minimize(&solution);
+            hunt.record(&puzzle, options);
+        }
+    });
+
+    hunt.checkpoint(options);
+    hunt.report()
+}
+
+/// Finds the deadly rectangles of a complete grid.
+///
+/// Four cells in two rows, two columns and two boxes holding `a b / b a` can
+/// swap digits and stay valid, so every puzzle for this solution needs a clue
+/// in each such set.
+fn unavoidable_rectangles(solution: &Board) -> Vec<[usize; 4]> {
+    let mut sets = Vec::new();
+    for r1 in 0..9 {
+        for r2 in r1 + 1..9 {
+            for c1 in 0..9 {
+                for c2 in c1 + 1..9 {
+                    let cells = [r1 * 9 + c1, r1 * 9 + c2, r2 * 9 + c1, r2 * 9 + c2];
+                    let boxes: HashSet<usize> = [(r1, c1), (r1, c2), (r2, c1), (r2, c2)]
+                        .iter()
+                        .map(|&(r, c)| Board::get_box_index(r, c))
+                        .collect();
+                    let v = |cell: usize| solution.cells[cell];
+                    if boxes.len() == 2 && v(cells[0]) == v(cells[3]) && v(cells[1]) == v(cells[2]) {
+                        sets.push(cells);
+                    }
+                }
+            }
+        }
+    }
+    sets
+}
+
+/// Maps a case-insensitive difficulty name onto the generator's labels
+pub fn normalize_difficulty(difficulty: &str) -> &'static str {
+    match difficulty.to_lowercase().as_str() {
+        "easy" => "Easy",
+        "hard" => "Hard",
+        _ => "Medium",
+    }
+}
+
+/// How clues are removed from a complete grid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovalOptions {
+    /// How many cells to empty, picked uniformly from this range. Fewer may be
+    /// emptied if removing more would make the solution non-unique.
+    pub remove: RangeInclusive<u32>,
+    /// Difficulty label for the puzzle; when None the puzzle is rated instead
+    pub difficulty: Option<String>,
+}
+
+impl RemovalOptions {
+    /// The removal range the default generator uses for a difficulty label
+    pub fn for_difficulty(difficulty: &str) -> Self {
+        GeneratorOptions::default().removal(difficulty)
+    }
+
+    /// Checks that the removal range is non-empty and leaves enough clues
+    pub fn validate(&self) -> Result<()> {
+        if self.remove.is_empty() || *self.remove.end() > MAX_REMOVE {
+            return Err(SudokuError::ParseError(format!(
+                "Invalid removal range {:?}, expected start <= end <= {}",
+                self.remove, MAX_REMOVE
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Puzzles `generate_with_difficulty` makes looking for one whose estimate
+/// matches its label
+const LABEL_ATTEMPTS: usize = 8;
+
+/// Position of a difficulty label in order of hardness, for the labels
+/// `rating::estimate` gives
+fn level(difficulty: &str) -> Option<usize> {
+    ["Easy", "Medium", "Hard", "Expert"].iter().position(|&label| label == difficulty)
+}
+
+/// Removal range used for difficulty labels without a profile
+const FALLBACK_REMOVE: RangeInclusive<u32> = 45..=50;
+
+/// Most cells that can be emptied while leaving the 17 clues a unique puzzle needs
+const MAX_REMOVE: u32 = 64;
+
+/// One difficulty the generator can produce
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DifficultyProfile {
+    /// Label given to puzzles of this difficulty
+    pub name: String,
+    /// Relative weight when `generate` picks a difficulty
+    pub weight: u32,
+    /// How many cells to empty, as in `RemovalOptions::remove`
+    pub remove: RangeInclusive<u32>,
+}
+
+/// Difficulty mix and clue-removal ranges of a `BoardGenerator`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorOptions {
+    pub difficulties: Vec<DifficultyProfile>,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        let profile = |name: &str, weight, remove| DifficultyProfile { name: name.to_string(), weight, remove };
+        Self {
+            difficulties: vec![
+                profile("Easy", 4, 30..=35),
+                profile("Medium", 62, 40..=50),
+                profile("Hard", 34, 51..=60),
+            ],
+        }
+    }
+}
+
+impl GeneratorOptions {
+    /// Loads options from a JSON file and checks them
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let options: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Checks that some difficulty has weight, the weights add up to at most
+    /// `u32::MAX` and every removal range is usable
+    pub fn validate(&self) -> Result<()> {
+        if self.difficulties.iter().all(|profile| profile.weight == 0) {
+            return Err(SudokuError::ParseError("At least one difficulty needs a non-zero weight".to_string()));
+        }
+        let total = self.difficulties.iter().try_fold(0u32, |total, profile| total.checked_add(profile.weight));
+        if total.is_none() {
+            return Err(SudokuError::ParseError(format!("Difficulty weights add up to more than {}", u32::MAX)));
+        }
+        for profile in &self.difficulties {
+            if profile.remove.is_empty() || *profile.remove.end() > MAX_REMOVE {
+                return Err(SudokuError::ParseError(format!(
+                    "Invalid removal range {:?} for {}, expected start <= end <= {}",
+                    profile.remove, profile.name, MAX_REMOVE
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removal options for a difficulty label, falling back to a middling range
+    /// for labels without a profile
+    pub fn removal(&self, difficulty: &str) -> RemovalOptions {
+        let remove = self
+            .difficulties
+            .iter()
+            .find(|profile| profile.name == difficulty)
+            .map_or(FALLBACK_REMOVE, |profile| profile.remove.clone());
+        RemovalOptions { remove, difficulty: Some(difficulty.to_string()) }
+    }
+}
+
+/// Givens left in a mini puzzle of each difficulty. Children start from
+/// nearly full grids; unlabelled difficulties get the Medium range.
+pub fn mini_clues(size: MiniSize, difficulty: &str) -> RangeInclusive<usize> {
+    match (size, normalize_difficulty(difficulty)) {
+        (MiniSize::Four, "Easy") => 10..=11,
+        (MiniSize::Four, "Hard") => 6..=7,
+        (MiniSize::Four, _) => 8..=9,
+        (MiniSize::Six, "Easy") => 24..=26,
+        (MiniSize::Six, "Hard") => 16..=18,
+        (MiniSize::Six, _) => 20..=22,
+    }
+}
+
+/// A generated 4×4 or 6×6 puzzle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiniPuzzle {
+    pub puzzle: MiniBoard,
+    pub solution: MiniBoard,
+    pub difficulty: String,
+}
+
+/// Givens left in a 16×16 puzzle of each difficulty; unlabelled
+/// difficulties get the Medium range
+pub fn hex_clues(difficulty: &str) -> RangeInclusive<usize> {
+    match normalize_difficulty(difficulty) {
+        "Easy" => 150..=160,
+        "Hard" => 110..=120,
+        _ => 125..=135,
+    }
+}
+
+/// Search placements a 16×16 uniqueness check may take before the clue it
+/// was testing is kept instead. Near the Hard clue counts a handful of checks
+/// would otherwise take longer than all the others together.
+const HEX_SEARCH_BUDGET: usize = 20_000;
+
+/// A generated 16×16 puzzle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexPuzzle {
+    pub puzzle: HexBoard,
+    pub solution: HexBoard,
+    pub difficulty: String,
+}
+
+/// A handful of complete grids to derive puzzles from.
+///
+/// Filling a grid by backtracking is a large part of generating a puzzle.
+/// Puzzles made in bulk can instead start from a solution in the pool passed
+/// through a random symmetry transform, which is still a valid grid, and
+/// differ in which clues are removed.
+#[derive(Debug, Clone)]
+pub struct SolutionPool {
+    solutions: Vec<Board>,
+}
+
+impl SolutionPool {
+    /// Fills `size` solutions (at least one) with `generator`
+    pub fn new(generator: &mut BoardGenerator, size: usize) -> Self {
+        Self { solutions: (0..size.max(1)).map(|_| generator.random_solution()).collect() }
+    }
+
+    /// A random solution from the pool under a random transform
+    pub fn shuffled<R: Rng + ?Sized>(&self, rng: &mut R) -> Board {
+        let solution = self.solutions.choose(rng).expect("the pool is never empty");
+        Transform::random(rng).apply(solution)
+    }
+}
 
 pub struct BoardGenerator {
-    rng: SmallRng,
-    difficulty_weights: [(u32, &'static str); 3],
+    /// A fixed algorithm, unlike `SmallRng`, so seeded puzzles are the same
+    /// on every platform and `rand` release
+    rng: ChaCha8Rng,
+    options: GeneratorOptions,
+    /// Seed given to `with_seed`, if any
+    seed: Option<u64>,
+    /// Puzzles produced so far
+    generated: u64,
+}
+
+impl Default for BoardGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BoardGenerator {
     pub fn new() -> Self {
         Self {
-            rng: SmallRng::from_entropy(),
-            difficulty_weights: [
-                (4, "Easy"),
-                (62, "Medium"),
-                (34, "Hard"),
-            ],
+            rng: ChaCha8Rng::from_entropy(),
+            options: GeneratorOptions::default(),
+            seed: None,
+            generated: 0,
+        }
+    }
+
+    /// Creates a generator with a fixed seed; the same seed always yields the same puzzles
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            seed: Some(seed),
+            ..Self::new()
         }
     }
 
+    /// Replaces the difficulty mix and removal ranges
+    pub fn with_options(mut self, options: GeneratorOptions) -> Result<Self> {
+        options.validate()?;
+        self.options = options;
+        Ok(self)
+    }
+
     pub fn generate(&mut self) -> Result<Grid> {
+        let difficulty = self.get_weighted_difficulty();
+        self.generate_with_difficulty(&difficulty)
+    }
+
+    /// Generates a puzzle from a solution drawn from `pool`, with the
+    /// difficulty picked as in `generate`
+    pub fn generate_from_pool(&mut self, pool: &SolutionPool) -> Result<Grid> {
+        let solution = pool.shuffled(&mut self.rng);
+        let difficulty = self.get_weighted_difficulty();
+        let options = self.options.removal(&difficulty);
+        self.generate_from_solution(&solution, &options)
+    }
+
+    /// Generates a puzzle with the given difficulty label.
+    ///
+    /// Emptying the profile's number of cells is only a rough guide to
+    /// difficulty, so for the labels `rating::estimate` knows, up to
+    /// `LABEL_ATTEMPTS` puzzles are made until one's trial-solve estimate
+    /// agrees with the label. When none does, the closest is kept.
+    pub fn generate_with_difficulty(&mut self, difficulty: &str) -> Result<Grid> {
+        span!(DEBUG, "generate", difficulty, attempts = tracing::field::Empty);
+        let options = self.options.removal(difficulty);
+        let wanted = level(difficulty);
+        let mut closest: Option<(usize, Board, Vec<Vec<i32>>)> = None;
+        for attempt in 1..=wanted.map_or(1, |_| LABEL_ATTEMPTS) {
+            let mut solution = vec![vec![0; 9]; 9];
+            {
+                span!(DEBUG, "fill_board");
+                self.fill_board(&mut solution, 0, 0);
+            }
+            let solution = Board::new(&solution);
+            let puzzle = self.remove_clues(&solution, &options);
+            let distance = wanted.map_or(0, |wanted| {
+                rating::estimate(&Board::new(&puzzle))
+                    .ok()
+                    .and_then(|estimate| level(estimate.difficulty()))
+                    .map_or(usize::MAX, |estimated| estimated.abs_diff(wanted))
+            });
+            record!("attempts", attempt);
+            if closest.as_ref().is_none_or(|(fewest, ..)| distance < *fewest) {
+                closest = Some((distance, solution, puzzle));
+            }
+            if distance == 0 {
+                break;
+            }
+        }
+        let (_, solution, puzzle) = closest.expect("at least one puzzle is made");
+        Ok(self.finish(&solution, puzzle, &options))
+    }
+
+    /// Generates a puzzle from one filled grid, with no label check
+    fn generate_once(&mut self, difficulty: &str) -> Result<Grid> {
         let mut solution = vec![vec![0; 9]; 9];
-        
-        // Generate solved board
         self.fill_board(&mut solution, 0, 0);
-        let mut board = solution.clone();
+        let options = self.options.removal(difficulty);
+        self.generate_from_solution(&Board::new(&solution), &options)
+    }
 
-        // Remove numbers based on difficulty
-        let difficulty = self.get_weighted_difficulty();
-        let cells_to_remove = match difficulty {
-            "Easy" => 30..=35,
-            "Medium" => 40..=50,
-            "Hard" => 51..=60,
-            _ => 45..=50,
-        };
+    /// Makes a puzzle from a caller-supplied complete grid by removing clues.
+    ///
+    /// Clues are only removed while the puzzle keeps a unique solution, so the
+    /// result always solves back to `solution`. Fails if `options` has an
+    /// empty removal range or one that would empty more than 64 cells.
+    pub fn generate_from_solution(&mut self, solution: &Board, options: &RemovalOptions) -> Result<Grid> {
+        options.validate()?;
+        if !SimdValidator::validate_solution(solution) {
+            return Err(SudokuError::InvalidBoard);
+        }
+        let puzzle = self.remove_clues(solution, options);
+        Ok(self.finish(solution, puzzle, options))
+    }
 
-        let remove_count = self.rng.gen_range(cells_to_remove);
+    /// Empties as many cells of `solution` as `options` picks
+    fn remove_clues(&mut self, solution: &Board, options: &RemovalOptions) -> Vec<Vec<i32>> {
+        let mut board = solution.to_vec();
+        let remove_count = self.rng.gen_range(options.remove.clone());
+        span!(DEBUG, "remove_clues", target = remove_count, removed = tracing::field::Empty);
         self.remove_numbers(&mut board, remove_count);
+        board
+    }
 
-        Ok(Grid {
+    /// Labels a finished puzzle, rating it when `options` has no label
+    fn finish(&mut self, solution: &Board, board: Vec<Vec<i32>>, options: &RemovalOptions) -> Grid {
+        let (difficulty, rated) = match &options.difficulty {
+            Some(label) => (label.clone(), None),
+            None => {
+                span!(DEBUG, "rate");
+                let rated = rating::rate(&Board::new(&board)).difficulty().to_string();
+                (rated.clone(), Some(rated))
+            }
+        };
+        let metadata = self.metadata(&board, rated);
+        Grid {
             value: board,
-            solution,
-            difficulty: difficulty.to_string(),
-        })
+            solution: Some(solution.to_vec()),
+            difficulty,
+            metadata,
+        }
+    }
+
+    /// Generates a 4×4 or 6×6 puzzle with the clue count `mini_clues` gives
+    /// for `difficulty`. Clues are only removed while the puzzle keeps a unique
+    /// solution, so it may keep a few more than the range asks for.
+    pub fn generate_mini(&mut self, size: MiniSize, difficulty: &str) -> MiniPuzzle {
+        let difficulty = normalize_difficulty(difficulty);
+        span!(DEBUG, "generate_mini", difficulty, side = size.side());
+        let solution = MiniBoard::empty(size)
+            .solve()
+            .expect("an empty board always has a solution")
+            .shuffled(&mut self.rng);
+        let target = self.rng.gen_range(mini_clues(size, difficulty));
+
+        let mut puzzle = solution;
+        let mut clues = size.cells();
+        let mut cells: Vec<usize> = (0..size.cells()).collect();
+        cells.shuffle(&mut self.rng);
+        for cell in cells {
+            if clues == target {
+                break;
+            }
+            let (row, col) = (cell / size.side(), cell % size.side());
+            puzzle.set(row, col, 0);
+            if puzzle.count_solutions_up_to(2) == 1 {
+                clues -= 1;
+            } else {
+                puzzle.set(row, col, solution.get(row, col));
+            }
+        }
+        MiniPuzzle { puzzle, solution, difficulty: difficulty.to_string() }
+    }
+
+    /// Generates a 16×16 puzzle with the clue count `hex_clues` gives for
+    /// `difficulty`, removing clues in random order as `generate_mini` does.
+    ///
+    /// Two things keep this from blowing up at this size. A clue whose peers
+    /// leave its cell only one candidate is removed without a search, which
+    /// covers most of the early removals. The remaining uniqueness checks
+    /// run under `HEX_SEARCH_BUDGET`, and a clue whose check runs out is
+    /// kept, so the puzzle may keep a few more clues than the range asks for.
+    pub fn generate_hex(&mut self, difficulty: &str) -> HexPuzzle {
+        let difficulty = normalize_difficulty(difficulty);
+        span!(DEBUG, "generate_hex", difficulty);
+        let solution = HexBoard::solved().shuffled(&mut self.rng);
+        let target = self.rng.gen_range(hex_clues(difficulty));
+
+        let mut puzzle = solution.clone();
+        let mut clues = crate::hex::CELLS;
+        let mut cells: Vec<usize> = (0..crate::hex::CELLS).collect();
+        cells.shuffle(&mut self.rng);
+        for cell in cells {
+            if clues == target {
+                break;
+            }
+            let (row, col) = (cell / crate::hex::SIDE, cell % crate::hex::SIDE);
+            puzzle.set(row, col, 0);
+            let forced = puzzle.candidates(row, col).count_ones() == 1;
+            if forced || puzzle.count_solutions_within(2, HEX_SEARCH_BUDGET) == Some(1) {
+                clues -= 1;
+            } else {
+                puzzle.set(row, col, solution.get(row, col));
+            }
+        }
+        HexPuzzle { puzzle, solution, difficulty: difficulty.to_string() }
+    }
+
+    /// Generates a puzzle whose logical solve path needs `technique`.
+    ///
+    /// Puzzles are generated and rated until one is found whose hardest required
+    /// technique is exactly `technique`, giving up after `max_attempts`.
+    pub fn generate_requiring(&mut self, technique: Technique, max_attempts: usize) -> Result<Grid> {
+        let difficulty = match technique {
+            Technique::NakedSingle | Technique::HiddenSingle => "Easy",
+            _ => "Hard",
+        };
+
+        span!(DEBUG, "generate_requiring", %technique, attempts = tracing::field::Empty);
+        for attempt in 1..=max_attempts {
+            record!("attempts", attempt);
+            let mut grid = self.generate_with_difficulty(difficulty)?;
+            let rating = rating::rate(&Board::new(&grid.value));
+            if rating.solved && rating.hardest == Some(technique) {
+                grid.difficulty = rating.difficulty().to_string();
+                grid.metadata.rating = Some(grid.difficulty.clone());
+                return Ok(grid);
+            }
+        }
+
+        Err(SudokuError::GenerationFailed(format!(
+            "no puzzle requiring {} found in {} attempts",
+            technique, max_attempts
+        )))
+    }
+
+    /// Generates a puzzle whose givens occupy exactly the cells set in `mask`.
+    ///
+    /// Random solutions are tried until one leaves a unique puzzle when only
+    /// the masked cells are kept. Fails if none is found, which is certain for
+    /// patterns with fewer than 17 cells and common for sparse ones.
+    pub fn generate_with_pattern(&mut self, mask: [bool; 81]) -> Result<Grid> {
+        let clues = mask.iter().filter(|&&given| given).count();
+        if clues < 17 {
+            return Err(SudokuError::GenerationFailed(format!(
+                "a pattern needs at least 17 clues, this one has {}",
+                clues
+            )));
+        }
+
+        for _ in 0..PATTERN_ATTEMPTS {
+            let solution = self.random_solution();
+            let mut puzzle = solution.clone();
+            for (cell, &given) in mask.iter().enumerate() {
+                if !given {
+                    puzzle.cells[cell] = 0;
+                }
+            }
+            if count_solutions_up_to(&puzzle, 2) == 1 {
+                let difficulty = rating::rate(&puzzle).difficulty().to_string();
+                let metadata = self.metadata(&puzzle.to_vec(), Some(difficulty.clone()));
+                return Ok(Grid { value: puzzle.to_vec(), solution: Some(solution.to_vec()), difficulty, metadata });
+            }
+        }
+
+        Err(SudokuError::GenerationFailed(format!(
+            "no unique puzzle fits the {}-clue pattern after {} attempts",
+            clues, PATTERN_ATTEMPTS
+        )))
+    }
+
+    /// Returns a random complete grid with much less bias than `fill_board`.
+    ///
+    /// Backtracking with shuffled digits favours some grids over others. The
+    /// filled grid is therefore passed through a uniformly random symmetry
+    /// transform (digit relabelling, band/stack and row/column permutations,
+    /// transposition), which makes every grid in its equivalence class equally
+    /// likely. Which class is picked still follows the backtracking fill.
+    pub fn random_solution(&mut self) -> Board {
+        let mut grid = vec![vec![0; 9]; 9];
+        self.fill_board(&mut grid, 0, 0);
+        Transform::random(&mut self.rng).apply(&Board::new(&grid))
+    }
+
+    /// Provenance for a puzzle this generator just made. The seed is only
+    /// recorded for a seeded generator's first puzzle, the one it reproduces.
+    fn metadata(&mut self, value: &[Vec<i32>], rating: Option<String>) -> GridMetadata {
+        let mut metadata = GridMetadata::new(value, PuzzleSource::Generator);
+        metadata.seed = self.seed.filter(|_| self.generated == 0);
+        metadata.rating = rating;
+        self.generated += 1;
+        metadata
     }
 
     fn fill_board(&mut self, board: &mut Vec<Vec<i32>>, row: usize, col: usize) -> bool {
@@ -98,14 +782,45 @@ impl BoardGenerator {
         true
     }
 
-    fn remove_numbers(&mut self, board: &mut Vec<Vec<i32>>, count: u32) {
+    /// Removes clues from `solution` in random order until none can go without
+    /// losing uniqueness, skipping removals that would empty an unavoidable set
+    fn minimize(&mut self, solution: &Board) -> Board {
+        span!(DEBUG, "minimize", clues = tracing::field::Empty);
+        let sets = unavoidable_rectangles(solution);
+        let mut sets_by_cell: Vec<Vec<usize>> = vec![Vec::new(); 81];
+        for (i, set) in sets.iter().enumerate() {
+            for &cell in set {
+                sets_by_cell[cell].push(i);
+            }
+        }
+
+        let mut puzzle = solution.clone();
+        let mut cells: Vec<usize> = (0..81).collect();
+        cells.shuffle(&mut self.rng);
+        for cell in cells {
+            let last_clue_of_a_set = sets_by_cell[cell]
+                .iter()
+                .any(|&i| sets[i].iter().all(|&other| other == cell || puzzle.cells[other] == 0));
+            if last_clue_of_a_set {
+                continue;
+            }
+            let value = puzzle.cells[cell];
+            puzzle.cells[cell] = 0;
+            if count_solutions_up_to(&puzzle, 2) > 1 {
+                puzzle.cells[cell] = value;
+            }
+        }
+        record!("clues", puzzle.cells.iter().filter(|&&v| v != 0).count());
+        puzzle
+    }
+
+    fn remove_numbers(&mut self, board: &mut [Vec<i32>], count: u32) {
         let mut positions: Vec<(usize, usize)> = (0..9)
             .flat_map(|i| (0..9).map(move |j| (i, j)))
             .collect();
         positions.shuffle(&mut self.rng);
 
         let mut removed = 0;
-        let mut unique_solutions = HashSet::new();
 
         for (row, col) in positions {
             if removed >= count {
@@ -115,66 +830,39 @@ impl BoardGenerator {
             let temp = board[row][col];
             board[row][col] = 0;
 
-            // Verify uniqueness (simplified check)
-            if self.count_solutions(board, &mut unique_solutions, 2) > 1 {
+            // Keep the clue if removing it would allow a second solution
+            if count_solutions_up_to(&Board::new(board), 2) > 1 {
                 board[row][col] = temp;
                 continue;
             }
 
             removed += 1;
         }
+        record!("removed", removed);
     }
 
-    fn count_solutions(&self, board: &Vec<Vec<i32>>, solutions: &mut HashSet<String>, limit: usize) -> usize {
-        if solutions.len() >= limit {
-            return solutions.len();
-        }
-
-        if let Some(pos) = self.find_empty(board) {
-            let (row, col) = pos;
-            for num in 1..=9 {
-                if self.is_valid_placement(board, row, col, num) {
-                    let mut new_board = board.clone();
-                    new_board[row][col] = num;
-                    self.count_solutions(&new_board, solutions, limit);
-                }
-            }
-        } else {
-            solutions.insert(board.iter().flatten().map(|&x| x.to_string()).collect());
-        }
-
-        solutions.len()
-    }
-
-    fn find_empty(&self, board: &Vec<Vec<i32>>) -> Option<(usize, usize)> {
-        for i in 0..9 {
-            for j in 0..9 {
-                if board[i][j] == 0 {
-                    return Some((i, j));
-                }
-            }
-        }
-        None
-    }
-
-    fn get_weighted_difficulty(&mut self) -> &'static str {
-        let total: u32 = self.difficulty_weights.iter().map(|&(w, _)| w).sum();
+    fn get_weighted_difficulty(&mut self) -> String {
+        let difficulties = &self.options.difficulties;
+        // Summed in u64 so weights near u32::MAX can't overflow
+        let total: u64 = difficulties.iter().map(|profile| u64::from(profile.weight)).sum();
         let mut rand_val = self.rng.gen_range(0..total);
         
-        for &(weight, difficulty) in &self.difficulty_weights {
+        for profile in difficulties {
+            let weight = u64::from(profile.weight);
             if rand_val < weight {
-                return difficulty;
+                return profile.name.clone();
             }
             rand_val -= weight;
         }
         
-        self.difficulty_weights[1].1 // Default to Medium
+        unreachable!("weights sum to total")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mini;
 
     #[test]
     fn test_board_generation() {
@@ -182,23 +870,285 @@ mod tests {
         let grid = generator.generate().unwrap();
         
         // Verify board dimensions
+        let solution = grid.solution.as_ref().unwrap();
         assert_eq!(grid.value.len(), 9);
-        assert_eq!(grid.solution.len(), 9);
+        assert_eq!(solution.len(), 9);
         for i in 0..9 {
             assert_eq!(grid.value[i].len(), 9);
-            assert_eq!(grid.solution[i].len(), 9);
+            assert_eq!(solution[i].len(), 9);
         }
 
         // Verify solution is valid
         for row in 0..9 {
             for col in 0..9 {
                 if grid.value[row][col] != 0 {
-                    assert_eq!(grid.value[row][col], grid.solution[row][col]);
+                    assert_eq!(grid.value[row][col], solution[row][col]);
                 }
             }
         }
     }
 
+    #[test]
+    fn test_generate_with_pattern() {
+        // A symmetric 29-clue diamond
+        let mask = parse_pattern(
+            "....x....
+             ...xxx...
+             ..xx.xx..
+             .xx...xx.
+             xx..x..xx
+             .xx...xx.
+             ..xx.xx..
+             ...xxx...
+             ....x....",
+        )
+        .unwrap();
+        let mut generator = BoardGenerator::with_seed(11);
+        let grid = generator.generate_with_pattern(mask).unwrap();
+        let puzzle = Board::new(&grid.value);
+        for (cell, &given) in mask.iter().enumerate() {
+            assert_eq!(puzzle.cells[cell] != 0, given, "cell {}", cell);
+        }
+        assert_eq!(count_solutions_up_to(&puzzle, 2), 1);
+
+        assert!(generator.generate_with_pattern([false; 81]).is_err());
+        assert!(parse_pattern("x..").is_err());
+    }
+
+    #[test]
+    fn test_generate_from_solution() {
+        let mut generator = BoardGenerator::with_seed(9);
+        let solution = generator.random_solution();
+        let options = RemovalOptions { remove: 45..=45, difficulty: None };
+        let grid = generator.generate_from_solution(&solution, &options).unwrap();
+
+        assert_eq!(grid.solution_board().unwrap(), solution);
+        let puzzle = Board::new(&grid.value);
+        assert!(puzzle.cells.iter().zip(&solution.cells).all(|(&p, &s)| p == 0 || p == s));
+        assert_eq!(count_solutions_up_to(&puzzle, 2), 1);
+        assert_eq!(grid.difficulty, rating::rate(&puzzle).difficulty());
+
+        let mut incomplete = solution.clone();
+        incomplete.set(0, 0, 0);
+        assert!(generator.generate_from_solution(&incomplete, &options).is_err());
+
+        #[allow(clippy::reversed_empty_ranges)]
+        for remove in [50..=40, 60..=70] {
+            let options = RemovalOptions { remove, difficulty: None };
+            assert!(matches!(
+                generator.generate_from_solution(&solution, &options),
+                Err(SudokuError::ParseError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unavoidable_rectangles() {
+        let mut generator = BoardGenerator::with_seed(4);
+        let solution = generator.random_solution();
+        for set in unavoidable_rectangles(&solution) {
+            // Swapping the rectangle's digits gives another valid grid
+            let mut swapped = solution.clone();
+            let (a, b) = (solution.cells[set[0]], solution.cells[set[1]]);
+            swapped.cells[set[0]] = b;
+            swapped.cells[set[3]] = b;
+            swapped.cells[set[1]] = a;
+            swapped.cells[set[2]] = a;
+            assert!(SimdValidator::validate_solution(&swapped));
+        }
+    }
+
+    #[test]
+    fn test_hunt_min_clues() {
+        let dir = std::env::temp_dir().join(format!("sudoku-hunt-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let options = HuntOptions {
+            checkpoint: Some(dir.join("best.json")),
+            checkpoint_interval: Duration::ZERO,
+            seed: Some(1),
+        };
+        let report = hunt_min_clues_with(40, Duration::from_secs(30), &options);
+        assert!(report.reached_target);
+        assert!(report.best_clues <= 40);
+        assert!(report.distinct_at_best >= 1);
+
+        let best = Board::new(&report.best.unwrap().value);
+        assert_eq!(best.cells.iter().filter(|&&v| v != 0).count(), report.best_clues);
+        assert_eq!(count_solutions_up_to(&best, 2), 1);
+
+        let saved: HuntReport = serde_json::from_str(&fs::read_to_string(dir.join("best.json")).unwrap()).unwrap();
+        assert_eq!(saved.best_clues, report.best_clues);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_random_solution() {
+        let mut generator = BoardGenerator::with_seed(11);
+        let first = generator.random_solution();
+        let second = generator.random_solution();
+        assert!(crate::simd::SimdValidator::validate_solution(&first));
+        assert!(crate::simd::SimdValidator::validate_solution(&second));
+        assert_ne!(first, second);
+        assert_eq!(BoardGenerator::with_seed(11).random_solution(), first);
+
+        let mut seen = [false; 9];
+        for _ in 0..200 {
+            seen[generator.random_solution().get(0, 0) as usize - 1] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn test_generate_from_pool() {
+        let mut generator = BoardGenerator::with_seed(5);
+        let pool = SolutionPool::new(&mut generator, 2);
+        let grids: Vec<Grid> = (0..6).map(|_| generator.generate_from_pool(&pool).unwrap()).collect();
+        for grid in &grids {
+            assert!(crate::simd::SimdValidator::validate_solution(&grid.solution_board().unwrap()));
+            assert_eq!(count_solutions_up_to(&Board::new(&grid.value), 2), 1);
+        }
+        let distinct: HashSet<&Vec<Vec<i32>>> = grids.iter().map(|grid| &grid.value).collect();
+        assert_eq!(distinct.len(), grids.len());
+    }
+
+    #[test]
+    fn test_daily_is_deterministic() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let first = daily(date, "hard").unwrap();
+        let second = daily(date, "Hard").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.difficulty, "Hard");
+
+        let next_day = daily(date.succ_opt().unwrap(), "Hard").unwrap();
+        assert_ne!(first.solution, next_day.solution);
+
+        // Pinned, so a change to the generator or its RNG that would give
+        // everyone a different puzzle of the day fails here
+        assert_eq!(
+            Board::new(&first.value).to_line(),
+            "....3..61.4.......327..48......5.6......21.97..17.......8..2..59...482..7.......8"
+        );
+    }
+
+    #[test]
+    fn test_grid_metadata() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let grid = daily(date, "Easy").unwrap();
+        let metadata = &grid.metadata;
+        assert_eq!(metadata.source, Some(PuzzleSource::Daily));
+        assert_eq!(metadata.seed, Some(daily_seed(date, "Easy")));
+        assert_eq!(metadata.clues, Some(Board::new(&grid.value).cells.iter().filter(|&&v| v != 0).count()));
+        assert_eq!(metadata.created_at.unwrap().date_naive(), date);
+
+        // Metadata survives serde, and older JSON without it still loads
+        let json = serde_json::to_string(&grid).unwrap();
+        assert_eq!(serde_json::from_str::<Grid>(&json).unwrap(), grid);
+        let bare = r#"{"value":[],"solution":[],"difficulty":"Easy"}"#;
+        assert!(serde_json::from_str::<Grid>(bare).unwrap().metadata.is_empty());
+
+        // Only a seeded generator's first puzzle records the seed
+        let mut generator = BoardGenerator::with_seed(5);
+        let first = generator.generate().unwrap();
+        let second = generator.generate().unwrap();
+        assert_eq!(first.metadata.seed, Some(5));
+        assert_eq!(second.metadata.seed, None);
+        assert_eq!(BoardGenerator::with_seed(5).generate().unwrap().metadata.id, first.metadata.id);
+        assert_ne!(first.metadata.id, second.metadata.id);
+    }
+
+    #[test]
+    fn test_labels_match_estimate() {
+        let mut generator = BoardGenerator::with_seed(3);
+        let estimated = (0..5)
+            .map(|_| generator.generate_with_difficulty("Hard").unwrap())
+            .inspect(|grid| assert_eq!(grid.difficulty, "Hard"))
+            .filter(|grid| rating::estimate(&Board::new(&grid.value)).unwrap().difficulty() == "Hard")
+            .count();
+        assert!(estimated >= 4, "{} of 5 estimated Hard", estimated);
+        // The seed still reproduces the puzzle that was kept
+        let first = BoardGenerator::with_seed(3).generate_with_difficulty("Hard").unwrap();
+        assert_eq!(first.metadata.seed, Some(3));
+        assert_eq!(first.value, BoardGenerator::with_seed(3).generate_with_difficulty("Hard").unwrap().value);
+    }
+
+    #[test]
+    fn test_generate_mini() {
+        for size in [MiniSize::Four, MiniSize::Six] {
+            for difficulty in ["easy", "Medium", "Hard"] {
+                let mut generator = BoardGenerator::with_seed(5);
+                let mini = generator.generate_mini(size, difficulty);
+                assert_eq!(mini, BoardGenerator::with_seed(5).generate_mini(size, difficulty));
+                assert_eq!(mini.difficulty, normalize_difficulty(difficulty));
+                assert_eq!(mini.puzzle.size(), size);
+                assert!(mini::validate_solution(&mini.solution));
+                assert_eq!(mini.puzzle.count_solutions_up_to(2), 1);
+                assert_eq!(mini.puzzle.solve().unwrap(), mini.solution);
+
+                let clues = mini.puzzle.cells().iter().filter(|&&v| v != 0).count();
+                assert!(mini_clues(size, difficulty).contains(&clues), "{} clues in {}", clues, mini.puzzle);
+            }
+        }
+        // Different seeds shuffle the solution differently
+        let four = |seed| BoardGenerator::with_seed(seed).generate_mini(MiniSize::Four, "Easy").solution;
+        assert!((1..10).any(|seed| four(seed) != four(0)));
+    }
+
+    #[test]
+    fn test_generate_hex() {
+        for difficulty in ["Easy", "Hard"] {
+            let hex = BoardGenerator::with_seed(7).generate_hex(difficulty);
+            assert_eq!(hex, BoardGenerator::with_seed(7).generate_hex(difficulty));
+            assert!(crate::hex::validate_solution(&hex.solution));
+            assert_eq!(hex.puzzle.count_solutions_up_to(2), 1);
+            assert_eq!(hex.puzzle.solve().unwrap(), hex.solution);
+            assert!(hex_clues(difficulty).contains(&hex.puzzle.clues()), "{} clues", hex.puzzle.clues());
+        }
+    }
+
+    #[test]
+    fn test_generate_requiring() {
+        let mut generator = BoardGenerator::with_seed(7);
+        let grid = generator.generate_requiring(Technique::LockedCandidates, 200).unwrap();
+        let rating = rating::rate(&Board::new(&grid.value));
+        assert_eq!(rating.hardest, Some(Technique::LockedCandidates));
+        assert_eq!(grid.difficulty, "Medium");
+    }
+
+    #[test]
+    fn test_generator_options() {
+        let options = GeneratorOptions {
+            difficulties: vec![
+                DifficultyProfile { name: "Gentle".to_string(), weight: 1, remove: 20..=20 },
+                DifficultyProfile { name: "Never".to_string(), weight: 0, remove: 60..=60 },
+            ],
+        };
+        let mut generator = BoardGenerator::with_seed(9).with_options(options.clone()).unwrap();
+        for _ in 0..5 {
+            let grid = generator.generate().unwrap();
+            assert_eq!(grid.difficulty, "Gentle");
+            assert_eq!(Board::new(&grid.value).cells.iter().filter(|&&v| v == 0).count(), 20);
+        }
+
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(serde_json::from_str::<GeneratorOptions>(&json).unwrap(), options);
+
+        let zero_weights = GeneratorOptions {
+            difficulties: vec![DifficultyProfile { name: "Easy".to_string(), weight: 0, remove: 30..=35 }],
+        };
+        assert!(BoardGenerator::new().with_options(zero_weights).is_err());
+        let too_many = GeneratorOptions {
+            difficulties: vec![DifficultyProfile { name: "Easy".to_string(), weight: 1, remove: 60..=70 }],
+        };
+        assert!(too_many.validate().is_err());
+        let overflowing = GeneratorOptions {
+            difficulties: vec![
+                DifficultyProfile { name: "Easy".to_string(), weight: u32::MAX, remove: 30..=35 },
+                DifficultyProfile { name: "Hard".to_string(), weight: 1, remove: 50..=55 },
+            ],
+        };
+        assert!(matches!(overflowing.validate(), Err(SudokuError::ParseError(_))));
+    }
+
     #[test]
     fn test_difficulty_distribution() {
         let mut generator = BoardGenerator::new();
@@ -0,0 +1,257 @@
+//! Background generation of ready-to-serve puzzles.
+//!
+//! `spawn_pool` starts worker threads that keep a buffer of rated puzzles
+//! for each difficulty topped up to a target fill level, so callers get a
+//! puzzle without waiting on generation as long as the workers keep up.
+//! Workers fill the emptiest buffer first and sleep once every buffer is
+//! full or being filled, waking when a puzzle is taken. Dropping the handle
+//! (or `shutdown`) stops them, after any puzzle in progress.
+
+use super::{BoardGenerator, GeneratorOptions};
+use crate::{rating, Board, Grid, Result, SudokuError};
+use parking_lot::{Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Settings for `spawn_pool`
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    /// Difficulty labels to keep puzzles of
+    pub difficulties: Vec<String>,
+    /// Puzzles kept ready per difficulty
+    pub target: usize,
+    /// Worker threads
+    pub workers: usize,
+    /// Removal ranges for each difficulty
+    pub generator: GeneratorOptions,
+    /// Seed for reproducible puzzles; worker `i` uses `seed + i`. Which
+    /// buffer each puzzle lands in still depends on thread timing.
+    pub seed: Option<u64>,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        let generator = GeneratorOptions::default();
+        Self {
+            difficulties: generator.difficulties.iter().map(|profile| profile.name.clone()).collect(),
+            target: 16,
+            workers: thread::available_parallelism().map_or(1, |n| n.get() / 2).max(1),
+            generator,
+            seed: None,
+        }
+    }
+}
+
+/// Puzzles of one difficulty
+struct Buffer {
+    difficulty: String,
+    ready: Vec<Grid>,
+    /// Puzzles for this buffer that workers are generating
+    pending: usize,
+}
+
+struct Shared {
+    buffers: Mutex<Vec<Buffer>>,
+    /// Signalled whenever a puzzle is added or taken
+    changed: Condvar,
+    stop: AtomicBool,
+    target: usize,
+}
+
+impl Shared {
+    /// Claims a slot in the emptiest buffer with room, waiting while there is none.
+    /// None once the pool is stopping.
+    fn claim(&self) -> Option<usize> {
+        let mut buffers = self.buffers.lock();
+        loop {
+            if self.stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            let neediest = buffers
+                .iter()
+                .enumerate()
+                .map(|(i, buffer)| (buffer.ready.len() + buffer.pending, i))
+                .filter(|&(filled, _)| filled < self.target)
+                .min();
+            match neediest {
+                Some((_, i)) => {
+                    buffers[i].pending += 1;
+                    return Some(i);
+                }
+                None => self.changed.wait(&mut buffers),
+            }
+        }
+    }
+
+    /// Fills a slot claimed with `claim`, or gives it up when generation failed
+    fn fill(&self, index: usize, grid: Option<Grid>) {
+        let mut buffers = self.buffers.lock();
+        let buffer = &mut buffers[index];
+        buffer.pending -= 1;
+        buffer.ready.extend(grid);
+        self.changed.notify_all();
+    }
+}
+
+/// Handle to a running pool from `spawn_pool`
+pub struct GeneratorHandle {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// Starts `options.workers` threads generating puzzles for every difficulty
+/// in `options`. Errors if there are no difficulties or the generator
+/// options are invalid.
+pub fn spawn_pool(options: PoolOptions) -> Result<GeneratorHandle> {
+    options.generator.validate()?;
+    if options.difficulties.is_empty() {
+        return Err(SudokuError::ParseError("The pool needs at least one difficulty".to_string()));
+    }
+    let shared = Arc::new(Shared {
+        buffers: Mutex::new(
+            options
+                .difficulties
+                .iter()
+                .map(|difficulty| Buffer { difficulty: difficulty.clone(), ready: Vec::new(), pending: 0 })
+                .collect(),
+        ),
+        changed: Condvar::new(),
+        stop: AtomicBool::new(false),
+        target: options.target,
+    });
+    let base_seed = options.seed.unwrap_or_else(rand::random);
+
+    // Workers already started are stopped again if a later one fails to start
+    let mut handle = GeneratorHandle { shared, workers: Vec::new() };
+    for worker in 0..options.workers.max(1) {
+        let generator = BoardGenerator::with_seed(base_seed.wrapping_add(worker as u64))
+            .with_options(options.generator.clone())?;
+        let shared = handle.shared.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("sudoku-generator-{}", worker))
+            .spawn(move || run_worker(&shared, generator))?;
+        handle.workers.push(spawned);
+    }
+    Ok(handle)
+}
+
+fn run_worker(shared: &Shared, mut generator: BoardGenerator) {
+    while let Some(index) = shared.claim() {
+        let difficulty = shared.buffers.lock()[index].difficulty.clone();
+        let grid = generator.generate_with_difficulty(&difficulty).map(|mut grid| {
+            grid.metadata.rating = Some(rating::rate(&Board::new(&grid.value)).difficulty().to_string());
+            grid
+        });
+        match grid {
+            Ok(grid) => shared.fill(index, Some(grid)),
+            Err(e) => {
+                warn!("Pool worker failed to generate a {} puzzle: {}", difficulty, e);
+                shared.fill(index, None);
+            }
+        }
+    }
+}
+
+impl GeneratorHandle {
+    /// A ready puzzle of `difficulty` (matched case-insensitively), if there is one
+    pub fn try_take(&self, difficulty: &str) -> Option<Grid> {
+        let mut buffers = self.shared.buffers.lock();
+        let grid = buffers.iter_mut().find(|buffer| buffer.difficulty.eq_ignore_ascii_case(difficulty))?.ready.pop()?;
+        self.shared.changed.notify_all();
+        Some(grid)
+    }
+
+    /// Waits up to `timeout` for a puzzle of `difficulty`. Errors with
+    /// `GeneratorTimeout` if none is ready in time, and at once if the pool
+    /// doesn't generate that difficulty.
+    pub fn take_timeout(&self, difficulty: &str, timeout: Duration) -> Result<Grid> {
+        let deadline = Instant::now() + timeout;
+        let mut buffers = self.shared.buffers.lock();
+        let index = buffers
+            .iter()
+            .position(|buffer| buffer.difficulty.eq_ignore_ascii_case(difficulty))
+            .ok_or_else(|| SudokuError::ParseError(format!("The pool doesn't generate {} puzzles", difficulty)))?;
+        loop {
+            if let Some(grid) = buffers[index].ready.pop() {
+                self.shared.changed.notify_all();
+                return Ok(grid);
+            }
+            if self.shared.changed.wait_until(&mut buffers, deadline).timed_out() {
+                return Err(SudokuError::GeneratorTimeout);
+            }
+        }
+    }
+
+    /// How many puzzles are ready, per difficulty
+    pub fn ready(&self) -> Vec<(String, usize)> {
+        self.shared.buffers.lock().iter().map(|buffer| (buffer.difficulty.clone(), buffer.ready.len())).collect()
+    }
+
+    /// Stops the workers and waits for them to finish the puzzles in progress
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        // Take the lock so no worker is between checking `stop` and waiting
+        drop(self.shared.buffers.lock());
+        self.shared.changed.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for GeneratorHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(target: usize) -> PoolOptions {
+        PoolOptions { target, workers: 2, seed: Some(7), ..PoolOptions::default() }
+    }
+
+    #[test]
+    fn test_pool_fills_to_target() {
+        let pool = spawn_pool(options(3)).unwrap();
+        let hard = pool.take_timeout("hard", Duration::from_secs(30)).unwrap();
+        assert_eq!(hard.difficulty, "Hard");
+        assert!(hard.metadata.rating.is_some());
+        assert!(crate::validate_solution(&hard.solution_board().unwrap()));
+
+        // Workers stop at the target fill level
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while pool.ready().iter().any(|&(_, ready)| ready < 3) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert!(pool.ready().iter().all(|&(_, ready)| ready == 3), "{:?}", pool.ready());
+        assert!(pool.try_take("Easy").is_some());
+        assert!(pool.try_take("Expert").is_none());
+        assert!(pool.take_timeout("Expert", Duration::ZERO).is_err());
+        pool.shutdown();
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(spawn_pool(PoolOptions { difficulties: Vec::new(), ..options(1) }).is_err());
+        let mut generator = GeneratorOptions::default();
+        generator.difficulties[0].remove = 10..=80;
+        assert!(spawn_pool(PoolOptions { generator, ..options(1) }).is_err());
+    }
+
+    #[test]
+    fn test_timeout_when_idle() {
+        let pool = spawn_pool(options(0)).unwrap();
+        assert!(matches!(pool.take_timeout("Easy", Duration::from_millis(20)), Err(SudokuError::GeneratorTimeout)));
+    }
+}
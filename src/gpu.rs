@@ -0,0 +1,274 @@
+//! GPU propagation for very large batches (`gpu` feature).
+//!
+//! Most of a million-puzzle corpus is easy and medium boards that naked and
+//! hidden singles finish on their own. `Gpu::propagate` runs those two
+//! techniques in a wgpu compute kernel, one board per invocation, and
+//! `solve_all` hands whatever they leave open to the CPU search on rayon.
+//! Without an adapter (no GPU, or no driver for it) everything is solved on
+//! rayon instead.
+
+use crate::{solver::Solver, Board, Result, SudokuError};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use std::future::Future;
+use std::sync::{mpsc, Arc};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use tracing::{info, warn};
+use wgpu::util::DeviceExt;
+
+const KERNEL: &str = include_str!("gpu/propagate.wgsl");
+
+/// Invocations per workgroup, as declared in the kernel
+const WORKGROUP_SIZE: usize = 64;
+
+/// Boards per dispatch, which keeps the cell buffer under wgpu's default
+/// 128 MiB binding limit
+const MAX_DISPATCH: usize = 1 << 18;
+
+/// What propagation made of a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagated {
+    /// Singles ran out with cells still empty
+    Stuck,
+    Solved,
+    /// The givens, or the singles they force, clash
+    Contradiction,
+}
+
+/// A device with the propagation kernel loaded
+pub struct Gpu {
+    adapter: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// Opens the first adapter found, preferring a discrete GPU. Errors when
+    /// there is none.
+    pub fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        };
+        let adapter = block_on(instance.request_adapter(&options))
+            .ok_or_else(|| SudokuError::Gpu("no adapter found".to_string()))?;
+        let descriptor = wgpu::DeviceDescriptor { label: Some("sudoku"), ..Default::default() };
+        let (device, queue) = block_on(adapter.request_device(&descriptor, None)).map_err(gpu_error)?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("propagate"),
+            source: wgpu::ShaderSource::Wgsl(KERNEL.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("propagate"),
+            layout: None,
+            module: &module,
+            entry_point: Some("propagate"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Ok(Self { adapter: adapter.get_info().name, device, queue, pipeline })
+    }
+
+    /// Name of the adapter in use, as the driver reports it
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter
+    }
+
+    /// Fills in every cell naked and hidden singles reach, in place, and
+    /// reports how far each board got
+    pub fn propagate(&self, boards: &mut [Board]) -> Result<Vec<Propagated>> {
+        let mut outcomes = Vec::with_capacity(boards.len());
+        for chunk in boards.chunks_mut(MAX_DISPATCH) {
+            outcomes.extend(self.dispatch(chunk)?);
+        }
+        Ok(outcomes)
+    }
+
+    fn dispatch(&self, boards: &mut [Board]) -> Result<Vec<Propagated>> {
+        let cells: Vec<u8> = boards
+            .iter()
+            .flat_map(|board| board.cells)
+            .flat_map(|value| u32::from(value).to_le_bytes())
+            .collect();
+        let cell_bytes = cells.len() as u64;
+        let status_bytes = boards.len() as u64 * 4;
+
+        let cell_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("cells"),
+            contents: &cells,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+        let status_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("status"),
+            size: status_bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: cell_bytes + status_bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("propagate"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: cell_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: status_buffer.as_entire_binding() },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(boards.len().div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&cell_buffer, 0, &readback, 0, cell_bytes);
+        encoder.copy_buffer_to_buffer(&status_buffer, 0, &readback, cell_bytes, status_bytes);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().map_err(gpu_error)?.map_err(gpu_error)?;
+        let words: Vec<u32> = slice
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        readback.unmap();
+
+        let (values, status) = words.split_at(boards.len() * 81);
+        for (board, values) in boards.iter_mut().zip(values.chunks_exact(81)) {
+            for (cell, &value) in board.cells.iter_mut().zip(values) {
+                *cell = value as u8;
+            }
+        }
+        Ok(status
+            .iter()
+            .map(|&status| match status {
+                1 => Propagated::Solved,
+                2 => Propagated::Contradiction,
+                _ => Propagated::Stuck,
+            })
+            .collect())
+    }
+}
+
+/// The process-wide GPU, opened on first use; None when there is no adapter
+pub fn shared() -> Option<&'static Gpu> {
+    static GPU: Lazy<Option<Gpu>> = Lazy::new(|| match Gpu::new() {
+        Ok(gpu) => {
+            info!("Propagating on {}", gpu.adapter_name());
+            Some(gpu)
+        }
+        Err(e) => {
+            warn!("No GPU available, solving on the CPU: {}", e);
+            None
+        }
+    });
+    GPU.as_ref()
+}
+
+/// Solves every board, propagating singles on the shared GPU first when
+/// there is one, and returns the results in order
+pub fn solve_all(boards: &[Board]) -> Vec<Result<Board>> {
+    let mut propagated = boards.to_vec();
+    let outcomes = match shared().map(|gpu| gpu.propagate(&mut propagated)) {
+        Some(Ok(outcomes)) => outcomes,
+        Some(Err(e)) => {
+            warn!("GPU propagation failed, solving on the CPU: {}", e);
+            propagated = boards.to_vec();
+            vec![Propagated::Stuck; boards.len()]
+        }
+        None => vec![Propagated::Stuck; boards.len()],
+    };
+    propagated
+        .into_par_iter()
+        .zip(outcomes)
+        .map(|(mut board, outcome)| match outcome {
+            Propagated::Contradiction => Err(SudokuError::InvalidBoard),
+            // Solved boards go through the search too, which returns at once
+            // and runs the paranoid check when it is on
+            Propagated::Solved | Propagated::Stuck => Solver::solve_into(&mut board).map(|()| board),
+        })
+        .collect()
+}
+
+fn gpu_error(error: impl std::fmt::Display) -> SudokuError {
+    SudokuError::Gpu(error.to_string())
+}
+
+/// Runs a future to completion on this thread. Native wgpu resolves its
+/// futures without an executor, so this rarely parks.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    #[test]
+    fn test_solve_all() {
+        let names = ["easy", "hard", "duplicate_in_row", "solved", "no_candidates"];
+        let boards: Vec<Board> = names.iter().map(|name| fixture::get(name).unwrap().board()).collect();
+        let results = solve_all(&boards);
+        assert_eq!(results.len(), boards.len());
+        for (puzzle, result) in boards.iter().zip(&results).filter(|(_, result)| result.is_ok()) {
+            let solution = result.as_ref().unwrap();
+            assert!(crate::validate_solution(solution));
+            assert!(puzzle.cells.iter().zip(&solution.cells).all(|(&given, &value)| given == 0 || given == value));
+        }
+        let solved: Vec<bool> = results.iter().map(Result::is_ok).collect();
+        assert_eq!(solved, [true, true, false, true, false]);
+        assert!(solve_all(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_propagate() {
+        // Only runs where there is an adapter
+        let Some(gpu) = shared() else {
+            return;
+        };
+        let names = ["easy", "hard", "duplicate_in_row", "empty"];
+        let mut boards: Vec<Board> = names.iter().map(|name| fixture::get(name).unwrap().board()).collect();
+        let outcomes = gpu.propagate(&mut boards).unwrap();
+        assert_eq!(outcomes[0], Propagated::Solved);
+        assert!(crate::validate_solution(&boards[0]));
+        assert_eq!(outcomes[2], Propagated::Contradiction);
+        assert_eq!((outcomes[3], boards[3].clone()), (Propagated::Stuck, Board::empty()));
+        // Whatever singles fill in agrees with the one solution
+        let hard = fixture::get("hard").unwrap().board();
+        let mut solution = hard.clone();
+        Solver::solve_into(&mut solution).unwrap();
+        assert!(boards[1].cells.iter().zip(&solution.cells).all(|(&value, &expected)| value == 0 || value == expected));
+        assert!(boards[1].cells.iter().filter(|&&v| v != 0).count() >= hard.cells.iter().filter(|&&v| v != 0).count());
+    }
+}
@@ -0,0 +1,126 @@
+// Naked and hidden singles, one board per invocation.
+//
+// `cells` holds 81 values per board, 0 for an empty cell, and is overwritten
+// with the board as far as singles take it. `status` gets 1 if that solved
+// the board, 2 if it ran into a contradiction and 0 if it got stuck.
+
+@group(0) @binding(0) var<storage, read_write> cells: array<u32>;
+@group(0) @binding(1) var<storage, read_write> status: array<u32>;
+
+const ALL: u32 = 0x1FFu;
+
+// Cell `i` of unit `unit`, numbered as in `logic::UNITS`: rows, then columns, then boxes
+fn unit_cell(unit: u32, i: u32) -> u32 {
+    if unit < 9u {
+        return unit * 9u + i;
+    }
+    if unit < 18u {
+        return i * 9u + unit - 9u;
+    }
+    let b = unit - 18u;
+    return ((b / 3u) * 3u + i / 3u) * 9u + (b % 3u) * 3u + i % 3u;
+}
+
+@compute @workgroup_size(64)
+fn propagate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let board = id.x;
+    if board >= arrayLength(&status) {
+        return;
+    }
+    let base = board * 81u;
+    var grid: array<u32, 81>;
+    for (var cell = 0u; cell < 81u; cell++) {
+        grid[cell] = cells[base + cell];
+    }
+
+    var result = 0u;
+    loop {
+        // Digits placed in each unit; a repeat is a contradiction
+        var placed: array<u32, 27>;
+        var failed = false;
+        for (var unit = 0u; unit < 27u; unit++) {
+            var seen = 0u;
+            for (var i = 0u; i < 9u; i++) {
+                let value = grid[unit_cell(unit, i)];
+                if value != 0u {
+                    let bit = 1u << (value - 1u);
+                    failed = failed || (seen & bit) != 0u;
+                    seen |= bit;
+                }
+            }
+            placed[unit] = seen;
+        }
+
+        // Naked singles are placed as they are found. Two in one unit with
+        // the same digit show up as a repeat on the next pass.
+        var candidates: array<u32, 81>;
+        var progress = false;
+        var empty = 0u;
+        for (var cell = 0u; cell < 81u; cell++) {
+            if grid[cell] != 0u {
+                continue;
+            }
+            empty++;
+            let row = cell / 9u;
+            let col = cell % 9u;
+            let mask = ALL & ~(placed[row] | placed[9u + col] | placed[18u + (row / 3u) * 3u + col / 3u]);
+            candidates[cell] = mask;
+            if mask == 0u {
+                failed = true;
+            } else if countOneBits(mask) == 1u {
+                grid[cell] = firstTrailingBit(mask) + 1u;
+                progress = true;
+            }
+        }
+        if failed {
+            result = 2u;
+            break;
+        }
+        if empty == 0u {
+            result = 1u;
+            break;
+        }
+        if progress {
+            continue;
+        }
+
+        // Hidden singles: a digit with one place left in a unit
+        for (var unit = 0u; unit < 27u; unit++) {
+            var once = 0u;
+            var twice = 0u;
+            for (var i = 0u; i < 9u; i++) {
+                let mask = candidates[unit_cell(unit, i)];
+                twice |= once & mask;
+                once |= mask;
+            }
+            failed = failed || (once | placed[unit]) != ALL;
+            let hidden = once & ~twice;
+            for (var i = 0u; i < 9u; i++) {
+                let cell = unit_cell(unit, i);
+                let only = candidates[cell] & hidden;
+                if only == 0u || grid[cell] != 0u {
+                    continue;
+                }
+                // The only place for two digits
+                if countOneBits(only) > 1u {
+                    failed = true;
+                } else {
+                    grid[cell] = firstTrailingBit(only) + 1u;
+                    progress = true;
+                }
+            }
+        }
+        if failed {
+            result = 2u;
+            break;
+        }
+        if !progress {
+            break;
+        }
+    }
+
+    for (var cell = 0u; cell < 81u; cell++) {
+        cells[base + cell] = grid[cell];
+    }
+    status[board] = result;
+}
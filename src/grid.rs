@@ -0,0 +1,226 @@
+//! Checked construction of `Grid`s.
+//!
+//! A `Grid` is plain data, so nothing stops one being built with a ragged
+//! board, a placeholder solution of zeros or a difficulty in any case.
+//! `GridBuilder::build` checks the shape and values of the puzzle and its
+//! solution, that no two givens clash, that the solution is a completed grid
+//! keeping every given, and spells the difficulty the way the rest of the
+//! crate does.
+
+use crate::{logic::Unit, solver::Solver, validation, Board, Grid, GridMetadata, Result, SudokuError};
+
+/// Difficulty of a grid nobody has labelled
+pub const UNKNOWN_DIFFICULTY: &str = "Unknown";
+
+/// Labels matched case-insensitively and replaced by this spelling
+const DIFFICULTIES: [&str; 5] = ["Easy", "Medium", "Hard", "Expert", UNKNOWN_DIFFICULTY];
+
+/// Builds a `Grid`, validating it in `build`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridBuilder {
+    value: Vec<Vec<i32>>,
+    solution: Option<Vec<Vec<i32>>>,
+    difficulty: String,
+    metadata: GridMetadata,
+}
+
+impl GridBuilder {
+    /// Starts from the puzzle's rows, 0 for an empty cell
+    pub fn new(value: Vec<Vec<i32>>) -> Self {
+        Self { value, solution: None, difficulty: String::new(), metadata: GridMetadata::default() }
+    }
+
+    pub fn from_board(puzzle: &Board) -> Self {
+        Self::new(puzzle.to_vec())
+    }
+
+    /// The completed grid; without one the `Grid` has no solution
+    pub fn with_solution(mut self, solution: Vec<Vec<i32>>) -> Self {
+        self.solution = Some(solution);
+        self
+    }
+
+    /// Matched case-insensitively against Easy, Medium, Hard and Expert;
+    /// other labels are kept as given, and a blank one becomes "Unknown"
+    pub fn with_difficulty(mut self, difficulty: &str) -> Self {
+        self.difficulty = difficulty.to_string();
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: GridMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Checks the grid and builds it. The puzzle and solution must be 9 rows
+    /// of 9 values, the puzzle's in 0-9 with no digit given twice in a row,
+    /// column or box, and the solution's a valid completed grid that agrees
+    /// with every given. A repeated given is a `RuleViolation` at its second
+    /// cell in the first unit that has one.
+    pub fn build(self) -> Result<Grid> {
+        let puzzle = Board::try_new(&self.value)?;
+        if let Some(conflict) = validation::conflicts(&puzzle).into_iter().next() {
+            let (row, col) = conflict.cells[1];
+            let rule = match conflict.unit {
+                Unit::Row(_) => "row",
+                Unit::Column(_) => "column",
+                Unit::Box(_) => "box",
+            };
+            return Err(SudokuError::RuleViolation { rule: rule.to_string(), row, col, value: conflict.value });
+        }
+        if let Some(solution) = &self.solution {
+            let solution = Board::try_new(solution).map_err(in_solution)?;
+            if !crate::validate_solution(&solution) {
+                return Err(SudokuError::ParseError("the solution isn't a completed grid".to_string()));
+            }
+            let clash = (0..81).find(|&cell| puzzle.cells[cell] != 0 && puzzle.cells[cell] != solution.cells[cell]);
+            if let Some(cell) = clash {
+                return Err(SudokuError::ParseError(format!(
+                    "the given {} at r{}c{} differs from the solution's {}",
+                    puzzle.cells[cell],
+                    cell / 9 + 1,
+                    cell % 9 + 1,
+                    solution.cells[cell]
+                )));
+            }
+        }
+        Ok(Grid {
+            value: self.value,
+            solution: self.solution,
+            difficulty: normalize_difficulty(&self.difficulty),
+            metadata: self.metadata,
+        })
+    }
+}
+
+impl From<&Board> for Grid {
+    /// A puzzle on its own: no solution, difficulty "Unknown" and no metadata
+    fn from(puzzle: &Board) -> Self {
+        Grid {
+            value: puzzle.to_vec(),
+            solution: None,
+            difficulty: UNKNOWN_DIFFICULTY.to_string(),
+            metadata: GridMetadata::default(),
+        }
+    }
+}
+
+impl Grid {
+    /// The known solution as a board
+    pub fn solution_board(&self) -> Option<Board> {
+        self.solution.as_deref().map(Board::new)
+    }
+
+    /// The solution, solving the puzzle for it first when it isn't known.
+    /// When the puzzle has several solutions this is whichever the search
+    /// finds first; errors when it has none.
+    pub fn ensure_solution(&mut self) -> Result<&[Vec<i32>]> {
+        if self.solution.is_none() {
+            let mut board = Board::try_new(&self.value)?;
+            Solver::solve_into(&mut board)?;
+            self.solution = Some(board.to_vec());
+        }
+        Ok(self.solution.as_deref().unwrap_or_default())
+    }
+}
+
+fn normalize_difficulty(difficulty: &str) -> String {
+    let difficulty = difficulty.trim();
+    if difficulty.is_empty() {
+        return UNKNOWN_DIFFICULTY.to_string();
+    }
+    DIFFICULTIES
+        .iter()
+        .find(|label| label.eq_ignore_ascii_case(difficulty))
+        .map_or_else(|| difficulty.to_string(), |label| label.to_string())
+}
+
+/// Says an error from `Board::try_new` was about the solution
+fn in_solution(error: SudokuError) -> SudokuError {
+    match error {
+        SudokuError::ParseError(message) => SudokuError::ParseError(format!("solution: {}", message)),
+        SudokuError::InvalidValue { row, col, value } => {
+            SudokuError::ParseError(format!("solution: invalid value {} at position ({}, {})", value, row, col))
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    #[test]
+    fn test_build() {
+        let easy = fixture::get("easy").unwrap().grid().unwrap();
+        let grid = GridBuilder::new(easy.value.clone())
+            .with_solution(easy.solution.clone().unwrap())
+            .with_difficulty(" easy ")
+            .build()
+            .unwrap();
+        assert_eq!((&grid.value, &grid.solution, grid.difficulty.as_str()), (&easy.value, &easy.solution, "Easy"));
+
+        let puzzle = Board::new(&easy.value);
+        let grid = GridBuilder::from_board(&puzzle).with_difficulty("Fiendish").build().unwrap();
+        assert!(grid.solution.is_none());
+        assert_eq!(grid.difficulty, "Fiendish");
+        assert_eq!(GridBuilder::from_board(&puzzle).build().unwrap(), Grid::from(&puzzle));
+    }
+
+    #[test]
+    fn test_build_rejects() {
+        let easy = fixture::get("easy").unwrap().grid().unwrap();
+        let solution = easy.solution.clone().unwrap();
+        let build = |value: Vec<Vec<i32>>, solution: Vec<Vec<i32>>| {
+            GridBuilder::new(value).with_solution(solution).build().unwrap_err().to_string()
+        };
+
+        let mut ragged = easy.value.clone();
+        ragged[3].pop();
+        assert!(build(ragged, solution.clone()).contains("expected 9 values in row 3"));
+        let mut out_of_range = easy.value.clone();
+        out_of_range[0][2] = 10;
+        assert!(GridBuilder::new(out_of_range).build().is_err());
+        // The placeholder solution some callers used to pass
+        assert!(build(easy.value.clone(), vec![vec![0; 9]; 9]).contains("isn't a completed grid"));
+        assert!(build(easy.value.clone(), vec![vec![1; 9]; 8]).contains("solution: expected 9 rows"));
+
+        // A given that the solution doesn't keep, though it clashes with no other given
+        let mut value = easy.value.clone();
+        let puzzle = Board::new(&value);
+        let (row, col, digit) = (0..81 * 9)
+            .map(|i| (i / 81, i / 9 % 9, (i % 9) as u8 + 1))
+            .find(|&(r, c, d)| value[r][c] == 0 && d as i32 != solution[r][c] && puzzle.can_place(r, c, d))
+            .unwrap();
+        value[row][col] = digit as i32;
+        let message = build(value, solution.clone());
+        assert!(message.contains(&format!("at r{}c{}", row + 1, col + 1)), "{}", message);
+
+        // Two 5s in the first row, with or without a solution
+        let mut value = easy.value.clone();
+        assert_eq!((value[0][0], value[0][2]), (5, 0));
+        value[0][2] = 5;
+        for builder in [GridBuilder::new(value.clone()), GridBuilder::new(value).with_solution(solution)] {
+            match builder.build() {
+                Err(SudokuError::RuleViolation { rule, row, col, value }) => {
+                    assert_eq!((rule.as_str(), row, col, value), ("row", 0, 2, 5));
+                }
+                other => panic!("expected a rule violation, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ensure_solution() {
+        let easy = fixture::get("easy").unwrap().grid().unwrap();
+        let mut grid = Grid::from(&Board::new(&easy.value));
+        assert_eq!(grid.solution_board(), None);
+        assert_eq!(grid.ensure_solution().unwrap(), easy.solution.as_deref().unwrap());
+        assert_eq!(grid.solution_board(), easy.solution_board());
+
+        let mut clashing = Grid::from(&fixture::get("duplicate_in_row").unwrap().board());
+        assert!(clashing.ensure_solution().is_err());
+        assert!(clashing.solution.is_none());
+    }
+}
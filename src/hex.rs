@@ -0,0 +1,429 @@
+//! 16×16 puzzles ("hexadoku").
+//!
+//! A `HexBoard` has sixteen symbols, the digits 1 to 9 then the letters A to
+//! G, in 4×4 boxes. Values are stored as 1 to 16, so A is 10 and G is 16, and
+//! a unit's used symbols fit in one `u32` mask. The solver tries the cell with
+//! the fewest candidates first and can be given a node budget: at this size a
+//! sparse puzzle can take a naive search a very long time, and the generator
+//! would rather keep a clue than wait to prove it redundant.
+
+use crate::{Result, SudokuError};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::fmt;
+
+/// Side of the grid
+pub const SIDE: usize = 16;
+
+/// Side of a box
+pub const BOX: usize = 4;
+
+pub const CELLS: usize = SIDE * SIDE;
+
+/// Every value as a mask, bit `v` standing for value `v`
+const ALL: u32 = ((1 << SIDE) - 1) << 1;
+
+/// The character for a value: 1-9, then A-G, `.` for 0
+pub fn symbol(value: u8) -> char {
+    match value {
+        0 => '.',
+        1..=9 => char::from(b'0' + value),
+        _ => char::from(b'A' + value - 10),
+    }
+}
+
+/// The value of a character, case-insensitively; None for anything but a
+/// symbol or an empty-cell marker
+fn value_of(c: char) -> Option<u8> {
+    match c.to_ascii_uppercase() {
+        '.' | '0' | '-' | '_' => Some(0),
+        c @ '1'..='9' => Some(c as u8 - b'0'),
+        c @ 'A'..='G' => Some(c as u8 - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Returns true if `input` has as many cells as a 16×16 grid once whitespace
+/// is dropped
+pub fn detect(input: &str) -> bool {
+    input.chars().filter(|c| !c.is_whitespace()).count() == CELLS
+}
+
+/// A 16×16 grid, 0 for an empty cell
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HexBoard {
+    cells: [u8; CELLS],
+}
+
+impl Default for HexBoard {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl HexBoard {
+    pub fn empty() -> Self {
+        Self { cells: [0; CELLS] }
+    }
+
+    /// Parses 256 cells, row by row, ignoring whitespace. Symbols are 1-9
+    /// and A-G in either case; `.`, `0`, `-` or `_` marks an empty cell.
+    pub fn parse(input: &str) -> Result<Self> {
+        let cells: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+        if cells.len() != CELLS {
+            return Err(SudokuError::ParseError(format!("expected {} cells, found {}", CELLS, cells.len())));
+        }
+        let mut board = Self::empty();
+        for (cell, &c) in cells.iter().enumerate() {
+            board.cells[cell] =
+                value_of(c).ok_or_else(|| SudokuError::ParseError(format!("unexpected character '{}'", c)))?;
+        }
+        Ok(board)
+    }
+
+    /// Builds a board from 16 rows of 16 values, each 0 to 16
+    pub fn try_new(rows: &[Vec<i32>]) -> Result<Self> {
+        if rows.len() != SIDE {
+            return Err(SudokuError::ParseError(format!("expected {} rows, found {}", SIDE, rows.len())));
+        }
+        let mut board = Self::empty();
+        for (row, values) in rows.iter().enumerate() {
+            if values.len() != SIDE {
+                return Err(SudokuError::ParseError(format!(
+                    "expected {} values in row {}, found {}",
+                    SIDE,
+                    row,
+                    values.len()
+                )));
+            }
+            for (col, &value) in values.iter().enumerate() {
+                if !(0..=SIDE as i32).contains(&value) {
+                    return Err(SudokuError::InvalidValue { row, col, value });
+                }
+                board.set(row, col, value as u8);
+            }
+        }
+        Ok(board)
+    }
+
+    /// A complete grid. Every call gives the same one; `shuffled` makes it random.
+    pub fn solved() -> Self {
+        let mut board = Self::empty();
+        for row in 0..SIDE {
+            for col in 0..SIDE {
+                board.set(row, col, ((row % BOX * BOX + row / BOX + col) % SIDE) as u8 + 1);
+            }
+        }
+        board
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.cells[row * SIDE + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: u8) {
+        self.cells[row * SIDE + col] = value;
+    }
+
+    /// Row by row
+    pub fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    /// Filled cells
+    pub fn clues(&self) -> usize {
+        self.cells.iter().filter(|&&value| value != 0).count()
+    }
+
+    /// Values no peer of the cell holds, as a mask with bit `v` for value `v`
+    pub fn candidates(&self, row: usize, col: usize) -> u32 {
+        let (box_row, box_col) = (row / BOX * BOX, col / BOX * BOX);
+        let used = (0..SIDE).fold(0u32, |used, i| {
+            used | 1 << self.get(row, i)
+                | 1 << self.get(i, col)
+                | 1 << self.get(box_row + i / BOX, box_col + i % BOX)
+        });
+        ALL & !used
+    }
+
+    /// The board as one line, `.` for empty cells
+    pub fn to_line(&self) -> String {
+        self.cells.iter().map(|&value| symbol(value)).collect()
+    }
+
+    pub fn to_vec(&self) -> Vec<Vec<i32>> {
+        self.cells.chunks(SIDE).map(|row| row.iter().map(|&v| v as i32).collect()).collect()
+    }
+
+    /// Returns true if no two givens in a row, column or box share a value
+    pub fn is_consistent(&self) -> bool {
+        Search::new(self, usize::MAX).is_some()
+    }
+
+    /// Counts solutions, stopping once `limit` are found
+    pub fn count_solutions_up_to(&self, limit: usize) -> usize {
+        self.count_solutions_within(limit, usize::MAX).unwrap_or(limit)
+    }
+
+    /// Counts solutions as `count_solutions_up_to` does, giving up with None
+    /// once the search has tried `budget` placements
+    pub fn count_solutions_within(&self, limit: usize, budget: usize) -> Option<usize> {
+        let Some(mut search) = Search::new(self, budget) else {
+            return Some(0);
+        };
+        let mut count = 0;
+        search.run(&mut |_| {
+            count += 1;
+            count < limit
+        });
+        (!search.exhausted).then_some(count)
+    }
+
+    /// The first solution found. Errors with `InvalidBoard` when the givens
+    /// clash or there is no solution.
+    pub fn solve(&self) -> Result<HexBoard> {
+        let mut search = Search::new(self, usize::MAX).ok_or(SudokuError::InvalidBoard)?;
+        let mut solution = None;
+        search.run(&mut |found| {
+            solution = Some(Self { cells: *found });
+            false
+        });
+        solution.ok_or(SudokuError::InvalidBoard)
+    }
+
+    /// The board under a random relabelling of the symbols, reordering of rows
+    /// within bands, bands, columns within stacks and stacks, and maybe a
+    /// transposition, which keeps a valid grid valid and a unique puzzle unique
+    pub fn shuffled<R: Rng + ?Sized>(&self, rng: &mut R) -> HexBoard {
+        let mut values: Vec<u8> = (1..=SIDE as u8).collect();
+        values.shuffle(rng);
+        let rows = permutation(rng);
+        let cols = permutation(rng);
+        let transpose = rng.gen_bool(0.5);
+        let mut shuffled = Self::empty();
+        for row in 0..SIDE {
+            for col in 0..SIDE {
+                let (from_row, from_col) = if transpose { (cols[col], rows[row]) } else { (rows[row], cols[col]) };
+                match self.get(from_row, from_col) {
+                    0 => {}
+                    value => shuffled.set(row, col, values[value as usize - 1]),
+                }
+            }
+        }
+        shuffled
+    }
+}
+
+/// A random order of the 16 rows or columns that keeps each band or stack of
+/// four together, moving the bands and the lines within each
+fn permutation<R: Rng + ?Sized>(rng: &mut R) -> Vec<usize> {
+    let mut groups: Vec<usize> = (0..SIDE / BOX).collect();
+    groups.shuffle(rng);
+    groups
+        .into_iter()
+        .flat_map(|group| {
+            let mut lines: Vec<usize> = (group * BOX..(group + 1) * BOX).collect();
+            lines.shuffle(rng);
+            lines
+        })
+        .collect()
+}
+
+/// Returns true if every cell is filled and no unit repeats a value
+pub fn validate_solution(board: &HexBoard) -> bool {
+    board.cells.iter().all(|&value| value != 0) && board.is_consistent()
+}
+
+/// Depth-first search over a board, keeping each unit's used values as masks
+struct Search {
+    cells: [u8; CELLS],
+    rows: [u32; SIDE],
+    cols: [u32; SIDE],
+    boxes: [u32; SIDE],
+    /// Placements left before giving up
+    budget: usize,
+    /// Set when the budget ran out before the search finished
+    exhausted: bool,
+}
+
+impl Search {
+    /// None when two givens in a unit share a value
+    fn new(board: &HexBoard, budget: usize) -> Option<Self> {
+        let mut search = Self {
+            cells: [0; CELLS],
+            rows: [0; SIDE],
+            cols: [0; SIDE],
+            boxes: [0; SIDE],
+            budget,
+            exhausted: false,
+        };
+        for (cell, &value) in board.cells.iter().enumerate().filter(|&(_, &value)| value != 0) {
+            if search.candidates(cell) & 1 << value == 0 {
+                return None;
+            }
+            search.place(cell, value);
+        }
+        Some(search)
+    }
+
+    fn units(cell: usize) -> (usize, usize, usize) {
+        let (row, col) = (cell / SIDE, cell % SIDE);
+        (row, col, row / BOX * BOX + col / BOX)
+    }
+
+    fn candidates(&self, cell: usize) -> u32 {
+        let (row, col, bx) = Self::units(cell);
+        ALL & !(self.rows[row] | self.cols[col] | self.boxes[bx])
+    }
+
+    fn place(&mut self, cell: usize, value: u8) {
+        let (row, col, bx) = Self::units(cell);
+        self.cells[cell] = value;
+        self.rows[row] |= 1 << value;
+        self.cols[col] |= 1 << value;
+        self.boxes[bx] |= 1 << value;
+    }
+
+    fn clear(&mut self, cell: usize) {
+        let (row, col, bx) = Self::units(cell);
+        let bit = !(1 << self.cells[cell]);
+        self.cells[cell] = 0;
+        self.rows[row] &= bit;
+        self.cols[col] &= bit;
+        self.boxes[bx] &= bit;
+    }
+
+    /// Calls `found` with each solution; stops as soon as it returns false or
+    /// the budget runs out. Returns false once stopped.
+    fn run(&mut self, found: &mut dyn FnMut(&[u8; CELLS]) -> bool) -> bool {
+        // The empty cell with the fewest candidates
+        let mut best: Option<(usize, u32)> = None;
+        for cell in (0..CELLS).filter(|&cell| self.cells[cell] == 0) {
+            let candidates = self.candidates(cell);
+            if best.is_none_or(|(_, fewest)| candidates.count_ones() < fewest.count_ones()) {
+                best = Some((cell, candidates));
+            }
+            if candidates.count_ones() <= 1 {
+                break;
+            }
+        }
+        let Some((cell, mut candidates)) = best else {
+            return found(&self.cells);
+        };
+        while candidates != 0 {
+            if self.budget == 0 {
+                self.exhausted = true;
+                return false;
+            }
+            self.budget -= 1;
+            let value = candidates.trailing_zeros() as u8;
+            candidates &= candidates - 1;
+            self.place(cell, value);
+            let going = self.run(found);
+            self.clear(cell);
+            if !going {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl fmt::Display for HexBoard {
+    /// The grid with box borders, `.` for empty cells
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let border = format!("+{}\n", format!("{}+", "-".repeat(BOX * 2 + 1)).repeat(SIDE / BOX));
+        for row in 0..SIDE {
+            if row % BOX == 0 {
+                f.write_str(&border)?;
+            }
+            for col in 0..SIDE {
+                if col % BOX == 0 {
+                    f.write_str("| ")?;
+                }
+                write!(f, "{} ", symbol(self.get(row, col)))?;
+            }
+            f.write_str("|\n")?;
+        }
+        f.write_str(&border)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// `HexBoard::solved()` with every other cell of the first eight rows emptied
+    fn puzzle() -> HexBoard {
+        let mut board = HexBoard::solved();
+        for cell in (0..CELLS / 2).step_by(2) {
+            board.cells[cell] = 0;
+        }
+        board
+    }
+
+    #[test]
+    fn test_parse() {
+        let solved = HexBoard::solved();
+        let line = solved.to_line();
+        assert_eq!(&line[..SIDE], "123456789ABCDEFG");
+        assert_eq!(HexBoard::parse(&line).unwrap(), solved);
+        assert_eq!(HexBoard::parse(&line.to_lowercase()).unwrap(), solved);
+        assert_eq!(HexBoard::try_new(&solved.to_vec()).unwrap(), solved);
+        assert!(detect(&line) && !detect(&line[1..]));
+
+        let mut bad = line.clone();
+        bad.replace_range(5..6, "H");
+        assert!(HexBoard::parse(&bad).unwrap_err().to_string().contains("'H'"));
+        assert!(HexBoard::parse(&line[..81]).unwrap_err().to_string().contains("found 81"));
+        assert!(matches!(
+            HexBoard::try_new(&vec![vec![17; SIDE]; SIDE]),
+            Err(SudokuError::InvalidValue { row: 0, col: 0, value: 17 })
+        ));
+    }
+
+    #[test]
+    fn test_solve() {
+        assert!(validate_solution(&HexBoard::solved()));
+        let mut rng = StdRng::seed_from_u64(1);
+        let shuffled = HexBoard::solved().shuffled(&mut rng);
+        assert!(validate_solution(&shuffled));
+        assert_ne!(shuffled, HexBoard::solved());
+
+        let puzzle = puzzle().shuffled(&mut rng);
+        let solution = puzzle.solve().unwrap();
+        assert!(validate_solution(&solution));
+        assert!(puzzle.cells().iter().zip(solution.cells()).all(|(&given, &value)| given == 0 || given == value));
+        assert!(validate_solution(&HexBoard::empty().solve().unwrap()));
+
+        let mut clashing = HexBoard::empty();
+        clashing.set(0, 0, 16);
+        clashing.set(3, 3, 16);
+        assert!(!clashing.is_consistent());
+        assert!(matches!(clashing.solve(), Err(SudokuError::InvalidBoard)));
+        assert_eq!(clashing.count_solutions_up_to(2), 0);
+    }
+
+    #[test]
+    fn test_count_within_budget() {
+        assert_eq!(HexBoard::solved().count_solutions_within(2, 0), Some(1));
+        assert_eq!(HexBoard::empty().count_solutions_up_to(2), 2);
+        // Proving an empty grid has two solutions takes more than ten placements
+        assert_eq!(HexBoard::empty().count_solutions_within(2, 10), None);
+
+        let mut board = HexBoard::solved();
+        let value = board.get(0, 0);
+        board.set(0, 0, 0);
+        assert_eq!(board.candidates(0, 0), 1 << value);
+        assert_eq!(board.count_solutions_within(2, 100), Some(1));
+    }
+
+    #[test]
+    fn test_display() {
+        let text = puzzle().to_string();
+        assert_eq!(text.lines().next(), Some("+---------+---------+---------+---------+"));
+        assert_eq!(text.lines().nth(1), Some("| . 2 . 4 | . 6 . 8 | . A . C | . E . G |"));
+        assert_eq!(text.lines().count(), 21);
+    }
+}
@@ -0,0 +1,139 @@
+//! Hints for a partially solved grid.
+//!
+//! A hint is the next step `LogicSolver` would take from the grid, told at
+//! one of three levels so a player can ask for just as much help as they
+//! need: which technique to look for, where to look, or the full deduction.
+
+use crate::{
+    explain::{self, cell_list},
+    logic::{LogicSolver, SolveStep, Technique},
+    solver::count_solutions_up_to,
+    Board, Result, SudokuError,
+};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// How much a hint gives away
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HintLevel {
+    /// The technique, and the unit to look in when there is one
+    Nudge,
+    /// The cells involved, but not the digit
+    #[default]
+    Cell,
+    /// The whole deduction
+    Full,
+}
+
+impl FromStr for HintLevel {
+    type Err = SudokuError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "nudge" => Ok(Self::Nudge),
+            "cell" => Ok(Self::Cell),
+            "full" => Ok(Self::Full),
+            _ => Err(SudokuError::ParseError(format!("unknown hint level '{}' (nudge, cell, full)", s))),
+        }
+    }
+}
+
+/// The next logical move, told at some level
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hint {
+    pub level: HintLevel,
+    pub technique: Technique,
+    pub text: String,
+    /// The step itself; only given away at `HintLevel::Full`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<SolveStep>,
+}
+
+impl Hint {
+    fn new(step: SolveStep, level: HintLevel) -> Self {
+        let technique = step.technique;
+        let text = match level {
+            HintLevel::Nudge => match step.unit {
+                Some(unit) => format!("Look for {} in {}", pattern(technique), unit),
+                None => format!("Look for {}", pattern(technique)),
+            },
+            HintLevel::Cell => match step.placements.as_slice() {
+                [placement] => format!(
+                    "{} can be filled in: look for {} there",
+                    explain::cell_name(placement.row, placement.col),
+                    pattern(technique)
+                ),
+                _ => format!(
+                    "The {} pattern in {} removes candidates from {}",
+                    technique,
+                    cell_list(step.cells.iter().copied()),
+                    cell_list(step.eliminations.iter().map(|e| (e.row, e.col)))
+                ),
+            },
+            HintLevel::Full => format!("{}: {}", technique, explain::explain(&step)),
+        };
+        Self { level, technique, text, step: (level == HintLevel::Full).then_some(step) }
+    }
+}
+
+/// Names a technique as a pattern to look for: "a Naked Single", "an X-Wing", "Locked Candidates"
+fn pattern(technique: Technique) -> String {
+    let name = technique.name();
+    if name.ends_with('s') {
+        name.to_string()
+    } else if name.starts_with('X') {
+        format!("an {}", name)
+    } else {
+        format!("a {}", name)
+    }
+}
+
+/// Finds the next logical move from `grid`, a puzzle with the player's
+/// entries filled in.
+///
+/// Returns `None` when the grid is complete or no known technique applies.
+/// Grids whose entries clash or that no longer have a solution, because an
+/// entry is wrong, are `InvalidBoard`.
+pub fn next_hint(grid: &Board, level: HintLevel) -> Result<Option<Hint>> {
+    if !grid.is_consistent() || count_solutions_up_to(grid, 1) == 0 {
+        return Err(SudokuError::InvalidBoard);
+    }
+    Ok(LogicSolver::new(grid).find_step().map(|step| Hint::new(step, level)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    #[test]
+    fn test_hint_levels() {
+        let grid = fixture::get("easy").unwrap().board();
+        let nudge = next_hint(&grid, HintLevel::Nudge).unwrap().unwrap();
+        let full = next_hint(&grid, HintLevel::Full).unwrap().unwrap();
+        assert_eq!(nudge.technique, full.technique);
+        assert!(nudge.text.starts_with("Look for a "), "{}", nudge.text);
+        assert!(nudge.step.is_none());
+
+        // Only the full hint gives the digit away
+        let placement = full.step.as_ref().unwrap().placements[0];
+        let cell = next_hint(&grid, HintLevel::Cell).unwrap().unwrap();
+        assert!(cell.text.starts_with(&explain::cell_name(placement.row, placement.col)), "{}", cell.text);
+        assert!(full.text.contains(&format!("must be {}", placement.value)), "{}", full.text);
+
+        assert_eq!("FULL".parse::<HintLevel>().unwrap(), HintLevel::Full);
+        assert!("everything".parse::<HintLevel>().is_err());
+    }
+
+    #[test]
+    fn test_hint_on_wrong_or_finished_grid() {
+        assert!(next_hint(&fixture::get("solved").unwrap().board(), HintLevel::Full).unwrap().is_none());
+
+        // A wrong entry that clashes with nothing still leaves no solution
+        let mut grid = fixture::get("easy").unwrap().board();
+        grid.set(0, 2, 1);
+        assert!(grid.is_consistent());
+        assert!(matches!(next_hint(&grid, HintLevel::Nudge), Err(SudokuError::InvalidBoard)));
+    }
+}
@@ -1,14 +1,65 @@
+// Grid code reads most naturally with explicit row/column indices
+#![allow(clippy::needless_range_loop)]
+
 use serde::{Deserialize, Serialize};
-use std::fmt;
 
+pub use grid::GridBuilder;
+pub use validation::ValidationReport;
+
+#[macro_use]
+mod trace;
 pub mod solver;
 pub mod api;
+pub mod cache;
 pub mod benchmark;
 pub mod simd;
 pub mod generator;
+pub mod pencilmarks;
+pub mod bitboard;
+pub mod progress;
+pub mod game;
+pub mod logic;
+pub mod rating;
+pub mod explain;
+pub mod hint;
+pub mod contradiction;
+pub mod replay;
+pub mod server;
+pub mod access;
+pub mod symmetry;
+pub mod diff;
+pub mod describe;
+pub mod render;
+pub mod qr;
+pub mod share;
+pub mod formats;
+pub mod metrics;
+pub mod providers;
+pub mod dlx;
+pub mod portfolio;
+pub mod verify;
+pub mod output;
+pub mod batch;
+pub mod stats;
+pub mod validation;
+pub mod grid;
+pub mod mini;
+pub mod hex;
+pub mod watch;
+pub mod config;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "otlp")]
+pub mod telemetry;
+#[cfg(feature = "sat")]
+pub mod sat;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 /// A bitset representation of candidate numbers for a Sudoku cell
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct CandidateSet(pub(crate) u16);
 
 impl CandidateSet {
@@ -26,19 +77,19 @@ impl CandidateSet {
 
     #[inline]
     pub fn add_candidate(&mut self, n: u8) {
-        debug_assert!(n >= 1 && n <= 9, "Invalid candidate number");
+        debug_assert!((1..=9).contains(&n), "Invalid candidate number");
         self.0 |= 1 << (n - 1);
     }
 
     #[inline]
     pub fn remove_candidate(&mut self, n: u8) {
-        debug_assert!(n >= 1 && n <= 9, "Invalid candidate number");
+        debug_assert!((1..=9).contains(&n), "Invalid candidate number");
         self.0 &= !(1 << (n - 1));
     }
 
     #[inline]
     pub fn has_candidate(&self, n: u8) -> bool {
-        debug_assert!(n >= 1 && n <= 9, "Invalid candidate number");
+        debug_assert!((1..=9).contains(&n), "Invalid candidate number");
         (self.0 & (1 << (n - 1))) != 0
     }
 
@@ -60,24 +111,47 @@ impl CandidateSet {
 
 /// A flat array representation of a Sudoku board
 #[repr(align(16))]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Board {
     pub(crate) cells: [u8; 81],
 }
 
 impl Board {
-    /// Creates a new board from a 2D grid
+    /// Creates a new board from a 2D grid.
+    ///
+    /// The grid is trusted: values outside 0-9 trip a debug assertion and more
+    /// than 9 rows or columns panic. Use `try_new` for untrusted input.
     pub fn new(grid: &[Vec<i32>]) -> Self {
         let mut cells = [0; 81];
         for (i, row) in grid.iter().enumerate() {
             for (j, &val) in row.iter().enumerate() {
-                debug_assert!(val >= 0 && val <= 9, "Invalid cell value");
+                debug_assert!((0..=9).contains(&val), "Invalid cell value");
                 cells[i * 9 + j] = val as u8;
             }
         }
         Self { cells }
     }
 
+    /// Creates a board from a 2D grid, rejecting anything but 9 rows of 9 values in 0-9
+    pub fn try_new(grid: &[Vec<i32>]) -> Result<Self> {
+        if grid.len() != 9 {
+            return Err(SudokuError::ParseError(format!("expected 9 rows, found {}", grid.len())));
+        }
+        let mut cells = [0; 81];
+        for (i, row) in grid.iter().enumerate() {
+            if row.len() != 9 {
+                return Err(SudokuError::ParseError(format!("expected 9 values in row {}, found {}", i, row.len())));
+            }
+            for (j, &val) in row.iter().enumerate() {
+                if !(0..=9).contains(&val) {
+                    return Err(SudokuError::InvalidValue { row: i, col: j, value: val });
+                }
+                cells[i * 9 + j] = val as u8;
+            }
+        }
+        Ok(Self { cells })
+    }
+
     /// Creates an empty board
     #[inline]
     pub fn empty() -> Self {
@@ -116,6 +190,71 @@ impl Board {
         self.get(row, col) == 0
     }
 
+    /// Returns true if `value` is not already present in the row, column, or box
+    pub fn can_place(&self, row: usize, col: usize, value: u8) -> bool {
+        let box_row = (row / 3) * 3;
+        let box_col = (col / 3) * 3;
+        (0..9).all(|i| {
+            self.get(row, i) != value
+                && self.get(i, col) != value
+                && self.get(box_row + i / 3, box_col + i % 3) != value
+        })
+    }
+
+    /// Parses an 81-character puzzle line.
+    ///
+    /// Digits 1-9 are givens; `0`, `.`, `-`, and `_` mark empty cells. Whitespace
+    /// is ignored, so grids split over several lines also parse.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut cells = [0u8; 81];
+        let mut count = 0;
+        for c in input.chars().filter(|c| !c.is_whitespace()) {
+            let value = match c {
+                '1'..='9' => c as u8 - b'0',
+                '0' | '.' | '-' | '_' => 0,
+                _ => return Err(SudokuError::ParseError(format!("unexpected character '{}'", c))),
+            };
+            if count == 81 {
+                return Err(SudokuError::ParseError("more than 81 cells".to_string()));
+            }
+            cells[count] = value;
+            count += 1;
+        }
+        if count != 81 {
+            return Err(SudokuError::ParseError(format!("expected 81 cells, found {}", count)));
+        }
+        Ok(Self { cells })
+    }
+
+    /// Formats the board as an 81-character line with `.` for empty cells
+    pub fn to_line(&self) -> String {
+        self.cells
+            .iter()
+            .map(|&v| if v == 0 { '.' } else { char::from(b'0' + v) })
+            .collect()
+    }
+
+    /// Returns true if no two givens in a row, column or box share a digit
+    pub fn is_consistent(&self) -> bool {
+        let mut seen = [0u16; 27];
+        for row in 0..9 {
+            for col in 0..9 {
+                let value = self.get(row, col);
+                if value == 0 {
+                    continue;
+                }
+                let bit = 1 << (value - 1);
+                for unit in [row, 9 + col, 18 + Self::get_box_index(row, col)] {
+                    if seen[unit] & bit != 0 {
+                        return false;
+                    }
+                    seen[unit] |= bit;
+                }
+            }
+        }
+        true
+    }
+
     /// Returns the box index (0-8) for a given row and column
     #[inline]
     pub fn get_box_index(row: usize, col: usize) -> usize {
@@ -123,40 +262,172 @@ impl Board {
     }
 }
 
-#[derive(Debug)]
+impl std::str::FromStr for Board {
+    type Err = SudokuError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+/// Parses untrusted puzzle input and checks that no two givens conflict.
+///
+/// Accepts an 81-cell line as read by `Board::parse`, a JSON `Grid`, or a JSON
+/// array of 9 rows. Never panics: malformed input of any kind is an error.
+pub fn validate_bytes(bytes: &[u8]) -> Result<Board> {
+    let board = parse_bytes(bytes)?;
+    if !board.is_consistent() {
+        return Err(SudokuError::InvalidBoard);
+    }
+    Ok(board)
+}
+
+/// Parses untrusted puzzle input in any format `validate_bytes` accepts,
+/// without checking the givens
+pub(crate) fn parse_bytes(bytes: &[u8]) -> Result<Board> {
+    let input = std::str::from_utf8(bytes).map_err(|e| SudokuError::ParseError(e.to_string()))?;
+    let trimmed = input.trim_start();
+    if trimmed.starts_with('{') {
+        Board::try_new(&serde_json::from_str::<Grid>(trimmed)?.value)
+    } else if trimmed.starts_with('[') {
+        Board::try_new(&serde_json::from_str::<Vec<Vec<i32>>>(trimmed)?)
+    } else {
+        Board::parse(input)
+    }
+}
+
+/// Returns true if `board` is a complete solution: every row, column and box
+/// holds each digit once. Runs the SIMD validator on targets with vector
+/// support and the scalar one elsewhere.
+pub fn validate_solution(board: &Board) -> bool {
+    simd::SimdValidator::validate_solution(board)
+}
+
+/// Checks a puzzle, or a board partway through solving, for clashing digits
+/// without solving it; `validation::diagnose` also checks solvability
+pub fn validate_puzzle(board: &Board) -> ValidationReport {
+    let conflicts = validation::conflicts(board);
+    ValidationReport {
+        filled: board.cells.iter().filter(|&&v| v != 0).count(),
+        solved: conflicts.is_empty() && validate_solution(board),
+        conflicts,
+    }
+}
+
+/// Parses untrusted puzzle input as `validate_bytes` does and solves it.
+/// Never panics; unsolvable puzzles are `InvalidBoard`.
+pub fn parse_and_solve(bytes: &[u8]) -> Result<Board> {
+    let mut board = validate_bytes(bytes)?;
+    solver::Solver::solve_into(&mut board)?;
+    Ok(board)
+}
+
+/// Errors from every part of the crate.
+///
+/// Failures caused by another library keep it as their `source()`. More
+/// variants may be added, so matches need a wildcard arm; `is_invalid_input`
+/// and `is_timeout` group the variants most callers care about.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum SudokuError {
+    #[error("API error: {0}")]
     ApiError(String),
+    /// An HTTP request failed or returned an error status
+    #[error("API request failed")]
+    Http(#[from] reqwest::Error),
+    #[error("Invalid Sudoku board")]
     InvalidBoard,
+    #[error("Invalid value {value} at position ({row}, {col})")]
     InvalidValue {
         row: usize,
         col: usize,
         value: i32,
     },
+    /// A given breaks one of the puzzle's rules, such as a digit repeated in
+    /// a row or one of a variant's extra regions
+    #[error("{value} at r{}c{} breaks the {rule} rule", row + 1, col + 1)]
+    RuleViolation {
+        rule: String,
+        row: usize,
+        col: usize,
+        value: u8,
+    },
+    #[error("Benchmark error: {0}")]
     BenchmarkError(String),
+    #[error("Cache lock timeout")]
     CacheTimeout,
+    #[error("Generator lock timeout")]
     GeneratorTimeout,
+    #[error("Parse error: {0}")]
+    ParseError(String),
+    /// Malformed JSON input
+    #[error("Invalid JSON")]
+    Json(#[from] serde_json::Error),
+    /// Malformed TOML configuration
+    #[error("Invalid TOML")]
+    Toml(#[from] toml::de::Error),
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
+    /// A rayon pool for solving or batch work couldn't be started
+    #[error("Couldn't start a thread pool")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    /// A blocking task handed to the async runtime panicked or was cancelled
+    #[error("Background task failed")]
+    Task(#[from] tokio::task::JoinError),
+    /// The OTLP trace exporter couldn't be set up
+    #[cfg(feature = "otlp")]
+    #[error("Couldn't build the OTLP exporter")]
+    Telemetry(#[from] opentelemetry_otlp::ExporterBuildError),
+    #[error("Generation failed: {0}")]
+    GenerationFailed(String),
+    #[error("Solve timed out")]
+    SolveTimeout,
+    /// A solution failed the post-solve check, which points at a solver or
+    /// validator bug rather than at the input
+    #[error("Solution failed verification: {0}")]
+    VerificationFailed(String),
+    /// The GPU couldn't be opened or a dispatch failed
+    #[cfg(feature = "gpu")]
+    #[error("GPU error: {0}")]
+    Gpu(String),
 }
 
-impl std::error::Error for SudokuError {}
+impl SudokuError {
+    /// Whether the input itself was at fault: malformed, out of range or
+    /// breaking a rule, as opposed to a puzzle with no solution
+    pub fn is_invalid_input(&self) -> bool {
+        matches!(
+            self,
+            SudokuError::ParseError(_)
+                | SudokuError::Json(_)
+                | SudokuError::Toml(_)
+                | SudokuError::InvalidValue { .. }
+                | SudokuError::RuleViolation { .. }
+        )
+    }
 
-impl fmt::Display for SudokuError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SudokuError::ApiError(msg) => write!(f, "API error: {}", msg),
-            SudokuError::InvalidBoard => write!(f, "Invalid Sudoku board"),
-            SudokuError::InvalidValue { row, col, value } => {
-                write!(f, "Invalid value {} at position ({}, {})", value, row, col)
-            }
-            SudokuError::BenchmarkError(msg) => write!(f, "Benchmark error: {}", msg),
-            SudokuError::CacheTimeout => write!(f, "Cache lock timeout"),
-            SudokuError::GeneratorTimeout => write!(f, "Generator lock timeout"),
+    /// The message followed by each underlying cause, for showing to a
+    /// person, such as `Invalid JSON: EOF while parsing a list at line 1
+    /// column 7`. `Display` gives only the first part, so error chains
+    /// printed source by source don't repeat it.
+    pub fn report(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
         }
+        message
     }
-}
 
-impl From<reqwest::Error> for SudokuError {
-    fn from(err: reqwest::Error) -> Self {
-        SudokuError::ApiError(err.to_string())
+    /// Whether something ran out of time, locally or waiting on the network
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            SudokuError::CacheTimeout | SudokuError::GeneratorTimeout | SudokuError::SolveTimeout => true,
+            SudokuError::Http(err) => err.is_timeout(),
+            _ => false,
+        }
     }
 }
 
@@ -166,11 +437,81 @@ impl From<&str> for SudokuError {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
 pub struct Grid {
     pub value: Vec<Vec<i32>>,
-    pub solution: Vec<Vec<i32>>,
+    /// The completed grid when the source knows it; files, users and some
+    /// providers give the puzzle alone. `Grid::ensure_solution` solves for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<Vec<Vec<i32>>>,
     pub difficulty: String,
+    /// Provenance, omitted from JSON when nothing is known
+    #[serde(default, skip_serializing_if = "GridMetadata::is_empty")]
+    pub metadata: GridMetadata,
+}
+
+/// Where a puzzle came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PuzzleSource {
+    /// Fetched from the Dosuku API
+    Api,
+    /// Made by the local generator
+    Generator,
+    /// The generator's puzzle of the day
+    Daily,
+    /// Read from a puzzle collection file
+    Imported,
+}
+
+/// Optional provenance of a `Grid`, filled in by whatever produced it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct GridMetadata {
+    /// Stable identifier derived from the givens, so equal puzzles share an id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Generator seed that reproduces the puzzle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Number of givens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clues: Option<usize>,
+    /// Difficulty from the internal logical rating, which may differ from the
+    /// `difficulty` label of API puzzles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PuzzleSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl GridMetadata {
+    /// Metadata for a puzzle just produced by `source`: id, clue count and creation time
+    pub fn new(value: &[Vec<i32>], source: PuzzleSource) -> Self {
+        let board = Board::new(value);
+        Self {
+            id: Some(format!("{:016x}", fnv1a(board.to_line().as_bytes()))),
+            clues: Some(board.cells.iter().filter(|&&v| v != 0).count()),
+            source: Some(source),
+            created_at: Some(chrono::Utc::now()),
+            ..Self::default()
+        }
+    }
+
+    /// Returns true if no field is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 64-bit FNV-1a. Unlike `std`'s default hasher its output never changes, so
+/// it is safe for ids and seeds that must stay stable across releases.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
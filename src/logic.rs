@@ -0,0 +1,886 @@
+//! Human-style logical solving.
+//!
+//! `LogicSolver` keeps a candidate grid alongside the board and applies one
+//! deduction at a time, reporting each as a `SolveStep`. Techniques are tried
+//! from simplest to hardest so the step sequence resembles how a person would
+//! work through the puzzle.
+
+use crate::{bitboard::BitBoards, pencilmarks::PencilMarks, Board, CandidateSet, Result, SudokuError};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Cell indices of every row, column, and box (in that order)
+pub(crate) static UNITS: Lazy<[[usize; 9]; 27]> = Lazy::new(|| {
+    let mut units = [[0; 9]; 27];
+    for i in 0..9 {
+        for j in 0..9 {
+            units[i][j] = i * 9 + j;
+            units[9 + i][j] = j * 9 + i;
+            units[18 + i][j] = ((i / 3) * 3 + j / 3) * 9 + (i % 3) * 3 + j % 3;
+        }
+    }
+    units
+});
+
+/// The 20 cells sharing a row, column, or box with each cell
+pub(crate) static PEERS: Lazy<[[usize; 20]; 81]> = Lazy::new(|| {
+    let mut peers = [[0; 20]; 81];
+    for (cell, cell_peers) in peers.iter_mut().enumerate() {
+        let (row, col) = (cell / 9, cell % 9);
+        let mut n = 0;
+        for other in 0..81 {
+            let (r, c) = (other / 9, other % 9);
+            let shares_unit = r == row
+                || c == col
+                || Board::get_box_index(r, c) == Board::get_box_index(row, col);
+            if other != cell && shares_unit {
+                cell_peers[n] = other;
+                n += 1;
+            }
+        }
+    }
+    peers
+});
+
+/// A solving technique, ordered roughly by difficulty
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Technique {
+    NakedSingle,
+    HiddenSingle,
+    LockedCandidates,
+    NakedPair,
+    HiddenPair,
+    NakedTriple,
+    HiddenTriple,
+    XWing,
+    RemotePair,
+    HiddenQuad,
+    XYWing,
+    XYZWing,
+}
+
+impl Technique {
+    /// All techniques, easiest first
+    pub const ALL: [Technique; 12] = [
+        Technique::NakedSingle,
+        Technique::HiddenSingle,
+        Technique::LockedCandidates,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::XWing,
+        Technique::RemotePair,
+        Technique::HiddenQuad,
+        Technique::XYWing,
+        Technique::XYZWing,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Technique::NakedSingle => "Naked Single",
+            Technique::HiddenSingle => "Hidden Single",
+            Technique::LockedCandidates => "Locked Candidates",
+            Technique::NakedPair => "Naked Pair",
+            Technique::HiddenPair => "Hidden Pair",
+            Technique::NakedTriple => "Naked Triple",
+            Technique::HiddenTriple => "Hidden Triple",
+            Technique::XWing => "X-Wing",
+            Technique::RemotePair => "Remote Pair",
+            Technique::HiddenQuad => "Hidden Quad",
+            Technique::XYWing => "XY-Wing",
+            Technique::XYZWing => "XYZ-Wing",
+        }
+    }
+}
+
+impl std::str::FromStr for Technique {
+    type Err = SudokuError;
+
+    /// Parses a technique name, ignoring case, spaces, and dashes ("x-wing", "XWing")
+    fn from_str(s: &str) -> Result<Self> {
+        let normalize = |name: &str| -> String {
+            name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+        };
+        let wanted = normalize(s);
+        Technique::ALL
+            .iter()
+            .copied()
+            .find(|t| normalize(t.name()) == wanted)
+            .ok_or_else(|| SudokuError::ParseError(format!("unknown technique '{}'", s)))
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A row, column, or box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Unit {
+    Row(usize),
+    Column(usize),
+    Box(usize),
+}
+
+impl Unit {
+    /// Maps an index into `UNITS` back to the unit it describes
+    pub(crate) fn from_index(index: usize) -> Self {
+        match index / 9 {
+            0 => Unit::Row(index % 9),
+            1 => Unit::Column(index % 9),
+            _ => Unit::Box(index % 9),
+        }
+    }
+
+    /// Index of the unit in `UNITS`
+    pub(crate) fn index(&self) -> usize {
+        match *self {
+            Unit::Row(i) => i,
+            Unit::Column(i) => 9 + i,
+            Unit::Box(i) => 18 + i,
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::Row(i) => write!(f, "row {}", i + 1),
+            Unit::Column(i) => write!(f, "column {}", i + 1),
+            Unit::Box(i) => write!(f, "box {}", i + 1),
+        }
+    }
+}
+
+/// A digit at a position, used for both placements and eliminations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placement {
+    pub row: usize,
+    pub col: usize,
+    pub value: u8,
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "r{}c{}={}", self.row + 1, self.col + 1, self.value)
+    }
+}
+
+/// One logical deduction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolveStep {
+    pub technique: Technique,
+    /// Digits placed by this step
+    pub placements: Vec<Placement>,
+    /// Candidates removed by this step
+    pub eliminations: Vec<Placement>,
+    /// The unit the deduction is based on, if any
+    pub unit: Option<Unit>,
+    /// Cells forming the pattern behind the deduction, as (row, col)
+    pub cells: Vec<(usize, usize)>,
+    /// Digits forming the pattern, for subset techniques
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub digits: Vec<u8>,
+}
+
+/// Applies logical techniques step by step to a board
+#[derive(Debug, Clone)]
+pub struct LogicSolver {
+    board: Board,
+    candidates: [CandidateSet; 81],
+}
+
+impl LogicSolver {
+    pub fn new(board: &Board) -> Self {
+        let marks = PencilMarks::from_board(board);
+        let mut candidates = [CandidateSet::empty(); 81];
+        for (idx, set) in candidates.iter_mut().enumerate() {
+            if board.cells[idx] == 0 {
+                *set = marks.get(idx / 9, idx % 9);
+            }
+        }
+        Self { board: board.clone(), candidates }
+    }
+
+    /// Starts from user-supplied pencil marks, intersected with the computed candidates
+    pub fn with_pencilmarks(board: &Board, marks: &PencilMarks) -> Self {
+        let mut solver = Self::new(board);
+        for idx in 0..81 {
+            solver.candidates[idx].0 &= marks.get(idx / 9, idx % 9).0;
+        }
+        solver
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Candidates of the cell at `row`, `col` (empty for filled cells)
+    pub fn candidates(&self, row: usize, col: usize) -> CandidateSet {
+        self.candidates[row * 9 + col]
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.board.cells.iter().all(|&v| v != 0)
+    }
+
+    /// Returns true if an empty cell has no candidates, a unit repeats a digit,
+    /// or a digit has no place left in a unit
+    pub fn has_contradiction(&self) -> bool {
+        if (0..81).any(|i| self.board.cells[i] == 0 && self.candidates[i].is_empty()) {
+            return true;
+        }
+        UNITS.iter().any(|unit| {
+            let mut placed = 0u16;
+            let mut seen = 0u16;
+            for &cell in unit {
+                match self.board.cells[cell] {
+                    0 => seen |= self.candidates[cell].0,
+                    v => {
+                        let bit = 1 << (v - 1);
+                        if placed & bit != 0 {
+                            return true;
+                        }
+                        placed |= bit;
+                    }
+                }
+            }
+            (seen | placed) != 0x1FF
+        })
+    }
+
+    /// Places a digit and removes it from the candidates of all peers
+    pub fn place(&mut self, row: usize, col: usize, value: u8) {
+        let idx = row * 9 + col;
+        self.board.set(row, col, value);
+        self.candidates[idx] = CandidateSet::empty();
+        for &peer in PEERS[idx].iter() {
+            self.candidates[peer].remove_candidate(value);
+        }
+    }
+
+    /// Finds the next deduction without applying it
+    pub fn find_step(&self) -> Option<SolveStep> {
+        Technique::ALL.iter().find_map(|&technique| self.find_technique(technique))
+    }
+
+    /// Finds a deduction using one specific technique
+    pub fn find_technique(&self, technique: Technique) -> Option<SolveStep> {
+        span!(TRACE, "technique", %technique, found = tracing::field::Empty);
+        let step = match technique {
+            Technique::NakedSingle => self.find_naked_single(),
+            Technique::HiddenSingle => self.find_hidden_single(),
+            Technique::LockedCandidates => self.find_locked_candidates(),
+            Technique::NakedPair => self.find_naked_subset(technique, 2),
+            Technique::HiddenPair => self.find_hidden_subset(technique, 2),
+            Technique::NakedTriple => self.find_naked_subset(technique, 3),
+            Technique::HiddenTriple => self.find_hidden_subset(technique, 3),
+            Technique::XWing => self.find_x_wing(),
+            Technique::RemotePair => self.find_remote_pair(),
+            Technique::HiddenQuad => self.find_hidden_subset(technique, 4),
+            Technique::XYWing => self.find_xy_wing(),
+            Technique::XYZWing => self.find_xyz_wing(),
+        };
+        record!("found", step.is_some());
+        step
+    }
+
+    /// Applies a previously found step
+    pub fn apply(&mut self, step: &SolveStep) {
+        for p in &step.placements {
+            self.place(p.row, p.col, p.value);
+        }
+        for e in &step.eliminations {
+            self.candidates[e.row * 9 + e.col].remove_candidate(e.value);
+        }
+    }
+
+    /// Finds and applies the next deduction
+    pub fn step(&mut self) -> Option<SolveStep> {
+        if self.has_contradiction() {
+            return None;
+        }
+        let step = self.find_step()?;
+        self.apply(&step);
+        Some(step)
+    }
+
+    /// Applies deductions until the board is solved or no technique applies
+    pub fn solve(&mut self) -> Vec<SolveStep> {
+        span!(DEBUG, "logic_solve", steps = tracing::field::Empty, solved = tracing::field::Empty);
+        let mut steps = Vec::new();
+        while !self.is_solved() {
+            match self.step() {
+                Some(step) => steps.push(step),
+                None => break,
+            }
+        }
+        record!("steps", steps.len());
+        record!("solved", self.is_solved());
+        steps
+    }
+
+    fn find_naked_single(&self) -> Option<SolveStep> {
+        (0..81)
+            .find(|&i| self.board.cells[i] == 0 && self.candidates[i].count_candidates() == 1)
+            .map(|i| SolveStep {
+                technique: Technique::NakedSingle,
+                placements: vec![Placement {
+                    row: i / 9,
+                    col: i % 9,
+                    value: self.candidates[i].iter_candidates().next().unwrap_or(0),
+                }],
+                eliminations: Vec::new(),
+                unit: None,
+                cells: vec![(i / 9, i % 9)],
+                digits: Vec::new(),
+            })
+    }
+
+    fn find_hidden_single(&self) -> Option<SolveStep> {
+        // Boxes first, which is how most people scan
+        for unit_idx in (18..27).chain(0..18) {
+            for value in 1..=9u8 {
+                let mut spots = UNITS[unit_idx]
+                    .iter()
+                    .filter(|&&cell| self.candidates[cell].has_candidate(value));
+                if let (Some(&cell), None) = (spots.next(), spots.next()) {
+                    return Some(SolveStep {
+                        technique: Technique::HiddenSingle,
+                        placements: vec![Placement { row: cell / 9, col: cell % 9, value }],
+                        eliminations: Vec::new(),
+                        unit: Some(Unit::from_index(unit_idx)),
+                        cells: vec![(cell / 9, cell % 9)],
+                        digits: Vec::new(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Cells of a unit that still have `value` as a candidate
+    fn spots(&self, unit_idx: usize, value: u8) -> Vec<usize> {
+        UNITS[unit_idx]
+            .iter()
+            .copied()
+            .filter(|&cell| self.candidates[cell].has_candidate(value))
+            .collect()
+    }
+
+    /// Candidates of `value` in `targets` that are not in `exclude`
+    fn eliminations_in(&self, targets: &[usize], exclude: &[usize], value: u8) -> Vec<Placement> {
+        targets
+            .iter()
+            .filter(|cell| !exclude.contains(cell) && self.candidates[**cell].has_candidate(value))
+            .map(|&cell| Placement { row: cell / 9, col: cell % 9, value })
+            .collect()
+    }
+
+    /// Pointing (box -> line) and claiming (line -> box) eliminations
+    fn find_locked_candidates(&self) -> Option<SolveStep> {
+        for value in 1..=9u8 {
+            // Pointing: all spots in a box share a row or column
+            for box_idx in 18..27 {
+                let spots = self.spots(box_idx, value);
+                if spots.len() < 2 {
+                    continue;
+                }
+                let lines = [
+                    (spots.iter().all(|&c| c / 9 == spots[0] / 9), spots[0] / 9),
+                    (spots.iter().all(|&c| c % 9 == spots[0] % 9), 9 + spots[0] % 9),
+                ];
+                for (aligned, line_idx) in lines {
+                    if !aligned {
+                        continue;
+                    }
+                    let eliminations = self.eliminations_in(&UNITS[line_idx], &spots, value);
+                    if !eliminations.is_empty() {
+                        return Some(self.pattern_step(Technique::LockedCandidates, eliminations, Some(box_idx), &spots));
+                    }
+                }
+            }
+
+            // Claiming: all spots in a row or column share a box
+            for line_idx in 0..18 {
+                let spots = self.spots(line_idx, value);
+                if spots.len() < 2 {
+                    continue;
+                }
+                let box_of = |c: usize| Board::get_box_index(c / 9, c % 9);
+                if spots.iter().all(|&c| box_of(c) == box_of(spots[0])) {
+                    let box_idx = 18 + box_of(spots[0]);
+                    let eliminations = self.eliminations_in(&UNITS[box_idx], &spots, value);
+                    if !eliminations.is_empty() {
+                        return Some(self.pattern_step(Technique::LockedCandidates, eliminations, Some(line_idx), &spots));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// `size` cells of a unit that between them hold only `size` digits, which
+    /// therefore can't go anywhere else in the unit
+    fn find_naked_subset(&self, technique: Technique, size: usize) -> Option<SolveStep> {
+        (0..27).find_map(|unit_idx| {
+            let (pattern, digits) = naked_subset_in(&self.candidates, unit_idx, size)?;
+            let eliminations = CandidateSet(digits)
+                .iter_candidates()
+                .flat_map(|value| self.eliminations_in(&UNITS[unit_idx], &pattern, value))
+                .collect();
+            let step = self.pattern_step(technique, eliminations, Some(unit_idx), &pattern);
+            Some(SolveStep { digits: CandidateSet(digits).iter_candidates().collect(), ..step })
+        })
+    }
+
+    /// `size` digits confined to the same `size` cells of a unit, which
+    /// therefore can't hold any other digit
+    fn find_hidden_subset(&self, technique: Technique, size: usize) -> Option<SolveStep> {
+        for (unit_idx, unit) in UNITS.iter().enumerate() {
+            // Positions within the unit of each digit's spots, as bit masks
+            let mut spots = [0u16; 9];
+            for (pos, &cell) in unit.iter().enumerate() {
+                for value in self.candidates[cell].iter_candidates() {
+                    spots[value as usize - 1] |= 1 << pos;
+                }
+            }
+            let eligible: Vec<u8> = (1..=9u8)
+                .filter(|&v| (1..=size as u32).contains(&spots[v as usize - 1].count_ones()))
+                .collect();
+
+            for digits in combinations(&eligible, size) {
+                let positions = digits.iter().fold(0u16, |acc, &v| acc | spots[v as usize - 1]);
+                if positions.count_ones() as usize != size {
+                    continue;
+                }
+                let pattern: Vec<usize> = (0..9).filter(|pos| positions & (1 << pos) != 0).map(|pos| unit[pos]).collect();
+                let eliminations: Vec<Placement> = pattern
+                    .iter()
+                    .flat_map(|&cell| {
+                        self.candidates[cell]
+                            .iter_candidates()
+                            .filter(|value| !digits.contains(value))
+                            .map(move |value| Placement { row: cell / 9, col: cell % 9, value })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+                if !eliminations.is_empty() {
+                    let step = self.pattern_step(technique, eliminations, Some(unit_idx), &pattern);
+                    return Some(SolveStep { digits, ..step });
+                }
+            }
+        }
+        None
+    }
+
+    /// Two rows (or columns) where a digit is confined to the same two columns (or rows)
+    fn find_x_wing(&self) -> Option<SolveStep> {
+        let bits = BitBoards::from(self);
+        for value in 1..=9u8 {
+            // Base lines are rows (0..9) crossing columns, then columns (9..18) crossing rows
+            for (base, cover) in [(0usize, 9usize), (9, 0)] {
+                let lines = bits.candidate_lines(value, base == 9);
+                for a in 0..9 {
+                    if lines[a].count_ones() != 2 {
+                        continue;
+                    }
+                    let crossing: Vec<usize> = (0..9).filter(|&x| lines[a] & 1 << x != 0).collect();
+                    for b in a + 1..9 {
+                        if lines[b] != lines[a] {
+                            continue;
+                        }
+                        let pattern: Vec<usize> = [a, b]
+                            .iter()
+                            .flat_map(|&line| crossing.iter().map(move |&x| UNITS[base + line][x]))
+                            .collect();
+                        let eliminations: Vec<Placement> = crossing
+                            .iter()
+                            .flat_map(|&x| self.eliminations_in(&UNITS[cover + x], &pattern, value))
+                            .collect();
+                        if !eliminations.is_empty() {
+                            return Some(self.pattern_step(Technique::XWing, eliminations, None, &pattern));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// A pivot holding x or y, one peer holding x or z and another holding
+    /// y or z: one of the two peers is z, so no cell seeing both can be
+    fn find_xy_wing(&self) -> Option<SolveStep> {
+        let bivalue = self.cells_with_candidates(2);
+        for &pivot in &bivalue {
+            let pivot_set = self.candidates[pivot].0;
+            for &a in bivalue.iter().filter(|&&a| sees(pivot, a)) {
+                let (x, z) = (pivot_set & self.candidates[a].0, self.candidates[a].0 & !pivot_set);
+                if x.count_ones() != 1 {
+                    continue;
+                }
+                let y = pivot_set & !x;
+                for &b in bivalue.iter().filter(|&&b| b != a && sees(pivot, b) && self.candidates[b].0 == y | z) {
+                    let value = digit(z);
+                    let eliminations = self.eliminations_in(&common_peers(&[a, b]), &[pivot], value);
+                    if !eliminations.is_empty() {
+                        let step = self.pattern_step(Technique::XYWing, eliminations, None, &[pivot, a, b]);
+                        return Some(SolveStep { digits: vec![digit(x), digit(y), value], ..step });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// A pivot holding x, y or z with peers holding x or z and y or z: one of
+    /// the three is z, so no cell seeing all three can be
+    fn find_xyz_wing(&self) -> Option<SolveStep> {
+        let bivalue = self.cells_with_candidates(2);
+        for pivot in self.cells_with_candidates(3) {
+            let pivot_set = self.candidates[pivot].0;
+            let wings: Vec<usize> = bivalue
+                .iter()
+                .copied()
+                .filter(|&cell| sees(pivot, cell) && self.candidates[cell].0 & !pivot_set == 0)
+                .collect();
+            for (i, &a) in wings.iter().enumerate() {
+                for &b in &wings[i + 1..] {
+                    let (set_a, set_b) = (self.candidates[a].0, self.candidates[b].0);
+                    let z = set_a & set_b;
+                    if z.count_ones() != 1 || set_a | set_b != pivot_set {
+                        continue;
+                    }
+                    let value = digit(z);
+                    let eliminations = self.eliminations_in(&common_peers(&[pivot, a, b]), &[], value);
+                    if !eliminations.is_empty() {
+                        let step = self.pattern_step(Technique::XYZWing, eliminations, None, &[pivot, a, b]);
+                        return Some(SolveStep { digits: vec![digit(set_a & !z), digit(set_b & !z), value], ..step });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// A chain of cells that all hold the same two digits, each seeing the
+    /// next, alternates between them. Cells seeing both ends of a chain with
+    /// an even number of cells can hold neither digit.
+    fn find_remote_pair(&self) -> Option<SolveStep> {
+        let mut pairs: Vec<u16> = self.cells_with_candidates(2).iter().map(|&cell| self.candidates[cell].0).collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        for pair in pairs {
+            let chain: Vec<usize> = (0..81).filter(|&cell| self.candidates[cell].0 == pair).collect();
+            if chain.len() < 4 {
+                continue;
+            }
+            for (i, &start) in chain.iter().enumerate() {
+                // Shortest paths from `start` along the chain
+                let mut previous = [None; 81];
+                let mut distance = [usize::MAX; 81];
+                distance[start] = 0;
+                let mut queue = std::collections::VecDeque::from([start]);
+                while let Some(cell) = queue.pop_front() {
+                    let unvisited: Vec<usize> = chain
+                        .iter()
+                        .copied()
+                        .filter(|&next| distance[next] == usize::MAX && sees(cell, next))
+                        .collect();
+                    for next in unvisited {
+                        distance[next] = distance[cell] + 1;
+                        previous[next] = Some(cell);
+                        queue.push_back(next);
+                    }
+                }
+
+                // An even number of cells puts the ends on different digits
+                let ends = chain[i + 1..]
+                    .iter()
+                    .filter(|&&end| distance[end] != usize::MAX && distance[end] >= 3 && distance[end] % 2 == 1);
+                for &end in ends {
+                    let mut path = vec![end];
+                    while let Some(cell) = previous[*path.last().unwrap()] {
+                        path.push(cell);
+                    }
+                    path.reverse();
+                    let targets = common_peers(&[start, end]);
+                    let eliminations: Vec<Placement> = CandidateSet(pair)
+                        .iter_candidates()
+                        .flat_map(|value| self.eliminations_in(&targets, &path, value))
+                        .collect();
+                    if !eliminations.is_empty() {
+                        let step = self.pattern_step(Technique::RemotePair, eliminations, None, &path);
+                        return Some(SolveStep { digits: CandidateSet(pair).iter_candidates().collect(), ..step });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Empty cells with exactly `count` candidates
+    fn cells_with_candidates(&self, count: u32) -> Vec<usize> {
+        (0..81).filter(|&cell| self.candidates[cell].count_candidates() == count).collect()
+    }
+
+    fn pattern_step(
+        &self,
+        technique: Technique,
+        eliminations: Vec<Placement>,
+        unit_idx: Option<usize>,
+        pattern: &[usize],
+    ) -> SolveStep {
+        SolveStep {
+            technique,
+            placements: Vec::new(),
+            eliminations,
+            unit: unit_idx.map(Unit::from_index),
+            cells: pattern.iter().map(|&c| (c / 9, c % 9)).collect(),
+            digits: Vec::new(),
+        }
+    }
+}
+
+/// Returns true if two different cells share a row, column, or box
+fn sees(a: usize, b: usize) -> bool {
+    PEERS[a].contains(&b)
+}
+
+/// Cells outside `cells` that see every one of them
+fn common_peers(cells: &[usize]) -> Vec<usize> {
+    (0..81).filter(|cell| !cells.contains(cell) && cells.iter().all(|&other| sees(*cell, other))).collect()
+}
+
+/// The digit of a single-bit candidate mask
+fn digit(mask: u16) -> u8 {
+    mask.trailing_zeros() as u8 + 1
+}
+
+/// Finds `size` cells of unit `unit_idx` whose candidates together are just
+/// `size` digits, with at least one of those digits left elsewhere in the
+/// unit to eliminate. Returns the cells and the digits as a bit mask.
+///
+/// Filled cells must have no candidates.
+pub(crate) fn naked_subset_in(candidates: &[CandidateSet], unit_idx: usize, size: usize) -> Option<(Vec<usize>, u16)> {
+    let unit = &UNITS[unit_idx];
+    let cells: Vec<usize> = unit
+        .iter()
+        .copied()
+        .filter(|&cell| (1..=size as u32).contains(&candidates[cell].count_candidates()))
+        .collect();
+    combinations(&cells, size).into_iter().find_map(|pattern| {
+        let digits = pattern.iter().fold(0u16, |acc, &cell| acc | candidates[cell].0);
+        let elsewhere = unit
+            .iter()
+            .filter(|cell| !pattern.contains(cell))
+            .any(|&cell| candidates[cell].0 & digits != 0);
+        (digits.count_ones() as usize == size && elsewhere).then_some((pattern, digits))
+    })
+}
+
+/// Applies naked pair and triple eliminations to `candidates` until none is
+/// left, returning how many candidates were removed. Filled cells must have
+/// no candidates.
+pub(crate) fn prune_naked_subsets(candidates: &mut [CandidateSet]) -> usize {
+    let mut removed = 0;
+    loop {
+        let found = (2..=3).find_map(|size| (0..27).find_map(|unit_idx| {
+            naked_subset_in(candidates, unit_idx, size).map(|(pattern, digits)| (unit_idx, pattern, digits))
+        }));
+        let Some((unit_idx, pattern, digits)) = found else {
+            return removed;
+        };
+        for &cell in UNITS[unit_idx].iter().filter(|cell| !pattern.contains(cell)) {
+            removed += (candidates[cell].0 & digits).count_ones() as usize;
+            candidates[cell].0 &= !digits;
+        }
+    }
+}
+
+/// Every `size`-element subset of `items`, in lexicographic order
+pub(crate) fn combinations<T: Copy>(items: &[T], size: usize) -> Vec<Vec<T>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for (i, &first) in items.iter().enumerate() {
+        for mut rest in combinations(&items[i + 1..], size - 1) {
+            rest.insert(0, first);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units_and_peers() {
+        assert_eq!(UNITS[9], [0, 9, 18, 27, 36, 45, 54, 63, 72]);
+        assert_eq!(UNITS[18 + 4], [30, 31, 32, 39, 40, 41, 48, 49, 50]);
+        assert!(!PEERS[0].contains(&80));
+        assert!(PEERS[0].contains(&20));
+    }
+
+    #[test]
+    fn test_singles_solve_easy_puzzle() {
+        let board = Board::new(&[
+            vec![5,3,0,0,7,0,0,0,0],
+            vec![6,0,0,1,9,5,0,0,0],
+            vec![0,9,8,0,0,0,0,6,0],
+            vec![8,0,0,0,6,0,0,0,3],
+            vec![4,0,0,8,0,3,0,0,1],
+            vec![7,0,0,0,2,0,0,0,6],
+            vec![0,6,0,0,0,0,2,8,0],
+            vec![0,0,0,4,1,9,0,0,5],
+            vec![0,0,0,0,8,0,0,7,9],
+        ]);
+        let mut solver = LogicSolver::new(&board);
+        let steps = solver.solve();
+        assert!(solver.is_solved());
+        assert_eq!(steps.len(), 51);
+        assert!(crate::simd::SimdValidator::validate_solution(solver.board()));
+    }
+
+    #[test]
+    fn test_locked_candidates_and_x_wing() {
+        // Needs an X-Wing on 1s after locked candidates have been applied
+        let board = Board::parse(
+            "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5",
+        )
+        .unwrap();
+        let mut solver = LogicSolver::new(&board);
+        let steps = solver.solve();
+        assert!(solver.is_solved());
+        assert!(steps.iter().any(|s| s.technique == Technique::XWing));
+        assert!(crate::simd::SimdValidator::validate_solution(solver.board()));
+    }
+
+    #[test]
+    fn test_hidden_subsets() {
+        // In row 1, 4 and 7 only fit in r1c1 and r1c5
+        let mut solver = LogicSolver::new(&Board::empty());
+        for col in [1, 2, 3, 5, 6, 7, 8] {
+            solver.candidates[col].remove_candidate(4);
+            solver.candidates[col].remove_candidate(7);
+        }
+        let step = solver.find_technique(Technique::HiddenPair).unwrap();
+        assert_eq!(step.unit, Some(Unit::Row(0)));
+        assert_eq!(step.cells, [(0, 0), (0, 4)]);
+        assert_eq!(step.digits, [4, 7]);
+        assert_eq!(step.eliminations.len(), 14);
+        assert!(step.eliminations.iter().all(|e| e.value != 4 && e.value != 7));
+        solver.apply(&step);
+        assert_eq!(solver.candidates(0, 4).iter_candidates().collect::<Vec<_>>(), [4, 7]);
+        assert!(solver.find_technique(Technique::HiddenPair).is_none());
+
+        // A triple where no digit fits in all three cells
+        let mut solver = LogicSolver::new(&Board::empty());
+        let allowed = [(1, [0, 1]), (2, [1, 2]), (3, [0, 2])];
+        for (value, cells) in allowed {
+            for cell in 0..9 {
+                if !cells.contains(&cell) {
+                    solver.candidates[cell].remove_candidate(value);
+                }
+            }
+        }
+        let step = solver.find_technique(Technique::HiddenTriple).unwrap();
+        assert_eq!((step.unit, step.digits.clone()), (Some(Unit::Row(0)), vec![1, 2, 3]));
+        assert_eq!(step.eliminations.len(), 3 * 7 - 3);
+    }
+
+    #[test]
+    fn test_naked_subsets() {
+        // r1c1 and r1c2 can only be 1 or 2
+        let mut solver = LogicSolver::new(&Board::empty());
+        for cell in [0, 1] {
+            solver.candidates[cell] = CandidateSet(0b11);
+        }
+        let step = solver.find_technique(Technique::NakedPair).unwrap();
+        assert_eq!(step.unit, Some(Unit::Row(0)));
+        assert_eq!((step.cells.clone(), step.digits.clone()), (vec![(0, 0), (0, 1)], vec![1, 2]));
+        assert_eq!(step.eliminations.len(), 14);
+        solver.apply(&step);
+        // The box still has 1s and 2s outside the pair to remove
+        let step = solver.find_technique(Technique::NakedPair).unwrap();
+        assert_eq!(step.unit, Some(Unit::Box(0)));
+        solver.apply(&step);
+        assert!(solver.find_technique(Technique::NakedPair).is_none());
+
+        // {1,2}, {2,3}, {1,3} form a triple
+        let mut solver = LogicSolver::new(&Board::empty());
+        for (cell, set) in [(0, 0b011), (4, 0b110), (8, 0b101)] {
+            solver.candidates[cell] = CandidateSet(set);
+        }
+        assert!(solver.find_technique(Technique::NakedPair).is_none());
+        let step = solver.find_technique(Technique::NakedTriple).unwrap();
+        assert_eq!(step.digits, [1, 2, 3]);
+        assert_eq!(step.eliminations.len(), 6 * 3);
+
+        let mut candidates = solver.candidates;
+        assert!(prune_naked_subsets(&mut candidates) >= 18);
+        assert!((1..8).filter(|&c| c != 4).all(|c| candidates[c].0 & 0b111 == 0));
+    }
+
+    #[test]
+    fn test_wings() {
+        // Pivot r1c1 {1,2} with pincers r1c5 {1,3} and r5c1 {2,3}: r5c5 can't be 3
+        let mut solver = LogicSolver::new(&Board::empty());
+        for (cell, set) in [(0, 0b011), (4, 0b101), (36, 0b110)] {
+            solver.candidates[cell] = CandidateSet(set);
+        }
+        let step = solver.find_technique(Technique::XYWing).unwrap();
+        assert_eq!(step.cells, [(0, 0), (0, 4), (4, 0)]);
+        assert_eq!(step.digits, [1, 2, 3]);
+        assert_eq!(step.eliminations, [Placement { row: 4, col: 4, value: 3 }]);
+
+        // Pivot r1c1 {1,2,3} with r1c2 {1,3} and r2c1 {2,3}: 3 goes from the rest of box 1
+        let mut solver = LogicSolver::new(&Board::empty());
+        for (cell, set) in [(0, 0b111), (1, 0b101), (9, 0b110)] {
+            solver.candidates[cell] = CandidateSet(set);
+        }
+        let step = solver.find_technique(Technique::XYZWing).unwrap();
+        assert_eq!(step.cells, [(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(step.eliminations.len(), 6);
+        assert!(step.eliminations.iter().all(|e| e.value == 3 && e.row < 3 && e.col < 3));
+    }
+
+    #[test]
+    fn test_remote_pair() {
+        // r1c1 - r1c6 - r6c6 - r6c9, all {1,2}: r1c9 and r6c1 see both ends
+        let mut solver = LogicSolver::new(&Board::empty());
+        for cell in [0, 5, 50, 53] {
+            solver.candidates[cell] = CandidateSet(0b11);
+        }
+        let step = solver.find_technique(Technique::RemotePair).unwrap();
+        assert_eq!(step.cells, [(0, 0), (0, 5), (5, 5), (5, 8)]);
+        assert_eq!(step.digits, [1, 2]);
+        let mut cells: Vec<(usize, usize)> = step.eliminations.iter().map(|e| (e.row, e.col)).collect();
+        cells.sort_unstable();
+        cells.dedup();
+        assert_eq!(cells, [(0, 8), (5, 0)]);
+        assert_eq!(step.eliminations.len(), 4);
+
+        // Three cells in a row don't make a chain with different ends
+        solver.candidates[53] = CandidateSet::all();
+        assert!(solver.find_technique(Technique::RemotePair).is_none());
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut board = Board::empty();
+        for col in 0..8 {
+            board.set(0, col, col as u8 + 1);
+        }
+        board.set(1, 8, 9);
+        let solver = LogicSolver::new(&board);
+        assert!(solver.has_contradiction());
+    }
+}
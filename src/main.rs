@@ -1,108 +1,1663 @@
 //! A Sudoku solver that uses recursive backtracking and integrates with the Dosuku API.
 //! 
 //! This program:
-//! 1. Fetches a new Sudoku puzzle from the Dosuku API
+//! 1. Fetches a new Sudoku puzzle from the Dosuku API (`--source` as for `solve`)
 //! 2. Solves it using recursive backtracking with parallel processing
 //! 3. Verifies the solution against the API's solution
 //! 4. Checks for solution uniqueness
-//! 5. Displays both solutions if they differ
+//! 5. Shows both solutions side by side if they differ, marking the cells that disagree
+//!
+//! Other modes:
+//! - `sudoku solve [--explain] [--portfolio] [--source any|api|generator|cache] [puzzle]` solves a puzzle,
+//!   optionally explaining each step; `--portfolio` races several search strategies and keeps the first
+//!   to finish, and `--source` limits where a puzzle is fetched from when none is given. A puzzle of 16 or
+//!   36 cells is solved as a 4×4 (2×2 boxes) or 6×6 (2×3 boxes) mini puzzle, and one of 256 cells as a
+//!   16×16 puzzle with symbols 1-9 and A-G
+//! - `sudoku benchmark [count] [--json file] [--report file.html]` runs the solver benchmark, optionally
+//!   also writing an HTML page with charts of the run and of any comparison
+//!   (`--save-baseline name` stores the run; `--compare name [--threshold pct]` diffs against one;
+//!   `--scaling [--threads N]` measures throughput with 1, 2, 4, ... N threads;
+//!   `--per-board-timeout 500ms` records boards that run over as DNF;
+//!   `--generator [--seed n]` times puzzle generation per difficulty instead;
+//!   `--calibrate` compares API difficulty labels with the internal rating;
+//!   `--api [--concurrency N] [--think-time 50ms]` times cache hits, API fetches and generator
+//!   fallbacks over `count` board requests, without solving;
+//!   `--puzzles file [--from euler|kaggle|lines]` benchmarks a puzzle collection instead of API boards)
+//! - `sudoku bench-compare <old.json> <new.json>` compares two `benchmark --json` runs metric by metric,
+//!   with a rank-test p-value saying whether each per-board change looks real or like noise
+//! - `sudoku solve-file <puzzles|-> [--output file] [--jobs N] [--gpu]` solves a file of puzzles,
+//!   one per line, in parallel; `--gpu` (in builds with the `gpu` feature) propagates singles on the
+//!   GPU first and leaves only what they don't finish to the CPU
+//! - `sudoku rate <puzzles|-> [--output file] [--jobs N] [--hardness]` grades a file of puzzles by the
+//!   techniques they need, with a distribution summary; `--hardness` adds each puzzle's
+//!   backdoor size and search-tree size
+//! - `sudoku import <collection|-> [--from lines|euler|kaggle] [--output file]` converts a Project Euler
+//!   p096 file or the Kaggle CSV dataset to one puzzle per line (the format is guessed when not given)
+//! - `sudoku stats <collection|-> [--from lines|euler|kaggle]` summarizes a collection: clue counts,
+//!   difficulty and hardest-technique histograms, duplicates up to symmetry, and puzzles without
+//!   exactly one solution
+//! - `cat puzzles.txt | sudoku --pipe [--jobs N] > solved.txt` solves puzzles from stdin,
+//!   writing only the solutions to stdout, one per line
+//! - `sudoku validate <puzzle|file>` diagnoses a puzzle: whether it parses, which givens
+//!   conflict, and whether it has no, one or several solutions
+//! - `sudoku hint <current-grid> [--level nudge|cell|full]` prints the next logical move,
+//!   giving away as much as the level asks for (default `cell`)
+//! - `sudoku record <file.json> [puzzle]` records a solve replay
+//! - `sudoku replay <file.json> [delay-ms]` animates a recorded replay
+//! - `sudoku daily [difficulty] [YYYY-MM-DD]` prints the puzzle of the day
+//! - `sudoku generate [difficulty] [--config options.json] [--seed n] [--size 4|6|9]` generates a
+//!   puzzle locally, with the difficulty mix and clue ranges optionally read from a `GeneratorOptions`
+//!   JSON file; `--size 4` or `--size 6` makes a mini puzzle instead (Easy unless a difficulty is given)
+//!   and `--size 16` a 16×16 one with symbols 1-9 and A-G (Medium unless given)
+//! - `sudoku serve [addr] [--api-key key[:per-minute]]... [--rate-limit per-minute]` runs the HTTP server
+//! - `sudoku train <technique>` generates a puzzle that needs the given technique
+//! - `sudoku hunt [target-clues] [seconds] [--checkpoint file] [--seed n]` searches for very-low-clue puzzles
+//! - `sudoku watch [--interval 1s] [--window 10s] [--duration 300s] [--api]` shows a live
+//!   dashboard of puzzles generated (or with `--api`, fetched) and solved in a loop
+//! - `sudoku verify [--count N] [--seed S]` cross-checks the solver backends on random puzzles
+//! - `sudoku share <puzzle> [--svg file] [--light]` prints a puzzle's compact share code and a QR code
+//!   of it (for a light-background terminal with `--light`), optionally also written as SVG;
+//!   `sudoku share --decode <code|link>` turns a share code back into a puzzle
+//! - `sudoku link <puzzle> [--base-url url]` prints a link that opens the puzzle on a `serve`
+//!   instance (by default the configured `serve` address)
+//!
+//! Every mode takes `--format pretty|line|json` for its output on stdout, and `--describe`
+//! to print boards as sentences, row by row and box by box, for screen readers instead of as grids.
+//! Grids are drawn in large print with `--large` and with ASCII borders with `--ascii`; digits are
+//! colored (givens, filled in, conflicting) on a terminal unless `--no-color` or `NO_COLOR` is set,
+//! and always with `--color`. `--paranoid` re-checks every solution with the scalar validator
+//! against the SIMD one and the givens (always on in debug builds). The exit
+//! status is 0 for success (a puzzle solved uniquely), 1 for other failures, 2 for
+//! multiple solutions, 3 for an unsolvable puzzle and 4 for invalid input.
+//!
+//! Settings for the API client, cache, generator, solver and `serve` can be kept in a
+//! `sudoku.toml` (or the file named by `SUDOKU_CONFIG`) and overridden with `SUDOKU_*`
+//! environment variables; see the `config` module. Command-line flags win over both.
+//!
+//! Logs go to stderr. In any mode, `--log-level <level|directives>` (default `RUST_LOG`,
+//! then `debug`, or `warn` with `--pipe`), `--quiet` and `--log-format pretty|compact|json` control them.
+//!
+//! Built with `--features otlp`, `serve` and `benchmark` also export trace spans
+//! over OTLP/HTTP when given `--otlp` (collector from the `OTEL_EXPORTER_OTLP_*`
+//! variables) or `--otlp-endpoint http://collector:4318`.
 
-use sudoku::{api, solver::Solver, benchmark};
-use tracing::{info, error, Level};
-use tracing_subscriber::FmtSubscriber;
+use sudoku::{
+    api::{self, SourcePreference},
+    batch, benchmark,
+    config::Config,
+    diff::SideBySide,
+    explain, formats,
+    generator::{self, BoardGenerator},
+    hex::{self, HexBoard},
+    hint::{self, HintLevel},
+    logic::{LogicSolver, Technique},
+    mini::{MiniBoard, MiniSize},
+    output::{OutputFormat, SolveReport, Status},
+    providers::fixture::FixtureProvider,
+    render::term::{self, Borders, CellSize, TermOptions},
+    replay::{Replay, ReplayEvent},
+    server, share,
+    solver::{self, Solver},
+    stats, validation, verify, watch, Board, Grid,
+};
+use once_cell::sync::OnceCell;
+use tracing::{info, error, warn, level_filters::LevelFilter};
+use tracing_subscriber::{prelude::*, EnvFilter, Layer};
 use std::env;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Prints a line of machine-readable output. Unlike `println!` it doesn't
+/// panic when stdout is a closed pipe, as with `sudoku ... | head`.
+macro_rules! emit {
+    ($($arg:tt)*) => {{
+        use std::io::Write;
+        let _ = writeln!(std::io::stdout().lock(), $($arg)*);
+    }};
+}
+
+/// Counts allocations for the benchmark's per-solve figures
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static GLOBAL: benchmark::CountingAllocator = benchmark::CountingAllocator;
+
+/// Set by `--describe`: boards are read out in sentences for screen readers instead of drawn
+static DESCRIBE: AtomicBool = AtomicBool::new(false);
+
+/// How grids are drawn, from `--large`, `--ascii`, `--color` and `--no-color`
+static TERM_OPTIONS: OnceCell<TermOptions> = OnceCell::new();
 
 #[tokio::main]
-async fn main() {
-    // Initialize logging with debug level
-    FmtSubscriber::builder()
-        .with_max_level(Level::DEBUG)
-        .with_thread_ids(true)
-        .with_file(true)
-        .with_line_number(true)
-        .with_target(false)
-        .with_thread_names(true)
-        .with_ansi(true)
-        .pretty()
+async fn main() -> ExitCode {
+    // Parse command line arguments; logging and output flags may appear anywhere
+    let mut args: Vec<String> = env::args().collect();
+    // Pipe mode keeps stderr quiet too unless asked otherwise
+    let default_level = if args.iter().any(|a| a == "--pipe") { "warn" } else { "debug" };
+    let options = take_log_options(&mut args, default_level).and_then(|log| Ok((log, take_format(&mut args)?)));
+    let (log_options, format) = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(Status::InvalidInput.code());
+        }
+    };
+    if let Some(i) = args.iter().position(|a| a == "--describe") {
+        args.remove(i);
+        DESCRIBE.store(true, Ordering::Relaxed);
+    }
+    let _ = TERM_OPTIONS.set(take_term_options(&mut args));
+    if let Some(i) = args.iter().position(|a| a == "--paranoid") {
+        args.remove(i);
+        solver::set_paranoid(true);
+    }
+
+    // Log to stderr, exporting spans too if asked
+    #[cfg(feature = "otlp")]
+    let (otlp, _telemetry) = match otlp_config(&args).map(|config| sudoku::telemetry::otlp_layer(&config)) {
+        Some(Ok((layer, telemetry))) => (Some(layer), Some(telemetry)),
+        Some(Err(e)) => {
+            eprintln!("Trace export disabled: {}", e.report());
+            (None, None)
+        }
+        None => (None, None),
+    };
+    #[cfg(not(feature = "otlp"))]
+    let otlp: Option<tracing_subscriber::layer::Identity> = None;
+    tracing_subscriber::registry()
+        .with(console_layer(log_options))
+        .with(otlp.with_filter(LevelFilter::DEBUG))
         .init();
 
-    // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
+    // A one-shot command exits long before a warm-up's boards would be
+    // served, so only the long-running ones warm the cache by default. A
+    // benchmark that solves doesn't either: the warm-up's thread would skew
+    // the wall time, CPU time and allocations it measures. `benchmark --api`
+    // times cache hits, so it keeps one.
+    let long_running = match args.get(1).map(String::as_str) {
+        Some("serve" | "watch") => true,
+        Some("benchmark") => args.iter().any(|a| a == "--api"),
+        _ => false,
+    };
+    // The shared API client has to be set up before anything uses it
+    let config = Config::discover().and_then(|mut config| {
+        if config.api.warm_up.is_none() && !long_running {
+            config.api.warm_up = Some(0);
+        }
+        api::init(&config).map(|()| config)
+    });
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Invalid configuration: {}", e.report());
+            return ExitCode::from(Status::InvalidInput.code());
+        }
+    };
+
+    if let Some(parallelism) = config.solver.parallelism() {
+        solver::set_parallelism(parallelism);
+    }
+
+    let status = run(&args, format, &config).await;
+    api::shutdown();
+    ExitCode::from(status.code())
+}
+
+/// Runs the subcommand named in `args` with settings from `config`, printing
+/// results in `format`
+async fn run(args: &[String], format: OutputFormat, config: &Config) -> Status {
+    if args.iter().any(|a| a == "--pipe") {
+        return pipe(args);
+    }
     match args.get(1).map(|s| s.as_str()) {
         Some("benchmark") => {
             let count = args.get(2)
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(100);
+            let json_path = flag_value(args, "--json");
+            let report_path = flag_value(args, "--report");
+            let save_baseline = flag_value(args, "--save-baseline");
+            let compare = flag_value(args, "--compare");
+            let threshold = flag_value(args, "--threshold")
+                .and_then(|s| s.parse::<f64>().ok())
+                .map(|pct| pct / 100.0)
+                .unwrap_or(benchmark::DEFAULT_REGRESSION_THRESHOLD);
+
+            if args.iter().any(|a| a == "--calibrate") {
+                return match benchmark::run_calibration(count).await {
+                    Ok(calibration) => {
+                        match format {
+                            OutputFormat::Json => print_json(&calibration),
+                            _ => calibration.print(),
+                        }
+                        Status::Solved
+                    }
+                    Err(e) => {
+                        error!("Calibration failed: {}", e.report());
+                        Status::Failed
+                    }
+                };
+            }
+
+            if args.iter().any(|a| a == "--generator") {
+                let seed = flag_value(args, "--seed").and_then(|s| s.parse().ok());
+                return match benchmark::run_generator_benchmark(count, seed) {
+                    Ok(results) => {
+                        match format {
+                            OutputFormat::Json => print_json(&results),
+                            _ => benchmark::print_generator_results(&results),
+                        }
+                        Status::Solved
+                    }
+                    Err(e) => {
+                        error!("Generator benchmark failed: {}", e.report());
+                        Status::Failed
+                    }
+                };
+            }
+
+            if args.iter().any(|a| a == "--api") {
+                let mut workload = benchmark::ApiWorkload { requests: count, ..Default::default() };
+                if let Some(concurrency) = flag_value(args, "--concurrency").and_then(|s| s.parse().ok()) {
+                    workload.concurrency = concurrency;
+                }
+                match flag_value(args, "--think-time").map(benchmark::parse_duration) {
+                    Some(Ok(think_time)) => workload.think_time = think_time,
+                    Some(Err(e)) => {
+                        error!("{}", e.report());
+                        return Status::InvalidInput;
+                    }
+                    None => {}
+                }
+                return match benchmark::run_api_benchmark(&api::default_client(), &workload).await {
+                    Ok(results) => {
+                        match format {
+                            OutputFormat::Json => print_json(&results),
+                            _ => results.print(),
+                        }
+                        Status::Solved
+                    }
+                    Err(e) => {
+                        error!("API benchmark failed: {}", e.report());
+                        Status::Failed
+                    }
+                };
+            }
+
+            if args.iter().any(|a| a == "--scaling") {
+                let max_threads = flag_value(args, "--threads")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or_else(num_cpus::get);
+                return match benchmark::run_scaling_benchmark(count, max_threads).await {
+                    Ok(results) => {
+                        match format {
+                            OutputFormat::Json => print_json(&results),
+                            _ => benchmark::print_scaling(&results),
+                        }
+                        Status::Solved
+                    }
+                    Err(e) => {
+                        error!("Scaling benchmark failed: {}", e.report());
+                        Status::Failed
+                    }
+                };
+            }
             
-            info!("Running benchmark with {} boards...", count);
-            match benchmark::run_benchmark(count, true).await {
-                Ok(results) => results.print_results(),
-                Err(e) => error!("Benchmark failed: {}", e),
+            let per_board_timeout = match flag_value(args, "--per-board-timeout").map(benchmark::parse_duration) {
+                Some(Ok(timeout)) => Some(timeout),
+                Some(Err(e)) => {
+                    error!("{}", e.report());
+                    return Status::InvalidInput;
+                }
+                None => None,
+            };
+
+            // Redraw a progress bar in place; skipped when stderr isn't a terminal
+            let show_progress = std::io::stderr().is_terminal();
+            let draw_progress = |progress: &benchmark::BenchmarkProgress| {
+                if show_progress {
+                    eprint!("\r{}", progress.bar(30));
+                    if progress.completed == progress.total {
+                        eprintln!();
+                    }
+                }
+            };
+            let results = if let Some(path) = flag_value(args, "--puzzles") {
+                // A collection is benchmarked whole unless a count is given
+                let limit = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(usize::MAX);
+                let Some(boards) = read_collection(path, args, limit) else {
+                    return Status::InvalidInput;
+                };
+                info!("Running benchmark with {} boards from {}...", boards.len(), path);
+                benchmark::run_benchmark_on(&boards, per_board_timeout, draw_progress)
+            } else {
+                info!("Running benchmark with {} boards...", count);
+                benchmark::run_benchmark_with_progress(count, true, per_board_timeout, draw_progress).await
+            };
+            let results = match results {
+                Ok(results) => results,
+                Err(e) => {
+                    error!("Benchmark failed: {}", e.report());
+                    return Status::Failed;
+                }
+            };
+            match format {
+                OutputFormat::Pretty => results.print_results(),
+                OutputFormat::Line => {
+                    for board in &results.boards {
+                        let outcome = if board.solved { "solved" } else if board.dnf { "dnf" } else { "failed" };
+                        emit!("{} {} {}", board.puzzle, outcome, board.solve_time.as_micros());
+                    }
+                }
+                OutputFormat::Json => print_json(&results),
+            }
+            let mut status = Status::Solved;
+            if let Some(path) = json_path {
+                match results.save_json(path) {
+                    Ok(()) => info!("Wrote results to {}", path),
+                    Err(e) => {
+                        error!("Failed to write results: {}", e.report());
+                        status = Status::Failed;
+                    }
+                }
+            }
+            let mut compared = None;
+            if let Some(name) = compare {
+                match benchmark::BenchmarkResults::load_baseline(name) {
+                    Ok(baseline) => {
+                        let comparison = results.compare(&baseline, threshold);
+                        match format {
+                            OutputFormat::Json => print_json(&comparison),
+                            OutputFormat::Line => {}
+                            OutputFormat::Pretty => comparison.print(),
+                        }
+                        if comparison.has_regressions() {
+                            error!("Performance regressed against baseline '{}'", name);
+                            status = Status::Failed;
+                        }
+                        compared = Some((name, comparison));
+                    }
+                    Err(e) => {
+                        error!("Failed to load baseline '{}': {}", name, e);
+                        status = Status::Failed;
+                    }
+                }
+            }
+            if let Some(path) = report_path {
+                match results.save_report(path, compared.as_ref().map(|(name, comparison)| (*name, comparison))) {
+                    Ok(()) => info!("Wrote report to {}", path),
+                    Err(e) => {
+                        error!("Failed to write report: {}", e.report());
+                        status = Status::Failed;
+                    }
+                }
+            }
+            if let Some(name) = save_baseline {
+                match results.save_baseline(name) {
+                    Ok(path) => info!("Saved baseline '{}' to {}", name, path.display()),
+                    Err(e) => {
+                        error!("Failed to save baseline: {}", e.report());
+                        status = Status::Failed;
+                    }
+                }
+            }
+            status
+        }
+        Some("bench-compare") => {
+            let paths: Vec<&String> = args.iter().skip(2).filter(|a| !a.starts_with("--")).collect();
+            let [old_path, new_path] = paths[..] else {
+                error!("Usage: sudoku bench-compare <old.json> <new.json>");
+                return Status::InvalidInput;
+            };
+            let load = |path: &str| {
+                benchmark::BenchmarkResults::load_json(path)
+                    .map_err(|e| error!("Failed to read benchmark results from {}: {}", path, e))
+                    .ok()
+            };
+            let (Some(old), Some(new)) = (load(old_path), load(new_path)) else {
+                return Status::InvalidInput;
+            };
+            let comparison = benchmark::compare_runs(&old, &new);
+            match format {
+                OutputFormat::Pretty => comparison.print(),
+                OutputFormat::Json => print_json(&comparison),
+                OutputFormat::Line => {
+                    for m in &comparison.metrics {
+                        let p = m.p_value.map_or_else(|| "-".to_string(), |p| format!("{:.4}", p));
+                        emit!("{} {} {} {:+.4} {}", m.name, m.old, m.new, m.change, p);
+                    }
+                }
+            }
+            Status::Solved
+        }
+        Some("solve") => {
+            let explain = args.iter().skip(2).any(|a| a == "--explain");
+            let portfolio = args.iter().skip(2).any(|a| a == "--portfolio");
+            let Some(source) = source_preference(args) else {
+                return Status::InvalidInput;
+            };
+            // The value of `--source` isn't the puzzle
+            let source_at = args.iter().position(|a| a == "--source").map(|i| i + 1);
+            let line = args.iter().enumerate().skip(2).find(|&(i, a)| !a.starts_with("--") && Some(i) != source_at);
+            let puzzle = match line.map(|(_, line)| line) {
+                Some(line) if MiniSize::detect(line).is_some() => {
+                    return match MiniBoard::parse(line) {
+                        Ok(puzzle) => solve_small(&puzzle, format),
+                        Err(e) => {
+                            error!("Failed to load puzzle: {}", e.report());
+                            Status::of_error(&e)
+                        }
+                    };
+                }
+                Some(line) if hex::detect(line) => {
+                    return match HexBoard::parse(line) {
+                        Ok(puzzle) => solve_small(&puzzle, format),
+                        Err(e) => {
+                            error!("Failed to load puzzle: {}", e.report());
+                            Status::of_error(&e)
+                        }
+                    };
+                }
+                Some(line) => Board::parse(line),
+                None => api::fetch_new_board_with(source).await.map(|grid| Board::new(&grid.value)),
+            };
+            match puzzle {
+                Ok(puzzle) => solve_puzzle(&puzzle, explain, portfolio, format, config.solver.timeout),
+                Err(e) => {
+                    error!("Failed to load puzzle: {}", e.report());
+                    Status::of_error(&e)
+                }
+            }
+        }
+        Some("solve-file") => {
+            let Some(path) = args.get(2).filter(|a| !a.starts_with("--")) else {
+                error!("Usage: sudoku solve-file <puzzles|-> [--output file] [--jobs N] [--gpu]");
+                return Status::InvalidInput;
+            };
+            let Some(options) = batch_options(args) else {
+                return Status::InvalidInput;
+            };
+
+            let Some((input, output)) = open_batch_files(path, args) else {
+                return Status::Failed;
+            };
+
+            info!("Solving puzzles from {} with {} threads", path, options.jobs);
+            let summary = match batch::solve_lines(input, output, &options) {
+                Ok(summary) => summary,
+                Err(e) => {
+                    error!("Batch solve failed: {}", e.report());
+                    return Status::Failed;
+                }
+            };
+            // Solutions go to stdout when there is no --output, so keep the summary out of it
+            let to_stdout = flag_value(args, "--output").is_none();
+            match format {
+                OutputFormat::Pretty if !to_stdout => summary.print(),
+                OutputFormat::Json if !to_stdout => print_json(&summary),
+                _ => info!(
+                    "Solved {}/{} puzzles in {:.2?} ({} unsolvable, {} invalid)",
+                    summary.solved, summary.puzzles, summary.elapsed, summary.unsolvable, summary.invalid
+                ),
+            }
+            batch_status(&summary)
+        }
+        Some("rate") => {
+            let Some(path) = args.get(2).filter(|a| !a.starts_with("--")) else {
+                error!("Usage: sudoku rate <puzzles|-> [--output file] [--jobs N] [--hardness]");
+                return Status::InvalidInput;
+            };
+            let Some(options) = batch_options(args) else {
+                return Status::InvalidInput;
+            };
+            let Some((input, output)) = open_batch_files(path, args) else {
+                return Status::Failed;
+            };
+
+            info!("Rating puzzles from {} with {} threads", path, options.jobs);
+            let summary = match batch::rate_lines(input, output, &options, format == OutputFormat::Json) {
+                Ok(summary) => summary,
+                Err(e) => {
+                    error!("Batch rating failed: {}", e.report());
+                    return Status::Failed;
+                }
+            };
+            // JSON lines go to stdout when there is no --output, so keep the summary out of it
+            let to_stdout = flag_value(args, "--output").is_none();
+            match format {
+                OutputFormat::Pretty => summary.print(),
+                OutputFormat::Json if !to_stdout => print_json(&summary),
+                _ => info!("Rated {} puzzles in {:.2?} ({} invalid)", summary.rated(), summary.elapsed, summary.invalid),
+            }
+            if summary.invalid > 0 {
+                Status::InvalidInput
+            } else {
+                Status::Solved
+            }
+        }
+        Some("import") => {
+            let Some(path) = args.get(2).filter(|a| !a.starts_with("--")) else {
+                error!("Usage: sudoku import <collection|-> [--from lines|euler|kaggle] [--output file]");
+                return Status::InvalidInput;
+            };
+            let from = match flag_value(args, "--from").map(str::parse::<formats::Format>).transpose() {
+                Ok(from) => from,
+                Err(e) => {
+                    error!("{}", e.report());
+                    return Status::InvalidInput;
+                }
+            };
+            let Some((input, mut output)) = open_batch_files(path, args) else {
+                return Status::Failed;
+            };
+            let records = formats::read(input, from);
+            info!("Importing {} puzzles from {}", records.format(), path);
+            let (mut imported, mut invalid) = (0, 0);
+            for record in records {
+                let written = match record {
+                    Ok(grid) if format == OutputFormat::Json => serde_json::to_string(&grid)
+                        .map_err(std::io::Error::from)
+                        .and_then(|json| writeln!(output, "{}", json)),
+                    Ok(grid) => writeln!(output, "{}", Board::new(&grid.value).to_line()),
+                    Err(e) => {
+                        warn!("Skipping {}", e.report());
+                        invalid += 1;
+                        continue;
+                    }
+                };
+                if let Err(e) = written {
+                    error!("Failed to write puzzles: {}", e);
+                    return Status::Failed;
+                }
+                imported += 1;
+            }
+            if let Err(e) = output.flush() {
+                error!("Failed to write puzzles: {}", e);
+                return Status::Failed;
+            }
+            info!("Imported {} puzzles ({} invalid)", imported, invalid);
+            if invalid > 0 { Status::InvalidInput } else { Status::Solved }
+        }
+        Some("stats") => {
+            let Some(path) = args.get(2).filter(|a| !a.starts_with("--")) else {
+                error!("Usage: sudoku stats <collection|-> [--from lines|euler|kaggle]");
+                return Status::InvalidInput;
+            };
+            let from = match flag_value(args, "--from").map(str::parse::<formats::Format>).transpose() {
+                Ok(from) => from,
+                Err(e) => {
+                    error!("{}", e.report());
+                    return Status::InvalidInput;
+                }
+            };
+            let Some((input, _)) = open_batch_files(path, args) else {
+                return Status::Failed;
+            };
+            info!("Analyzing puzzles from {}", path);
+            let summary = match stats::analyze(input, from) {
+                Ok(summary) => summary,
+                Err(e) => {
+                    error!("Failed to read {}: {}", path, e);
+                    return Status::Failed;
+                }
+            };
+            match format {
+                OutputFormat::Pretty => summary.print(),
+                OutputFormat::Json => print_json(&summary),
+                _ => info!(
+                    "Analyzed {} puzzles: {} distinct, {} without a unique solution",
+                    summary.puzzles,
+                    summary.distinct,
+                    summary.violations.len()
+                ),
+            }
+            if summary.invalid > 0 { Status::InvalidInput } else { Status::Solved }
+        }
+        Some("validate") => {
+            let Some(input) = args.get(2).filter(|a| !a.starts_with("--")) else {
+                error!("Usage: sudoku validate <puzzle|file>");
+                return Status::InvalidInput;
+            };
+            let bytes = if std::path::Path::new(input).is_file() {
+                match std::fs::read(input) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        error!("Failed to read {}: {}", input, e);
+                        return Status::Failed;
+                    }
+                }
+            } else {
+                input.as_bytes().to_vec()
+            };
+            let diagnosis = validation::diagnose_bytes(&bytes);
+            match format {
+                OutputFormat::Pretty => {
+                    // Show where clashing givens are before listing them
+                    if let (false, Ok(board)) = (diagnosis.conflicts.is_empty(), Board::parse(&diagnosis.puzzle)) {
+                        print_board(&board, None);
+                    }
+                    diagnosis.print()
+                }
+                OutputFormat::Line => emit!("{} {}", diagnosis.puzzle, diagnosis.status()),
+                OutputFormat::Json => print_json(&diagnosis),
+            }
+            diagnosis.status()
+        }
+        Some("hint") => {
+            let Some(grid) = args.get(2).filter(|a| !a.starts_with("--")) else {
+                error!("Usage: sudoku hint <current-grid> [--level nudge|cell|full]");
+                return Status::InvalidInput;
+            };
+            let level = match flag_value(args, "--level").map(str::parse::<HintLevel>).transpose() {
+                Ok(level) => level.unwrap_or_default(),
+                Err(e) => {
+                    error!("{}", e.report());
+                    return Status::InvalidInput;
+                }
+            };
+            let grid = match Board::parse(grid) {
+                Ok(grid) => grid,
+                Err(e) => {
+                    error!("Failed to parse grid: {}", e.report());
+                    return Status::InvalidInput;
+                }
+            };
+            match hint::next_hint(&grid, level) {
+                Ok(Some(hint)) => {
+                    match format {
+                        OutputFormat::Json => print_json(&hint),
+                        _ => emit!("{}", hint.text),
+                    }
+                    // A full hint gives the cell away, so show where it is
+                    let target = hint.step.as_ref().and_then(|step| step.placements.first());
+                    if let (OutputFormat::Pretty, Some(target)) = (format, target) {
+                        print_board_at(&grid, None, Some((target.row, target.col)));
+                    }
+                    Status::Solved
+                }
+                Ok(None) if !grid.to_line().contains('.') => {
+                    info!("The grid is already complete");
+                    Status::Solved
+                }
+                Ok(None) => {
+                    error!("No logical move found; the next step needs a technique this solver doesn't know");
+                    Status::Failed
+                }
+                Err(e) if grid.is_consistent() => {
+                    error!("The grid has a wrong entry: it can no longer be solved");
+                    Status::of_error(&e)
+                }
+                Err(_) => {
+                    error!("The grid has clashing digits");
+                    if format == OutputFormat::Pretty {
+                        print_board(&grid, None);
+                        print_conflicts(&grid);
+                    }
+                    Status::InvalidInput
+                }
+            }
+        }
+        Some("record") => {
+            let Some(path) = args.get(2) else {
+                error!("Usage: sudoku record <file.json> [puzzle]");
+                return Status::InvalidInput;
+            };
+            let puzzle = match args.get(3) {
+                Some(line) => Board::parse(line),
+                None => api::fetch_new_board().await.map(|grid| Board::new(&grid.value)),
+            };
+            let puzzle = match puzzle {
+                Ok(puzzle) => puzzle,
+                Err(e) => {
+                    error!("Failed to load puzzle: {}", e.report());
+                    return Status::of_error(&e);
+                }
+            };
+            let replay = Replay::record(&puzzle);
+            match format {
+                OutputFormat::Pretty => info!(
+                    "Recorded {} events ({} guesses, {} backtracks)",
+                    replay.events.len(),
+                    replay.guesses(),
+                    replay.backtracks()
+                ),
+                OutputFormat::Line => emit!("{}", puzzle.to_line()),
+                OutputFormat::Json => print_json(&serde_json::json!({
+                    "puzzle": puzzle.to_line(),
+                    "events": replay.events.len(),
+                    "guesses": replay.guesses(),
+                    "backtracks": replay.backtracks(),
+                })),
+            }
+            if let Err(e) = replay.save(path) {
+                error!("Failed to save replay: {}", e.report());
+                return Status::Failed;
+            }
+            if replay.solution.is_some() { Status::Solved } else { Status::Unsolvable }
+        }
+        Some("replay") => {
+            let Some(path) = args.get(2) else {
+                error!("Usage: sudoku replay <file.json> [delay-ms]");
+                return Status::InvalidInput;
+            };
+            let delay = args.get(3)
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(Duration::from_millis(150));
+
+            match Replay::load(path).and_then(|replay| play_replay(&replay, delay, format).map(|()| replay)) {
+                Ok(replay) if replay.solution.is_some() => Status::Solved,
+                Ok(_) => Status::Unsolvable,
+                Err(e) => {
+                    error!("Failed to play replay: {}", e.report());
+                    Status::of_error(&e)
+                }
+            }
+        }
+        Some("daily") => {
+            let difficulty = args.get(2).map(|s| s.as_str()).unwrap_or("Medium");
+            let date = match args.get(3) {
+                Some(s) => match chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+                    Ok(date) => date,
+                    Err(_) => {
+                        error!("Date must be in YYYY-MM-DD format");
+                        return Status::InvalidInput;
+                    }
+                },
+                None => chrono::Utc::now().date_naive(),
+            };
+
+            match generator::daily(date, difficulty) {
+                Ok(grid) => {
+                    print_grid(&grid, format, &format!("Puzzle of the day for {} (Difficulty: {}):", date, grid.difficulty));
+                    Status::Solved
+                }
+                Err(e) => {
+                    error!("Failed to generate daily puzzle: {}", e.report());
+                    Status::Failed
+                }
+            }
+        }
+        Some("generate") => {
+            let mut generator = match flag_value(args, "--seed").and_then(|s| s.parse().ok()) {
+                Some(seed) => BoardGenerator::with_seed(seed),
+                None => BoardGenerator::new(),
+            };
+            if let Some(path) = flag_value(args, "--config") {
+                match generator::GeneratorOptions::load(path).and_then(|options| generator.with_options(options)) {
+                    Ok(configured) => generator = configured,
+                    Err(e) => {
+                        error!("Failed to load generator options from {}: {}", path, e);
+                        return Status::InvalidInput;
+                    }
+                }
+            } else if let Some(options) = &config.generator {
+                match generator.with_options(options.clone()) {
+                    Ok(configured) => generator = configured,
+                    Err(e) => {
+                        error!("Invalid generator options: {}", e.report());
+                        return Status::InvalidInput;
+                    }
+                }
+            }
+
+            let difficulty = args.get(2).filter(|a| !a.starts_with("--"));
+            let difficulty = |default| difficulty.map_or(default, String::as_str);
+            match flag_value(args, "--size") {
+                None | Some("9") => {}
+                Some("4") => {
+                    let mini = generator.generate_mini(MiniSize::Four, difficulty("Easy"));
+                    return print_generated(&mini.puzzle, &mini.solution, &mini.difficulty, 4, format);
+                }
+                Some("6") => {
+                    let mini = generator.generate_mini(MiniSize::Six, difficulty("Easy"));
+                    return print_generated(&mini.puzzle, &mini.solution, &mini.difficulty, 6, format);
+                }
+                Some("16") => {
+                    let hex = generator.generate_hex(difficulty("Medium"));
+                    return print_generated(&hex.puzzle, &hex.solution, &hex.difficulty, 16, format);
+                }
+                Some(other) => {
+                    error!("Invalid size {}, expected 4, 6, 9 or 16", other);
+                    return Status::InvalidInput;
+                }
+            }
+            let grid = match args.get(2).filter(|a| !a.starts_with("--")) {
+                Some(difficulty) => generator.generate_with_difficulty(difficulty),
+                None => generator.generate(),
+            };
+            match grid {
+                Ok(grid) => {
+                    if format == OutputFormat::Pretty {
+                        println!("{}", Board::new(&grid.value).to_line());
+                    }
+                    print_grid(&grid, format, &format!("Generated puzzle (Difficulty: {}):", grid.difficulty));
+                    Status::Solved
+                }
+                Err(e) => {
+                    error!("Failed to generate puzzle: {}", e.report());
+                    Status::Failed
+                }
+            }
+        }
+        Some("train") => {
+            let technique = match args.get(2).map(|s| s.parse::<Technique>()) {
+                Some(Ok(technique)) => technique,
+                Some(Err(e)) => {
+                    error!("{}", e.report());
+                    return Status::InvalidInput;
+                }
+                None => {
+                    error!("Usage: sudoku train <technique>");
+                    return Status::InvalidInput;
+                }
+            };
+
+            let mut generator = BoardGenerator::new();
+            match generator.generate_requiring(technique, 500) {
+                Ok(grid) => {
+                    print_grid(&grid, format, &format!("Practice puzzle for {} (Difficulty: {}):", technique, grid.difficulty));
+                    Status::Solved
+                }
+                Err(e) => {
+                    error!("{}", e.report());
+                    Status::Failed
+                }
+            }
+        }
+        Some("hunt") => {
+            let positional: Vec<&String> = args.iter().skip(2).take_while(|a| !a.starts_with("--")).collect();
+            let target = positional.first().and_then(|s| s.parse().ok()).unwrap_or(22);
+            let seconds = positional.get(1).and_then(|s| s.parse().ok()).unwrap_or(600);
+            let options = generator::HuntOptions {
+                checkpoint: flag_value(args, "--checkpoint").map(Into::into),
+                seed: flag_value(args, "--seed").and_then(|s| s.parse().ok()),
+                ..Default::default()
+            };
+
+            info!("Hunting for a puzzle with at most {} clues for {}s", target, seconds);
+            let report = generator::hunt_min_clues_with(target, Duration::from_secs(seconds), &options);
+            info!(
+                "Tried {} minimal puzzles in {:.1}s",
+                report.attempts,
+                report.elapsed.as_secs_f64()
+            );
+            if format == OutputFormat::Json {
+                print_json(&report);
+            }
+            match &report.best {
+                Some(grid) => {
+                    info!(
+                        "Best: {} clues ({} distinct at that count){}",
+                        report.best_clues,
+                        report.distinct_at_best,
+                        if report.reached_target { "" } else { ", target not reached" }
+                    );
+                    if format != OutputFormat::Json {
+                        println!("{}", Board::new(&grid.value).to_line());
+                    }
+                    if format == OutputFormat::Pretty {
+                        print_board(&Board::new(&grid.value), None);
+                    }
+                    Status::Solved
+                }
+                None => {
+                    error!("No puzzle found within the time budget");
+                    Status::Failed
+                }
+            }
+        }
+        Some("watch") => {
+            let mut options = watch::WatchOptions::default();
+            for (flag, field) in [("--interval", &mut options.interval), ("--window", &mut options.window)] {
+                match flag_value(args, flag).map(benchmark::parse_duration) {
+                    Some(Ok(value)) if !value.is_zero() => *field = value,
+                    Some(Ok(_)) | Some(Err(_)) => {
+                        error!("Invalid {}: {}", flag, flag_value(args, flag).unwrap_or_default());
+                        return Status::InvalidInput;
+                    }
+                    None => {}
+                }
+            }
+            match flag_value(args, "--duration").map(benchmark::parse_duration) {
+                Some(Ok(duration)) => options.duration = Some(duration),
+                Some(Err(e)) => {
+                    error!("{}", e.report());
+                    return Status::InvalidInput;
+                }
+                None => {}
+            }
+
+            // Redraw in place on a terminal; anywhere else, append
+            let redraw = format == OutputFormat::Pretty && std::io::stdout().is_terminal();
+            let on_refresh = |snapshot: &watch::Snapshot| match format {
+                OutputFormat::Pretty if redraw => print!("\x1b[2J\x1b[H{}", snapshot),
+                OutputFormat::Json => emit!("{}", serde_json::to_string(snapshot).unwrap_or_default()),
+                _ => emit!("{}", snapshot.line()),
+            };
+            let snapshot = if args.iter().any(|a| a == "--api") {
+                watch::run(&api::default_client(), &options, on_refresh).await
+            } else {
+                // Puzzles from the local generator only, so a soak test doesn't hammer the API
+                let client = api::ApiClient::new(FixtureProvider::new(Vec::new()))
+                    .with_min_request_interval(Duration::ZERO)
+                    .with_cache_policy(config.cache.policy());
+                let client = match &config.generator {
+                    Some(generator) => match client.with_generator_options(generator) {
+                        Ok(client) => client,
+                        Err(e) => {
+                            error!("Invalid generator options: {}", e.report());
+                            return Status::InvalidInput;
+                        }
+                    },
+                    None => client,
+                };
+                watch::run(&client, &options, on_refresh).await
+            };
+            if redraw {
+                print!("\x1b[2J\x1b[H{}", snapshot);
+            } else if format == OutputFormat::Pretty {
+                println!("\n{}", snapshot);
+            }
+            if snapshot.failures == 0 { Status::Solved } else { Status::Failed }
+        }
+        Some("verify") => {
+            let count = flag_value(args, "--count").and_then(|s| s.parse().ok()).unwrap_or(1000);
+            let seed = flag_value(args, "--seed").and_then(|s| s.parse().ok()).unwrap_or(1);
+
+            info!("Cross-checking solvers on {} random puzzles (seed {})", count, seed);
+            let report = verify::run(count, seed);
+            match format {
+                OutputFormat::Pretty => report.print(),
+                OutputFormat::Line => report.mismatches.iter().for_each(|m| emit!("{}", m.puzzle)),
+                OutputFormat::Json => print_json(&report),
+            }
+            if report.passed() { Status::Solved } else { Status::Failed }
+        }
+        Some("share") => {
+            let svg = flag_value(args, "--svg");
+            let Some(input) = args.iter().skip(2).find(|a| !a.starts_with("--") && Some(a.as_str()) != svg) else {
+                error!("Usage: sudoku share <puzzle> [--svg file] [--light] | sudoku share --decode <code>");
+                return Status::InvalidInput;
+            };
+            if args.iter().any(|a| a == "--decode") {
+                return match share::decode_link(input) {
+                    Ok(puzzle) => {
+                        match format {
+                            OutputFormat::Pretty => print_board(&puzzle, None),
+                            OutputFormat::Line => emit!("{}", puzzle.to_line()),
+                            OutputFormat::Json => print_json(&serde_json::json!({ "puzzle": puzzle.to_line(), "code": input })),
+                        }
+                        Status::Solved
+                    }
+                    Err(e) => {
+                        error!("{}", e.report());
+                        Status::of_error(&e)
+                    }
+                };
+            }
+            let puzzle = match Board::parse(input) {
+                Ok(puzzle) => puzzle,
+                Err(e) => {
+                    error!("Failed to parse puzzle: {}", e.report());
+                    return Status::of_error(&e);
+                }
+            };
+            let code = share::encode(&puzzle);
+            let qr = share::qr_code(&puzzle);
+            if let Some(path) = svg {
+                if let Err(e) = std::fs::write(path, qr.to_svg(8)) {
+                    error!("Failed to write {}: {}", path, e);
+                    return Status::Failed;
+                }
+                info!("Wrote QR code to {}", path);
+            }
+            match format {
+                OutputFormat::Pretty => {
+                    print!("{}", qr.to_text(!args.iter().any(|a| a == "--light")));
+                    emit!("{}", code);
+                }
+                OutputFormat::Line => emit!("{}", code),
+                OutputFormat::Json => print_json(&serde_json::json!({ "puzzle": puzzle.to_line(), "code": code })),
+            }
+            Status::Solved
+        }
+        Some("link") => {
+            let base_url = flag_value(args, "--base-url");
+            let Some(input) = args.iter().skip(2).find(|a| !a.starts_with("--") && Some(a.as_str()) != base_url) else {
+                error!("Usage: sudoku link <puzzle> [--base-url url]");
+                return Status::InvalidInput;
+            };
+            let puzzle = match Board::parse(input) {
+                Ok(puzzle) => puzzle,
+                Err(e) => {
+                    error!("Failed to parse puzzle: {}", e.report());
+                    return Status::of_error(&e);
+                }
+            };
+            let base_url = match base_url {
+                Some(url) => url.to_string(),
+                None => format!("http://{}", config.serve.addr.as_deref().unwrap_or(server::DEFAULT_ADDR)),
+            };
+            let link = share::link(&base_url, &puzzle);
+            match format {
+                OutputFormat::Json => print_json(&serde_json::json!({
+                    "puzzle": puzzle.to_line(),
+                    "code": share::encode(&puzzle),
+                    "url": link,
+                })),
+                _ => emit!("{}", link),
+            }
+            Status::Solved
+        }
+        Some("serve") => {
+            let mut serve = config.serve.clone();
+            if let Some(addr) = args.get(2).filter(|a| !a.starts_with("--")) {
+                serve.addr = Some(addr.clone());
+            }
+            let addr = serve.addr.as_deref().unwrap_or(server::DEFAULT_ADDR);
+            if let Some(limit) = flag_value(args, "--rate-limit") {
+                match limit.parse() {
+                    Ok(per_minute) => serve.rate_limit = Some(per_minute),
+                    Err(_) => {
+                        error!("Invalid rate limit: {}", limit);
+                        return Status::InvalidInput;
+                    }
+                }
+            }
+            // `--api-key` may be repeated; any given replace the configured keys
+            let keys: Vec<String> = args.windows(2).filter(|w| w[0] == "--api-key").map(|w| w[1].clone()).collect();
+            if !keys.is_empty() {
+                serve.api_keys = keys;
+            }
+            let policy = match serve.access_policy() {
+                Ok(policy) => policy,
+                Err(e) => {
+                    error!("{}", e.report());
+                    return Status::InvalidInput;
+                }
+            };
+            if policy.requires_key() {
+                info!("API keys required");
+            }
+            match addr.parse() {
+                Ok(addr) => match server::serve_with(addr, policy).await {
+                    Ok(()) => Status::Solved,
+                    Err(e) => {
+                        error!("Server failed: {}", e.report());
+                        Status::Failed
+                    }
+                },
+                Err(_) => {
+                    error!("Invalid listen address: {}", addr);
+                    Status::InvalidInput
+                }
             }
         }
         _ => {
+            let Some(source) = source_preference(args) else {
+                return Status::InvalidInput;
+            };
             info!("Fetching new Sudoku board from API...");
             
-            match api::fetch_new_board().await {
-                Ok(grid) => {
-                    info!("Original board (Difficulty: {}):", grid.difficulty);
-                    print_board(&grid.value);
-
-                    let mut solver = Solver::new(grid.clone());
-                    match solver.solve() {
-                        Ok(solution) => {
-                            info!("Our solution:");
-                            print_board(&solution);
-                            
-                            if solver.verify_solution() {
-                                info!("✅ Solution verified against API's solution!");
-                            } else {
-                                error!("❌ Our solution differs from API's solution!");
-                                info!("API's solution:");
-                                print_board(&solver.get_original_solution());
-                            }
-
-                            if solver.has_unique_solution() {
-                                info!("✅ This puzzle has a unique solution!");
-                            } else {
-                                info!("⚠️  This puzzle has multiple valid solutions!");
-                            }
+            let grid = match api::fetch_new_board_with(source).await {
+                Ok(grid) => grid,
+                Err(e) => {
+                    error!("Failed to fetch board: {}", e.report());
+                    return Status::Failed;
+                }
+            };
+            let puzzle = Board::new(&grid.value);
+            if format == OutputFormat::Pretty {
+                info!("Original board (Difficulty: {}):", grid.difficulty);
+                print_board(&puzzle, None);
+            }
+
+            let mut solver = Solver::new(grid.clone()).with_uniqueness_check();
+            if let Some(timeout) = config.solver.timeout {
+                solver = solver.with_timeout(timeout);
+            }
+            let result = solver.solve();
+            let report = SolveReport::new(&puzzle, &result);
+            match (&result, format) {
+                (Ok(solution), OutputFormat::Pretty) => {
+                    info!("Our solution (found in {:?}):", solution.stats.duration);
+                    print_board(&solution.board, Some(&puzzle));
+                    
+                    match (solution.matches_reference, solver.get_original_solution()) {
+                        (Some(true), _) => info!("✅ Solution verified against API's solution!"),
+                        (Some(false), Some(reference)) => {
+                            let reference = Board::new(&reference);
+                            let differing = solution.board.diff(&reference).len();
+                            error!("❌ Our solution differs from API's solution in {} cells!", differing);
+                            print!("{}", SideBySide::new(&solution.board, &reference).with_labels("Ours", "API's"));
                         }
-                        Err(e) => error!("Failed to solve board: {}", e),
+                        _ => info!("No reference solution to verify against"),
+                    }
+
+                    if solution.unique == Some(true) {
+                        info!("✅ This puzzle has a unique solution!");
+                    } else {
+                        info!("⚠️  This puzzle has multiple valid solutions!");
                     }
                 }
-                Err(e) => error!("Failed to fetch board: {}", e),
+                (Err(e), OutputFormat::Pretty) => error!("Failed to solve board: {}", e),
+                _ => print_report(&report, format),
+            }
+            report.status
+        }
+    }
+}
+
+/// Trace export settings for `serve` and `benchmark`, enabled by `--otlp`,
+/// `--otlp-endpoint url`, or the `OTEL_EXPORTER_OTLP_ENDPOINT` variable
+#[cfg(feature = "otlp")]
+fn otlp_config(args: &[String]) -> Option<sudoku::telemetry::OtlpConfig> {
+    if !matches!(args.get(1).map(|s| s.as_str()), Some("serve" | "benchmark")) {
+        return None;
+    }
+    let config = sudoku::telemetry::OtlpConfig::default();
+    match flag_value(args, "--otlp-endpoint") {
+        Some(endpoint) => Some(config.with_endpoint(endpoint)),
+        None if args.iter().any(|a| a == "--otlp") || env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() => Some(config),
+        None => None,
+    }
+}
+
+/// How console logs are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Pretty,
+    Compact,
+    Json,
+}
+
+/// Console logging settings from `--log-level`, `--quiet` and `--log-format`
+#[derive(Debug)]
+struct LogOptions {
+    filter: EnvFilter,
+    format: LogFormat,
+}
+
+/// Removes the logging flags from `args`, so subcommands never see them.
+///
+/// `--log-level` takes a level or `RUST_LOG`-style directives and wins over
+/// `RUST_LOG`, which wins over `default_level`. `--quiet` (`-q`) logs errors
+/// only.
+fn take_log_options(args: &mut Vec<String>, default_level: &str) -> Result<LogOptions, String> {
+    let mut level = None;
+    let mut format = LogFormat::Pretty;
+    let mut quiet = false;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--quiet" | "-q" => {
+                quiet = true;
+                args.remove(i);
+            }
+            flag @ ("--log-level" | "--log-format") => {
+                let flag = flag.to_string();
+                let value = args.get(i + 1).cloned().ok_or_else(|| format!("{} needs a value", flag))?;
+                args.drain(i..i + 2);
+                if flag == "--log-level" {
+                    level = Some(value);
+                } else {
+                    format = match value.as_str() {
+                        "pretty" => LogFormat::Pretty,
+                        "compact" => LogFormat::Compact,
+                        "json" => LogFormat::Json,
+                        _ => return Err(format!("unknown log format '{}' (pretty, compact, json)", value)),
+                    };
+                }
             }
+            _ => i += 1,
+        }
+    }
+
+    let filter = match (quiet, level) {
+        (true, _) => EnvFilter::new("error"),
+        (false, Some(level)) => EnvFilter::try_new(&level).map_err(|e| format!("invalid log level '{}': {}", level, e))?,
+        (false, None) => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level)),
+    };
+    Ok(LogOptions { filter, format })
+}
+
+/// Console log layer writing to stderr, so stdout carries only results
+fn console_layer<S>(options: LogOptions) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    match options.format {
+        LogFormat::Pretty => layer
+            .with_thread_ids(true)
+            .with_file(true)
+            .with_line_number(true)
+            .with_target(false)
+            .with_thread_names(true)
+            .with_ansi(std::io::stderr().is_terminal())
+            .pretty()
+            .with_filter(options.filter)
+            .boxed(),
+        LogFormat::Compact => layer
+            .with_target(false)
+            .with_ansi(std::io::stderr().is_terminal())
+            .compact()
+            .with_filter(options.filter)
+            .boxed(),
+        LogFormat::Json => layer.json().with_current_span(true).with_filter(options.filter).boxed(),
+    }
+}
+
+/// Removes `--format json|line|pretty` from `args`
+fn take_format(args: &mut Vec<String>) -> Result<OutputFormat, String> {
+    let Some(i) = args.iter().position(|a| a == "--format") else {
+        return Ok(OutputFormat::Pretty);
+    };
+    let value = args.get(i + 1).cloned().ok_or("--format needs a value")?;
+    args.drain(i..i + 2);
+    value.parse().map_err(|e: sudoku::SudokuError| e.to_string())
+}
+
+/// Prints `value` as one line of JSON
+fn print_json(value: &impl serde::Serialize) {
+    match serde_json::to_string(value) {
+        Ok(json) => emit!("{}", json),
+        Err(e) => error!("Failed to serialize output: {}", e),
+    }
+}
+
+/// Prints a generated puzzle, under `title` when pretty
+fn print_grid(grid: &Grid, format: OutputFormat, title: &str) {
+    match format {
+        OutputFormat::Pretty => {
+            info!("{}", title);
+            print_board(&Board::new(&grid.value), None);
         }
+        OutputFormat::Line => emit!("{}", Board::new(&grid.value).to_line()),
+        OutputFormat::Json => print_json(grid),
+    }
+}
+
+/// Prints a solve result in the machine-readable formats
+fn print_report(report: &SolveReport, format: OutputFormat) {
+    match format {
+        OutputFormat::Json => print_json(report),
+        _ => emit!("{}", report.solution.as_deref().unwrap_or(&report.puzzle)),
     }
 }
 
-/// Prints a Sudoku board in a pretty format with grid lines.
-/// 
-/// # Arguments
-/// 
-/// * `board` - A 9x9 grid represented as a slice of vectors containing integers.
-///            Empty cells are represented by 0.
-fn print_board(board: &[Vec<i32>]) {
-    println!("┌───────┬───────┬───────┐");
-    for (i, row) in board.iter().enumerate() {
-        print!("│ ");
-        for (j, &cell) in row.iter().enumerate() {
-            if cell == 0 {
-                print!("· ");
+/// Solves puzzle lines from stdin to stdout and nothing else, for use in a
+/// shell pipeline
+fn pipe(args: &[String]) -> Status {
+    let Some(options) = batch_options(args) else {
+        return Status::InvalidInput;
+    };
+    let input = std::io::BufReader::new(std::io::stdin());
+    match batch::solve_lines(input, std::io::stdout().lock(), &options) {
+        Ok(summary) => batch_status(&summary),
+        Err(e) => {
+            error!("Batch solve failed: {}", e.report());
+            Status::Failed
+        }
+    }
+}
+
+/// Puzzle input and result output of a batch
+type BatchFiles = (Box<dyn std::io::BufRead + Send>, Box<dyn std::io::Write>);
+
+/// Opens the puzzle file (`-` for stdin) and the `--output` file (default
+/// stdout) of a batch, or `None` after logging why not
+fn open_batch_files(path: &str, args: &[String]) -> Option<BatchFiles> {
+    let input: Box<dyn std::io::BufRead + Send> = if path == "-" {
+        Box::new(std::io::BufReader::new(std::io::stdin()))
+    } else {
+        match std::fs::File::open(path) {
+            Ok(file) => Box::new(std::io::BufReader::new(file)),
+            Err(e) => {
+                error!("Failed to open {}: {}", path, e);
+                return None;
+            }
+        }
+    };
+    let output: Box<dyn std::io::Write> = match flag_value(args, "--output") {
+        Some(out) => match std::fs::File::create(out) {
+            Ok(file) => Box::new(std::io::BufWriter::new(file)),
+            Err(e) => {
+                error!("Failed to create {}: {}", out, e);
+                return None;
+            }
+        },
+        None => Box::new(std::io::stdout().lock()),
+    };
+    Some((input, output))
+}
+
+/// Reads up to `limit` puzzles from a collection file in the format named by
+/// `--from` (guessed if absent), or `None` after logging why it couldn't
+fn read_collection(path: &str, args: &[String], limit: usize) -> Option<Vec<Grid>> {
+    let from = match flag_value(args, "--from").map(str::parse::<formats::Format>).transpose() {
+        Ok(from) => from,
+        Err(e) => {
+            error!("{}", e.report());
+            return None;
+        }
+    };
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            error!("Failed to open {}: {}", path, e);
+            return None;
+        }
+    };
+    match formats::read(std::io::BufReader::new(file), from).take(limit).collect() {
+        Ok(grids) => Some(grids),
+        Err(e) => {
+            error!("Failed to read {}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Batch options from `--jobs`, `--hardness` and `--gpu`, or `None` after logging a bad value
+fn batch_options(args: &[String]) -> Option<batch::BatchOptions> {
+    let mut options = batch::BatchOptions::default();
+    if let Some(jobs) = flag_value(args, "--jobs") {
+        match jobs.parse() {
+            Ok(jobs) if jobs > 0 => options.jobs = jobs,
+            _ => {
+                error!("Invalid job count: {}", jobs);
+                return None;
+            }
+        }
+    }
+    options.hardness = args.iter().any(|a| a == "--hardness");
+    options.gpu = args.iter().any(|a| a == "--gpu");
+    if options.gpu && !cfg!(feature = "gpu") {
+        error!("--gpu needs a build with the gpu feature");
+        return None;
+    }
+    if options.gpu {
+        // A dispatch only pays for itself over many boards
+        options.chunk_size = options.chunk_size.max(1 << 16);
+    }
+    Some(options)
+}
+
+/// Exit status for a batch: the worst outcome of any of its puzzles
+fn batch_status(summary: &batch::BatchSummary) -> Status {
+    if summary.invalid > 0 {
+        Status::InvalidInput
+    } else if summary.unsolvable > 0 {
+        Status::Unsolvable
+    } else {
+        Status::Solved
+    }
+}
+
+/// Returns the argument following `flag`, e.g. the path in `--json out.json`
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+/// The `--source` flag's preference, `Any` without one; None after
+/// reporting a value it doesn't know
+fn source_preference(args: &[String]) -> Option<SourcePreference> {
+    match flag_value(args, "--source").map(str::parse).transpose() {
+        Ok(source) => Some(source.unwrap_or_default()),
+        Err(e) => {
+            error!("Invalid --source: {}", e.report());
+            None
+        }
+    }
+}
+
+/// Solves a puzzle and prints the result, explaining each logical step if asked.
+/// Backtracking gives up after `timeout`, if any.
+fn solve_puzzle(puzzle: &Board, explain: bool, portfolio: bool, format: OutputFormat, timeout: Option<Duration>) -> Status {
+    let pretty = format == OutputFormat::Pretty;
+    if pretty {
+        info!("Puzzle:");
+        print_board(puzzle, None);
+    }
+
+    let mut steps = Vec::new();
+    if explain {
+        let mut logic = LogicSolver::new(puzzle);
+        steps = explain::explain_all(&logic.solve());
+        if pretty {
+            for line in &steps {
+                println!("{}", line);
+            }
+        }
+        if logic.is_solved() {
+            if pretty {
+                info!("Solution:");
+                print_board(logic.board(), Some(puzzle));
             } else {
-                print!("{} ", cell);
+                let report = SolveReport {
+                    puzzle: puzzle.to_line(),
+                    solution: Some(logic.board().to_line()),
+                    status: Status::Solved,
+                    duration_ms: None,
+                    nodes: None,
+                    error: None,
+                    steps,
+                };
+                print_report(&report, format);
+            }
+            return Status::Solved;
+        }
+        if pretty {
+            info!("Logic is stuck here; finishing with backtracking");
+        }
+    }
+
+    let grid = Grid::from(puzzle);
+    let mut solver = Solver::new(grid).with_uniqueness_check();
+    if let Some(timeout) = timeout {
+        solver = solver.with_timeout(timeout);
+    }
+    if portfolio {
+        solver = solver.with_portfolio();
+    }
+    let result = solver.solve();
+    let report = SolveReport { steps, ..SolveReport::new(puzzle, &result) };
+    match (&result, pretty) {
+        (Ok(solution), true) => {
+            info!("Solution:");
+            print_board(&solution.board, Some(puzzle));
+        }
+        (Err(e), true) => {
+            error!("Failed to solve board: {}", e.report());
+            // The puzzle above shows where; say which units break
+            if !print_conflicts(puzzle) {
+                if let Some(trace) = solver.explain_unsolvable() {
+                    println!("{}", trace);
+                }
+            }
+        }
+        (_, false) => print_report(&report, format),
+    }
+    report.status
+}
+
+/// What `solve_small` and `print_generated` need of a mini or 16×16 board
+trait SmallBoard: std::fmt::Display + Sized {
+    fn solve(&self) -> sudoku::Result<Self>;
+    fn count_solutions_up_to(&self, limit: usize) -> usize;
+    fn is_consistent(&self) -> bool;
+    fn to_line(&self) -> String;
+    fn to_vec(&self) -> Vec<Vec<i32>>;
+}
+
+macro_rules! small_board {
+    ($board:ty) => {
+        impl SmallBoard for $board {
+            fn solve(&self) -> sudoku::Result<Self> {
+                <$board>::solve(self)
+            }
+            fn count_solutions_up_to(&self, limit: usize) -> usize {
+                <$board>::count_solutions_up_to(self, limit)
+            }
+            fn is_consistent(&self) -> bool {
+                <$board>::is_consistent(self)
+            }
+            fn to_line(&self) -> String {
+                <$board>::to_line(self)
+            }
+            fn to_vec(&self) -> Vec<Vec<i32>> {
+                <$board>::to_vec(self)
+            }
+        }
+    };
+}
+
+small_board!(MiniBoard);
+small_board!(HexBoard);
+
+/// Prints a generated mini or 16×16 puzzle
+fn print_generated<B: SmallBoard>(
+    puzzle: &B,
+    solution: &B,
+    difficulty: &str,
+    side: usize,
+    format: OutputFormat,
+) -> Status {
+    match format {
+        OutputFormat::Pretty => {
+            println!("{}", puzzle.to_line());
+            info!("Generated {0}×{0} puzzle (Difficulty: {1}):", side, difficulty);
+            print!("{}", puzzle);
+        }
+        OutputFormat::Line => emit!("{}", puzzle.to_line()),
+        OutputFormat::Json => print_json(&serde_json::json!({
+            "value": puzzle.to_vec(),
+            "solution": solution.to_vec(),
+            "difficulty": difficulty,
+        })),
+    }
+    Status::Solved
+}
+
+/// Solves a 4×4, 6×6 or 16×16 puzzle, which the full solver doesn't take
+fn solve_small<B: SmallBoard>(puzzle: &B, format: OutputFormat) -> Status {
+    let pretty = format == OutputFormat::Pretty;
+    if pretty {
+        info!("Puzzle:");
+        print!("{}", puzzle);
+    }
+    let start = Instant::now();
+    let result = puzzle.solve();
+    let mut report = SolveReport {
+        puzzle: puzzle.to_line(),
+        solution: None,
+        status: Status::Solved,
+        duration_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
+        nodes: None,
+        error: None,
+        steps: Vec::new(),
+    };
+    match result {
+        Ok(solution) => {
+            report.status = Status::of_solution(puzzle.count_solutions_up_to(2) == 1);
+            report.solution = Some(solution.to_line());
+            if pretty {
+                info!("Solution:");
+                print!("{}", solution);
+            }
+        }
+        Err(e) => {
+            report.status = if puzzle.is_consistent() { Status::of_error(&e) } else { Status::InvalidInput };
+            if pretty {
+                error!("Failed to solve board: {}", e.report());
             }
-            if (j + 1) % 3 == 0 && j < 8 {
-                print!("│ ");
+            report.error = Some(e.to_string());
+        }
+    }
+    if !pretty {
+        print_report(&report, format);
+    }
+    report.status
+}
+
+/// Animates a replay in the terminal, redrawing the board after each event.
+/// The machine-readable formats print each event instead, without delay.
+fn play_replay(replay: &Replay, delay: Duration, format: OutputFormat) -> sudoku::Result<()> {
+    let total = replay.events.len();
+    let puzzle = Board::parse(&replay.puzzle)?;
+    for (i, (board, event)) in replay.playback()?.enumerate() {
+        match format {
+            OutputFormat::Pretty => {
+                // Clear the screen and move the cursor home before redrawing
+                print!("\x1b[2J\x1b[H");
+                let placed = match event {
+                    ReplayEvent::Step { placements, .. } => placements.first(),
+                    ReplayEvent::Guess { placement } => Some(placement),
+                    ReplayEvent::Backtrack => None,
+                };
+                print_board_at(&board, Some(&puzzle), placed.map(|p| (p[0] as usize, p[1] as usize)));
+                println!("[{}/{}] {}", i + 1, total, event);
+                std::thread::sleep(delay);
             }
+            OutputFormat::Line => emit!("{} {}", board.to_line(), event),
+            OutputFormat::Json => print_json(&serde_json::json!({ "board": board.to_line(), "event": event })),
         }
-        println!("│");
-        if (i + 1) % 3 == 0 && i < 8 {
-            println!("├───────┼───────┼───────┤");
+    }
+    if format == OutputFormat::Pretty {
+        match &replay.solution {
+            Some(_) => println!("Solved with {} guesses and {} backtracks", replay.guesses(), replay.backtracks()),
+            None => println!("No solution exists"),
         }
     }
-    println!("└───────┴───────┴───────┘");
+    Ok(())
+}
+
+/// Lists every unit in which `board` repeats a digit, returning false if there are none
+fn print_conflicts(board: &Board) -> bool {
+    let conflicts = validation::conflicts(board);
+    for conflict in &conflicts {
+        println!("  {}", conflict);
+    }
+    !conflicts.is_empty()
+}
+
+/// Takes the grid drawing flags out of `args`. Color defaults to on for a
+/// terminal without `NO_COLOR` set.
+fn take_term_options(args: &mut Vec<String>) -> TermOptions {
+    let mut take = |flag: &str| match args.iter().position(|a| a == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let size = if take("--large") { CellSize::Large } else { CellSize::Normal };
+    let borders = if take("--ascii") { Borders::Ascii } else { Borders::Unicode };
+    let color = match (take("--color"), take("--no-color")) {
+        (_, true) => false,
+        (true, false) => true,
+        (false, false) => std::io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+    };
+    TermOptions::default().with_size(size).with_borders(borders).with_color(color)
 }
 
+/// Prints a board as a grid, or as sentences with `--describe`. Digits that
+/// aren't givens of `puzzle`, if known, are colored as filled in.
+fn print_board(board: &Board, puzzle: Option<&Board>) {
+    print_board_at(board, puzzle, None);
+}
 
+/// Prints a board as `print_board` does, highlighting the cell at `highlight`
+fn print_board_at(board: &Board, puzzle: Option<&Board>, highlight: Option<(usize, usize)>) {
+    if DESCRIBE.load(Ordering::Relaxed) {
+        print!("{}", board.describe());
+        return;
+    }
+    let options = TermOptions { highlight, ..TERM_OPTIONS.get().copied().unwrap_or_default() };
+    print!("{}", term::render(board, puzzle, &options));
+}
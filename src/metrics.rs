@@ -0,0 +1,87 @@
+//! Prometheus metrics, exposed by serve mode at `GET /metrics`.
+//!
+//! Metrics live in the process-wide default registry, so anything in the
+//! crate can record them; they cost a few atomic operations when nobody scrapes.
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, Encoder, Histogram,
+    IntCounter, IntCounterVec, TextEncoder,
+};
+
+/// Puzzles returned by the server, labelled by endpoint
+pub static PUZZLES_SERVED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!("sudoku_puzzles_served_total", "Puzzles served over HTTP", &["endpoint"])
+        .expect("metric registers once")
+});
+
+/// Wall-clock time of `Solver::solve`
+pub static SOLVE_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "sudoku_solve_duration_seconds",
+        "Time taken to solve a puzzle",
+        vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]
+    )
+    .expect("metric registers once")
+});
+
+/// Boards served straight from the board cache
+pub static CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("sudoku_cache_hits_total", "Boards served from the cache").expect("metric registers once")
+});
+
+/// Board requests the cache couldn't satisfy
+pub static CACHE_MISSES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("sudoku_cache_misses_total", "Board requests that missed the cache")
+        .expect("metric registers once")
+});
+
+/// Upstream API fetches that failed after all retries
+pub static API_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("sudoku_api_failures_total", "Failed puzzle API fetches").expect("metric registers once")
+});
+
+/// Boards generated locally because the API or rate limiter was unavailable
+pub static GENERATOR_FALLBACKS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!("sudoku_generator_fallbacks_total", "Boards generated locally as a fallback")
+        .expect("metric registers once")
+});
+
+/// Renders every registered metric in the Prometheus text format
+pub fn render() -> String {
+    // Touch each metric so it is listed even before its first update
+    Lazy::force(&PUZZLES_SERVED);
+    Lazy::force(&SOLVE_DURATION);
+    Lazy::force(&CACHE_HITS);
+    Lazy::force(&CACHE_MISSES);
+    Lazy::force(&API_FAILURES);
+    Lazy::force(&GENERATOR_FALLBACKS);
+
+    let mut buffer = Vec::new();
+    // Encoding into a Vec only fails on malformed metric names
+    let _ = TextEncoder::new().encode(&prometheus::gather(), &mut buffer);
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_lists_metrics() {
+        CACHE_HITS.inc();
+        PUZZLES_SERVED.with_label_values(&["puzzle"]).inc();
+        let text = render();
+        for name in [
+            "sudoku_puzzles_served_total",
+            "sudoku_solve_duration_seconds",
+            "sudoku_cache_hits_total",
+            "sudoku_cache_misses_total",
+            "sudoku_api_failures_total",
+            "sudoku_generator_fallbacks_total",
+        ] {
+            assert!(text.contains(name), "missing {}", name);
+        }
+        assert!(text.contains("sudoku_puzzles_served_total{endpoint=\"puzzle\"}"));
+    }
+}
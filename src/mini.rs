@@ -0,0 +1,383 @@
+//! 4×4 and 6×6 puzzles for younger players.
+//!
+//! A `MiniBoard` is a square grid of side 4, with 2×2 boxes, or of side 6,
+//! with boxes two rows tall and three columns wide, filled with the digits 1
+//! up to the side. Candidates fit in the same `u16` digit masks as on the
+//! full board and a cell's peers in one `u64`, so the solver is a small
+//! backtracker that tries the cell with the fewest candidates first, which
+//! finishes these sizes at once.
+
+use crate::{Result, SudokuError};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::fmt;
+
+/// Cells of the largest mini board
+pub const MAX_CELLS: usize = 36;
+
+/// The side of a mini board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MiniSize {
+    /// 4×4 with 2×2 boxes
+    Four,
+    /// 6×6 with 2×3 boxes
+    Six,
+}
+
+impl MiniSize {
+    pub const fn side(self) -> usize {
+        match self {
+            MiniSize::Four => 4,
+            MiniSize::Six => 6,
+        }
+    }
+
+    /// Rows and columns of one box
+    pub const fn box_shape(self) -> (usize, usize) {
+        match self {
+            MiniSize::Four => (2, 2),
+            MiniSize::Six => (2, 3),
+        }
+    }
+
+    pub const fn cells(self) -> usize {
+        self.side() * self.side()
+    }
+
+    /// The size whose grid has `cells` cells
+    pub fn from_cells(cells: usize) -> Option<Self> {
+        [MiniSize::Four, MiniSize::Six].into_iter().find(|size| size.cells() == cells)
+    }
+
+    /// The size of the puzzle written in `input`, judged by how many cells it
+    /// has once whitespace is dropped; None for anything else, such as a 9×9 line
+    pub fn detect(input: &str) -> Option<Self> {
+        Self::from_cells(input.chars().filter(|c| !c.is_whitespace()).count())
+    }
+
+    /// Every digit as a mask, bit `d` standing for digit `d`
+    fn digits(self) -> u16 {
+        ((1 << self.side()) - 1) << 1
+    }
+
+    /// The cells sharing a row, column or box with each cell
+    fn peers(self) -> Vec<u64> {
+        let (side, (box_rows, box_cols)) = (self.side(), self.box_shape());
+        (0..self.cells())
+            .map(|cell| {
+                let (row, col) = (cell / side, cell % side);
+                (0..self.cells())
+                    .filter(|&other| other != cell)
+                    .filter(|&other| {
+                        let (r, c) = (other / side, other % side);
+                        r == row || c == col || (r / box_rows == row / box_rows && c / box_cols == col / box_cols)
+                    })
+                    .fold(0, |mask, other| mask | 1 << other)
+            })
+            .collect()
+    }
+}
+
+/// A 4×4 or 6×6 grid, 0 for an empty cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MiniBoard {
+    size: MiniSize,
+    /// Row by row; only the first `size.cells()` are used
+    cells: [u8; MAX_CELLS],
+}
+
+impl MiniBoard {
+    pub fn empty(size: MiniSize) -> Self {
+        Self { size, cells: [0; MAX_CELLS] }
+    }
+
+    /// Parses 16 or 36 cells, row by row, ignoring whitespace. Digits run
+    /// from 1 to the side; `.`, `0`, `-` or `_` marks an empty cell.
+    pub fn parse(input: &str) -> Result<Self> {
+        let cells: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+        let size = MiniSize::from_cells(cells.len()).ok_or_else(|| {
+            SudokuError::ParseError(format!("expected 16 or 36 cells, found {}", cells.len()))
+        })?;
+        let mut board = Self::empty(size);
+        for (cell, &c) in cells.iter().enumerate() {
+            board.cells[cell] = match c {
+                '0' | '.' | '-' | '_' => 0,
+                '1'..='9' if (c as usize - '0' as usize) <= size.side() => c as u8 - b'0',
+                _ => return Err(SudokuError::ParseError(format!("unexpected character '{}'", c))),
+            };
+        }
+        Ok(board)
+    }
+
+    /// Builds a board from 4 or 6 rows of as many values, each 0 up to the side
+    pub fn try_new(rows: &[Vec<i32>]) -> Result<Self> {
+        let size = match rows.len() {
+            4 => MiniSize::Four,
+            6 => MiniSize::Six,
+            n => return Err(SudokuError::ParseError(format!("expected 4 or 6 rows, found {}", n))),
+        };
+        let mut board = Self::empty(size);
+        for (row, values) in rows.iter().enumerate() {
+            if values.len() != size.side() {
+                return Err(SudokuError::ParseError(format!(
+                    "expected {} values in row {}, found {}",
+                    size.side(),
+                    row,
+                    values.len()
+                )));
+            }
+            for (col, &value) in values.iter().enumerate() {
+                if !(0..=size.side() as i32).contains(&value) {
+                    return Err(SudokuError::InvalidValue { row, col, value });
+                }
+                board.set(row, col, value as u8);
+            }
+        }
+        Ok(board)
+    }
+
+    pub fn size(&self) -> MiniSize {
+        self.size
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.cells[row * self.size.side() + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: u8) {
+        self.cells[row * self.size.side() + col] = value;
+    }
+
+    /// The cells in use, row by row
+    pub fn cells(&self) -> &[u8] {
+        &self.cells[..self.size.cells()]
+    }
+
+    /// The board as one line, `.` for empty cells
+    pub fn to_line(&self) -> String {
+        self.cells().iter().map(|&v| if v == 0 { '.' } else { char::from(b'0' + v) }).collect()
+    }
+
+    pub fn to_vec(&self) -> Vec<Vec<i32>> {
+        self.cells().chunks(self.size.side()).map(|row| row.iter().map(|&v| v as i32).collect()).collect()
+    }
+
+    /// Returns true if no two givens in a row, column or box share a digit
+    pub fn is_consistent(&self) -> bool {
+        let peers = self.size.peers();
+        self.cells()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &value)| value != 0)
+            .all(|(cell, &value)| bits(peers[cell]).all(|peer| self.cells[peer] != value))
+    }
+
+    /// Counts solutions, stopping once `limit` are found
+    pub fn count_solutions_up_to(&self, limit: usize) -> usize {
+        if !self.is_consistent() {
+            return 0;
+        }
+        let mut cells = self.cells;
+        let mut count = 0;
+        search(&mut cells, self.size, &self.size.peers(), &mut |_| {
+            count += 1;
+            count < limit
+        });
+        count
+    }
+
+    /// The first solution found. Errors with `InvalidBoard` when the givens
+    /// clash or there is no solution.
+    pub fn solve(&self) -> Result<MiniBoard> {
+        if !self.is_consistent() {
+            return Err(SudokuError::InvalidBoard);
+        }
+        let mut cells = self.cells;
+        let mut solution = None;
+        search(&mut cells, self.size, &self.size.peers(), &mut |found| {
+            solution = Some(*found);
+            false
+        });
+        solution.map(|cells| Self { size: self.size, cells }).ok_or(SudokuError::InvalidBoard)
+    }
+
+    /// The board under a random relabelling of the digits and reordering of
+    /// rows within bands, bands, columns within stacks and stacks, which
+    /// keeps a valid grid valid and a unique puzzle unique
+    pub fn shuffled<R: Rng + ?Sized>(&self, rng: &mut R) -> MiniBoard {
+        let (side, (box_rows, box_cols)) = (self.size.side(), self.size.box_shape());
+        let mut digits: Vec<u8> = (1..=side as u8).collect();
+        digits.shuffle(rng);
+        let rows = permutation(side, box_rows, rng);
+        let cols = permutation(side, box_cols, rng);
+        let mut shuffled = Self::empty(self.size);
+        for row in 0..side {
+            for col in 0..side {
+                match self.get(rows[row], cols[col]) {
+                    0 => {}
+                    value => shuffled.set(row, col, digits[value as usize - 1]),
+                }
+            }
+        }
+        shuffled
+    }
+}
+
+/// A random order of `0..side` that keeps each group of `group` consecutive
+/// lines together, moving the groups and the lines within each
+fn permutation<R: Rng + ?Sized>(side: usize, group: usize, rng: &mut R) -> Vec<usize> {
+    let mut groups: Vec<usize> = (0..side / group).collect();
+    groups.shuffle(rng);
+    groups
+        .into_iter()
+        .flat_map(|g| {
+            let mut lines: Vec<usize> = (g * group..(g + 1) * group).collect();
+            lines.shuffle(rng);
+            lines
+        })
+        .collect()
+}
+
+/// Returns true if every cell is filled and no unit repeats a digit
+pub fn validate_solution(board: &MiniBoard) -> bool {
+    board.cells().iter().all(|&value| value != 0) && board.is_consistent()
+}
+
+/// The bits set in `mask`, lowest first
+fn bits(mut mask: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(bit)
+    })
+}
+
+/// Fills `cells` depth first, calling `found` with each solution; stops as
+/// soon as `found` returns false. Returns false once stopped.
+fn search(
+    cells: &mut [u8; MAX_CELLS],
+    size: MiniSize,
+    peers: &[u64],
+    found: &mut dyn FnMut(&[u8; MAX_CELLS]) -> bool,
+) -> bool {
+    // The empty cell with the fewest candidates
+    let mut best: Option<(usize, u16)> = None;
+    for cell in (0..size.cells()).filter(|&cell| cells[cell] == 0) {
+        let used = bits(peers[cell]).fold(0u16, |used, peer| used | 1 << cells[peer]);
+        let candidates = size.digits() & !used;
+        if best.is_none_or(|(_, fewest)| candidates.count_ones() < fewest.count_ones()) {
+            best = Some((cell, candidates));
+        }
+        if candidates.count_ones() <= 1 {
+            break;
+        }
+    }
+    let Some((cell, candidates)) = best else {
+        return found(cells);
+    };
+    for digit in (1..=size.side() as u8).filter(|&digit| candidates & 1 << digit != 0) {
+        cells[cell] = digit;
+        if !search(cells, size, peers, found) {
+            cells[cell] = 0;
+            return false;
+        }
+    }
+    cells[cell] = 0;
+    true
+}
+
+impl fmt::Display for MiniBoard {
+    /// The grid with box borders, `.` for empty cells
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (side, (box_rows, box_cols)) = (self.size.side(), self.size.box_shape());
+        let border = format!("+{}\n", format!("{}+", "-".repeat(box_cols * 2 + 1)).repeat(side / box_cols));
+        for row in 0..side {
+            if row % box_rows == 0 {
+                f.write_str(&border)?;
+            }
+            for col in 0..side {
+                if col % box_cols == 0 {
+                    f.write_str("| ")?;
+                }
+                match self.get(row, col) {
+                    0 => f.write_str(". ")?,
+                    value => write!(f, "{} ", value)?,
+                }
+            }
+            f.write_str("|\n")?;
+        }
+        f.write_str(&border)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOUR: &str = ".... 4... ...3 1..2";
+    const SIX: &str = "..6..4 ...... .4..35 ..2.6. ..3..6 ...5..";
+
+    #[test]
+    fn test_parse() {
+        let four = MiniBoard::parse(FOUR).unwrap();
+        assert_eq!(four.size(), MiniSize::Four);
+        assert_eq!((four.get(1, 0), four.get(3, 3)), (4, 2));
+        assert_eq!(four.to_line(), "....4......31..2");
+        assert_eq!(MiniBoard::try_new(&four.to_vec()).unwrap(), four);
+        assert_eq!(MiniSize::detect(SIX), Some(MiniSize::Six));
+        assert_eq!(MiniSize::detect(&".".repeat(81)), None);
+
+        assert!(MiniBoard::parse("1..5 .4.. ..3. 3..2").is_err(), "5 is out of range on a 4×4 board");
+        assert!(MiniBoard::parse("1..4 .4..").unwrap_err().to_string().contains("found 8"));
+        assert!(MiniBoard::try_new(&[vec![0; 4], vec![0; 4], vec![0; 3], vec![0; 4]]).is_err());
+        assert!(matches!(
+            MiniBoard::try_new(&vec![vec![0, 7, 0, 0, 0, 0]; 6]),
+            Err(SudokuError::InvalidValue { row: 0, col: 1, value: 7 })
+        ));
+    }
+
+    #[test]
+    fn test_solve() {
+        for puzzle in [FOUR, SIX] {
+            let board = MiniBoard::parse(puzzle).unwrap();
+            let solution = board.solve().unwrap();
+            assert!(validate_solution(&solution), "{}", solution);
+            assert!(board.cells().iter().zip(solution.cells()).all(|(&given, &value)| given == 0 || given == value));
+            assert_eq!(board.count_solutions_up_to(2), 1, "{}", puzzle);
+        }
+
+        // 2×3 boxes: the first two rows of a 6×6 solution hold every digit in each box half
+        let six = MiniBoard::parse(SIX).unwrap().solve().unwrap();
+        for box_col in [0, 3] {
+            let mut digits: Vec<u8> =
+                (0..2).flat_map(|r| (box_col..box_col + 3).map(move |c| six.get(r, c))).collect();
+            digits.sort_unstable();
+            assert_eq!(digits, [1, 2, 3, 4, 5, 6]);
+        }
+
+        let empty = MiniBoard::empty(MiniSize::Four);
+        assert!(validate_solution(&empty.solve().unwrap()));
+        assert_eq!(empty.count_solutions_up_to(1000), 288);
+        let clashing = MiniBoard::parse("11.. .... .... ....").unwrap();
+        assert!(!clashing.is_consistent());
+        assert!(matches!(clashing.solve(), Err(SudokuError::InvalidBoard)));
+        // Consistent givens that leave the last cell of row 1 nothing
+        let stuck = MiniBoard::parse("123. ...4 .... ....").unwrap();
+        assert_eq!(stuck.count_solutions_up_to(2), 0);
+        assert!(!validate_solution(&MiniBoard::parse(FOUR).unwrap()));
+    }
+
+    #[test]
+    fn test_display() {
+        let four = MiniBoard::parse(FOUR).unwrap().to_string();
+        assert_eq!(four.lines().next(), Some("+-----+-----+"));
+        assert_eq!(four.lines().nth(1), Some("| . . | . . |"));
+        assert_eq!(four.lines().count(), 7);
+        let six = MiniBoard::parse(SIX).unwrap().to_string();
+        assert_eq!(six.lines().nth(1), Some("| . . 6 | . . 4 |"));
+        assert_eq!(six.lines().count(), 10);
+    }
+}
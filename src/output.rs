@@ -0,0 +1,170 @@
+//! Output formats and exit statuses of the command-line tool.
+//!
+//! Every subcommand prints its result in one of three formats: `pretty` for
+//! people, `line` for one puzzle (or record) per line, and `json` for
+//! programs. The process exit status tells scripts how a puzzle turned out
+//! without parsing anything.
+
+use crate::{solver::Solution, Board, SudokuError};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// How results are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Boxed grids and tables
+    #[default]
+    Pretty,
+    /// 81-character puzzle lines, `.` for empty cells
+    Line,
+    /// One JSON document per result
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = SudokuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(Self::Pretty),
+            "line" => Ok(Self::Line),
+            "json" => Ok(Self::Json),
+            _ => Err(SudokuError::ParseError(format!("unknown output format '{}' (json, line, pretty)", s))),
+        }
+    }
+}
+
+/// Exit status of the command-line tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Success; for a puzzle, solved with a unique solution
+    Solved = 0,
+    /// Anything else went wrong, e.g. the API was unreachable
+    Failed = 1,
+    MultipleSolutions = 2,
+    Unsolvable = 3,
+    /// Malformed puzzle, conflicting givens, or bad arguments
+    InvalidInput = 4,
+}
+
+impl Status {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Status for a puzzle that solved, unique or not
+    pub fn of_solution(unique: bool) -> Self {
+        if unique {
+            Status::Solved
+        } else {
+            Status::MultipleSolutions
+        }
+    }
+
+    /// Status for an error while loading or solving a puzzle
+    pub fn of_error(error: &SudokuError) -> Self {
+        match error {
+            _ if error.is_invalid_input() => Status::InvalidInput,
+            SudokuError::InvalidBoard => Status::Unsolvable,
+            _ => Status::Failed,
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Status::Solved => "solved",
+            Status::Failed => "failed",
+            Status::MultipleSolutions => "multiple_solutions",
+            Status::Unsolvable => "unsolvable",
+            Status::InvalidInput => "invalid_input",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Outcome of solving one puzzle, as printed by `solve`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SolveReport {
+    pub puzzle: String,
+    /// A solution, the only one when `status` is `solved`
+    pub solution: Option<String>,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Explanation of each logical step, when asked for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<String>,
+}
+
+impl SolveReport {
+    /// Report for the result of `Solver::solve` on `puzzle`. Conflicting
+    /// givens count as invalid input rather than unsolvable.
+    pub fn new(puzzle: &Board, result: &crate::Result<Solution>) -> Self {
+        let mut report = Self {
+            puzzle: puzzle.to_line(),
+            solution: None,
+            status: Status::Solved,
+            duration_ms: None,
+            nodes: None,
+            error: None,
+            steps: Vec::new(),
+        };
+        match result {
+            Ok(solution) => {
+                report.solution = Some(solution.board.to_line());
+                report.status = Status::of_solution(solution.unique != Some(false));
+                report.duration_ms = Some(solution.stats.duration.as_secs_f64() * 1000.0);
+                report.nodes = Some(solution.stats.nodes);
+            }
+            Err(e) => {
+                report.status = if puzzle.is_consistent() { Status::of_error(e) } else { Status::InvalidInput };
+                report.error = Some(e.to_string());
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{providers::fixture, solver::Solver, Grid};
+
+    fn solve(name: &str) -> SolveReport {
+        let puzzle = fixture::get(name).unwrap().board();
+        let grid = Grid::from(&puzzle);
+        SolveReport::new(&puzzle, &Solver::new(grid).with_uniqueness_check().solve())
+    }
+
+    #[test]
+    fn test_statuses() {
+        assert_eq!(solve("easy").status, Status::Solved);
+        assert_eq!(solve("deadly_rectangle").status, Status::MultipleSolutions);
+        assert_eq!(solve("no_candidates").status, Status::Unsolvable);
+        assert_eq!(solve("duplicate_in_row").status, Status::InvalidInput);
+        assert_eq!(Status::of_error(&Board::parse("12").unwrap_err()), Status::InvalidInput);
+        assert_eq!(Status::of_error(&serde_json::from_str::<u8>("x").unwrap_err().into()), Status::InvalidInput);
+        assert_eq!(Status::of_error(&SudokuError::SolveTimeout), Status::Failed);
+        assert_eq!(
+            [Status::Solved, Status::MultipleSolutions, Status::Unsolvable, Status::InvalidInput].map(Status::code),
+            [0, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let json = serde_json::to_value(solve("easy")).unwrap();
+        assert_eq!(json["status"], "solved");
+        assert_eq!(json["solution"].as_str().unwrap().len(), 81);
+        assert!("yaml".parse::<OutputFormat>().is_err());
+        assert_eq!("JSON".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+    }
+}
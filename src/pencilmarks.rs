@@ -0,0 +1,246 @@
+//! Pencil-mark (candidate) grids and the common pencilmark text format.
+//!
+//! The text format lists the candidates of all 81 cells in row-major order,
+//! separated by whitespace. Border characters (`|`, `-`, `+`, `.`, `:`, `'`)
+//! are ignored, so grids copied from most desktop solvers parse as-is. A cell
+//! with no candidates left is written as a lone `-`:
+//!
+//! ```text
+//! .----------------.----------------.----------------.
+//! | 5    3    124  | 26   7    2468 | 1489 149  248  |
+//! ```
+
+use crate::{Board, CandidateSet, Result, SudokuError};
+use std::fmt;
+use std::str::FromStr;
+
+/// Characters used for grid lines in pencilmark dumps
+const BORDER_CHARS: &[char] = &['|', '-', '+', '.', ':', '\''];
+
+/// Written in place of a cell with no candidates. Border runs are always
+/// wider than one character, so a lone `-` can't be mistaken for one.
+const NO_CANDIDATES: &str = "-";
+
+/// Candidate sets for every cell of a 9x9 board
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PencilMarks {
+    cells: [CandidateSet; 81],
+}
+
+impl PencilMarks {
+    /// Creates pencil marks with every candidate allowed in every cell
+    pub fn all() -> Self {
+        Self { cells: [CandidateSet::all(); 81] }
+    }
+
+    /// Creates pencil marks with no candidates noted in any cell
+    pub fn empty() -> Self {
+        Self { cells: [CandidateSet::empty(); 81] }
+    }
+
+    /// Computes the full candidate grid for a board.
+    ///
+    /// Filled cells get their value as the only candidate; empty cells get
+    /// every digit not already present in their row, column, or box.
+    pub fn from_board(board: &Board) -> Self {
+        let mut marks = Self::all();
+        for row in 0..9 {
+            for col in 0..9 {
+                let value = board.get(row, col);
+                let mut set = CandidateSet::empty();
+                if value != 0 {
+                    set.add_candidate(value);
+                } else {
+                    for num in 1..=9 {
+                        if board.can_place(row, col, num) {
+                            set.add_candidate(num);
+                        }
+                    }
+                }
+                marks.set(row, col, set);
+            }
+        }
+        marks
+    }
+
+    /// Gets the candidates at the specified position
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> CandidateSet {
+        debug_assert!(row < 9 && col < 9, "Invalid board position");
+        self.cells[row * 9 + col]
+    }
+
+    /// Sets the candidates at the specified position
+    #[inline]
+    pub fn set(&mut self, row: usize, col: usize, candidates: CandidateSet) {
+        debug_assert!(row < 9 && col < 9, "Invalid board position");
+        self.cells[row * 9 + col] = candidates;
+    }
+
+    /// Parses pencil marks from the common text format
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens: Vec<&str> = input
+            .split_whitespace()
+            .flat_map(|word| match word {
+                NO_CANDIDATES => vec![""],
+                _ => word.split(|c: char| BORDER_CHARS.contains(&c)).filter(|t| !t.is_empty()).collect(),
+            })
+            .collect();
+
+        if tokens.len() != 81 {
+            return Err(SudokuError::ParseError(format!(
+                "expected 81 candidate groups, found {}",
+                tokens.len()
+            )));
+        }
+
+        let mut marks = Self::all();
+        for (i, token) in tokens.iter().enumerate() {
+            let mut set = CandidateSet::empty();
+            for c in token.chars() {
+                match c.to_digit(10) {
+                    Some(d @ 1..=9) => set.add_candidate(d as u8),
+                    _ => {
+                        return Err(SudokuError::ParseError(format!(
+                            "invalid candidate '{}' at r{}c{}",
+                            c,
+                            i / 9 + 1,
+                            i % 9 + 1
+                        )))
+                    }
+                }
+            }
+            marks.cells[i] = set;
+        }
+        Ok(marks)
+    }
+}
+
+impl Default for PencilMarks {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl From<[[CandidateSet; 9]; 9]> for PencilMarks {
+    /// Takes candidates indexed `[row][col]`, as from `Solver::candidate_grid`
+    fn from(grid: [[CandidateSet; 9]; 9]) -> Self {
+        let mut marks = Self::empty();
+        for (row, cells) in grid.iter().enumerate() {
+            for (col, &set) in cells.iter().enumerate() {
+                marks.set(row, col, set);
+            }
+        }
+        marks
+    }
+}
+
+impl FromStr for PencilMarks {
+    type Err = SudokuError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for PencilMarks {
+    /// Writes the marks as an aligned grid with box borders
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text: Vec<String> = self
+            .cells
+            .iter()
+            .map(|set| {
+                if set.is_empty() {
+                    NO_CANDIDATES.to_string()
+                } else {
+                    set.iter_candidates().map(|n| char::from(b'0' + n)).collect()
+                }
+            })
+            .collect();
+
+        // Each column is padded to its widest cell
+        let mut widths = [1usize; 9];
+        for (i, cell) in text.iter().enumerate() {
+            widths[i % 9] = widths[i % 9].max(cell.len());
+        }
+        let stack_width = |stack: usize| widths[stack * 3..stack * 3 + 3].iter().sum::<usize>() + 4;
+
+        let border = |f: &mut fmt::Formatter<'_>, left: char, mid: char, right: char| {
+            write!(f, "{}", left)?;
+            for stack in 0..3 {
+                write!(f, "{}", "-".repeat(stack_width(stack)))?;
+                write!(f, "{}", if stack < 2 { mid } else { right })?;
+            }
+            writeln!(f)
+        };
+
+        border(f, '.', '.', '.')?;
+        for row in 0..9 {
+            write!(f, "|")?;
+            for col in 0..9 {
+                write!(f, " {:<width$}", text[row * 9 + col], width = widths[col])?;
+                if col % 3 == 2 {
+                    write!(f, " |")?;
+                }
+            }
+            writeln!(f)?;
+            if row == 2 || row == 5 {
+                border(f, ':', '+', ':')?;
+            }
+        }
+        border(f, '\'', '\'', '\'')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_board() -> Board {
+        Board::new(&[
+            vec![5,3,0,0,7,0,0,0,0],
+            vec![6,0,0,1,9,5,0,0,0],
+            vec![0,9,8,0,0,0,0,6,0],
+            vec![8,0,0,0,6,0,0,0,3],
+            vec![4,0,0,8,0,3,0,0,1],
+            vec![7,0,0,0,2,0,0,0,6],
+            vec![0,6,0,0,0,0,2,8,0],
+            vec![0,0,0,4,1,9,0,0,5],
+            vec![0,0,0,0,8,0,0,7,9],
+        ])
+    }
+
+    #[test]
+    fn test_from_board() {
+        let marks = PencilMarks::from_board(&sample_board());
+        assert_eq!(marks.get(0, 0).iter_candidates().collect::<Vec<_>>(), vec![5]);
+        assert_eq!(marks.get(0, 2).iter_candidates().collect::<Vec<_>>(), vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let marks = PencilMarks::from_board(&sample_board());
+        let text = marks.to_string();
+        assert!(text.starts_with(".---"));
+        assert_eq!(text.parse::<PencilMarks>().unwrap(), marks);
+    }
+
+    #[test]
+    fn test_round_trip_empty_cell() {
+        let mut marks = PencilMarks::from_board(&sample_board());
+        marks.set(0, 2, CandidateSet::empty());
+        marks.set(8, 8, CandidateSet::empty());
+        let text = marks.to_string();
+        assert_eq!(text.matches(" - ").count(), 2);
+        assert_eq!(text.parse::<PencilMarks>().unwrap(), marks);
+        assert_eq!(PencilMarks::parse(&["-"; 81].join(" ")).unwrap(), PencilMarks::empty());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(PencilMarks::parse("123 456").is_err());
+        let mut tokens = vec!["123"; 81];
+        tokens[10] = "1x";
+        assert!(PencilMarks::parse(&tokens.join(" ")).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Portfolio solving: several search strategies race on their own threads.
+//!
+//! Any one backtracking order can be beaten by a puzzle built against it;
+//! the classic anti-brute-force puzzles force a top-left, lowest-digit-first
+//! search through millions of dead ends. Such puzzles are rarely hard for
+//! every strategy at once, so racing a few strategies and keeping the first
+//! answer bounds the worst case by the best of them. The first strategy to
+//! finish wins, whether it finds a solution or proves there is none, and the
+//! others are cancelled.
+
+use crate::{
+    dlx,
+    solver::{first_solution_ordered, CellOrder},
+    Board, Result, SudokuError,
+};
+use crossbeam::channel::RecvTimeoutError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// A search strategy in the portfolio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Strategy {
+    /// Fewest candidates first, digits in ascending order
+    MostConstrained,
+    /// Fewest candidates first, digits in descending order
+    MostConstrainedDescending,
+    /// Cells in reading order, digits in ascending order
+    RowMajor,
+    /// Cells in reverse reading order, digits in descending order
+    ReverseRowMajor,
+    /// Dancing links over the exact-cover matrix
+    DancingLinks,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 5] = [
+        Strategy::MostConstrained,
+        Strategy::MostConstrainedDescending,
+        Strategy::RowMajor,
+        Strategy::ReverseRowMajor,
+        Strategy::DancingLinks,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::MostConstrained => "most constrained",
+            Strategy::MostConstrainedDescending => "most constrained, descending",
+            Strategy::RowMajor => "row major",
+            Strategy::ReverseRowMajor => "reverse row major",
+            Strategy::DancingLinks => "dancing links",
+        }
+    }
+
+    /// Searches for one solution until `stop` is set, returning it and the
+    /// number of placements tried
+    fn run(self, board: &Board, stop: &AtomicBool) -> (Option<Board>, u64) {
+        match self {
+            Strategy::MostConstrained => first_solution_ordered(board, CellOrder::MostConstrained, false, stop),
+            Strategy::MostConstrainedDescending => first_solution_ordered(board, CellOrder::MostConstrained, true, stop),
+            Strategy::RowMajor => first_solution_ordered(board, CellOrder::First, false, stop),
+            Strategy::ReverseRowMajor => first_solution_ordered(board, CellOrder::Last, true, stop),
+            Strategy::DancingLinks => {
+                let (mut found, nodes) = dlx::solve_until(board, 1, stop);
+                (found.pop(), nodes)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The winning result of a race
+#[derive(Debug, Clone, PartialEq)]
+pub struct Race {
+    pub solution: Board,
+    pub winner: Strategy,
+    /// Placements the winner tried
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+/// Races `strategies` on `board`, each on its own thread, and returns the
+/// first solution found.
+///
+/// Returns `InvalidBoard` if the givens conflict or the first strategy to
+/// finish proves there is no solution, and `SolveTimeout` if none finishes
+/// within `timeout`. The losing searches are cancelled and have stopped by
+/// the time this returns.
+pub fn race(board: &Board, strategies: &[Strategy], timeout: Option<Duration>) -> Result<Race> {
+    if !board.is_consistent() || strategies.is_empty() {
+        return Err(SudokuError::InvalidBoard);
+    }
+    let start = Instant::now();
+    let stop = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        let (sender, receiver) = crossbeam::channel::bounded(strategies.len());
+        for &strategy in strategies {
+            let (sender, stop) = (sender.clone(), &stop);
+            scope.spawn(move || {
+                let _ = sender.send((strategy, strategy.run(board, stop)));
+            });
+        }
+        drop(sender);
+
+        let finished = match timeout {
+            Some(timeout) => receiver.recv_deadline(start + timeout),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        // Cancel the rest before the scope waits for them
+        stop.store(true, Ordering::Relaxed);
+        match finished {
+            Ok((winner, (Some(solution), nodes))) => Ok(Race { solution, winner, nodes, elapsed: start.elapsed() }),
+            Ok((_, (None, _))) | Err(RecvTimeoutError::Disconnected) => Err(SudokuError::InvalidBoard),
+            Err(RecvTimeoutError::Timeout) => Err(SudokuError::SolveTimeout),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{providers::fixture::{self, FixtureKind}, simd::SimdValidator};
+
+    #[test]
+    fn test_every_strategy_agrees() {
+        // The naive orders are slow on the hard fixtures, so only the easy ones run them all
+        for name in ["easy", "x_wing"] {
+            let board = fixture::get(name).unwrap().board();
+            let expected = dlx::solve(&board, 1);
+            for strategy in Strategy::ALL {
+                let (solution, nodes) = strategy.run(&board, &AtomicBool::new(false));
+                assert_eq!(solution.as_ref(), expected.first(), "{} on {}", strategy, name);
+                assert!(nodes > 0);
+            }
+        }
+        for fixture in fixture::of_kind(FixtureKind::Unique) {
+            let board = fixture.board();
+            let race = race(&board, &Strategy::ALL, None).unwrap();
+            assert_eq!(Some(race.solution), dlx::solve(&board, 1).pop(), "{}", fixture.name);
+        }
+    }
+
+    #[test]
+    fn test_race() {
+        // Built so that a top-left, ascending search wades through a huge tree
+        let anti_brute_force = Board::parse(
+            "..............3.85..1.2.......5.7.....4...1...9.......5......73..2.1........4...9",
+        )
+        .unwrap();
+        let race = race(&anti_brute_force, &Strategy::ALL, Some(Duration::from_secs(30))).unwrap();
+        assert!(SimdValidator::validate_solution(&race.solution));
+        assert_ne!(race.winner, Strategy::RowMajor);
+
+        let mut conflicting = Board::empty();
+        conflicting.set(0, 0, 1);
+        conflicting.set(0, 1, 1);
+        assert!(matches!(super::race(&conflicting, &Strategy::ALL, None), Err(SudokuError::InvalidBoard)));
+
+        // A cell with no candidates left is proven unsolvable by whoever finishes first
+        let stuck = fixture::get("no_candidates").unwrap().board();
+        assert!(matches!(super::race(&stuck, &Strategy::ALL, None), Err(SudokuError::InvalidBoard)));
+
+        // The row-major search alone can't finish the anti-brute-force puzzle quickly
+        let timed_out = super::race(&anti_brute_force, &[Strategy::RowMajor], Some(Duration::from_millis(50)));
+        assert!(matches!(timed_out, Err(SudokuError::SolveTimeout)));
+
+        let grid = crate::Grid::from(&anti_brute_force);
+        let solution = crate::solver::Solver::new(grid).with_portfolio().with_uniqueness_check().solve().unwrap();
+        assert_eq!(solution.board, race.solution);
+        assert_eq!(solution.unique, Some(true));
+    }
+}
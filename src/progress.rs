@@ -0,0 +1,136 @@
+//! "Check my work" support for partially solved boards.
+
+use crate::{solver::{count_solutions_up_to, enumerate_solutions}, Board, Result, SudokuError};
+use serde::{Deserialize, Serialize};
+
+/// Status of a single cell in a player's attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellStatus {
+    /// The cell is empty in the attempt
+    Empty,
+    /// The cell is one of the puzzle's givens
+    Given,
+    /// The value matches the puzzle's unique solution
+    Correct,
+    /// The value differs from the unique solution, or overwrites a given
+    Incorrect,
+    /// The puzzle has several solutions and this value is not forced by all of them
+    Undetermined,
+}
+
+/// Classifies every cell of `attempt` against the solution of `puzzle`.
+///
+/// Returns 81 statuses in row-major order. When the puzzle has more than one
+/// solution, a value is correct only if every solution has it, incorrect if no
+/// solution has it, and undetermined otherwise.
+pub fn check_progress(puzzle: &Board, attempt: &Board) -> Result<Vec<CellStatus>> {
+    let solutions = enumerate_solutions(puzzle, 2);
+    if solutions.is_empty() {
+        return Err(SudokuError::InvalidBoard);
+    }
+    let unique = solutions.len() == 1;
+
+    let mut statuses = Vec::with_capacity(81);
+    for row in 0..9 {
+        for col in 0..9 {
+            let given = puzzle.get(row, col);
+            let value = attempt.get(row, col);
+            let status = if value == 0 {
+                CellStatus::Empty
+            } else if given != 0 {
+                if given == value {
+                    CellStatus::Given
+                } else {
+                    CellStatus::Incorrect
+                }
+            } else if unique {
+                if solutions[0].get(row, col) == value {
+                    CellStatus::Correct
+                } else {
+                    CellStatus::Incorrect
+                }
+            } else {
+                classify_ambiguous(puzzle, row, col, value)
+            };
+            statuses.push(status);
+        }
+    }
+    Ok(statuses)
+}
+
+/// Classifies a value in a puzzle with several solutions
+fn classify_ambiguous(puzzle: &Board, row: usize, col: usize, value: u8) -> CellStatus {
+    let has_solution_with = |digit: u8| {
+        if !puzzle.can_place(row, col, digit) {
+            return false;
+        }
+        let mut board = puzzle.clone();
+        board.set(row, col, digit);
+        count_solutions_up_to(&board, 1) > 0
+    };
+
+    if !has_solution_with(value) {
+        CellStatus::Incorrect
+    } else if (1..=9).filter(|&d| d != value).any(has_solution_with) {
+        CellStatus::Undetermined
+    } else {
+        CellStatus::Correct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn puzzle() -> Board {
+        Board::new(&[
+            vec![5,3,0,0,7,0,0,0,0],
+            vec![6,0,0,1,9,5,0,0,0],
+            vec![0,9,8,0,0,0,0,6,0],
+            vec![8,0,0,0,6,0,0,0,3],
+            vec![4,0,0,8,0,3,0,0,1],
+            vec![7,0,0,0,2,0,0,0,6],
+            vec![0,6,0,0,0,0,2,8,0],
+            vec![0,0,0,4,1,9,0,0,5],
+            vec![0,0,0,0,8,0,0,7,9],
+        ])
+    }
+
+    #[test]
+    fn test_check_progress() {
+        let puzzle = puzzle();
+        let mut attempt = puzzle.clone();
+        attempt.set(0, 2, 4); // correct
+        attempt.set(0, 3, 8); // incorrect, solution has 6
+        attempt.set(0, 0, 1); // overwrites a given
+
+        let statuses = check_progress(&puzzle, &attempt).unwrap();
+        assert_eq!(statuses.len(), 81);
+        assert_eq!(statuses[0], CellStatus::Incorrect);
+        assert_eq!(statuses[1], CellStatus::Given);
+        assert_eq!(statuses[2], CellStatus::Correct);
+        assert_eq!(statuses[3], CellStatus::Incorrect);
+        assert_eq!(statuses[5], CellStatus::Empty);
+    }
+
+    #[test]
+    fn test_check_progress_multiple_solutions() {
+        // A single given leaves the rest of the grid wide open
+        let mut puzzle = Board::empty();
+        puzzle.set(0, 0, 5);
+        let mut attempt = puzzle.clone();
+        attempt.set(4, 4, 5);
+        attempt.set(0, 1, 5);
+
+        let statuses = check_progress(&puzzle, &attempt).unwrap();
+        assert_eq!(statuses[40], CellStatus::Undetermined);
+        assert_eq!(statuses[1], CellStatus::Incorrect);
+    }
+
+    #[test]
+    fn test_check_progress_unsolvable() {
+        let mut puzzle = puzzle();
+        puzzle.set(0, 2, 5);
+        assert!(check_progress(&puzzle, &puzzle.clone()).is_err());
+    }
+}
@@ -0,0 +1,4 @@
+//! `BoardProvider` implementations besides the Dosuku `api::HttpProvider`.
+
+pub mod fault;
+pub mod fixture;
@@ -0,0 +1,186 @@
+//! Fault injection for the provider chain.
+//!
+//! A `FaultInjector` decides, call by call, whether a fetch is delayed,
+//! fails, or both, either from a fixed script or from a seeded random draw,
+//! so the same faults recur on every run. `FaultyProvider` puts one in front
+//! of any `BoardProvider` to exercise `ApiClient`'s fallback to the
+//! generator; `api::HttpProvider::with_faults` consults one before every
+//! request attempt, which exercises its retries. The `[api]` settings
+//! `fault_rate` and `fault_delay` turn random faults on for chaos runs.
+
+use crate::{api::BoardProvider, Grid, Result, SudokuError};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::debug;
+
+/// What happens to one call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The call goes through
+    Pass,
+    /// The call fails at once
+    Fail,
+    /// The call waits this long, then goes through
+    Delay(Duration),
+    /// The call waits this long, then fails, like a request timing out
+    Timeout(Duration),
+}
+
+/// Which calls fail or are delayed
+#[derive(Debug, Clone, PartialEq)]
+pub enum FaultPlan {
+    /// Faults for successive calls; calls after the last go through
+    Script(Vec<Fault>),
+    /// Each call fails with probability `failure_rate` after a delay drawn
+    /// uniformly up to `max_delay`. The draw depends only on `seed` and the
+    /// call's number, so concurrent callers still see the same sequence.
+    Random { failure_rate: f64, max_delay: Duration, seed: u64 },
+}
+
+/// Applies a `FaultPlan`, counting calls as they come
+#[derive(Debug)]
+pub struct FaultInjector {
+    plan: FaultPlan,
+    calls: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl FaultInjector {
+    pub fn new(plan: FaultPlan) -> Self {
+        Self { plan, calls: AtomicUsize::new(0), failures: AtomicUsize::new(0) }
+    }
+
+    /// The fault planned for call `call`, counting from 0
+    pub fn fault(&self, call: usize) -> Fault {
+        match &self.plan {
+            FaultPlan::Script(faults) => faults.get(call).copied().unwrap_or(Fault::Pass),
+            FaultPlan::Random { failure_rate, max_delay, seed } => {
+                let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(call as u64));
+                let delay = max_delay.mul_f64(rng.gen::<f64>());
+                match (rng.gen_bool(failure_rate.clamp(0.0, 1.0)), delay.is_zero()) {
+                    (true, true) => Fault::Fail,
+                    (true, false) => Fault::Timeout(delay),
+                    (false, true) => Fault::Pass,
+                    (false, false) => Fault::Delay(delay),
+                }
+            }
+        }
+    }
+
+    /// Applies the next call's fault, waiting out its delay, and errs with
+    /// `ApiError` when the call is to fail
+    pub async fn inject(&self) -> Result<()> {
+        let call = self.calls.fetch_add(1, Ordering::Relaxed);
+        let fault = self.fault(call);
+        if let Fault::Delay(delay) | Fault::Timeout(delay) = fault {
+            tokio::time::sleep(delay).await;
+        }
+        if matches!(fault, Fault::Fail | Fault::Timeout(_)) {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            debug!("Injected a failure into call {}", call);
+            return Err(SudokuError::ApiError(format!("injected fault in call {}", call)));
+        }
+        Ok(())
+    }
+
+    /// Calls seen so far
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Calls made to fail so far
+    pub fn failures(&self) -> usize {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// A provider whose fetches pass through a `FaultInjector` first; failed
+/// fetches never reach it
+#[derive(Debug)]
+pub struct FaultyProvider<P> {
+    inner: P,
+    faults: FaultInjector,
+}
+
+impl<P: BoardProvider> FaultyProvider<P> {
+    pub fn new(inner: P, plan: FaultPlan) -> Self {
+        Self { inner, faults: FaultInjector::new(plan) }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn faults(&self) -> &FaultInjector {
+        &self.faults
+    }
+}
+
+impl<P: BoardProvider> BoardProvider for FaultyProvider<P> {
+    async fn fetch(&self) -> Result<Grid> {
+        self.faults.inject().await?;
+        self.inner.fetch().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ApiClient;
+    use crate::providers::fixture::FixtureProvider;
+    use crate::PuzzleSource;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_scripted_faults() {
+        let delay = Duration::from_millis(20);
+        let plan = FaultPlan::Script(vec![Fault::Fail, Fault::Delay(delay), Fault::Timeout(delay)]);
+        let provider = FaultyProvider::new(FixtureProvider::default(), plan);
+
+        assert!(provider.fetch().await.is_err());
+        let start = Instant::now();
+        assert!(provider.fetch().await.is_ok());
+        assert!(start.elapsed() >= delay);
+        assert!(provider.fetch().await.is_err());
+        assert!(start.elapsed() >= delay * 2);
+        // Past the end of the script everything goes through
+        assert!(provider.fetch().await.is_ok());
+        assert_eq!((provider.faults().calls(), provider.faults().failures()), (4, 2));
+        assert_eq!(provider.inner().served(), 2);
+    }
+
+    #[test]
+    fn test_random_faults_repeat() {
+        let plan = |failure_rate: f64| FaultPlan::Random { failure_rate, max_delay: Duration::from_millis(50), seed: 9 };
+        let (first, second) = (FaultInjector::new(plan(0.3)), FaultInjector::new(plan(0.3)));
+        let faults: Vec<Fault> = (0..200).map(|call| first.fault(call)).collect();
+        assert_eq!(faults, (0..200).map(|call| second.fault(call)).collect::<Vec<_>>());
+        let failed = faults.iter().filter(|fault| matches!(fault, Fault::Fail | Fault::Timeout(_))).count();
+        assert!((30..90).contains(&failed), "{} of 200 failed", failed);
+        assert!(faults.iter().all(|fault| match fault {
+            Fault::Delay(delay) | Fault::Timeout(delay) => *delay <= Duration::from_millis(50),
+            _ => true,
+        }));
+
+        let never = FaultInjector::new(plan(0.0));
+        let always = FaultInjector::new(plan(1.0));
+        assert!((0..50).all(|call| matches!(never.fault(call), Fault::Pass | Fault::Delay(_))));
+        assert!((0..50).all(|call| matches!(always.fault(call), Fault::Fail | Fault::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_client_falls_back_on_faults() {
+        let plan = FaultPlan::Script(vec![Fault::Pass, Fault::Fail]);
+        let client = ApiClient::new(FaultyProvider::new(FixtureProvider::default(), plan))
+            .with_warm_up(0)
+            .with_min_request_interval(Duration::ZERO);
+        let first = client.fetch_new_board().await.unwrap();
+        assert_eq!(first.value, FixtureProvider::default().fetch().await.unwrap().value);
+        // The provider's board is cached, so the next fetch doesn't reach it
+        assert_eq!(client.fetch_new_board().await.unwrap(), first);
+        let fallback = client.fetch_new_board().await.unwrap();
+        assert_eq!(fallback.metadata.source, Some(PuzzleSource::Generator));
+        assert_eq!(client.provider().faults().calls(), 2);
+    }
+}
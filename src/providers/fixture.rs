@@ -0,0 +1,157 @@
+//! Known puzzles for deterministic tests.
+//!
+//! The fixtures cover ordinary puzzles of each difficulty plus the edge cases
+//! solvers and validators trip over: a 17-clue minimum, a solved grid, an
+//! empty grid, several solutions, no solution, and conflicting givens.
+//! `FixtureProvider` serves the solvable ones through the `BoardProvider`
+//! interface, so an `api::ApiClient` can be tested without the network.
+
+use crate::{api::BoardProvider, solver::Solver, Board, Grid, Result, SudokuError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What a fixture exercises
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureKind {
+    /// An ordinary puzzle with exactly one solution
+    Unique,
+    /// Every cell is already filled in correctly
+    Solved,
+    /// The givens admit more than one solution
+    MultipleSolutions,
+    /// The givens don't conflict, but no solution exists
+    NoSolution,
+    /// Two givens in the same row, column or box share a digit
+    Conflicting,
+}
+
+/// A named puzzle with known properties
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixture {
+    pub name: &'static str,
+    /// 81 characters, `.` for empty cells
+    pub puzzle: &'static str,
+    pub kind: FixtureKind,
+    /// Difficulty label used when the fixture is served as a `Grid`
+    pub difficulty: &'static str,
+}
+
+const fn fixture(name: &'static str, puzzle: &'static str, kind: FixtureKind, difficulty: &'static str) -> Fixture {
+    Fixture { name, puzzle, kind, difficulty }
+}
+
+/// Every fixture
+pub const FIXTURES: &[Fixture] = &[
+    fixture("easy", "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79", FixtureKind::Unique, "Easy"),
+    fixture("x_wing", "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5", FixtureKind::Unique, "Medium"),
+    fixture("hard", "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..", FixtureKind::Unique, "Hard"),
+    fixture("sparse", "..9748...7.........2.1.9.....7...24..64.1.59..98...3.....8.3.2.........6...2759..", FixtureKind::Unique, "Hard"),
+    fixture("seventeen_clues", ".......1.4.........2...........5.4.7..8...3....1.9....3..4..2...5.1........8.6...", FixtureKind::Unique, "Hard"),
+    fixture("solved", "534678912672195348198342567859761423426853791713924856961537284287419635345286179", FixtureKind::Solved, "Easy"),
+    fixture("empty", ".................................................................................", FixtureKind::MultipleSolutions, "Unknown"),
+    fixture("deadly_rectangle", "534..8912672195348198342567859..1423426853791713924856961537284287419635345286179", FixtureKind::MultipleSolutions, "Unknown"),
+    fixture("no_candidates", "12345678.........9...............................................................", FixtureKind::NoSolution, "Unknown"),
+    fixture("duplicate_in_row", "11...............................................................................", FixtureKind::Conflicting, "Unknown"),
+];
+
+/// Looks up a fixture by name
+pub fn get(name: &str) -> Option<&'static Fixture> {
+    FIXTURES.iter().find(|fixture| fixture.name == name)
+}
+
+/// Fixtures of one kind
+pub fn of_kind(kind: FixtureKind) -> impl Iterator<Item = &'static Fixture> {
+    FIXTURES.iter().filter(move |fixture| fixture.kind == kind)
+}
+
+impl Fixture {
+    pub fn board(&self) -> Board {
+        Board::parse(self.puzzle).expect("fixtures are well-formed")
+    }
+
+    /// The fixture with its solution, for fixtures that have exactly one
+    pub fn grid(&self) -> Option<Grid> {
+        if !matches!(self.kind, FixtureKind::Unique | FixtureKind::Solved) {
+            return None;
+        }
+        let puzzle = self.board();
+        let mut solution = puzzle.clone();
+        Solver::solve_into(&mut solution).ok()?;
+        Some(Grid {
+            value: puzzle.to_vec(),
+            solution: Some(solution.to_vec()),
+            difficulty: self.difficulty.to_string(),
+            metadata: Default::default(),
+        })
+    }
+}
+
+/// Serves the uniquely solvable fixtures in turn, starting over after the last
+#[derive(Debug)]
+pub struct FixtureProvider {
+    grids: Vec<Grid>,
+    next: AtomicUsize,
+}
+
+impl Default for FixtureProvider {
+    fn default() -> Self {
+        Self::new(of_kind(FixtureKind::Unique).filter_map(Fixture::grid).collect())
+    }
+}
+
+impl FixtureProvider {
+    /// Serves `grids` in turn; an empty list fails every fetch
+    pub fn new(grids: Vec<Grid>) -> Self {
+        Self { grids, next: AtomicUsize::new(0) }
+    }
+
+    /// Number of boards served so far
+    pub fn served(&self) -> usize {
+        self.next.load(Ordering::Relaxed)
+    }
+}
+
+impl BoardProvider for FixtureProvider {
+    async fn fetch(&self) -> Result<Grid> {
+        if self.grids.is_empty() {
+            return Err(SudokuError::ApiError("fixture provider has no boards".to_string()));
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.grids.len();
+        Ok(self.grids[index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{simd::SimdValidator, solver::count_solutions_up_to};
+
+    #[test]
+    fn test_fixture_kinds() {
+        for fixture in FIXTURES {
+            let board = fixture.board();
+            let solutions = count_solutions_up_to(&board, 2);
+            match fixture.kind {
+                FixtureKind::Unique => assert_eq!(solutions, 1, "{}", fixture.name),
+                FixtureKind::Solved => assert!(SimdValidator::validate_solution(&board), "{}", fixture.name),
+                FixtureKind::MultipleSolutions => assert_eq!(solutions, 2, "{}", fixture.name),
+                FixtureKind::NoSolution | FixtureKind::Conflicting => {
+                    assert_eq!(solutions, 0, "{}", fixture.name)
+                }
+            }
+            assert_eq!(fixture.grid().is_some(), matches!(fixture.kind, FixtureKind::Unique | FixtureKind::Solved));
+        }
+        let seventeen = get("seventeen_clues").unwrap().board();
+        assert_eq!(seventeen.cells.iter().filter(|&&v| v != 0).count(), 17);
+    }
+
+    #[tokio::test]
+    async fn test_fixture_provider() {
+        let provider = FixtureProvider::default();
+        let unique = of_kind(FixtureKind::Unique).count();
+        for fixture in of_kind(FixtureKind::Unique).chain(of_kind(FixtureKind::Unique).take(1)) {
+            assert_eq!(provider.fetch().await.unwrap().value, fixture.board().to_vec());
+        }
+        assert_eq!(provider.served(), unique + 1);
+        assert!(FixtureProvider::new(Vec::new()).fetch().await.is_err());
+    }
+}
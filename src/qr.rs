@@ -0,0 +1,592 @@
+//! A small QR code encoder.
+//!
+//! Byte mode only, error correction level M, versions 1-10 (up to 213
+//! bytes), which is plenty for a share code. The symbol follows the usual
+//! layout: finder, timing and alignment patterns, format and version
+//! information, Reed-Solomon error correction over interleaved blocks, and
+//! whichever of the eight masks scores the lowest penalty.
+
+use crate::{Result, SudokuError};
+use std::fmt::Write;
+
+const MAX_VERSION: usize = 10;
+
+/// Error correction codewords per block at level M, by version
+const ECC_PER_BLOCK: [usize; MAX_VERSION + 1] = [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+
+/// Error correction blocks at level M, by version
+const BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+
+/// Level M as written in the format information
+const LEVEL_M: u32 = 0b00;
+
+/// Light modules to leave around the symbol
+const QUIET_ZONE: usize = 4;
+
+/// Dark-light run of 1:1:3:1:1 followed by four light modules, which
+/// scanners could mistake for a finder pattern
+const FINDER_LIKE: [bool; 11] = [true, false, true, true, true, false, true, false, false, false, false];
+
+/// An encoded QR code symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    version: usize,
+    mask: u8,
+    size: usize,
+    /// Row-major, true for dark
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encodes `data` in the smallest version that holds it
+    pub fn encode(data: &[u8]) -> Result<Self> {
+        let version = (1..=MAX_VERSION)
+            .find(|&version| 4 + count_bits(version) + data.len() * 8 <= data_codewords(version) * 8)
+            .ok_or_else(|| SudokuError::ParseError(format!("{} bytes is too long for a QR code", data.len())))?;
+
+        let mut canvas = Canvas::new(version);
+        canvas.draw_function_patterns(version);
+        canvas.draw_codewords(&add_ecc(&pad_data(data, version), version));
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut masked = canvas.clone();
+                masked.apply_mask(mask);
+                masked.draw_format(mask);
+                masked.penalty()
+            })
+            .unwrap_or(0);
+        canvas.apply_mask(mask);
+        canvas.draw_format(mask);
+        Ok(Self { version, mask, size: canvas.size, modules: canvas.dark })
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Width and height in modules, quiet zone excluded
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    /// Whether the module at `x`, `y` of the symbol with its quiet zone is
+    /// dark; anything outside the symbol is light
+    fn is_dark_padded(&self, x: usize, y: usize) -> bool {
+        x >= QUIET_ZONE && y >= QUIET_ZONE && self.is_dark(x - QUIET_ZONE, y - QUIET_ZONE)
+    }
+
+    /// Renders the symbol with half-block characters, two module rows per
+    /// line, quiet zone included. Terminals with light text on a dark
+    /// background draw the blocks light, so `dark_background` draws the
+    /// light modules instead of the dark ones.
+    pub fn to_text(&self, dark_background: bool) -> String {
+        let width = self.size + 2 * QUIET_ZONE;
+        let mut out = String::new();
+        for y in (0..width).step_by(2) {
+            for x in 0..width {
+                // A row past the bottom edge counts as more quiet zone
+                let top = self.is_dark_padded(x, y) != dark_background;
+                let bottom = self.is_dark_padded(x, y + 1) != dark_background;
+                out.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Renders the symbol as an SVG image, `scale` pixels per module
+    pub fn to_svg(&self, scale: usize) -> String {
+        let width = self.size + 2 * QUIET_ZONE;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in (0..self.size).filter(|&x| self.is_dark(x, y)) {
+                let _ = write!(path, "M{},{}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE);
+            }
+        }
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {0} {0}\" width=\"{1}\" height=\"{1}\" ",
+                "shape-rendering=\"crispEdges\">\n<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\n",
+                "<path d=\"{2}\" fill=\"#000\"/>\n</svg>\n"
+            ),
+            width,
+            width * scale,
+            path
+        )
+    }
+}
+
+/// Modules of a symbol under construction
+#[derive(Clone)]
+struct Canvas {
+    size: usize,
+    dark: Vec<bool>,
+    /// Modules taken by function patterns, which data and masks skip
+    reserved: Vec<bool>,
+}
+
+impl Canvas {
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        Self { size, dark: vec![false; size * size], reserved: vec![false; size * size] }
+    }
+
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.dark[y * self.size + x] = dark;
+        self.reserved[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        for i in 0..self.size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        let far = self.size - 4;
+        for (x, y) in [(3, 3), (far, 3), (3, far)] {
+            self.draw_finder(x, y);
+        }
+        let positions = alignment_positions(version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // The corners that would overlap a finder pattern are left out
+                if (i, j) != (0, 0) && (i, j) != (0, last) && (i, j) != (last, 0) {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+        // Reserve the format modules; the real bits go in once the mask is chosen
+        self.draw_format(0);
+        self.draw_version(version);
+    }
+
+    /// Draws a finder pattern and its separator centred on `cx`, `cy`
+    fn draw_finder(&mut self, cx: usize, cy: usize) {
+        for dy in -4..=4i32 {
+            for dx in -4..=4i32 {
+                let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                if (0..self.size as i32).contains(&x) && (0..self.size as i32).contains(&y) {
+                    let ring = dx.abs().max(dy.abs());
+                    self.set_function(x as usize, y as usize, ring != 2 && ring != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, cx: usize, cy: usize) {
+        for dy in -2..=2i32 {
+            for dx in -2..=2i32 {
+                let ring = dx.abs().max(dy.abs());
+                self.set_function((cx as i32 + dx) as usize, (cy as i32 + dy) as usize, ring != 1);
+            }
+        }
+    }
+
+    fn draw_format(&mut self, mask: u8) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        let size = self.size;
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // Always dark
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self, version: usize) {
+        if version < 7 {
+            return;
+        }
+        let bits = version_bits(version);
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Places codewords in the zigzag of two-module columns, right to left,
+    /// alternately upwards and downwards, skipping the vertical timing pattern
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let mut bit = 0;
+        let mut right = self.size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..self.size {
+                let y = if upward { self.size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if !self.reserved[y * self.size + x] && bit < codewords.len() * 8 {
+                        self.dark[y * self.size + x] = (codewords[bit / 8] >> (7 - bit % 8)) & 1 != 0;
+                        bit += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.reserved[y * self.size + x] {
+                    self.dark[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    /// How hard the symbol is to scan: long runs, 2x2 blocks, finder-like
+    /// patterns and an imbalance of dark and light all add to it
+    fn penalty(&self) -> usize {
+        let n = self.size;
+        let mut score = 0;
+        for i in 0..n {
+            let row: Vec<bool> = (0..n).map(|j| self.is_dark(j, i)).collect();
+            let column: Vec<bool> = (0..n).map(|j| self.is_dark(i, j)).collect();
+            for line in [row, column] {
+                let mut run = 1;
+                for j in 1..n {
+                    if line[j] == line[j - 1] {
+                        run += 1;
+                        score += match run {
+                            5 => 3,
+                            6.. => 1,
+                            _ => 0,
+                        };
+                    } else {
+                        run = 1;
+                    }
+                }
+                score += 40
+                    * line
+                        .windows(FINDER_LIKE.len())
+                        .filter(|window| **window == FINDER_LIKE || window.iter().rev().eq(FINDER_LIKE.iter()))
+                        .count();
+            }
+        }
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let dark = self.is_dark(x, y);
+                if [(x + 1, y), (x, y + 1), (x + 1, y + 1)].iter().all(|&(x, y)| self.is_dark(x, y) == dark) {
+                    score += 3;
+                }
+            }
+        }
+        let percent_dark = self.dark.iter().filter(|&&dark| dark).count() * 100 / self.dark.len();
+        score + 10 * (percent_dark.abs_diff(50) / 5)
+    }
+}
+
+/// Bits in the character count field of a byte-mode segment
+fn count_bits(version: usize) -> usize {
+    if version < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+/// Modules left for codewords once the function patterns are drawn
+fn raw_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_modules(version) / 8 - ECC_PER_BLOCK[version] * BLOCKS[version]
+}
+
+/// Centres of the alignment patterns along each axis
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let size = version * 4 + 17;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// The 15 format bits for level M and `mask`, BCH-protected and masked
+fn format_bits(mask: u8) -> u32 {
+    let data = LEVEL_M << 3 | mask as u32;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+/// The 18 version bits, BCH-protected
+fn version_bits(version: usize) -> u32 {
+    let mut rem = version as u32;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+    }
+    (version as u32) << 12 | rem
+}
+
+/// The data codewords: a byte-mode segment, terminator and padding
+fn pad_data(data: &[u8], version: usize) -> Vec<u8> {
+    let capacity = data_codewords(version) * 8;
+    let mut bits = Vec::with_capacity(capacity);
+    let mut push = |value: usize, count: usize| bits.extend((0..count).rev().map(|i| (value >> i) & 1 != 0));
+    push(0b0100, 4);
+    push(data.len(), count_bits(version));
+    for &byte in data {
+        push(byte as usize, 8);
+    }
+    let terminator = (capacity - bits.len()).min(4);
+    bits.resize(bits.len() + terminator, false);
+    bits.resize(bits.len().div_ceil(8) * 8, false);
+
+    let mut codewords: Vec<u8> = bits.chunks(8).map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8)).collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() == capacity / 8 {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+/// Splits `data` into blocks, appends each block's error correction, and
+/// interleaves the blocks codeword by codeword
+fn add_ecc(data: &[u8], version: usize) -> Vec<u8> {
+    let (blocks, ecc_len) = (BLOCKS[version], ECC_PER_BLOCK[version]);
+    let raw = raw_modules(version) / 8;
+    // The first blocks are one data codeword shorter than the rest
+    let short_blocks = blocks - raw % blocks;
+    let short_len = raw / blocks;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut rest = data;
+    let blocks: Vec<Vec<u8>> = (0..blocks)
+        .map(|i| {
+            let (block, tail) = rest.split_at(short_len - ecc_len + usize::from(i >= short_blocks));
+            rest = tail;
+            let mut block = block.to_vec();
+            let ecc = rs_remainder(&block, &divisor);
+            if i < short_blocks {
+                // Placeholder so every block has the same length; never emitted
+                block.push(0);
+            }
+            block.extend(ecc);
+            block
+        })
+        .collect();
+
+    let mut interleaved = Vec::with_capacity(raw);
+    for i in 0..=short_len {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_len - ecc_len || j >= short_blocks {
+                interleaved.push(block[i]);
+            }
+        }
+    }
+    interleaved
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u8 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1D);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
+
+/// Coefficients of the Reed-Solomon generator polynomial of `degree`,
+/// highest power first with the leading 1 left out
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root: u8 = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+/// The error correction codewords for `data`
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (x, &y) in result.iter_mut().zip(divisor) {
+            *x ^= gf_multiply(y, factor);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads the codewords back out of `qr`, undoing its mask
+    fn read_codewords(qr: &QrCode) -> Vec<u8> {
+        let mut canvas = Canvas::new(qr.version);
+        canvas.draw_function_patterns(qr.version);
+        canvas.dark = qr.modules.clone();
+        canvas.apply_mask(qr.mask);
+
+        let mut bits = Vec::new();
+        let mut right = canvas.size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..canvas.size {
+                let y = if upward { canvas.size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if !canvas.reserved[y * canvas.size + x] {
+                        bits.push(canvas.is_dark(x, y));
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+        bits.chunks_exact(8).map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8)).collect()
+    }
+
+    #[test]
+    fn test_reed_solomon() {
+        // "HELLO WORLD" as version 1-M in alphanumeric mode
+        let data = [0x20, 0x5B, 0x0B, 0x78, 0xD1, 0x72, 0xDC, 0x4D, 0x43, 0x40, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11];
+        assert_eq!(rs_remainder(&data, &rs_divisor(10)), [0xC4, 0x23, 0x27, 0x77, 0xEB, 0xD7, 0xE7, 0xE2, 0x5D, 0x17]);
+        assert_eq!(gf_multiply(0x80, 0x02), 0x1D);
+    }
+
+    #[test]
+    fn test_tables() {
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(format_bits(4), 0b100010111111001);
+        assert_eq!(version_bits(7), 0b000111110010010100);
+        assert_eq!([1, 2, 5, 7, 10].map(|v| raw_modules(v) / 8), [26, 44, 134, 196, 346]);
+        assert_eq!([1, 4, 10].map(data_codewords), [16, 64, 216]);
+        assert_eq!(alignment_positions(10), [6, 28, 50]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let qr = QrCode::encode(b"hello").unwrap();
+        assert_eq!((qr.version(), qr.size()), (1, 21));
+        // Finder patterns in three corners, with light separators
+        for (x, y) in [(0, 0), (14, 0), (0, 14)] {
+            assert!(qr.is_dark(x, y) && qr.is_dark(x + 6, y + 6) && qr.is_dark(x + 3, y + 3));
+            assert!(!qr.is_dark(x + 1, y + 1));
+        }
+        assert!(!qr.is_dark(7, 7) && !qr.is_dark(13, 7) && qr.is_dark(8, 13));
+        assert!((8..13).all(|i| qr.is_dark(i, 6) == (i % 2 == 0)));
+
+        // Both copies of the format bits name the chosen mask
+        let first: u32 = (0..=5).map(|i| (8, i)).chain([(8, 7), (8, 8), (7, 8)]).chain((9..15).map(|i| (14 - i, 8)))
+            .enumerate()
+            .map(|(bit, (x, y))| (qr.is_dark(x, y) as u32) << bit)
+            .sum();
+        assert_eq!(first, format_bits(qr.mask));
+        let second: u32 = (0..8).map(|i| (20 - i, 8)).chain((8..15).map(|i| (8, 6 + i)))
+            .enumerate()
+            .map(|(bit, (x, y))| (qr.is_dark(x, y) as u32) << bit)
+            .sum();
+        assert_eq!(second, format_bits(qr.mask));
+
+        let codewords = read_codewords(&qr);
+        assert_eq!(codewords, add_ecc(&pad_data(b"hello", 1), 1));
+        assert_eq!(&codewords[..7], [0x40, 0x56, 0x86, 0x56, 0xC6, 0xC6, 0xF0]);
+    }
+
+    #[test]
+    fn test_larger_versions() {
+        let data: Vec<u8> = (0..=200).collect();
+        let qr = QrCode::encode(&data).unwrap();
+        assert_eq!((qr.version(), qr.size()), (10, 57));
+        let codewords = read_codewords(&qr);
+        assert_eq!(codewords, add_ecc(&pad_data(&data, 10), 10));
+        // Interleaving takes the first codeword of each of the five blocks in turn
+        assert_eq!(codewords[0], 0x40);
+        assert_eq!(codewords[1], pad_data(&data, 10)[43]);
+
+        assert!(QrCode::encode(&[0; 213]).is_ok());
+        assert!(matches!(QrCode::encode(&[0; 214]), Err(SudokuError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_render() {
+        let qr = QrCode::encode(b"sudoku").unwrap();
+        let text = qr.to_text(false);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), (qr.size() + 2 * QUIET_ZONE).div_ceil(2));
+        assert!(lines.iter().all(|line| line.chars().count() == qr.size() + 2 * QUIET_ZONE));
+        assert!(lines[0].trim().is_empty() && lines[2].contains('█'));
+        assert_ne!(qr.to_text(true), text);
+
+        let svg = qr.to_svg(4);
+        assert!(svg.starts_with("<svg") && svg.contains("width=\"116\"") && svg.contains("M4,4h1v1h-1z"));
+    }
+}
@@ -0,0 +1,290 @@
+//! Technique-based difficulty rating.
+//!
+//! A puzzle is rated by solving it with `LogicSolver` and recording which
+//! techniques were needed. Since techniques are always tried easiest first,
+//! the hardest technique used is one the puzzle genuinely requires.
+//!
+//! `hardness` adds two search-based measures that don't depend on which
+//! techniques the solver knows: the backdoor size and the search-tree size.
+//!
+//! `estimate` is the cheap option: one budgeted trial solve, labelled by how
+//! often the search hit a dead end and how many of its placements were forced.
+//! It is much quicker than `rate` and agrees with it on most Easy puzzles, but
+//! tells Medium, Hard and Expert apart only roughly.
+
+use crate::{
+    logic::{self, LogicSolver, Technique},
+    solver::{enumerate_solutions, search_tree_size, Budget, Solver},
+    Board, Grid, Result, SudokuError,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Result of rating a puzzle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    /// True if logic alone solved the puzzle
+    pub solved: bool,
+    /// Hardest technique applied
+    pub hardest: Option<Technique>,
+    /// Number of times each technique was applied
+    pub technique_counts: BTreeMap<Technique, usize>,
+    /// Total number of logical steps
+    pub steps: usize,
+    /// Search-based measures, when requested with `rate_with_hardness`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardness: Option<Hardness>,
+}
+
+/// Largest backdoor `hardness` looks for
+pub const MAX_BACKDOOR: usize = 3;
+
+/// Technique-independent measures of how hard a puzzle is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hardness {
+    /// Fewest cells that must be filled in from the solution before naked and
+    /// hidden singles finish the puzzle. `None` if more than `MAX_BACKDOOR`.
+    pub backdoor: Option<usize>,
+    /// Placements a depth-first search tries to solve the puzzle and prove the
+    /// solution unique
+    pub search_nodes: u64,
+}
+
+impl Rating {
+    /// Returns true if the solve path used `technique`
+    pub fn uses(&self, technique: Technique) -> bool {
+        self.technique_counts.contains_key(&technique)
+    }
+
+    /// Maps the rating onto the crate's difficulty labels.
+    ///
+    /// Puzzles that logic can't finish are labelled "Expert".
+    pub fn difficulty(&self) -> &'static str {
+        if !self.solved {
+            return "Expert";
+        }
+        match self.hardest {
+            None | Some(Technique::NakedSingle) | Some(Technique::HiddenSingle) => "Easy",
+            Some(Technique::LockedCandidates) => "Medium",
+            Some(_) => "Hard",
+        }
+    }
+}
+
+/// Search nodes `estimate` may spend; a puzzle needing more is Expert
+pub const ESTIMATE_BUDGET: u64 = 100_000;
+
+/// Difficulty guessed from how a trial solve went
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    /// Placements the search tried, including those proving the solution unique
+    pub nodes: u64,
+    /// Dead ends the search backed out of
+    pub backtracks: u64,
+    /// Share of placements made in cells with a single candidate
+    pub singles_ratio: f64,
+    /// True if the search ran out of budget before finishing
+    pub exhausted: bool,
+}
+
+impl Estimate {
+    /// Maps the trial solve onto the crate's difficulty labels. A search that
+    /// barely backtracks is Easy; the dead-end thresholds between Medium,
+    /// Hard and Expert were fitted against `Rating::difficulty`.
+    pub fn difficulty(&self) -> &'static str {
+        match self.backtracks {
+            _ if self.exhausted => "Expert",
+            0..=2 if self.singles_ratio >= 0.9 => "Easy",
+            0..=15 => "Medium",
+            16..=60 => "Hard",
+            _ => "Expert",
+        }
+    }
+}
+
+/// Estimates a puzzle's difficulty from a trial solve within `ESTIMATE_BUDGET`.
+/// Returns `InvalidBoard` if the givens conflict or the search finds no
+/// solution or several.
+pub fn estimate(board: &Board) -> Result<Estimate> {
+    let bounded = Solver::new(Grid::from(board)).solve_bounded(Budget::nodes(ESTIMATE_BUDGET))?;
+    if bounded.unique == Some(false) {
+        return Err(SudokuError::InvalidBoard);
+    }
+    let nodes = bounded.stats.nodes;
+    Ok(Estimate {
+        nodes,
+        backtracks: bounded.backtracks,
+        singles_ratio: if nodes == 0 { 1.0 } else { bounded.forced as f64 / nodes as f64 },
+        exhausted: !bounded.solved || bounded.unique.is_none(),
+    })
+}
+
+/// Rates a puzzle by the techniques needed to solve it
+pub fn rate(board: &Board) -> Rating {
+    let mut solver = LogicSolver::new(board);
+    let steps = solver.solve();
+
+    let mut technique_counts = BTreeMap::new();
+    for step in &steps {
+        *technique_counts.entry(step.technique).or_insert(0) += 1;
+    }
+
+    Rating {
+        solved: solver.is_solved(),
+        hardest: technique_counts.keys().next_back().copied(),
+        technique_counts,
+        steps: steps.len(),
+        hardness: None,
+    }
+}
+
+/// Rates a puzzle and measures its hardness, if it has a unique solution
+pub fn rate_with_hardness(board: &Board) -> Rating {
+    Rating { hardness: hardness(board).ok(), ..rate(board) }
+}
+
+/// Measures the backdoor size and search-tree size of a puzzle.
+///
+/// Returns `InvalidBoard` unless the puzzle has exactly one solution. Finding
+/// a backdoor of size k tries every k cells left after singles, so a puzzle
+/// with no small backdoor takes up to a few seconds.
+pub fn hardness(board: &Board) -> Result<Hardness> {
+    let solutions = enumerate_solutions(board, 2);
+    let [solution] = solutions.as_slice() else {
+        return Err(SudokuError::InvalidBoard);
+    };
+    Ok(Hardness { backdoor: backdoor_size(board, solution), search_nodes: search_tree_size(board) })
+}
+
+/// Fewest cells to fill in from `solution` for singles to solve `board`
+fn backdoor_size(board: &Board, solution: &Board) -> Option<usize> {
+    let mut solver = LogicSolver::new(board);
+    if fill_singles(&mut solver) {
+        return Some(0);
+    }
+    let open: Vec<usize> = (0..81).filter(|&cell| solver.board().cells[cell] == 0).collect();
+    (1..=MAX_BACKDOOR).find(|&size| {
+        logic::combinations(&open, size).par_iter().any(|cells| {
+            let mut trial = solver.clone();
+            for &cell in cells {
+                trial.place(cell / 9, cell % 9, solution.cells[cell]);
+            }
+            fill_singles(&mut trial)
+        })
+    })
+}
+
+/// Applies naked and hidden singles until none are left, returning true if
+/// that solved the board
+fn fill_singles(solver: &mut LogicSolver) -> bool {
+    while let Some(step) =
+        solver.find_technique(Technique::NakedSingle).or_else(|| solver.find_technique(Technique::HiddenSingle))
+    {
+        solver.apply(&step);
+    }
+    solver.is_solved()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_easy_and_hard() {
+        let easy = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let rating = rate(&easy);
+        assert!(rating.solved);
+        assert_eq!(rating.difficulty(), "Easy");
+
+        let x_wing = Board::parse(
+            "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5",
+        )
+        .unwrap();
+        let rating = rate(&x_wing);
+        assert!(rating.uses(Technique::XWing));
+        assert_eq!(rating.hardest, Some(Technique::XWing));
+        assert_eq!(rating.difficulty(), "Hard");
+    }
+
+    #[test]
+    fn test_hardness() {
+        let easy = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let measured = hardness(&easy).unwrap();
+        assert_eq!(measured.backdoor, Some(0));
+        assert!(measured.search_nodes > 0);
+        assert!(rate(&easy).hardness.is_none());
+        assert_eq!(rate_with_hardness(&easy).hardness, Some(measured));
+
+        // Singles stall on this one, so it needs at least one guess
+        let x_wing = Board::parse(
+            "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5",
+        )
+        .unwrap();
+        let measured = hardness(&x_wing).unwrap();
+        assert!(matches!(measured.backdoor, Some(1..=MAX_BACKDOOR)), "{:?}", measured);
+
+        assert!(matches!(hardness(&Board::empty()), Err(SudokuError::InvalidBoard)));
+    }
+
+    #[test]
+    fn test_estimate() {
+        let easy = crate::providers::fixture::get("easy").unwrap().board();
+        let estimated = estimate(&easy).unwrap();
+        assert_eq!((estimated.difficulty(), estimated.backtracks, estimated.exhausted), ("Easy", 0, false));
+        assert!(estimated.singles_ratio > 0.9);
+
+        let x_wing = Board::parse(
+            "1.....569492.561.8.561.924...964.8.1.64.1....218.356.4.4.5...169.5.614.2621.....5",
+        )
+        .unwrap();
+        assert_ne!(estimate(&x_wing).unwrap().difficulty(), "Easy");
+        // Proving this one unique takes more than the budget
+        let sparse = Board::parse(
+            "........1.......23..4..5......1.........3.6....7...58.....67....1...4...52.......",
+        )
+        .unwrap();
+        let estimated = estimate(&sparse).unwrap();
+        assert!(estimated.exhausted && estimated.nodes == ESTIMATE_BUDGET, "{:?}", estimated);
+        assert_eq!(estimated.difficulty(), "Expert");
+
+        assert!(matches!(estimate(&Board::empty()), Err(SudokuError::InvalidBoard)));
+        let clashing = crate::providers::fixture::get("duplicate_in_row").unwrap().board();
+        assert!(matches!(estimate(&clashing), Err(SudokuError::InvalidBoard)));
+    }
+
+    #[test]
+    fn test_estimate_against_rating() {
+        let levels = ["Easy", "Medium", "Hard", "Expert"];
+        let level = |difficulty| levels.iter().position(|&label| label == difficulty).unwrap();
+        let mut generator = crate::generator::BoardGenerator::with_seed(11);
+        let (mut easy, mut agreed_easy, mut close) = (0, 0, 0);
+        for _ in 0..60 {
+            let solution = generator.random_solution();
+            let options = crate::generator::RemovalOptions { remove: 40..=58, difficulty: Some("Any".to_string()) };
+            let puzzle = Board::new(&generator.generate_from_solution(&solution, &options).unwrap().value);
+            let (estimated, rated) = (estimate(&puzzle).unwrap().difficulty(), rate(&puzzle).difficulty());
+            if estimated == "Easy" {
+                easy += 1;
+                agreed_easy += usize::from(rated == "Easy");
+            }
+            close += usize::from(level(estimated).abs_diff(level(rated)) <= 1);
+        }
+        // An Easy estimate is nearly always right; the rest is within a level most of the time
+        assert!(easy > 0 && agreed_easy * 10 >= easy * 9, "{} of {} Easy estimates agreed", agreed_easy, easy);
+        assert!(close >= 45, "{} of 60 within a level", close);
+    }
+
+    #[test]
+    fn test_rate_unsolvable_by_logic() {
+        let rating = rate(&Board::empty());
+        assert!(!rating.solved);
+        assert_eq!(rating.difficulty(), "Expert");
+    }
+}
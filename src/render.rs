@@ -0,0 +1,3 @@
+//! Drawing boards for display.
+
+pub mod term;
@@ -0,0 +1,233 @@
+//! Boards drawn for a terminal.
+//!
+//! `render` draws a board as a grid in normal or large print, with
+//! box-drawing or plain ASCII borders. With color on, digits are colored by
+//! where they came from: givens bold, digits filled in since cyan, and digits
+//! that clash with another in a row, column or box red; without color the
+//! clashing digits are marked with a `!`. One cell can be highlighted, such
+//! as the target of a hint or the placement just replayed.
+
+use crate::{validation, Board};
+
+/// How much room each cell gets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellSize {
+    /// One character per digit
+    #[default]
+    Normal,
+    /// Digits drawn three characters wide and three lines tall
+    Large,
+}
+
+/// Characters the grid lines are drawn with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Borders {
+    #[default]
+    Unicode,
+    /// `+`, `-` and `|`, for terminals and fonts without box drawing
+    Ascii,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TermOptions {
+    pub size: CellSize,
+    pub borders: Borders,
+    /// Color digits with ANSI escapes
+    pub color: bool,
+    /// `(row, col)` of a cell to mark: in reverse video with color, after a `>` without
+    pub highlight: Option<(usize, usize)>,
+}
+
+impl TermOptions {
+    pub fn with_size(mut self, size: CellSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_borders(mut self, borders: Borders) -> Self {
+        self.borders = borders;
+        self
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_highlight(mut self, row: usize, col: usize) -> Self {
+        self.highlight = Some((row, col));
+        self
+    }
+}
+
+/// Seven-segment digits for large print, top to bottom; index 0 is an empty cell
+const GLYPHS: [[&str; 3]; 10] = [
+    ["   ", " · ", "   "],
+    ["   ", "  |", "  |"],
+    [" _ ", " _|", "|_ "],
+    [" _ ", " _|", " _|"],
+    ["   ", "|_|", "  |"],
+    [" _ ", "|_ ", " _|"],
+    [" _ ", "|_ ", "|_|"],
+    [" _ ", "  |", "  |"],
+    [" _ ", "|_|", "|_|"],
+    [" _ ", "|_|", " _|"],
+];
+
+const BOLD: &str = "1";
+const CYAN: &str = "36";
+const RED: &str = "1;31";
+const REVERSE: &str = "7";
+
+/// Draws `board`, one line per text row with a trailing newline. `puzzle`
+/// tells givens apart from digits filled in since; without it every digit
+/// counts as a given.
+pub fn render(board: &Board, puzzle: Option<&Board>, options: &TermOptions) -> String {
+    let mut conflicting = [false; 81];
+    for conflict in validation::conflicts(board) {
+        for (row, col) in conflict.cells {
+            conflicting[row * 9 + col] = true;
+        }
+    }
+    let (lines_per_cell, cell_width) = match options.size {
+        CellSize::Normal => (1, 1),
+        CellSize::Large => (3, 3),
+    };
+    let segment = (cell_width + 1) * 3 + 1;
+    let (vertical, horizontal) = match options.borders {
+        Borders::Unicode => ("│", "─"),
+        Borders::Ascii => ("|", "-"),
+    };
+    let rule = |corners: [&str; 4]| {
+        let line = horizontal.repeat(segment);
+        format!("{}{}{}{}{}{}{}\n", corners[0], line, corners[1], line, corners[2], line, corners[3])
+    };
+    let (top, middle, bottom) = match options.borders {
+        Borders::Unicode => (["┌", "┬", "┬", "┐"], ["├", "┼", "┼", "┤"], ["└", "┴", "┴", "┘"]),
+        Borders::Ascii => (["+"; 4], ["+"; 4], ["+"; 4]),
+    };
+
+    let mut out = rule(top);
+    for row in 0..9 {
+        for line in 0..lines_per_cell {
+            out.push_str(vertical);
+            for col in 0..9 {
+                let value = board.get(row, col);
+                let highlighted = options.highlight == Some((row, col));
+                // Without color, markers beside the cell on its middle line stand in
+                let marker = match (options.color || line != lines_per_cell / 2, highlighted) {
+                    (true, _) => ' ',
+                    (false, true) => '>',
+                    (false, false) if conflicting[row * 9 + col] => '!',
+                    (false, false) => ' ',
+                };
+                out.push(marker);
+                let text = match options.size {
+                    CellSize::Normal if value == 0 => "·",
+                    CellSize::Normal => &"123456789"[value as usize - 1..value as usize],
+                    CellSize::Large => GLYPHS[value as usize][line],
+                };
+                let mut codes = Vec::new();
+                if options.color {
+                    if conflicting[row * 9 + col] {
+                        codes.push(RED);
+                    } else if value != 0 && puzzle.is_none_or(|puzzle| puzzle.get(row, col) != 0) {
+                        codes.push(BOLD);
+                    } else if value != 0 {
+                        codes.push(CYAN);
+                    }
+                    if highlighted {
+                        codes.push(REVERSE);
+                    }
+                }
+                if codes.is_empty() {
+                    out.push_str(text);
+                } else {
+                    out.push_str(&format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text));
+                }
+                if col % 3 == 2 {
+                    out.push(' ');
+                    out.push_str(vertical);
+                }
+            }
+            out.push('\n');
+        }
+        if row % 3 == 2 && row < 8 {
+            out.push_str(&rule(middle));
+        }
+    }
+    out.push_str(&rule(bottom));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    fn is_box_drawing(c: char) -> bool {
+        ('\u{2500}'..='\u{257F}').contains(&c)
+    }
+
+    #[test]
+    fn test_plain() {
+        let easy = fixture::get("easy").unwrap().board();
+        let drawn = render(&easy, None, &TermOptions::default());
+        let lines: Vec<&str> = drawn.lines().collect();
+        assert_eq!(lines.len(), 13);
+        assert_eq!(lines[0], "┌───────┬───────┬───────┐");
+        assert_eq!(lines[4], "├───────┼───────┼───────┤");
+        assert!(lines.iter().all(|line| line.chars().count() == 25));
+        let cells: String = drawn.chars().filter(|&c| c.is_ascii_digit() || c == '·').collect();
+        assert_eq!(cells.replace('·', "."), easy.to_line());
+
+        let ascii = render(&easy, None, &TermOptions::default().with_borders(Borders::Ascii).with_highlight(0, 2));
+        assert!(ascii.starts_with("+-------+-------+-------+\n") && !ascii.chars().any(is_box_drawing));
+        assert_eq!(ascii.matches('>').count(), 1);
+        assert_eq!(ascii.lines().nth(1).unwrap().chars().position(|c| c == '>'), Some(5));
+    }
+
+    #[test]
+    fn test_large() {
+        let mut board = Board::empty();
+        board.set(0, 0, 8);
+        board.set(8, 8, 1);
+        let drawn = render(&board, None, &TermOptions::default().with_size(CellSize::Large).with_highlight(4, 4));
+        let lines: Vec<&str> = drawn.lines().collect();
+        assert_eq!(lines.len(), 9 * 3 + 4);
+        assert!(lines.iter().all(|line| line.chars().count() == 3 * 13 + 4));
+        assert_eq!(&lines[1][3..7], "  _ ");
+        assert!(lines[2].starts_with("│ |_| ") && lines[3].starts_with("│ |_| "));
+        assert!(lines[lines.len() - 2].ends_with("  | │"));
+        // The marker sits on the middle line of the cell only
+        assert_eq!(drawn.matches('>').count(), 1);
+        assert!(lines[4 * 3 + 2 + 1].contains("> · "));
+    }
+
+    #[test]
+    fn test_color() {
+        let easy = fixture::get("easy").unwrap().board();
+        let mut board = easy.clone();
+        let (row, col) = (0..81).map(|cell| (cell / 9, cell % 9)).find(|&(r, c)| easy.get(r, c) == 0).unwrap();
+        let value = (1..=9).find(|&v| board.can_place(row, col, v)).unwrap();
+        board.set(row, col, value);
+        let options = TermOptions::default().with_color(true);
+        let drawn = render(&board, Some(&easy), &options);
+        let givens = easy.cells.iter().filter(|&&v| v != 0).count();
+        assert_eq!(drawn.matches("\x1b[1m").count(), givens);
+        assert_eq!(drawn.matches(&format!("\x1b[36m{}\x1b[0m", value)).count(), 1);
+        assert!(!drawn.contains("\x1b[1;31m"));
+
+        // Doubling a given in its row makes both red
+        let given = (0..9).find(|&c| easy.get(row, c) != 0).unwrap();
+        board.set(row, col, easy.get(row, given));
+        let drawn = render(&board, Some(&easy), &options.with_highlight(row, col));
+        assert_eq!(drawn.matches("\x1b[1;31m").count(), 1);
+        assert_eq!(drawn.matches("\x1b[1;31;7m").count(), 1);
+        assert!(!drawn.contains('>') && !drawn.contains('!'));
+
+        let plain = render(&board, Some(&easy), &TermOptions::default());
+        assert_eq!(plain.matches('!').count(), 2);
+        assert!(!plain.contains('\x1b'));
+    }
+}
@@ -0,0 +1,334 @@
+//! Recording and playback of a solver's decision sequence.
+//!
+//! A replay stores the puzzle and every decision made while solving it:
+//! logical steps, guesses made when logic runs out, and backtracks when a
+//! guess leads to a contradiction. Replays serialize to compact JSON and can be
+//! played back frame by frame.
+
+use crate::{
+    logic::{LogicSolver, Placement, SolveStep, Technique},
+    solver::SolveEvent,
+    Board, Result, SudokuError,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A placement or elimination encoded as `[row, col, value]`
+pub type CompactPlacement = [u8; 3];
+
+fn compact(p: &Placement) -> CompactPlacement {
+    [p.row as u8, p.col as u8, p.value]
+}
+
+fn expand(p: &CompactPlacement) -> Placement {
+    Placement { row: p[0] as usize, col: p[1] as usize, value: p[2] }
+}
+
+/// A single recorded solver decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "e")]
+pub enum ReplayEvent {
+    /// A logical deduction
+    #[serde(rename = "s")]
+    Step {
+        #[serde(rename = "t")]
+        technique: Technique,
+        #[serde(rename = "p", default, skip_serializing_if = "Vec::is_empty")]
+        placements: Vec<CompactPlacement>,
+        #[serde(rename = "x", default, skip_serializing_if = "Vec::is_empty")]
+        eliminations: Vec<CompactPlacement>,
+    },
+    /// A trial placement made when no technique applies
+    #[serde(rename = "g")]
+    Guess {
+        #[serde(rename = "p")]
+        placement: CompactPlacement,
+    },
+    /// Abandons the most recent guess and everything after it
+    #[serde(rename = "b")]
+    Backtrack,
+}
+
+impl ReplayEvent {
+    fn from_step(step: &SolveStep) -> Self {
+        ReplayEvent::Step {
+            technique: step.technique,
+            placements: step.placements.iter().map(compact).collect(),
+            eliminations: step.eliminations.iter().map(compact).collect(),
+        }
+    }
+}
+
+impl fmt::Display for ReplayEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayEvent::Step { technique, placements, eliminations } => {
+                write!(f, "{}", technique)?;
+                for p in placements {
+                    write!(f, " {}", expand(p))?;
+                }
+                if !eliminations.is_empty() {
+                    write!(f, " (eliminates")?;
+                    for e in eliminations {
+                        let e = expand(e);
+                        write!(f, " r{}c{}<>{}", e.row + 1, e.col + 1, e.value)?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            ReplayEvent::Guess { placement } => write!(f, "Guess {}", expand(placement)),
+            ReplayEvent::Backtrack => write!(f, "Backtrack"),
+        }
+    }
+}
+
+/// A recorded solve
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    /// The puzzle as an 81-character line
+    pub puzzle: String,
+    /// The solution reached, if any
+    pub solution: Option<String>,
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    /// Solves `puzzle` with logic plus backtracking, recording every decision
+    pub fn record(puzzle: &Board) -> Self {
+        let mut events = Vec::new();
+        let solution = search(LogicSolver::new(puzzle), &mut |event| {
+            events.push(match event {
+                SolveEvent::Step { step } => ReplayEvent::from_step(&step),
+                SolveEvent::Guess { row, col, value } => {
+                    ReplayEvent::Guess { placement: [row as u8, col as u8, value] }
+                }
+                SolveEvent::Backtrack => ReplayEvent::Backtrack,
+                // The search leaves reporting the outcome to its caller
+                SolveEvent::Solved { .. } | SolveEvent::Unsolvable => return true,
+            });
+            true
+        });
+        Self {
+            puzzle: puzzle.to_line(),
+            solution: solution.map(|b| b.to_line()),
+            events,
+        }
+    }
+
+    /// Number of guesses made during the solve
+    pub fn guesses(&self) -> usize {
+        self.events.iter().filter(|e| matches!(e, ReplayEvent::Guess { .. })).count()
+    }
+
+    /// Number of backtracks made during the solve
+    pub fn backtracks(&self) -> usize {
+        self.events.iter().filter(|e| matches!(e, ReplayEvent::Backtrack)).count()
+    }
+
+    /// Writes the replay as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Reads a replay previously written with `save`, checking that every
+    /// event names a real cell and digit
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let replay: Self = serde_json::from_str(&json)?;
+        replay.validate()?;
+        Ok(replay)
+    }
+
+    /// Checks that every placement, elimination and guess has a row and
+    /// column below 9 and a digit from 1 to 9
+    pub fn validate(&self) -> Result<()> {
+        for (i, event) in self.events.iter().enumerate() {
+            let (placements, eliminations): (&[CompactPlacement], &[CompactPlacement]) = match event {
+                ReplayEvent::Step { placements, eliminations, .. } => (placements, eliminations),
+                ReplayEvent::Guess { placement } => (std::slice::from_ref(placement), &[]),
+                ReplayEvent::Backtrack => (&[], &[]),
+            };
+            for &[row, col, value] in placements.iter().chain(eliminations) {
+                if row >= 9 || col >= 9 || !(1..=9).contains(&value) {
+                    return Err(SudokuError::ParseError(format!(
+                        "Replay event {} has an invalid placement [{}, {}, {}]",
+                        i + 1,
+                        row,
+                        col,
+                        value
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over the board state after each event, or an
+    /// error if the puzzle doesn't parse or an event fails `validate`
+    pub fn playback(&self) -> Result<Playback<'_>> {
+        self.validate()?;
+        Ok(Playback {
+            board: Board::parse(&self.puzzle)?,
+            guess_stack: Vec::new(),
+            events: self.events.iter(),
+        })
+    }
+}
+
+/// Iterator produced by `Replay::playback`
+pub struct Playback<'a> {
+    board: Board,
+    guess_stack: Vec<Board>,
+    events: std::slice::Iter<'a, ReplayEvent>,
+}
+
+impl<'a> Iterator for Playback<'a> {
+    type Item = (Board, &'a ReplayEvent);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.events.next()?;
+        match event {
+            ReplayEvent::Step { placements, .. } => {
+                for p in placements {
+                    self.board.set(p[0] as usize, p[1] as usize, p[2]);
+                }
+            }
+            ReplayEvent::Guess { placement } => {
+                self.guess_stack.push(self.board.clone());
+                self.board.set(placement[0] as usize, placement[1] as usize, placement[2]);
+            }
+            ReplayEvent::Backtrack => {
+                if let Some(board) = self.guess_stack.pop() {
+                    self.board = board;
+                }
+            }
+        }
+        Some((self.board.clone(), event))
+    }
+}
+
+/// Applies logic until stuck, then guesses on the most constrained cell,
+/// reporting every decision to `emit`. Gives up, returning None, as soon as
+/// `emit` returns false.
+pub(crate) fn search(mut solver: LogicSolver, emit: &mut dyn FnMut(SolveEvent) -> bool) -> Option<Board> {
+    loop {
+        if solver.has_contradiction() {
+            return None;
+        }
+        if solver.is_solved() {
+            return Some(solver.board().clone());
+        }
+        match solver.find_step() {
+            Some(step) => {
+                solver.apply(&step);
+                if !emit(SolveEvent::Step { step }) {
+                    return None;
+                }
+            }
+            None => break,
+        }
+    }
+
+    let (row, col) = (0..81)
+        .filter(|&i| solver.board().cells[i] == 0)
+        .min_by_key(|&i| solver.candidates(i / 9, i % 9).count_candidates())
+        .map(|i| (i / 9, i % 9))?;
+
+    for value in solver.candidates(row, col).iter_candidates() {
+        if !emit(SolveEvent::Guess { row, col, value }) {
+            return None;
+        }
+        let mut next = solver.clone();
+        next.place(row, col, value);
+        if let Some(solution) = search(next, emit) {
+            return Some(solution);
+        }
+        if !emit(SolveEvent::Backtrack) {
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_playback() {
+        // Two givens leave nothing for logic to work with, so this needs guessing
+        let puzzle = Board::parse(
+            "1...............................................................................2",
+        )
+        .unwrap();
+        let replay = Replay::record(&puzzle);
+        let solution = replay.solution.clone().expect("puzzle is solvable");
+        assert!(replay.guesses() > 0);
+
+        let last = replay.playback().unwrap().last().unwrap().0;
+        assert_eq!(last.to_line(), solution);
+    }
+
+    #[test]
+    fn test_backtracks_restore_board() {
+        let replay = Replay {
+            puzzle: ".".repeat(81),
+            solution: None,
+            events: vec![
+                ReplayEvent::Guess { placement: [0, 0, 1] },
+                ReplayEvent::Step { technique: Technique::NakedSingle, placements: vec![[0, 1, 2]], eliminations: vec![] },
+                ReplayEvent::Backtrack,
+            ],
+        };
+        let frames: Vec<_> = replay.playback().unwrap().collect();
+        assert_eq!(frames[1].0.get(0, 1), 2);
+        assert_eq!(frames[2].0, Board::empty());
+    }
+
+    #[test]
+    fn test_rejects_invalid_placements() {
+        let with = |event: ReplayEvent| Replay { puzzle: ".".repeat(81), solution: None, events: vec![event] };
+        let step = |placement| ReplayEvent::Step {
+            technique: Technique::NakedSingle,
+            placements: vec![placement],
+            eliminations: vec![],
+        };
+        for placement in [[9, 0, 1], [0, 9, 1], [8, 8, 10], [0, 0, 0]] {
+            assert!(with(step(placement)).playback().is_err(), "{:?}", placement);
+            assert!(with(ReplayEvent::Guess { placement }).validate().is_err(), "{:?}", placement);
+        }
+        let elimination = ReplayEvent::Step {
+            technique: Technique::NakedSingle,
+            placements: vec![],
+            eliminations: vec![[0, 200, 1]],
+        };
+        assert!(with(elimination).validate().is_err());
+        assert!(with(step([8, 8, 9])).playback().is_ok());
+
+        // Loading checks too, so a bad file fails before playback
+        let path = std::env::temp_dir().join(format!("sudoku-replay-{}.json", std::process::id()));
+        fs::write(&path, r#"{"puzzle":"","solution":null,"events":[{"e":"g","p":[81,0,1]}]}"#).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(loaded, Err(SudokuError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let puzzle = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let replay = Replay::record(&puzzle);
+        assert_eq!(replay.guesses(), 0);
+        let json = serde_json::to_string(&replay).unwrap();
+        assert!(json.contains(r#""e":"s""#));
+        let decoded: Replay = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, replay);
+    }
+}
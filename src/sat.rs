@@ -0,0 +1,310 @@
+//! SAT encoding and solver backend.
+//!
+//! A puzzle becomes a CNF formula over 729 variables, one per placement
+//! (`cell * 9 + digit - 1`, numbered from 1 in DIMACS): every cell holds at
+//! least and at most one digit, every digit appears at least and at most once
+//! per row, column and box, and every given is a unit clause. `Cnf::to_dimacs`
+//! writes the formula out for an external solver such as MiniSat; `solve`
+//! runs a built-in DPLL search over it, propagating units with two watched
+//! literals per clause and branching only on the at-least-one clauses. It
+//! depends on no SAT crate. Like `dlx`, it shares no code with the other
+//! solvers, so `verify` uses it as another cross-check.
+
+use crate::{logic::UNITS, Board};
+use std::fmt::Write;
+
+/// Number of variables: one per cell and digit
+pub const VARIABLES: usize = 729;
+
+/// A literal: variable `lit / 2`, negated when `lit` is odd
+type Lit = usize;
+
+fn positive(var: usize) -> Lit {
+    var * 2
+}
+
+fn negative(var: usize) -> Lit {
+    var * 2 + 1
+}
+
+/// A formula in conjunctive normal form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cnf {
+    clauses: Vec<Vec<Lit>>,
+}
+
+impl Cnf {
+    /// Encodes `board`, givens included
+    pub fn encode(board: &Board) -> Self {
+        let mut clauses = Vec::new();
+        let mut exactly_one = |vars: Vec<usize>| {
+            clauses.push(vars.iter().map(|&var| positive(var)).collect());
+            for (i, &a) in vars.iter().enumerate() {
+                for &b in &vars[i + 1..] {
+                    clauses.push(vec![negative(a), negative(b)]);
+                }
+            }
+        };
+        for cell in 0..81 {
+            exactly_one((0..9).map(|digit| cell * 9 + digit).collect());
+        }
+        for unit in UNITS.iter() {
+            for digit in 0..9 {
+                exactly_one(unit.iter().map(|&cell| cell * 9 + digit).collect());
+            }
+        }
+        for (cell, &value) in board.cells.iter().enumerate() {
+            if value != 0 {
+                clauses.push(vec![positive(cell * 9 + value as usize - 1)]);
+            }
+        }
+        Self { clauses }
+    }
+
+    pub fn clause_count(&self) -> usize {
+        self.clauses.len()
+    }
+
+    /// Writes the formula in DIMACS CNF format
+    pub fn to_dimacs(&self) -> String {
+        let mut out = format!("p cnf {} {}\n", VARIABLES, self.clauses.len());
+        for clause in &self.clauses {
+            for &lit in clause {
+                let var = (lit / 2 + 1) as i64;
+                let _ = write!(out, "{} ", if lit & 1 == 0 { var } else { -var });
+            }
+            out.push_str("0\n");
+        }
+        out
+    }
+}
+
+/// The value of `lit` under `values`, if its variable is assigned
+fn value(values: &[Option<bool>], lit: Lit) -> Option<bool> {
+    values[lit / 2].map(|value| value == (lit & 1 == 0))
+}
+
+/// DPLL search state, propagating with two watched literals per clause
+struct Search {
+    /// Clauses of two or more literals, each with its watched pair first.
+    /// Propagation reorders them, but never needs undoing on backtrack.
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching each literal, visited when it becomes false
+    watches: Vec<Vec<usize>>,
+    /// The at-least-one clauses, which the search branches on; the binary
+    /// at-most-one clauses are all settled by propagation
+    choices: Vec<usize>,
+    /// Value of each variable, if assigned
+    values: Vec<Option<bool>>,
+    /// Literals made true, in order, so they can be undone
+    trail: Vec<Lit>,
+}
+
+impl Search {
+    /// Sets up the search over every clause of `cnf` longer than one literal;
+    /// unit clauses are left to the caller to `assign`
+    fn new(cnf: &Cnf) -> Self {
+        let clauses: Vec<Vec<Lit>> = cnf.clauses.iter().filter(|clause| clause.len() > 1).cloned().collect();
+        let mut watches = vec![Vec::new(); VARIABLES * 2];
+        for (idx, clause) in clauses.iter().enumerate() {
+            watches[clause[0]].push(idx);
+            watches[clause[1]].push(idx);
+        }
+        let choices = (0..clauses.len()).filter(|&idx| clauses[idx].len() > 2).collect();
+        Self { clauses, watches, choices, values: vec![None; VARIABLES], trail: Vec::new() }
+    }
+
+    /// Makes `lit` true and propagates unit clauses. Returns false on a conflict.
+    fn assign(&mut self, lit: Lit) -> bool {
+        match value(&self.values, lit) {
+            Some(true) => return true,
+            Some(false) => return false,
+            None => {}
+        }
+        let mut head = self.trail.len();
+        self.values[lit / 2] = Some(lit & 1 == 0);
+        self.trail.push(lit);
+
+        while let Some(&assigned) = self.trail.get(head) {
+            head += 1;
+            // Only clauses watching the now-false literal can become unit
+            let falsified = assigned ^ 1;
+            let mut watching = std::mem::take(&mut self.watches[falsified]);
+            let mut conflict = false;
+            let mut i = 0;
+            while i < watching.len() {
+                let idx = watching[i];
+                let clause = &mut self.clauses[idx];
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                let other = clause[0];
+                if value(&self.values, other) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                // Move the watch to a literal that isn't false, if there is one
+                if let Some(k) = (2..clause.len()).find(|&k| value(&self.values, clause[k]) != Some(false)) {
+                    clause.swap(1, k);
+                    self.watches[clause[1]].push(idx);
+                    watching.swap_remove(i);
+                    continue;
+                }
+                if value(&self.values, other).is_some() {
+                    conflict = true;
+                    break;
+                }
+                self.values[other / 2] = Some(other & 1 == 0);
+                self.trail.push(other);
+                i += 1;
+            }
+            self.watches[falsified] = watching;
+            if conflict {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn undo(&mut self, trail_len: usize) {
+        for lit in self.trail.drain(trail_len..) {
+            self.values[lit / 2] = None;
+        }
+    }
+
+    /// The unsatisfied at-least-one clause with the fewest unassigned
+    /// literals, or None once all are satisfied
+    fn next_choice(&self) -> Option<usize> {
+        let mut best: Option<(usize, usize)> = None;
+        for &idx in &self.choices {
+            let mut open = 0;
+            for &lit in &self.clauses[idx] {
+                match value(&self.values, lit) {
+                    Some(true) => {
+                        open = usize::MAX;
+                        break;
+                    }
+                    Some(false) => {}
+                    None => open += 1,
+                }
+            }
+            if open < best.map_or(usize::MAX, |(_, fewest)| fewest) {
+                best = Some((idx, open));
+            }
+        }
+        best.map(|(idx, _)| idx)
+    }
+
+    /// Collects models into `found` until it holds `limit`. Returns true once it does.
+    fn search(&mut self, found: &mut Vec<Board>, limit: usize) -> bool {
+        let Some(idx) = self.next_choice() else {
+            found.push(self.board());
+            return found.len() >= limit;
+        };
+        // Propagation reorders the clause, so branch over a copy
+        let mut open = [0; 9];
+        let mut len = 0;
+        for &lit in &self.clauses[idx] {
+            if value(&self.values, lit).is_none() {
+                open[len] = lit;
+                len += 1;
+            }
+        }
+        let open = &open[..len];
+
+        // Branch on each literal in turn, with the ones before it false, so
+        // no model is found twice
+        let start = self.trail.len();
+        for (i, &lit) in open.iter().enumerate() {
+            let consistent = open[..i].iter().all(|&earlier| self.assign(earlier ^ 1)) && self.assign(lit);
+            if consistent && self.search(found, limit) {
+                return true;
+            }
+            self.undo(start);
+        }
+        false
+    }
+
+    /// The board of a model; variables left unassigned are false, which
+    /// every at-most-one clause allows
+    fn board(&self) -> Board {
+        let mut board = Board::empty();
+        for var in (0..VARIABLES).filter(|&var| self.values[var] == Some(true)) {
+            board.cells[var / 9] = (var % 9 + 1) as u8;
+        }
+        board
+    }
+}
+
+/// Collects up to `limit` solutions of `board`; none if its givens conflict
+pub fn solve(board: &Board, limit: usize) -> Vec<Board> {
+    let mut found = Vec::new();
+    if limit == 0 {
+        return found;
+    }
+    let cnf = Cnf::encode(board);
+    let mut search = Search::new(&cnf);
+    // Unit clauses first; a conflict among them means the givens clash
+    let units: Vec<Lit> = cnf.clauses.iter().filter(|clause| clause.len() == 1).map(|clause| clause[0]).collect();
+    if units.into_iter().all(|lit| search.assign(lit)) {
+        search.search(&mut found, limit);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture::{FixtureKind, FIXTURES};
+
+    #[test]
+    fn test_sat_fixtures() {
+        for fixture in FIXTURES {
+            let solutions = solve(&fixture.board(), 2);
+            let expected = match fixture.kind {
+                FixtureKind::Unique | FixtureKind::Solved => 1,
+                FixtureKind::MultipleSolutions => 2,
+                FixtureKind::NoSolution | FixtureKind::Conflicting => 0,
+            };
+            assert_eq!(solutions.len(), expected, "{}", fixture.name);
+            if let Some(grid) = fixture.grid() {
+                assert_eq!(solutions[0], grid.solution_board().unwrap(), "{}", fixture.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_enumerates_like_search() {
+        // Emptying two rows of a solved grid leaves a few solutions, and an
+        // empty grid more than any limit
+        let mut board = crate::providers::fixture::get("easy").unwrap().grid().unwrap().solution_board().unwrap();
+        for col in 0..9 {
+            board.set(7, col, 0);
+            board.set(8, col, 0);
+        }
+        let mut expected = crate::solver::enumerate_solutions(&board, 100);
+        let mut models = solve(&board, 100);
+        expected.sort_by_key(Board::to_line);
+        models.sort_by_key(Board::to_line);
+        assert_eq!(models, expected);
+
+        let models = solve(&Board::empty(), 500);
+        assert_eq!(models.len(), 500);
+        assert!(models.iter().all(crate::simd::SimdValidator::validate_solution));
+        let distinct: std::collections::HashSet<_> = models.iter().collect();
+        assert_eq!(distinct.len(), 500);
+    }
+
+    #[test]
+    fn test_dimacs() {
+        let mut board = Board::empty();
+        board.set(0, 0, 5);
+        let cnf = Cnf::encode(&board);
+        // 324 constraints, each one at-least clause and 36 at-most pairs, plus the given
+        assert_eq!(cnf.clause_count(), 324 * 37 + 1);
+        let dimacs = cnf.to_dimacs();
+        assert!(dimacs.starts_with(&format!("p cnf 729 {}\n", cnf.clause_count())));
+        assert!(dimacs.ends_with("\n5 0\n"));
+        assert!(dimacs.contains("\n-1 -2 0\n"));
+    }
+}
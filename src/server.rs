@@ -0,0 +1,868 @@
+//! HTTP server mode (`sudoku serve`).
+//!
+//! Serves puzzles as JSON:
+//! - `GET /puzzle` returns a board from the cache, API, or local generator;
+//!   `GET /puzzle?difficulty=hard` one from a pool of pre-generated puzzles
+//! - `GET /daily?difficulty=hard&date=2024-03-14` returns the puzzle of the day
+//! - `GET /health` returns 200 when the server is up
+//! - `GET /metrics` returns Prometheus metrics
+//! - `GET /solve/stream?puzzle=<81 chars>&delay_ms=200` upgrades to a WebSocket
+//!   and streams each logical `SolveStep` as a JSON message while solving
+//! - `POST /solve/batch` solves up to `MAX_BATCH_SIZE` puzzles, sent in at most
+//!   `MAX_BATCH_BODY` bytes, in parallel within a shared time budget, returning
+//!   partial boards for any it runs out on
+//! - `GET /p/<code>` opens a share link: the puzzle and its solution as JSON,
+//!   or as a page with the solution folded away when the client asks for HTML
+//! - `GET /openapi.json` returns the OpenAPI document for these endpoints
+//!
+//! `serve_with` puts every endpoint except `/health` behind an `AccessPolicy`
+//! (bearer-token API keys and per-caller rate limits).
+
+use crate::{access::{AccessPolicy, Denied}, api, explain, generator, logic::SolveStep, metrics, share, solver::{Budget, SolveEvent, Solver}, Board, Grid, GridMetadata, PuzzleSource, Result, SudokuError};
+use chrono::{NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use futures_util::{SinkExt, StreamExt};
+use hyper::header::{ACCEPT, AUTHORIZATION, CONNECTION, CONTENT_LENGTH, RETRY_AFTER, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE, WWW_AUTHENTICATE};
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::body::{Bytes, HttpBody};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::{handshake::derive_accept_key, protocol::Role, Message};
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, error, info};
+use utoipa::{OpenApi, ToSchema};
+
+/// OpenAPI document for the server, served at `/openapi.json`
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Sudoku API", description = "Puzzles, daily challenges, and solving over HTTP"),
+    paths(health, metrics_text, puzzle, daily, solve_stream, solve_batch, shared_puzzle, openapi),
+    components(schemas(
+        Grid, GridMetadata, PuzzleSource, BatchRequest, BatchResult, BatchResponse, SharedPuzzle, ErrorBody
+    ))
+)]
+pub struct ApiDoc;
+
+/// Address `serve` listens on when none is configured
+pub const DEFAULT_ADDR: &str = "127.0.0.1:3000";
+
+/// Most puzzles accepted by one `POST /solve/batch` request
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+/// Largest `POST /solve/batch` body read, checked before any of it is
+/// parsed; a full batch of 81-character puzzles takes under 100 KiB
+pub const MAX_BATCH_BODY: usize = 1 << 20;
+
+/// Time budget for a batch when the request doesn't set one
+const DEFAULT_BATCH_BUDGET: Duration = Duration::from_secs(10);
+
+/// Upper bound on the time budget a request may ask for
+const MAX_BATCH_BUDGET: Duration = Duration::from_secs(60);
+
+/// Longest pause `GET /solve/stream` allows between steps; a larger
+/// `delay_ms` is cut down to this
+const MAX_STREAM_DELAY: Duration = Duration::from_secs(5);
+
+/// Time spent solving a puzzle opened from a share link
+const SHARED_SOLVE_BUDGET: Duration = Duration::from_secs(2);
+
+/// Longest `GET /puzzle?difficulty=` waits when the pool has run dry
+const POOL_WAIT: Duration = Duration::from_secs(5);
+
+/// Puzzles kept ready for `GET /puzzle?difficulty=`, started on first use
+static POOL: Lazy<generator::GeneratorHandle> = Lazy::new(|| {
+    generator::spawn_pool(generator::PoolOptions::default()).expect("the default pool options are valid")
+});
+
+/// Body of `POST /solve/batch`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    /// Puzzles as 81-character strings
+    pub puzzles: Vec<String>,
+    /// Time budget for the whole batch, capped at 60 seconds (default 10)
+    pub time_budget_ms: Option<u64>,
+}
+
+/// Body of every error response
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+}
+
+/// Outcome of one puzzle in a batch
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchResult {
+    pub puzzle: String,
+    /// The solution as an 81-character string, if one was found
+    pub solution: Option<String>,
+    /// Why the puzzle wasn't solved
+    pub error: Option<String>,
+    /// As far as the search got before the budget ran out, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<String>,
+    /// True if the puzzle was proven, within the budget, to have one solution
+    pub unique: bool,
+    pub solve_time_us: u64,
+    pub nodes: u64,
+}
+
+/// Response to `POST /solve/batch`, with results in request order
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BatchResponse {
+    pub results: Vec<BatchResult>,
+    pub solved: usize,
+    pub duration_ms: u64,
+}
+
+/// Response to `GET /p/{code}`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SharedPuzzle {
+    pub code: String,
+    /// The puzzle as an 81-character string
+    pub puzzle: String,
+    /// The solution as an 81-character string, if one was found
+    pub solution: Option<String>,
+    /// True if the puzzle was proven to have one solution
+    pub unique: bool,
+    /// Why the puzzle wasn't solved
+    pub error: Option<String>,
+}
+
+/// Messages sent over the solve stream WebSocket
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage {
+    /// One logical deduction, with a plain-English explanation
+    Step { step: SolveStep, explanation: String },
+    /// A trial placement, made once logic runs out
+    Guess { row: usize, col: usize, value: u8 },
+    /// The most recent guess failed and was undone
+    Backtrack,
+    /// Sent last; `board` is the solution, or the puzzle when there is none
+    Done { solved: bool, board: String },
+}
+
+/// Runs the server until the process is stopped, open to everyone
+pub async fn serve(addr: SocketAddr) -> Result<()> {
+    serve_with(addr, AccessPolicy::open()).await
+}
+
+/// Runs the server until the process is stopped, admitting requests through `policy`
+pub async fn serve_with(addr: SocketAddr, policy: AccessPolicy) -> Result<()> {
+    let policy = Arc::new(policy);
+    let make_service = make_service_fn(move |conn: &AddrStream| {
+        let policy = policy.clone();
+        let client = conn.remote_addr().ip();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| handle_guarded(policy.clone(), client, req)))
+        }
+    });
+
+    info!("Listening on http://{}", addr);
+    hyper::Server::try_bind(&addr)
+        .map_err(|e| SudokuError::ApiError(e.to_string()))?
+        .serve(make_service)
+        .await
+        .map_err(|e| SudokuError::ApiError(e.to_string()))
+}
+
+/// Checks the request against `policy`, then routes it
+pub async fn handle_guarded(
+    policy: Arc<AccessPolicy>,
+    client: IpAddr,
+    req: Request<Body>,
+) -> std::result::Result<Response<Body>, Infallible> {
+    // Health checks come from load balancers that don't hold keys
+    if req.uri().path() != "/health" {
+        let token = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        match policy.check(token, &client.to_string()) {
+            Ok(()) => {}
+            Err(Denied::Unauthorized) => {
+                let mut response = error_response(StatusCode::UNAUTHORIZED, "missing or invalid API key");
+                response.headers_mut().insert(WWW_AUTHENTICATE, "Bearer".parse().expect("valid header"));
+                return Ok(response);
+            }
+            Err(Denied::RateLimited { retry_after }) => {
+                let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded");
+                let secs = retry_after.as_secs_f64().ceil() as u64;
+                response.headers_mut().insert(RETRY_AFTER, secs.into());
+                return Ok(response);
+            }
+        }
+    }
+    handle(req).await
+}
+
+/// Routes a single request
+#[tracing::instrument(
+    name = "request",
+    skip_all,
+    fields(method = %req.method(), path = %req.uri().path(), status = tracing::field::Empty)
+)]
+pub async fn handle(req: Request<Body>) -> std::result::Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/health") => health(),
+        (&Method::GET, "/metrics") => metrics_text(),
+        (&Method::GET, "/openapi.json") => openapi(),
+        (&Method::GET, "/puzzle") => puzzle(&req).await,
+        (&Method::GET, "/daily") => daily(&req).await,
+        (&Method::GET, "/solve/stream") => solve_stream(req),
+        (&Method::POST, "/solve/batch") => solve_batch(req).await,
+        (&Method::GET, path) if path.starts_with(share::LINK_PATH) => shared_puzzle(&req).await,
+        _ => error_response(StatusCode::NOT_FOUND, "not found"),
+    };
+    tracing::Span::current().record("status", response.status().as_u16());
+    Ok(response)
+}
+
+#[utoipa::path(get, path = "/health", responses((status = 200, description = "The server is up", body = String)))]
+fn health() -> Response<Body> {
+    Response::new(Body::from("ok"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String))
+)]
+fn metrics_text() -> Response<Body> {
+    Response::builder()
+        .header("content-type", "text/plain; version=0.0.4")
+        .body(Body::from(metrics::render()))
+        .unwrap_or_default()
+}
+
+#[utoipa::path(get, path = "/openapi.json", responses((status = 200, description = "This document")))]
+fn openapi() -> Response<Body> {
+    match ApiDoc::openapi().to_json() {
+        Ok(json) => Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(json))
+            .unwrap_or_default(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/puzzle",
+    params(
+        ("difficulty" = Option<String>, Query, description = "Easy, Medium, or Hard, served from pre-generated puzzles")
+    ),
+    responses(
+        (status = 200, description = "A puzzle from the cache, API, or local generator", body = Grid),
+        (status = 503, description = "No puzzle could be produced", body = ErrorBody)
+    )
+)]
+async fn puzzle(req: &Request<Body>) -> Response<Body> {
+    let fetched = match query_param(req, "difficulty") {
+        Some(difficulty) => {
+            let difficulty = generator::normalize_difficulty(&difficulty);
+            match tokio::task::spawn_blocking(move || POOL.take_timeout(difficulty, POOL_WAIT)).await {
+                Ok(taken) => taken,
+                Err(e) => Err(SudokuError::GenerationFailed(e.to_string())),
+            }
+        }
+        None => api::fetch_new_board().await,
+    };
+    match fetched {
+        Ok(grid) => {
+            metrics::PUZZLES_SERVED.with_label_values(&["puzzle"]).inc();
+            json_response(StatusCode::OK, &grid)
+        }
+        Err(e) => error_response(StatusCode::SERVICE_UNAVAILABLE, &e.report()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/daily",
+    params(
+        ("difficulty" = Option<String>, Query, description = "Easy, Medium, or Hard (default Medium)"),
+        ("date" = Option<String>, Query, description = "YYYY-MM-DD (default today, UTC)")
+    ),
+    responses(
+        (status = 200, description = "The puzzle of the day", body = Grid),
+        (status = 400, description = "Malformed date", body = ErrorBody)
+    )
+)]
+async fn daily(req: &Request<Body>) -> Response<Body> {
+    let difficulty = query_param(req, "difficulty").unwrap_or_else(|| "Medium".to_string());
+    let date = match query_param(req, "date") {
+        Some(date) => match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => return error_response(StatusCode::BAD_REQUEST, "date must be YYYY-MM-DD"),
+        },
+        None => Utc::now().date_naive(),
+    };
+
+    // Generation is CPU-bound, so keep it off the async workers
+    match tokio::task::spawn_blocking(move || generator::daily(date, &difficulty)).await {
+        Ok(Ok(grid)) => {
+            metrics::PUZZLES_SERVED.with_label_values(&["daily"]).inc();
+            json_response(StatusCode::OK, &grid)
+        }
+        Ok(Err(e)) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.report()),
+        Err(e) => {
+            error!("Daily generation task failed: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "generation failed")
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/solve/batch",
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Results in request order", body = BatchResponse),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 413, description = "More than 1000 puzzles, or a body over 1 MiB", body = ErrorBody)
+    )
+)]
+async fn solve_batch(req: Request<Body>) -> Response<Body> {
+    let body = match read_body(req, MAX_BATCH_BODY).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let request: BatchRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    if request.puzzles.len() > MAX_BATCH_SIZE {
+        let message = format!("at most {} puzzles per batch", MAX_BATCH_SIZE);
+        return error_response(StatusCode::PAYLOAD_TOO_LARGE, &message);
+    }
+    let budget = request
+        .time_budget_ms
+        .map_or(DEFAULT_BATCH_BUDGET, Duration::from_millis)
+        .min(MAX_BATCH_BUDGET);
+
+    // Solving is CPU-bound, so keep it off the async workers
+    match tokio::task::spawn_blocking(move || batch_solve(&request.puzzles, budget)).await {
+        Ok(response) => json_response(StatusCode::OK, &response),
+        Err(e) => {
+            error!("Batch solve task failed: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "batch solve failed")
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/p/{code}",
+    params(("code" = String, Path, description = "A share code from `sudoku link` or `sudoku share`")),
+    responses(
+        (status = 200, description = "The puzzle and its solution; a page if `Accept` asks for HTML", body = SharedPuzzle),
+        (status = 400, description = "Not a share code", body = ErrorBody)
+    )
+)]
+async fn shared_puzzle(req: &Request<Body>) -> Response<Body> {
+    let code = req.uri().path()[share::LINK_PATH.len()..].to_string();
+    let board = match share::decode(&code) {
+        Ok(board) => board,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.report()),
+    };
+    let wants_html = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+
+    // Solving is CPU-bound, so keep it off the async workers
+    match tokio::task::spawn_blocking(move || solve_shared(code, &board)).await {
+        Ok(shared) if wants_html => Response::builder()
+            .header("content-type", "text/html; charset=utf-8")
+            .body(Body::from(shared_page(&shared)))
+            .unwrap_or_default(),
+        Ok(shared) => json_response(StatusCode::OK, &shared),
+        Err(e) => {
+            error!("Shared puzzle solve task failed: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "solve failed")
+        }
+    }
+}
+
+fn solve_shared(code: String, board: &Board) -> SharedPuzzle {
+    let mut shared = SharedPuzzle { code, puzzle: board.to_line(), solution: None, unique: false, error: None };
+    let grid = Grid::from(board);
+    match Solver::new(grid).solve_bounded(Budget::time(SHARED_SOLVE_BUDGET)) {
+        Ok(bounded) if bounded.solved => {
+            shared.solution = Some(bounded.board.to_line());
+            shared.unique = bounded.unique == Some(true);
+        }
+        Ok(_) => shared.error = Some(SudokuError::SolveTimeout.to_string()),
+        Err(e) => shared.error = Some(e.report()),
+    }
+    shared
+}
+
+/// A page showing the shared puzzle, with the solution folded away
+fn shared_page(shared: &SharedPuzzle) -> String {
+    let solution = match (&shared.solution, &shared.error) {
+        (Some(solution), _) => format!("<details><summary>Solution</summary>{}</details>", html_grid(solution)),
+        (None, Some(error)) => format!("<p>{}</p>", error),
+        (None, None) => String::new(),
+    };
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Sudoku {}</title><style>",
+            "table{{border-collapse:collapse;margin:1em 0}}",
+            "td{{width:2em;height:2em;text-align:center;border:1px solid #999}}",
+            "td:nth-child(3n){{border-right:2px solid}}tr:nth-child(3n) td{{border-bottom:2px solid}}",
+            "</style></head>\n<body><h1>Sudoku</h1>{}{}</body></html>\n"
+        ),
+        shared.code,
+        html_grid(&shared.puzzle),
+        solution
+    )
+}
+
+fn html_grid(line: &str) -> String {
+    let mut html = String::from("<table>");
+    for row in line.as_bytes().chunks(9) {
+        html.push_str("<tr>");
+        for &cell in row {
+            html.push_str("<td>");
+            if cell != b'.' {
+                html.push(cell as char);
+            }
+            html.push_str("</td>");
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+/// Solves `puzzles` in parallel; puzzles still unsolved when `budget` runs out time out
+fn batch_solve(puzzles: &[String], budget: Duration) -> BatchResponse {
+    let start = Instant::now();
+    let deadline = start + budget;
+
+    let results: Vec<BatchResult> = puzzles
+        .par_iter()
+        .map(|puzzle| {
+            let mut result = BatchResult {
+                puzzle: puzzle.clone(),
+                solution: None,
+                error: None,
+                partial: None,
+                unique: false,
+                solve_time_us: 0,
+                nodes: 0,
+            };
+            let board = match Board::parse(puzzle) {
+                Ok(board) => board,
+                Err(e) => {
+                    result.error = Some(e.to_string());
+                    return result;
+                }
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                result.error = Some(SudokuError::SolveTimeout.to_string());
+                return result;
+            }
+
+            let grid = Grid::from(&board);
+            match Solver::new(grid).solve_bounded(Budget::time(remaining)) {
+                Ok(bounded) => {
+                    if bounded.solved {
+                        result.solution = Some(bounded.board.to_line());
+                    } else {
+                        result.error = Some(SudokuError::SolveTimeout.to_string());
+                        result.partial = Some(bounded.board.to_line());
+                    }
+                    result.unique = bounded.unique == Some(true);
+                    result.solve_time_us = bounded.stats.duration.as_micros() as u64;
+                    result.nodes = bounded.stats.nodes;
+                }
+                Err(e) => result.error = Some(e.to_string()),
+            }
+            result
+        })
+        .collect();
+
+    BatchResponse {
+        solved: results.iter().filter(|r| r.solution.is_some()).count(),
+        results,
+        duration_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// Accepts the WebSocket upgrade and streams the solve on a background task
+#[utoipa::path(
+    get,
+    path = "/solve/stream",
+    params(
+        ("puzzle" = String, Query, description = "The puzzle as an 81-character string"),
+        ("delay_ms" = Option<u64>, Query, description = "Pause between steps, at most 5000")
+    ),
+    responses(
+        (status = 101, description = "WebSocket of JSON messages: `{\"type\": \"step\", \"step\", \"explanation\"}` per deduction, `guess` and `backtrack` messages once logic runs out, then `{\"type\": \"done\", \"solved\", \"board\"}`"),
+        (status = 400, description = "Missing puzzle or upgrade headers", body = ErrorBody)
+    )
+)]
+fn solve_stream(req: Request<Body>) -> Response<Body> {
+    let puzzle = match query_param(&req, "puzzle").map(|p| Board::parse(&p)) {
+        Some(Ok(board)) => board,
+        Some(Err(e)) => return error_response(StatusCode::BAD_REQUEST, &e.report()),
+        None => return error_response(StatusCode::BAD_REQUEST, "puzzle is required"),
+    };
+    let delay = stream_delay(&req);
+
+    let is_websocket = req
+        .headers()
+        .get(UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    let accept = match req.headers().get(SEC_WEBSOCKET_KEY) {
+        Some(key) if is_websocket => derive_accept_key(key.as_bytes()),
+        _ => return error_response(StatusCode::BAD_REQUEST, "expected a WebSocket upgrade"),
+    };
+
+    tokio::spawn(async move {
+        match hyper::upgrade::on(req).await {
+            Ok(upgraded) => {
+                let ws = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                if let Err(e) = stream_solve(ws, &puzzle, delay).await {
+                    debug!("Solve stream ended early: {}", e);
+                }
+            }
+            Err(e) => error!("WebSocket upgrade failed: {}", e),
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(UPGRADE, "websocket")
+        .header(CONNECTION, "upgrade")
+        .header(SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap_or_default()
+}
+
+/// Sends each decision of `Solver::solve_stream` as it is made, pausing
+/// `delay` between them
+async fn stream_solve<S>(
+    mut ws: WebSocketStream<S>,
+    puzzle: &Board,
+    delay: Duration,
+) -> std::result::Result<(), tokio_tungstenite::tungstenite::Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let grid = Grid::from(puzzle);
+    let mut events = Box::pin(Solver::new(grid).solve_stream());
+    while let Some(event) = events.next().await {
+        let message = match event {
+            SolveEvent::Step { step } => StreamMessage::Step { explanation: explain::explain(&step), step },
+            SolveEvent::Guess { row, col, value } => StreamMessage::Guess { row, col, value },
+            SolveEvent::Backtrack => StreamMessage::Backtrack,
+            SolveEvent::Solved { board } => StreamMessage::Done { solved: true, board },
+            SolveEvent::Unsolvable => StreamMessage::Done { solved: false, board: puzzle.to_line() },
+        };
+        let last = matches!(message, StreamMessage::Done { .. });
+        ws.send(event_message(&message)).await?;
+        if !last && !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+    ws.close(None).await
+}
+
+fn event_message(event: &StreamMessage) -> Message {
+    // Events hold only plain data, so serialization can't fail
+    Message::Text(serde_json::to_string(event).unwrap_or_default())
+}
+
+/// Returns the value of a query-string parameter, if present
+/// The `delay_ms` pause between streamed steps, capped at `MAX_STREAM_DELAY`
+fn stream_delay(req: &Request<Body>) -> Duration {
+    query_param(req, "delay_ms")
+        .and_then(|ms| ms.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or_default()
+        .min(MAX_STREAM_DELAY)
+}
+
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    req.uri().query()?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| value.to_string())
+    })
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    match serde_json::to_vec(body) {
+        Ok(json) => Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Body::from(json))
+            .unwrap_or_default(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Reads a request body of at most `limit` bytes. A larger one is turned
+/// away with 413, by its `Content-Length` when it has one, and otherwise as
+/// soon as the chunks read so far pass the limit.
+async fn read_body(req: Request<Body>, limit: usize) -> std::result::Result<Bytes, Response<Body>> {
+    let too_large = || error_response(StatusCode::PAYLOAD_TOO_LARGE, &format!("the body is over {} bytes", limit));
+    let declared = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return Err(too_large());
+    }
+    let mut body = req.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| error_response(StatusCode::BAD_REQUEST, &e.to_string()))?;
+        if bytes.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(bytes))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    let body = serde_json::to_vec(&ErrorBody { error: message.to_string() }).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grid;
+
+    async fn get(uri: &str) -> (StatusCode, Vec<u8>) {
+        let req = Request::get(uri).body(Body::empty()).unwrap();
+        let response = handle(req).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_daily_endpoint() {
+        let (status, body) = get("/daily?difficulty=easy&date=2024-03-14").await;
+        assert_eq!(status, StatusCode::OK);
+        let grid: Grid = serde_json::from_slice(&body).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        assert_eq!(grid, generator::daily(date, "Easy").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_puzzle_by_difficulty() {
+        let (status, body) = get("/puzzle?difficulty=hard").await;
+        assert_eq!(status, StatusCode::OK);
+        let grid: Grid = serde_json::from_slice(&body).unwrap();
+        assert_eq!(grid.difficulty, "Hard");
+        assert_eq!(grid.metadata.source, Some(PuzzleSource::Generator));
+    }
+
+    #[tokio::test]
+    async fn test_bad_requests() {
+        assert_eq!(get("/daily?date=yesterday").await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(get("/nope").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get("/health").await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_solve_stream() {
+        let make_service = make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle)) });
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let puzzle = "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        let url = format!("ws://{}/solve/stream?puzzle={}", addr, puzzle);
+        let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        let mut events = Vec::new();
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            events.push(serde_json::from_str::<serde_json::Value>(&text).unwrap());
+        }
+        let (done, steps) = events.split_last().unwrap();
+        assert!(!steps.is_empty());
+        assert!(steps.iter().all(|e| e["type"] == "step" && e["explanation"].is_string()));
+        assert_eq!(done["type"], "done");
+        assert_eq!(done["solved"], true);
+
+        // Two givens leave nothing for logic, so the solve has to guess
+        let sparse = format!("ws://{}/solve/stream?puzzle=12{}", addr, ".".repeat(79));
+        let (mut ws, _) = tokio_tungstenite::connect_async(sparse).await.unwrap();
+        let mut types = Vec::new();
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            types.push(serde_json::from_str::<serde_json::Value>(&text).unwrap()["type"].as_str().unwrap().to_string());
+        }
+        assert!(types.iter().any(|t| t == "guess"));
+        assert_eq!(types.last().map(String::as_str), Some("done"));
+
+        // A plain GET without the upgrade headers is rejected
+        let plain = format!("/solve/stream?puzzle={}", puzzle);
+        assert_eq!(get(&plain).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_stream_delay_is_capped() {
+        let delay = |uri: &str| stream_delay(&Request::get(uri).body(Body::empty()).unwrap());
+        assert_eq!(delay("/solve/stream"), Duration::ZERO);
+        assert_eq!(delay("/solve/stream?delay_ms=200"), Duration::from_millis(200));
+        assert_eq!(delay("/solve/stream?delay_ms=86400000"), MAX_STREAM_DELAY);
+        assert_eq!(delay(&format!("/solve/stream?delay_ms={}", u64::MAX)), MAX_STREAM_DELAY);
+    }
+
+    async fn post(uri: &str, body: String) -> (StatusCode, Vec<u8>) {
+        let req = Request::post(uri).body(Body::from(body)).unwrap();
+        let response = handle(req).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_solve_batch() {
+        let body = serde_json::json!({
+            "puzzles": [
+                "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+                "not a puzzle",
+                "55..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+            ]
+        });
+        let (status, body) = post("/solve/batch", body.to_string()).await;
+        assert_eq!(status, StatusCode::OK);
+        let response: BatchResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.results.len(), 3);
+        assert_eq!(response.solved, 1);
+        let solution = response.results[0].solution.as_ref().unwrap();
+        assert!(solution.starts_with("534678912"));
+        assert!(response.results[0].unique);
+        assert!(response.results[1].error.is_some());
+        assert!(response.results[2].solution.is_none());
+
+        let too_many = serde_json::json!({ "puzzles": vec![""; MAX_BATCH_SIZE + 1] });
+        assert_eq!(post("/solve/batch", too_many.to_string()).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+        // An oversized body is refused before it is parsed, whether or not it
+        // says how long it is
+        let huge = format!("{{\"puzzles\": [\"{}\"]}}", ".".repeat(MAX_BATCH_BODY));
+        let declared = Request::post("/solve/batch").header(CONTENT_LENGTH, huge.len()).body(Body::from(huge.clone()));
+        assert_eq!(handle(declared.unwrap()).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in huge.into_bytes().chunks(4096) {
+                if sender.send_data(Bytes::copy_from_slice(chunk)).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let streamed = Request::post("/solve/batch").body(body).unwrap();
+        assert_eq!(handle(streamed).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(post("/solve/batch", "{".to_string()).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_shared_puzzle() {
+        let board = crate::providers::fixture::get("easy").unwrap().board();
+        let link = share::link("http://localhost:3000", &board);
+        let path = &link["http://localhost:3000".len()..];
+        let (status, body) = get(path).await;
+        assert_eq!(status, StatusCode::OK);
+        let shared: SharedPuzzle = serde_json::from_slice(&body).unwrap();
+        assert_eq!(shared.puzzle, board.to_line());
+        assert_eq!(shared.solution.map(|line| Board::parse(&line).unwrap()), crate::dlx::solve(&board, 1).pop());
+        assert!(shared.unique);
+
+        let req = Request::get(path).header(ACCEPT, "text/html,*/*").body(Body::empty()).unwrap();
+        let response = handle(req).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "text/html; charset=utf-8");
+        let page = String::from_utf8(hyper::body::to_bytes(response.into_body()).await.unwrap().to_vec()).unwrap();
+        assert_eq!(page.matches("<table>").count(), 2);
+        assert!(page.contains("<details>"));
+
+        assert_eq!(get("/p/not-a-code").await.0, StatusCode::BAD_REQUEST);
+        let conflicting = fixture_code("duplicate_in_row");
+        let shared: SharedPuzzle = serde_json::from_slice(&get(&format!("/p/{}", conflicting)).await.1).unwrap();
+        assert_eq!(shared.error.as_deref(), Some("Invalid Sudoku board"));
+    }
+
+    fn fixture_code(name: &str) -> String {
+        share::encode(&crate::providers::fixture::get(name).unwrap().board())
+    }
+
+    #[test]
+    fn test_batch_budget_exhausted() {
+        let puzzles = vec![".".repeat(81); 3];
+        let response = batch_solve(&puzzles, Duration::ZERO);
+        assert_eq!(response.solved, 0);
+        assert!(response.results.iter().all(|r| r.error.as_deref() == Some("Solve timed out")));
+    }
+
+    #[tokio::test]
+    async fn test_openapi_document() {
+        let (status, body) = get("/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let paths =
+            ["/health", "/metrics", "/puzzle", "/daily", "/solve/stream", "/solve/batch", "/p/{code}", "/openapi.json"];
+        for path in paths {
+            assert!(doc["paths"][path].is_object(), "missing {}", path);
+        }
+        assert!(doc["components"]["schemas"]["BatchResponse"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_access_policy() {
+        let policy = Arc::new(AccessPolicy::open().with_key("secret", Some(1)));
+        let client = IpAddr::from([127, 0, 0, 1]);
+        let request = |key: Option<&str>, uri: &str| {
+            let mut builder = Request::get(uri);
+            if let Some(key) = key {
+                builder = builder.header(AUTHORIZATION, format!("Bearer {}", key));
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+        let status = |req| async { handle_guarded(policy.clone(), client, req).await.unwrap() };
+
+        assert_eq!(status(request(None, "/health")).await.status(), StatusCode::OK);
+        let denied = status(request(None, "/metrics")).await;
+        assert_eq!(denied.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(denied.headers()[WWW_AUTHENTICATE], "Bearer");
+        assert_eq!(status(request(Some("nope"), "/metrics")).await.status(), StatusCode::UNAUTHORIZED);
+
+        assert_eq!(status(request(Some("secret"), "/metrics")).await.status(), StatusCode::OK);
+        let limited = status(request(Some("secret"), "/metrics")).await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(limited.headers().contains_key(RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        get("/daily?difficulty=easy&date=2024-03-14").await;
+        let (status, body) = get("/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        let text = String::from_utf8(body).unwrap();
+        assert!(text.contains("sudoku_puzzles_served_total{endpoint=\"daily\"}"), "{}", text);
+    }
+}
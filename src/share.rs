@@ -0,0 +1,152 @@
+//! Compact share codes for moving puzzles between devices.
+//!
+//! A share code is URL-safe base64 (without padding) of an 81-bit mask of
+//! the given cells in reading order, followed by each given digit minus one
+//! in four bits. A typical 25-clue puzzle takes 32 characters instead of 81,
+//! and any puzzle fits a small QR code.
+//!
+//! Links put the code in the path, `http://host/p/<code>`, where `serve`
+//! answers with the puzzle and its solution. A `#` fragment would read more
+//! naturally but never reaches the server.
+
+use crate::{qr::QrCode, Board, Result, SudokuError};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Bytes in the mask of given cells
+const MASK_BYTES: usize = 11;
+
+/// Path under which `serve` answers share links
+pub const LINK_PATH: &str = "/p/";
+
+/// The share code for `board`
+pub fn encode(board: &Board) -> String {
+    let mut bytes = vec![0u8; MASK_BYTES];
+    let mut digits = Vec::new();
+    for (cell, &value) in board.cells.iter().enumerate() {
+        if value != 0 {
+            bytes[cell / 8] |= 0x80 >> (cell % 8);
+            digits.push(value - 1);
+        }
+    }
+    bytes.extend(digits.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)));
+    to_base64(&bytes)
+}
+
+/// Reads a share code back into a puzzle. The givens are not checked for conflicts.
+pub fn decode(code: &str) -> Result<Board> {
+    let code = code.trim();
+    let invalid = || SudokuError::ParseError(format!("not a share code: '{}'", code));
+    let bytes = from_base64(code).ok_or_else(invalid)?;
+    if bytes.len() < MASK_BYTES {
+        return Err(invalid());
+    }
+    let (mask, digits) = bytes.split_at(MASK_BYTES);
+    let mut board = Board::empty();
+    let mut given = 0;
+    for cell in 0..81 {
+        if mask[cell / 8] & (0x80 >> (cell % 8)) != 0 {
+            let byte = digits.get(given / 2).ok_or_else(invalid)?;
+            let digit = if given % 2 == 0 { byte >> 4 } else { byte & 0x0F };
+            if digit > 8 {
+                return Err(invalid());
+            }
+            board.cells[cell] = digit + 1;
+            given += 1;
+        }
+    }
+    // Trailing bytes, stray mask bits or padding would all encode differently
+    if encode(&board) != code {
+        return Err(invalid());
+    }
+    Ok(board)
+}
+
+/// A link to `board` on the server at `base_url`, such as `http://127.0.0.1:3000`
+pub fn link(base_url: &str, board: &Board) -> String {
+    format!("{}{}{}", base_url.trim_end_matches('/'), LINK_PATH, encode(board))
+}
+
+/// Reads a puzzle from a share code or from a link ending in one
+pub fn decode_link(input: &str) -> Result<Board> {
+    let input = input.trim();
+    decode(input.rsplit(['/', '#']).next().unwrap_or(input))
+}
+
+/// A QR code holding the share code for `board`
+pub fn qr_code(board: &Board) -> QrCode {
+    QrCode::encode(encode(board).as_bytes()).expect("share codes are at most 70 bytes")
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+        }
+    }
+    out
+}
+
+fn from_base64(code: &str) -> Option<Vec<u8>> {
+    let values = code
+        .bytes()
+        .map(|c| ALPHABET.iter().position(|&a| a == c).map(|v| v as u32))
+        .collect::<Option<Vec<_>>>()?;
+    let mut bytes = Vec::with_capacity(values.len() * 3 / 4);
+    for chunk in values.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let n = chunk.iter().enumerate().fold(0, |n, (i, &v)| n | v << (18 - 6 * i));
+        bytes.extend((0..chunk.len() - 1).map(|i| (n >> (16 - 8 * i)) as u8));
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture::FIXTURES;
+
+    #[test]
+    fn test_round_trip() {
+        for fixture in FIXTURES {
+            let board = fixture.board();
+            let code = encode(&board);
+            assert_eq!(decode(&code).unwrap(), board, "{}", fixture.name);
+            assert!(code.len() < 81, "{}", fixture.name);
+        }
+        assert_eq!(encode(&Board::empty()), "AAAAAAAAAAAAAAA");
+        let easy = crate::providers::fixture::get("easy").unwrap().board();
+        let givens = easy.cells.iter().filter(|&&v| v != 0).count();
+        assert_eq!(encode(&easy).len(), ((MASK_BYTES + givens.div_ceil(2)) * 4).div_ceil(3));
+        assert_eq!(qr_code(&easy).version(), 3);
+    }
+
+    #[test]
+    fn test_links() {
+        let board = crate::providers::fixture::get("hard").unwrap().board();
+        let link = link("http://localhost:3000/", &board);
+        assert_eq!(link, format!("http://localhost:3000/p/{}", encode(&board)));
+        assert_eq!(decode_link(&link).unwrap(), board);
+        assert_eq!(decode_link(&encode(&board)).unwrap(), board);
+        assert_eq!(decode_link(&format!("https://example.com/#{}", encode(&board))).unwrap(), board);
+        assert!(decode_link("http://localhost:3000/p/").is_err());
+    }
+
+    #[test]
+    fn test_invalid_codes() {
+        let code = encode(&crate::providers::fixture::get("easy").unwrap().board());
+        assert!(decode(&format!("  {}\n", code)).is_ok());
+        for bad in ["", "A", "AAAA", "not a code!", &code[..code.len() - 2], &format!("{}AA", code)] {
+            assert!(matches!(decode(bad), Err(SudokuError::ParseError(_))), "accepted {:?}", bad);
+        }
+        // A digit nibble of 9 or more
+        let mut bytes = vec![0u8; MASK_BYTES];
+        bytes[0] = 0x80;
+        bytes.push(0x90);
+        assert!(decode(&to_base64(&bytes)).is_err());
+    }
+}
@@ -1,364 +1,211 @@
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-use std::arch::x86_64::*;
+// Portable SIMD built on the `wide` crate. `wide` picks SSE2, NEON, or
+// SIMD128 at compile time and falls back to plain arrays elsewhere, so there is
+// a single implementation for every target. The one exception is the batch
+// check behind `validate_many`, which wasm32 builds with SIMD128 enabled run
+// on `std::arch::wasm32` intrinsics directly.
+//
+// Everything public here is safe to call on any target: vector types stay
+// private, and callers exchange plain `Board`s and `CandidateSet`s.
+
+use wide::u16x8;
+
+use crate::{logic::UNITS, Board, CandidateSet};
+
+/// Whether the vector code paths are in use.
+///
+/// Decided at compile time: true when the `simd` feature is on and `wide` has
+/// a vector backend for the target. A binary built for a vector extension
+/// can't start on a CPU without it, so there is nothing to detect at runtime.
+#[inline]
+pub const fn has_simd_support() -> bool {
+    cfg!(all(
+        feature = "simd",
+        any(
+            target_feature = "sse2",
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128"),
+        )
+    ))
+}
 
-#[cfg(target_arch = "aarch64")]
-use std::arch::aarch64::*;
+/// Implementation used for candidate checks during search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Simd,
+    Scalar,
+}
 
-use crate::{Board, CandidateSet};
+const BACKEND: Backend = if has_simd_support() { Backend::Simd } else { Backend::Scalar };
 
-/// Feature detection for SIMD support
+/// The backend this build uses, fixed at compile time by `has_simd_support`
 #[inline]
-pub fn has_simd_support() -> bool {
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    {
-        is_x86_feature_detected!("sse2")
-    }
-    #[cfg(target_arch = "aarch64")]
-    {
-        // NEON is always available on AArch64
-        true
-    }
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    {
-        false
+pub fn backend() -> Backend {
+    BACKEND
+}
+
+/// Returns true if any bit in the vector is set
+#[inline]
+fn any_bits_set(v: u16x8) -> bool {
+    v != u16x8::splat(0)
+}
+
+/// Packs up to nine cell values into a vector of candidate bits.
+///
+/// Lane `i` holds the bit for cell `i`; the ninth cell shares lane 7, which is
+/// fine for membership tests since only the union of the lanes matters.
+#[inline]
+fn unit_mask(values: impl Iterator<Item = u8>) -> u16x8 {
+    let mut lanes = [0u16; 8];
+    for (i, value) in values.enumerate() {
+        if value != 0 {
+            lanes[i.min(7)] |= 1 << (value - 1);
+        }
     }
+    u16x8::new(lanes)
 }
 
 /// SIMD-optimized candidate set using 128-bit operations
 #[derive(Debug, Clone, Copy)]
 pub struct SimdCandidateSet {
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    candidates: __m128i,
-    #[cfg(target_arch = "aarch64")]
-    candidates: uint16x8_t,
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    candidates: u16,
+    candidates: u16x8,
 }
 
-impl SimdCandidateSet {
-    /// Creates a new SIMD candidate set with all candidates enabled
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    #[inline]
-    pub unsafe fn new() -> Self {
-        Self {
-            candidates: _mm_set1_epi16(0x1FF) // All candidates available (9 bits set)
-        }
-    }
-
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    #[inline]
-    pub unsafe fn new() -> Self {
-        Self {
-            candidates: vdupq_n_u16(0x1FF) // All candidates available (9 bits set)
-        }
+impl Default for SimdCandidateSet {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+impl SimdCandidateSet {
+    /// Creates a new SIMD candidate set with all candidates enabled
     #[inline]
     pub fn new() -> Self {
         Self {
-            candidates: 0x1FF
+            candidates: u16x8::splat(0x1FF) // All candidates available (9 bits set)
         }
     }
 
     /// Removes multiple candidates at once using SIMD operations
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
     #[inline]
-    pub unsafe fn remove_candidates(&mut self, values: __m128i) {
-        self.candidates = _mm_andnot_si128(values, self.candidates);
-    }
-
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    #[inline]
-    pub unsafe fn remove_candidates(&mut self, values: uint16x8_t) {
-        self.candidates = vbicq_u16(self.candidates, values);
-    }
-
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    #[inline]
-    pub fn remove_candidates(&mut self, values: u16) {
-        self.candidates &= !values;
+    pub fn remove_candidates(&mut self, values: CandidateSet) {
+        self.candidates &= !u16x8::splat(values.0);
     }
 
     /// Checks for the presence of multiple candidates simultaneously
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
     #[inline]
-    pub unsafe fn has_candidates(&self, values: __m128i) -> bool {
-        let result = _mm_and_si128(self.candidates, values);
-        _mm_movemask_epi8(result) != 0
-    }
-
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    #[inline]
-    pub unsafe fn has_candidates(&self, values: uint16x8_t) -> bool {
-        let result = vandq_u16(self.candidates, values);
-        let _zero = vdupq_n_u16(0);
-        vmaxvq_u16(result) != 0
-    }
-
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    #[inline]
-    pub fn has_candidates(&self, values: u16) -> bool {
-        self.candidates & values != 0
+    pub fn has_candidates(&self, values: CandidateSet) -> bool {
+        any_bits_set(self.candidates & u16x8::splat(values.0))
     }
 
     /// Converts a regular CandidateSet to SIMD format
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    #[inline]
-    pub unsafe fn from_candidate_set(set: CandidateSet) -> Self {
-        Self {
-            candidates: _mm_set1_epi16(set.0 as i16)
-        }
-    }
-
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    #[inline]
-    pub unsafe fn from_candidate_set(set: CandidateSet) -> Self {
-        Self {
-            candidates: vdupq_n_u16(set.0)
-        }
-    }
-
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
     #[inline]
     pub fn from_candidate_set(set: CandidateSet) -> Self {
         Self {
-            candidates: set.0
+            candidates: u16x8::splat(set.0)
         }
     }
 
     /// Converts back to a regular CandidateSet
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    #[inline]
-    pub unsafe fn to_candidate_set(&self) -> CandidateSet {
-        let value = _mm_extract_epi16(self.candidates, 0) as u16;
-        CandidateSet(value)
-    }
-
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    #[inline]
-    pub unsafe fn to_candidate_set(&self) -> CandidateSet {
-        let value = vgetq_lane_u16(self.candidates, 0);
-        CandidateSet(value)
-    }
-
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
     #[inline]
     pub fn to_candidate_set(&self) -> CandidateSet {
-        CandidateSet(self.candidates)
+        CandidateSet(self.candidates.as_array_ref()[0])
     }
 }
 
 /// SIMD-optimized board representation for efficient validation
 #[derive(Debug)]
 pub struct SimdBoard {
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    rows: [__m128i; 9],
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    last_elements: [u16; 9],
-    #[cfg(target_arch = "aarch64")]
-    rows: [uint16x8_t; 9],
-    #[cfg(target_arch = "aarch64")]
-    last_elements: [u16; 9],
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    rows: [[u8; 9]; 9],
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    rows: [u16x8; 9],
     last_elements: [u16; 9],
 }
 
 impl SimdBoard {
     /// Creates a new SIMD board from a regular board
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    pub unsafe fn from_board(board: &Board) -> Self {
-        let mut simd_rows = [_mm_setzero_si128(); 9];
+    pub fn from_board(board: &Board) -> Self {
+        let mut rows = [u16x8::splat(0); 9];
         let mut last_elements = [0u16; 9];
-        
-        for row in 0..9 {
-            let row_data: [i16; 8] = board.cells[row * 9..row * 9 + 8]
-                .iter()
-                .map(|&x| x as i16)
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap();
-            
-            simd_rows[row] = _mm_loadu_si128(row_data.as_ptr() as *const __m128i);
-            last_elements[row] = board.cells[row * 9 + 8] as u16;
-        }
-        
-        Self { rows: simd_rows, last_elements }
-    }
 
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    pub unsafe fn from_board(board: &Board) -> Self {
-        let mut simd_rows = [vdupq_n_u16(0); 9];
-        let mut last_elements = [0u16; 9];
-        
         for row in 0..9 {
             let mut row_data = [0u16; 8];
             for col in 0..8 {
                 row_data[col] = board.cells[row * 9 + col] as u16;
             }
-            simd_rows[row] = vld1q_u16(row_data.as_ptr());
+            rows[row] = u16x8::new(row_data);
             last_elements[row] = board.cells[row * 9 + 8] as u16;
         }
-        
-        Self { 
-            rows: simd_rows,
-            last_elements,
-        }
-    }
 
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    pub fn from_board(board: &Board) -> Self {
-        let mut rows = [[0; 9]; 9];
-        let mut last_elements = [0u16; 9];
-        for row in 0..9 {
-            for col in 0..9 {
-                rows[row][col] = board.cells[row * 9 + col];
-            }
-            last_elements[row] = board.cells[row * 9 + 8] as u16;
-        }
         Self { rows, last_elements }
     }
 
-    /// Validates a row using SIMD operations
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
+    /// Validates a row: every cell holds a digit in 1..=9, none repeated
     #[inline]
-    pub unsafe fn is_valid_row(&self, row: usize) -> bool {
+    pub fn is_valid_row(&self, row: usize) -> bool {
         let row_data = self.rows[row];
-        let last_value = self.last_elements[row];
-        let mut seen = [false; 10];
-        
-        // Check first 8 elements
-        for i in 0..8 {
-            let value = _mm_extract_epi16(row_data, i) as usize;
-            if value == 0 || value > 9 || seen[value] {
+        let mut seen = 0u16;
+        for &value in row_data.as_array_ref().iter().chain(std::iter::once(&self.last_elements[row])) {
+            if value == 0 || value > 9 {
                 return false;
             }
-            seen[value] = true;
-        }
-        
-        // Check the 9th element
-        let value = last_value as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        
-        true
-    }
-
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    #[inline]
-    pub unsafe fn is_valid_row(&self, row: usize) -> bool {
-        let row_data = self.rows[row];
-        let last_value = self.last_elements[row];
-        let mut seen = [false; 10];
-        
-        // Check each element with constant indices
-        let value = vgetq_lane_u16(row_data, 0) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-
-        let value = vgetq_lane_u16(row_data, 1) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-
-        let value = vgetq_lane_u16(row_data, 2) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-
-        let value = vgetq_lane_u16(row_data, 3) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-
-        let value = vgetq_lane_u16(row_data, 4) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-
-        let value = vgetq_lane_u16(row_data, 5) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-
-        let value = vgetq_lane_u16(row_data, 6) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-
-        let value = vgetq_lane_u16(row_data, 7) as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        seen[value] = true;
-        
-        // Check the 9th element
-        let value = last_value as usize;
-        if value == 0 || value > 9 || seen[value] {
-            return false;
-        }
-        
-        true
-    }
-
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    #[inline]
-    pub fn is_valid_row(&self, row: usize) -> bool {
-        let mut seen = [false; 10];
-        for &value in &self.rows[row] {
-            if value == 0 || seen[value as usize] {
+            let bit = 1 << (value - 1);
+            if seen & bit != 0 {
                 return false;
             }
-            seen[value as usize] = true;
+            seen |= bit;
         }
+
         true
     }
 
     /// Validates multiple rows simultaneously
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    pub unsafe fn validate_multiple_rows(&self, start_row: usize, count: usize) -> bool {
+    pub fn validate_multiple_rows(&self, start_row: usize, count: usize) -> bool {
         (start_row..start_row + count)
             .all(|row| self.is_valid_row(row))
     }
+}
 
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    pub unsafe fn validate_multiple_rows(&self, start_row: usize, count: usize) -> bool {
-        (start_row..start_row + count)
-            .all(|row| self.is_valid_row(row))
-    }
+/// For eight interleaved boards, where `cells[i]` holds the digit bit of cell
+/// `i` in each lane, returns a lane of all ones for each board whose units
+/// all contain every digit, and zero otherwise
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+fn units_complete(cells: &[[u16; 8]; 81]) -> [u16; 8] {
+    let all_digits = u16x8::splat(0x1FF);
+    let mut valid = u16x8::splat(u16::MAX);
+    for unit in UNITS.iter() {
+        let seen = unit
+            .iter()
+            .fold(u16x8::splat(0), |acc, &cell| acc | u16x8::new(cells[cell]));
+        valid &= seen.cmp_eq(all_digits);
+    }
+    *valid.as_array_ref()
+}
 
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    pub fn validate_multiple_rows(&self, start_row: usize, count: usize) -> bool {
-        (start_row..start_row + count)
-            .all(|row| self.is_valid_row(row))
-    }
+/// `units_complete` written directly against `std::arch::wasm32`, so browser
+/// builds with SIMD128 get v128 instructions whatever `wide` lowers to
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn units_complete(cells: &[[u16; 8]; 81]) -> [u16; 8] {
+    use std::arch::wasm32 as arch;
+
+    let load = |lanes: &[u16; 8]| {
+        arch::u16x8(lanes[0], lanes[1], lanes[2], lanes[3], lanes[4], lanes[5], lanes[6], lanes[7])
+    };
+    let all_digits = arch::u16x8_splat(0x1FF);
+    let mut valid = arch::u16x8_splat(u16::MAX);
+    for unit in UNITS.iter() {
+        let seen = unit
+            .iter()
+            .fold(arch::u16x8_splat(0), |acc, &cell| arch::v128_or(acc, load(&cells[cell])));
+        valid = arch::v128_and(valid, arch::u16x8_eq(seen, all_digits));
+    }
+    [
+        arch::u16x8_extract_lane::<0>(valid),
+        arch::u16x8_extract_lane::<1>(valid),
+        arch::u16x8_extract_lane::<2>(valid),
+        arch::u16x8_extract_lane::<3>(valid),
+        arch::u16x8_extract_lane::<4>(valid),
+        arch::u16x8_extract_lane::<5>(valid),
+        arch::u16x8_extract_lane::<6>(valid),
+        arch::u16x8_extract_lane::<7>(valid),
+    ]
 }
 
 /// Provides optimized SIMD operations for board validation
@@ -367,59 +214,83 @@ pub struct SimdValidator;
 impl SimdValidator {
     /// Validates a solution using SIMD operations where available
     pub fn validate_solution(board: &Board) -> bool {
-        if has_simd_support() {
-            #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-            unsafe {
-                let simd_board = SimdBoard::from_board(board);
-                
-                // Validate rows
-                for row in 0..9 {
-                    if !simd_board.is_valid_row(row) {
-                        return false;
-                    }
+        if !has_simd_support() {
+            return Self::validate_solution_scalar(board);
+        }
+
+        let simd_board = SimdBoard::from_board(board);
+
+        // Validate rows
+        for row in 0..9 {
+            if !simd_board.is_valid_row(row) {
+                return false;
+            }
+        }
+
+        // Validate columns
+        for col in 0..9 {
+            let mut seen = [false; 10];
+            for row in 0..9 {
+                let value = board.get(row, col);
+                if value == 0 || value > 9 || seen[value as usize] {
+                    return false;
                 }
-                
-                // Validate columns
-                for col in 0..9 {
-                    let mut seen = [false; 10];
-                    for row in 0..9 {
-                        let value = board.get(row, col);
+                seen[value as usize] = true;
+            }
+        }
+
+        // Validate boxes
+        for box_row in 0..3 {
+            for box_col in 0..3 {
+                let mut seen = [false; 10];
+                for i in 0..3 {
+                    for j in 0..3 {
+                        let value = board.get(box_row * 3 + i, box_col * 3 + j);
                         if value == 0 || value > 9 || seen[value as usize] {
                             return false;
                         }
                         seen[value as usize] = true;
                     }
                 }
-                
-                // Validate boxes
-                for box_row in 0..3 {
-                    for box_col in 0..3 {
-                        let mut seen = [false; 10];
-                        for i in 0..3 {
-                            for j in 0..3 {
-                                let value = board.get(box_row * 3 + i, box_col * 3 + j);
-                                if value == 0 || value > 9 || seen[value as usize] {
-                                    return false;
-                                }
-                                seen[value as usize] = true;
-                            }
-                        }
+            }
+        }
+
+        true
+    }
+
+    /// Validates many solutions at once, one board per vector lane.
+    ///
+    /// Boards are processed eight at a time: each cell is turned into a digit
+    /// bit, and a unit is valid when the OR of its nine cells has all nine bits
+    /// set. Empty cells contribute no bit, so incomplete boards fail. Without
+    /// SIMD support each board goes through the scalar validator instead.
+    pub fn validate_many(boards: &[Board]) -> Vec<bool> {
+        if !has_simd_support() {
+            return boards.iter().map(Self::validate_solution_scalar).collect();
+        }
+        let mut results = Vec::with_capacity(boards.len());
+
+        for chunk in boards.chunks(8) {
+            // Interleave the chunk so cells[i] holds cell i of every board
+            let mut cells = [[0u16; 8]; 81];
+            for (lane, board) in chunk.iter().enumerate() {
+                for (i, &value) in board.cells.iter().enumerate() {
+                    if (1..=9).contains(&value) {
+                        cells[i][lane] = 1 << (value - 1);
                     }
                 }
-                
-                true
             }
-            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-            {
-                Self::validate_solution_fallback(board)
-            }
-        } else {
-            Self::validate_solution_fallback(board)
+
+            let valid = units_complete(&cells);
+            results.extend(valid[..chunk.len()].iter().map(|&lane| lane != 0));
         }
+
+        results
     }
 
-    /// Non-SIMD fallback implementation for validation
-    fn validate_solution_fallback(board: &Board) -> bool {
+    /// Validates a solution without vector code: the fallback on targets
+    /// without SIMD, and an independent check on `validate_solution`
+    pub fn validate_solution_scalar(board: &Board) -> bool {
         // Check rows
         for row in 0..9 {
             let mut seen = [false; 10];
@@ -467,74 +338,25 @@ impl SimdValidator {
 /// SIMD-optimized board validation and candidate checking
 #[derive(Debug, Clone)]
 pub struct SimdSolver {
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    row_masks: [__m128i; 9],
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    col_masks: [__m128i; 9],
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    box_masks: [__m128i; 9],
-    #[cfg(target_arch = "aarch64")]
-    row_masks: [uint16x8_t; 9],
-    #[cfg(target_arch = "aarch64")]
-    col_masks: [uint16x8_t; 9],
-    #[cfg(target_arch = "aarch64")]
-    box_masks: [uint16x8_t; 9],
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    row_masks: [[u8; 9]; 9],
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    col_masks: [[u8; 9]; 9],
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    box_masks: [[u8; 9]; 9],
+    row_masks: [u16x8; 9],
+    col_masks: [u16x8; 9],
+    box_masks: [u16x8; 9],
 }
 
 impl SimdSolver {
     /// Creates a new SIMD solver with precomputed masks
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    pub unsafe fn new(board: &Board) -> Self {
-        let mut row_masks = [_mm_setzero_si128(); 9];
-        let mut col_masks = [_mm_setzero_si128(); 9];
-        let mut box_masks = [_mm_setzero_si128(); 9];
+    pub fn new(board: &Board) -> Self {
+        let mut row_masks = [u16x8::splat(0); 9];
+        let mut col_masks = [u16x8::splat(0); 9];
+        let mut box_masks = [u16x8::splat(0); 9];
 
         // Precompute masks for each row, column, and box
-        for row in 0..9 {
-            let mut row_data = [0i16; 8];
-            for col in 0..9 {
-                let value = board.get(row, col);
-                if value != 0 {
-                    row_data[col.min(7)] |= 1 << (value - 1);
-                }
-            }
-            row_masks[row] = _mm_loadu_si128(row_data.as_ptr() as *const __m128i);
-        }
+        for i in 0..9 {
+            row_masks[i] = unit_mask((0..9).map(|col| board.get(i, col)));
+            col_masks[i] = unit_mask((0..9).map(|row| board.get(row, i)));
 
-        // Similar for columns
-        for col in 0..9 {
-            let mut col_data = [0i16; 8];
-            for row in 0..9 {
-                let value = board.get(row, col);
-                if value != 0 {
-                    col_data[row.min(7)] |= 1 << (value - 1);
-                }
-            }
-            col_masks[col] = _mm_loadu_si128(col_data.as_ptr() as *const __m128i);
-        }
-
-        // And boxes
-        for box_idx in 0..9 {
-            let box_row = (box_idx / 3) * 3;
-            let box_col = (box_idx % 3) * 3;
-            let mut box_data = [0i16; 8];
-            
-            for i in 0..3 {
-                for j in 0..3 {
-                    let value = board.get(box_row + i, box_col + j);
-                    if value != 0 {
-                        box_data[(i * 3 + j).min(7)] |= 1 << (value - 1);
-                    }
-                }
-            }
-            box_masks[box_idx] = _mm_loadu_si128(box_data.as_ptr() as *const __m128i);
+            let (box_row, box_col) = ((i / 3) * 3, (i % 3) * 3);
+            box_masks[i] = unit_mask((0..9).map(|j| board.get(box_row + j / 3, box_col + j % 3)));
         }
 
         Self {
@@ -544,229 +366,165 @@ impl SimdSolver {
         }
     }
 
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    pub unsafe fn new(board: &Board) -> Self {
-        let mut row_masks = [vdupq_n_u16(0); 9];
-        let mut col_masks = [vdupq_n_u16(0); 9];
-        let mut box_masks = [vdupq_n_u16(0); 9];
-
-        // Precompute masks for each row, column, and box
-        for row in 0..9 {
-            let mut row_data = [0u16; 8];
-            for col in 0..8 {
-                let value = board.get(row, col);
-                if value != 0 {
-                    row_data[col] = 1 << (value - 1);
-                }
-            }
-            row_masks[row] = vld1q_u16(row_data.as_ptr());
-            // Handle the 9th element by setting it in the first unused lane
-            let value = board.get(row, 8);
-            if value != 0 {
-                row_masks[row] = vsetq_lane_u16(1 << (value - 1), row_masks[row], 7);
-            }
+    /// Checks if a value can be placed at the given position using SIMD
+    #[inline]
+    pub fn is_valid_candidate(&self, row: usize, col: usize, value: u8) -> bool {
+        if value == 0 {
+            return false;
         }
+        let value_mask = u16x8::splat(1 << (value - 1));
+        let box_idx = (row / 3) * 3 + col / 3;
 
-        // Similar for columns
-        for col in 0..9 {
-            let mut col_data = [0u16; 8];
-            for row in 0..8 {
-                let value = board.get(row, col);
-                if value != 0 {
-                    col_data[row] = 1 << (value - 1);
-                }
-            }
-            col_masks[col] = vld1q_u16(col_data.as_ptr());
-            // Handle the 9th element by setting it in the first unused lane
-            let value = board.get(8, col);
-            if value != 0 {
-                col_masks[col] = vsetq_lane_u16(1 << (value - 1), col_masks[col], 7);
-            }
-        }
+        // One test covers the row, column, and box together
+        let used = self.row_masks[row] | self.col_masks[col] | self.box_masks[box_idx];
+        !any_bits_set(used & value_mask)
+    }
 
-        // And boxes
-        for box_idx in 0..9 {
-            let box_row = (box_idx / 3) * 3;
-            let box_col = (box_idx % 3) * 3;
-            let mut box_data = [0u16; 8];
-            
-            let mut idx = 0;
-            for i in 0..3 {
-                for j in 0..3 {
-                    if idx < 8 {
-                        let value = board.get(box_row + i, box_col + j);
-                        if value != 0 {
-                            box_data[idx] = 1 << (value - 1);
-                        }
-                        idx += 1;
-                    }
-                }
-            }
-            box_masks[box_idx] = vld1q_u16(box_data.as_ptr());
-            // Handle the 9th element by setting it in the first unused lane
-            let value = board.get(box_row + 2, box_col + 2);
-            if value != 0 {
-                box_masks[box_idx] = vsetq_lane_u16(1 << (value - 1), box_masks[box_idx], 7);
-            }
+    /// Updates the masks when a value is placed
+    #[inline]
+    pub fn update_masks(&mut self, row: usize, col: usize, value: u8) {
+        if value == 0 {
+            return;
         }
+        let value_mask = u16x8::splat(1 << (value - 1));
+        let box_idx = (row / 3) * 3 + col / 3;
 
-        Self {
-            row_masks,
-            col_masks,
-            box_masks,
+        self.row_masks[row] |= value_mask;
+        self.col_masks[col] |= value_mask;
+        self.box_masks[box_idx] |= value_mask;
+    }
+
+    /// Reverts `update_masks` when a placed value is taken back.
+    ///
+    /// Only valid for a value that `is_valid_candidate` accepted before it was
+    /// placed, since the digit's bit is cleared from the whole row, column, and box.
+    #[inline]
+    pub fn clear_masks(&mut self, row: usize, col: usize, value: u8) {
+        if value == 0 {
+            return;
         }
+        let keep = !u16x8::splat(1 << (value - 1));
+        let box_idx = (row / 3) * 3 + col / 3;
+
+        self.row_masks[row] &= keep;
+        self.col_masks[col] &= keep;
+        self.box_masks[box_idx] &= keep;
     }
+}
 
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    pub fn new(board: &Board) -> Self {
-        let mut row_masks = [[0; 9]; 9];
-        let mut col_masks = [[0; 9]; 9];
-        let mut box_masks = [[0; 9]; 9];
+/// Row, column, and box digit masks as plain integers
+#[derive(Debug, Clone)]
+pub struct ScalarSolver {
+    row_masks: [u16; 9],
+    col_masks: [u16; 9],
+    box_masks: [u16; 9],
+}
 
-        // Initialize masks without SIMD
+impl ScalarSolver {
+    pub fn new(board: &Board) -> Self {
+        let mut solver = Self {
+            row_masks: [0; 9],
+            col_masks: [0; 9],
+            box_masks: [0; 9],
+        };
         for row in 0..9 {
             for col in 0..9 {
-                let value = board.get(row, col);
-                if value != 0 {
-                    row_masks[row][col] = value;
-                    col_masks[col][row] = value;
-                    let box_idx = (row / 3) * 3 + col / 3;
-                    let box_pos = (row % 3) * 3 + col % 3;
-                    box_masks[box_idx][box_pos] = value;
-                }
+                solver.update_masks(row, col, board.get(row, col));
             }
         }
-
-        Self {
-            row_masks,
-            col_masks,
-            box_masks,
-        }
+        solver
     }
 
-    /// Checks if a value can be placed at the given position using SIMD
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    pub unsafe fn is_valid_candidate(&self, row: usize, col: usize, value: u8) -> bool {
-        let value_mask = _mm_set1_epi16(1 << (value - 1));
-        
-        // Check row
-        let row_check = _mm_and_si128(self.row_masks[row], value_mask);
-        if _mm_movemask_epi8(row_check) != 0 {
-            return false;
-        }
-
-        // Check column
-        let col_check = _mm_and_si128(self.col_masks[col], value_mask);
-        if _mm_movemask_epi8(col_check) != 0 {
-            return false;
-        }
-
-        // Check box
-        let box_idx = (row / 3) * 3 + col / 3;
-        let box_check = _mm_and_si128(self.box_masks[box_idx], value_mask);
-        if _mm_movemask_epi8(box_check) != 0 {
+    #[inline]
+    pub fn is_valid_candidate(&self, row: usize, col: usize, value: u8) -> bool {
+        if value == 0 {
             return false;
         }
-
-        true
+        let used = self.row_masks[row] | self.col_masks[col] | self.box_masks[(row / 3) * 3 + col / 3];
+        used & (1 << (value - 1)) == 0
     }
 
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    pub unsafe fn is_valid_candidate(&self, row: usize, col: usize, value: u8) -> bool {
+    #[inline]
+    pub fn update_masks(&mut self, row: usize, col: usize, value: u8) {
         if value == 0 {
-            return false;
-        }
-        let value_mask = vdupq_n_u16(1 << (value - 1));
-        
-        // Check row
-        let row_check = vandq_u16(self.row_masks[row], value_mask);
-        if vmaxvq_u16(row_check) != 0 {
-            return false;
+            return;
         }
+        let bit = 1 << (value - 1);
+        self.row_masks[row] |= bit;
+        self.col_masks[col] |= bit;
+        self.box_masks[(row / 3) * 3 + col / 3] |= bit;
+    }
 
-        // Check column
-        let col_check = vandq_u16(self.col_masks[col], value_mask);
-        if vmaxvq_u16(col_check) != 0 {
-            return false;
+    #[inline]
+    pub fn clear_masks(&mut self, row: usize, col: usize, value: u8) {
+        if value == 0 {
+            return;
         }
+        let bit = 1 << (value - 1);
+        self.row_masks[row] &= !bit;
+        self.col_masks[col] &= !bit;
+        self.box_masks[(row / 3) * 3 + col / 3] &= !bit;
+    }
+}
 
-        // Check box
-        let box_idx = (row / 3) * 3 + col / 3;
-        let box_check = vandq_u16(self.box_masks[box_idx], value_mask);
-        if vmaxvq_u16(box_check) != 0 {
-            return false;
-        }
+/// Candidate checker backed by whichever implementation `backend()` selected.
+///
+/// Callers use one type on every target; the choice between SIMD and scalar
+/// masks is made once at construction rather than at each call site.
+// Kept inline: boxing the SIMD masks would add a pointer chase to every check
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum CandidateChecker {
+    Simd(SimdSolver),
+    Scalar(ScalarSolver),
+}
 
-        true
+impl CandidateChecker {
+    /// Builds a checker for `board` using the process-wide backend
+    pub fn new(board: &Board) -> Self {
+        Self::with_backend(board, backend())
     }
 
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    pub fn is_valid_candidate(&self, row: usize, col: usize, value: u8) -> bool {
-        // Check row
-        if self.row_masks[row].contains(&value) {
-            return false;
+    /// Builds a checker for `board` using a specific backend
+    pub fn with_backend(board: &Board, backend: Backend) -> Self {
+        match backend {
+            Backend::Simd => CandidateChecker::Simd(SimdSolver::new(board)),
+            Backend::Scalar => CandidateChecker::Scalar(ScalarSolver::new(board)),
         }
+    }
 
-        // Check column
-        if self.col_masks[col].contains(&value) {
-            return false;
+    pub fn backend(&self) -> Backend {
+        match self {
+            CandidateChecker::Simd(_) => Backend::Simd,
+            CandidateChecker::Scalar(_) => Backend::Scalar,
         }
-
-        // Check box
-        let box_idx = (row / 3) * 3 + col / 3;
-        if self.box_masks[box_idx].contains(&value) {
-            return false;
-        }
-
-        true
     }
 
-    /// Updates the masks when a value is placed
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    #[target_feature(enable = "sse2")]
-    pub unsafe fn update_masks(&mut self, row: usize, col: usize, value: u8) {
-        let value_mask = _mm_set1_epi16(1 << (value - 1));
-        
-        // Update row mask
-        self.row_masks[row] = _mm_or_si128(self.row_masks[row], value_mask);
-        
-        // Update column mask
-        self.col_masks[col] = _mm_or_si128(self.col_masks[col], value_mask);
-        
-        // Update box mask
-        let box_idx = (row / 3) * 3 + col / 3;
-        self.box_masks[box_idx] = _mm_or_si128(self.box_masks[box_idx], value_mask);
+    /// Returns true if `value` is not yet used in the cell's row, column, or box
+    #[inline]
+    pub fn is_valid_candidate(&self, row: usize, col: usize, value: u8) -> bool {
+        match self {
+            CandidateChecker::Simd(solver) => solver.is_valid_candidate(row, col, value),
+            CandidateChecker::Scalar(solver) => solver.is_valid_candidate(row, col, value),
+        }
     }
 
-    #[cfg(target_arch = "aarch64")]
-    #[target_feature(enable = "neon")]
-    pub unsafe fn update_masks(&mut self, row: usize, col: usize, value: u8) {
-        if value == 0 {
-            return;
+    /// Records a placed value
+    #[inline]
+    pub fn update_masks(&mut self, row: usize, col: usize, value: u8) {
+        match self {
+            CandidateChecker::Simd(solver) => solver.update_masks(row, col, value),
+            CandidateChecker::Scalar(solver) => solver.update_masks(row, col, value),
         }
-        let value_mask = vdupq_n_u16(1 << (value - 1));
-        
-        // Update row mask
-        self.row_masks[row] = vorrq_u16(self.row_masks[row], value_mask);
-        
-        // Update column mask
-        self.col_masks[col] = vorrq_u16(self.col_masks[col], value_mask);
-        
-        // Update box mask
-        let box_idx = (row / 3) * 3 + col / 3;
-        self.box_masks[box_idx] = vorrq_u16(self.box_masks[box_idx], value_mask);
     }
 
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-    pub fn update_masks(&mut self, row: usize, col: usize, value: u8) {
-        self.row_masks[row][col] = value;
-        self.col_masks[col][row] = value;
-        let box_idx = (row / 3) * 3 + col / 3;
-        let box_pos = (row % 3) * 3 + col % 3;
-        self.box_masks[box_idx][box_pos] = value;
+    /// Takes back a value recorded with `update_masks`
+    #[inline]
+    pub fn clear_masks(&mut self, row: usize, col: usize, value: u8) {
+        match self {
+            CandidateChecker::Simd(solver) => solver.clear_masks(row, col, value),
+            CandidateChecker::Scalar(solver) => solver.clear_masks(row, col, value),
+        }
     }
 }
 
@@ -776,25 +534,21 @@ mod tests {
 
     #[test]
     fn test_simd_support_detection() {
-        let _ = has_simd_support();
+        let expected = cfg!(all(feature = "simd", target_arch = "x86_64"));
+        if cfg!(target_arch = "x86_64") {
+            assert_eq!(has_simd_support(), expected);
+        }
+        assert_eq!(backend() == Backend::Simd, has_simd_support());
     }
 
     #[test]
     fn test_simd_candidate_set() {
-        if !has_simd_support() {
-            return;
-        }
-
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-        unsafe {
-            let mut simd_set = SimdCandidateSet::new();
-            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-            let values = _mm_set1_epi16(0x1); // Remove candidate 1
-            #[cfg(target_arch = "aarch64")]
-            let values = vdupq_n_u16(0x1); // Remove candidate 1
-            simd_set.remove_candidates(values);
-            assert!(!simd_set.has_candidates(values));
-        }
+        let mut simd_set = SimdCandidateSet::new();
+        let values = CandidateSet(0x1); // Remove candidate 1
+        simd_set.remove_candidates(values);
+        assert!(!simd_set.has_candidates(values));
+        assert!(simd_set.has_candidates(CandidateSet(0x2)));
+        assert_eq!(simd_set.to_candidate_set().0, 0x1FE);
     }
 
     #[test]
@@ -805,12 +559,89 @@ mod tests {
             board.set(0, i, (i + 1) as u8);
         }
 
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-        unsafe {
-            if has_simd_support() {
-                let simd_board = SimdBoard::from_board(&board);
-                assert!(simd_board.is_valid_row(0));
+        let simd_board = SimdBoard::from_board(&board);
+        assert!(simd_board.is_valid_row(0));
+        assert!(!simd_board.is_valid_row(1));
+    }
+
+    #[test]
+    fn test_validate_many() {
+        let solved = Board::parse(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        let mut swapped = solved.clone();
+        swapped.set(0, 0, 3);
+        swapped.set(0, 1, 5);
+        let mut incomplete = solved.clone();
+        incomplete.set(4, 4, 0);
+
+        // More than one chunk, with a partial final chunk
+        let mut boards = vec![solved.clone(); 9];
+        boards[2] = swapped;
+        boards[8] = incomplete;
+
+        let results = SimdValidator::validate_many(&boards);
+        let expected: Vec<bool> = boards.iter().map(SimdValidator::validate_solution).collect();
+        assert_eq!(results, expected);
+        assert_eq!(results.iter().filter(|&&ok| !ok).count(), 2);
+    }
+
+    #[test]
+    fn test_clear_masks_undoes_update() {
+        let board = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let mut solver = SimdSolver::new(&board);
+        assert!(solver.is_valid_candidate(0, 2, 4));
+        solver.update_masks(0, 2, 4);
+        assert!(!solver.is_valid_candidate(0, 8, 4));
+        solver.clear_masks(0, 2, 4);
+        assert!(solver.is_valid_candidate(0, 8, 4));
+        assert!(!solver.is_valid_candidate(0, 8, 5), "givens are unaffected");
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let board = Board::parse(
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+        )
+        .unwrap();
+        let mut simd = CandidateChecker::with_backend(&board, Backend::Simd);
+        let mut scalar = CandidateChecker::with_backend(&board, Backend::Scalar);
+        assert_eq!(scalar.backend(), Backend::Scalar);
+
+        simd.update_masks(0, 1, 1);
+        scalar.update_masks(0, 1, 1);
+        for cell in 0..81 {
+            for value in 1..=9 {
+                let (row, col) = (cell / 9, cell % 9);
+                assert_eq!(simd.is_valid_candidate(row, col, value), scalar.is_valid_candidate(row, col, value));
             }
         }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_simd_solver_matches_scalar_checks() {
+        let board = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let solver = SimdSolver::new(&board);
+        for row in 0..9 {
+            for col in 0..9 {
+                for value in 1..=9 {
+                    assert_eq!(
+                        solver.is_valid_candidate(row, col, value),
+                        board.can_place(row, col, value),
+                        "r{}c{}={}",
+                        row + 1,
+                        col + 1,
+                        value
+                    );
+                }
+            }
+        }
+    }
+}
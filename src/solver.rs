@@ -1,42 +1,430 @@
-use crate::{Board, CandidateSet, Grid, Result, SudokuError, simd::{SimdValidator, SimdSolver, has_simd_support}};
-use rayon::prelude::*;
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::{Board, CandidateSet, Grid, Result, SudokuError, bitboard, contradiction::{self, ContradictionTrace}, logic::{self, LogicSolver, Placement, SolveStep, UNITS}, metrics, pencilmarks::PencilMarks, portfolio, replay, simd::{CandidateChecker, SimdValidator}};
+use futures_util::{stream, Stream};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use rand::Rng;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Statistics gathered while solving
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolveStats {
+    /// Wall-clock time spent in `Solver::solve`
+    pub duration: Duration,
+    /// Number of empty cells in the puzzle
+    pub empty_cells: usize,
+    /// Search nodes (trial placements) visited across all threads, counting
+    /// those of the solvers a `SolverState` carried over from
+    pub nodes: u64,
+}
+
+/// Limits on the work `Solver::solve_bounded` may do; unlimited by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// Most search nodes (trial placements) to visit
+    pub nodes: Option<u64>,
+    /// Longest to search; a duration too long to represent as a deadline
+    /// means no limit
+    pub time: Option<Duration>,
+}
+
+impl Budget {
+    pub fn nodes(nodes: u64) -> Self {
+        Self { nodes: Some(nodes), time: None }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self { nodes: None, time: Some(time) }
+    }
+
+    pub fn with_nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn with_time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+}
+
+/// What `Solver::solve_bounded` got done within its budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundedSolution {
+    /// The solution, or the fullest board the search reached without
+    /// breaking a rule if the budget ran out first
+    pub board: Board,
+    /// True if `board` is complete
+    pub solved: bool,
+    /// Whether the puzzle has exactly one solution, if the budget allowed
+    /// finding out
+    pub unique: Option<bool>,
+    /// Empty cells of the puzzle that `board` fills
+    pub filled: usize,
+    /// Dead ends the search backed out of: cells left with no candidate
+    pub backtracks: u64,
+    /// Placements in cells with a single candidate, which needed no guess
+    pub forced: u64,
+    pub stats: SolveStats,
+}
+
+/// A solved puzzle along with the context it was solved in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// The puzzle as given to the solver
+    pub puzzle: Board,
+    /// The completed board
+    pub board: Board,
+    /// Whether the puzzle has exactly one solution; None unless the solver
+    /// was built `with_uniqueness_check`
+    pub unique: Option<bool>,
+    /// Whether `board` equals the reference solution supplied with the grid;
+    /// None when the grid came without one
+    pub matches_reference: Option<bool>,
+    pub stats: SolveStats,
+}
+
+impl Solution {
+    /// The completed board as rows of values
+    pub fn to_vec(&self) -> Vec<Vec<i32>> {
+        self.board.to_vec()
+    }
+}
+
+/// A solver's knowledge of a puzzle, for picking up where it left off.
+///
+/// `Solver::state` takes it and `Solver::from_state` builds a solver from it
+/// without recomputing candidates, so eliminations from `with_pencilmarks`
+/// or `with_naked_subsets` carry over, along with the solver's settings and
+/// the search nodes visited so far. `place` fills a cell and updates only
+/// the candidates of its peers, as when a player enters a digit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolverState {
+    board: Board,
+    solution: Option<Board>,
+    candidates: Vec<CandidateSet>,
+    /// Search nodes visited by the solvers this state came from
+    pub nodes: u64,
+    timeout: Option<Duration>,
+    transposition_entries: Option<usize>,
+    portfolio: bool,
+    parallelism: Option<Parallelism>,
+    check_uniqueness: bool,
+}
+
+impl SolverState {
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Digits still possible in an empty cell; empty for a filled one
+    pub fn candidates(&self, row: usize, col: usize) -> CandidateSet {
+        self.candidates[row * 9 + col]
+    }
+
+    /// Places `value` at an empty cell and removes it from the candidates of
+    /// the cell's row, column and box. Errors with `InvalidValue` if the cell
+    /// is filled or `value` isn't one of its candidates.
+    pub fn place(&mut self, row: usize, col: usize, value: u8) -> Result<()> {
+        let cell = row * 9 + col;
+        if row >= 9 || col >= 9 || self.board.cells[cell] != 0 || !self.candidates[cell].has_candidate(value) {
+            return Err(SudokuError::InvalidValue { row, col, value: value as i32 });
+        }
+        self.board.cells[cell] = value;
+        self.candidates[cell] = CandidateSet::empty();
+        for peer in bitboard::cells(bitboard::PEER_MASKS[cell]) {
+            self.candidates[peer].remove_candidate(value);
+        }
+        Ok(())
+    }
+}
+
+/// A decision reported by `Solver::solve_stream`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SolveEvent {
+    /// A technique applied
+    Step { step: SolveStep },
+    /// A trial placement made when no technique applies
+    Guess { row: usize, col: usize, value: u8 },
+    /// The most recent guess led to a contradiction and was undone
+    Backtrack,
+    /// The solution, as an 81-character line; always the last event
+    Solved { board: String },
+    /// The puzzle has no solution; always the last event
+    Unsolvable,
+}
+
+/// Events `Solver::solve_stream` buffers ahead of a slow consumer
+const STREAM_BUFFER: usize = 64;
 
 pub struct Solver {
     board: Board,
-    solution: Board,
+    // The grid's known solution, if it came with one
+    solution: Option<Board>,
     // Pre-computed candidates for each cell
     candidates: Vec<CandidateSet>,
-    // Track if we found a unique solution
-    unique_solution: bool,
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-    simd_solver: Option<SimdSolver>,
+    // Whether the puzzle has exactly one solution, known after a solve that
+    // checked
+    unique_solution: Option<bool>,
+    checker: CandidateChecker,
+    // Search nodes visited, shared by the parallel branches
+    nodes: AtomicU64,
+    // Time allowed per solve, and the instant the current solve gives up
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    // Set once the deadline has passed so every branch stops
+    timed_out: AtomicBool,
+    // Entries allowed in the transposition table used when counting, if any
+    transposition_entries: Option<usize>,
+    // Race the portfolio strategies instead of running the parallel search
+    portfolio: bool,
+    // Overrides the process-wide parallelism
+    parallelism: Option<Parallelism>,
+    // Look for a second solution after solving
+    check_uniqueness: bool,
+}
+
+/// How `Solver::solve` spreads its search over threads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Parallelism {
+    /// Rayon's global pool, shared with the rest of the process
+    #[default]
+    Global,
+    /// A dedicated pool of this many threads, created on first use and
+    /// shared by every solver asking for the same number
+    Threads(usize),
+    /// No threads: each branch is searched in turn on the calling thread
+    Sequential,
+}
+
+impl Parallelism {
+    /// A pool of `threads` threads, or `Sequential` for one
+    pub fn threads(threads: usize) -> Self {
+        if threads <= 1 {
+            Parallelism::Sequential
+        } else {
+            Parallelism::Threads(threads)
+        }
+    }
+}
+
+/// Parallelism for solvers that don't set their own
+static PARALLELISM: RwLock<Parallelism> = RwLock::new(Parallelism::Global);
+
+/// Whether solutions are checked with `verify::check_solution` before they
+/// are returned; on by default in debug builds
+static PARANOID: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+
+/// Dedicated pools by thread count
+static POOLS: Lazy<Mutex<HashMap<usize, Arc<ThreadPool>>>> = Lazy::new(Default::default);
+
+/// Sets the parallelism of every solver that doesn't choose its own with
+/// `Solver::with_parallelism`.
+///
+/// Embedders that run their own thread pools (an async runtime, say) can
+/// keep the solver off rayon's global pool, which otherwise takes a thread
+/// per core.
+pub fn set_parallelism(parallelism: Parallelism) {
+    *PARALLELISM.write() = parallelism;
+}
+
+/// The parallelism set with `set_parallelism`
+pub fn parallelism() -> Parallelism {
+    *PARALLELISM.read()
+}
+
+/// Turns post-solve verification on or off for every solver in the process.
+///
+/// When on, `Solver::solve`, `solve_bounded` and `solve_into` re-check each
+/// solution with the scalar validator, compare it with the SIMD validator and
+/// check the givens were kept, returning `VerificationFailed` otherwise. It
+/// costs two validations per solve and is meant to catch platform-specific
+/// SIMD bugs in the field.
+pub fn set_paranoid(paranoid: bool) {
+    PARANOID.store(paranoid, Ordering::Relaxed);
 }
 
+/// Whether post-solve verification is on; see `set_paranoid`
+pub fn paranoid() -> bool {
+    PARANOID.load(Ordering::Relaxed)
+}
+
+/// Runs `verify::check_solution` when paranoid
+fn check_if_paranoid(puzzle: &Board, solution: &Board) -> Result<()> {
+    if paranoid() {
+        crate::verify::check_solution(puzzle, solution)?;
+    }
+    Ok(())
+}
+
+/// The dedicated pool with `threads` threads
+fn pool(threads: usize) -> Result<Arc<ThreadPool>> {
+    let mut pools = POOLS.lock();
+    if let Some(pool) = pools.get(&threads) {
+        return Ok(pool.clone());
+    }
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("solver-{}", i))
+        .build()?;
+    Ok(pools.entry(threads).or_insert(Arc::new(pool)).clone())
+}
+
+/// Whether a dedicated pool with `threads` threads has been created
+#[cfg(test)]
+pub(crate) fn has_pool(threads: usize) -> bool {
+    POOLS.lock().contains_key(&threads)
+}
+
+/// How many search nodes pass between deadline checks
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
 impl Solver {
     pub fn new(grid: Grid) -> Self {
         let board = Board::new(&grid.value);
-        let solution = Board::new(&grid.solution);
+        let solution = grid.solution_board();
         let mut solver = Self {
             board: board.clone(),
             solution,
             candidates: vec![CandidateSet::empty(); 81],
-            unique_solution: true,
-            #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-            simd_solver: if has_simd_support() {
-                unsafe { Some(SimdSolver::new(&board)) }
-            } else {
-                None
-            },
+            unique_solution: None,
+            checker: CandidateChecker::new(&board),
+            nodes: AtomicU64::new(0),
+            timeout: None,
+            deadline: None,
+            timed_out: AtomicBool::new(false),
+            transposition_entries: None,
+            portfolio: false,
+            parallelism: None,
+            check_uniqueness: false,
         };
         solver.precompute_candidates();
         solver
     }
 
+    /// Resumes from a state taken with `state`, keeping its candidates,
+    /// settings and node count rather than starting over from the board.
+    pub fn from_state(state: SolverState) -> Self {
+        Self {
+            checker: CandidateChecker::new(&state.board),
+            board: state.board,
+            solution: state.solution,
+            candidates: state.candidates,
+            unique_solution: None,
+            nodes: AtomicU64::new(state.nodes),
+            timeout: state.timeout,
+            deadline: None,
+            timed_out: AtomicBool::new(false),
+            transposition_entries: state.transposition_entries,
+            portfolio: state.portfolio,
+            parallelism: state.parallelism,
+            check_uniqueness: state.check_uniqueness,
+        }
+    }
+
+    /// The current board and candidates, with the settings and work done so
+    /// far, for `from_state`. `solve` fills the board in, so take the state
+    /// before solving to continue from the puzzle.
+    pub fn state(&self) -> SolverState {
+        SolverState {
+            board: self.board.clone(),
+            solution: self.solution.clone(),
+            candidates: self.candidates.clone(),
+            nodes: self.nodes.load(Ordering::Relaxed),
+            timeout: self.timeout,
+            transposition_entries: self.transposition_entries,
+            portfolio: self.portfolio,
+            parallelism: self.parallelism,
+            check_uniqueness: self.check_uniqueness,
+        }
+    }
+
+    /// Restricts the search to the supplied pencil marks.
+    ///
+    /// Each empty cell keeps only the candidates present in both the computed
+    /// candidate set and `marks`, so user eliminations are never undone.
+    pub fn with_pencilmarks(mut self, marks: &PencilMarks) -> Self {
+        for row in 0..9 {
+            for col in 0..9 {
+                if self.board.is_empty_cell(row, col) {
+                    let idx = row * 9 + col;
+                    self.candidates[idx] = CandidateSet(self.candidates[idx].0 & marks.get(row, col).0);
+                }
+            }
+        }
+        self
+    }
+
+    /// Has `solve` look for a second solution once it finds one, so that
+    /// `Solution::unique` is known. The check searches the puzzle again under
+    /// the same pencil marks, roughly doubling the cost of a solve; its time
+    /// is part of `SolveStats::duration`.
+    pub fn with_uniqueness_check(mut self) -> Self {
+        self.check_uniqueness = true;
+        self
+    }
+
+    /// Gives up solving once `timeout` has elapsed.
+    ///
+    /// The clock starts when `solve` is called and covers both the search and
+    /// the uniqueness check after it; whichever runs past it is cancelled and
+    /// `solve` returns `SudokuError::SolveTimeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Narrows the candidates with naked pair and triple eliminations.
+    ///
+    /// The eliminations hold for every completion of the puzzle, so the
+    /// backtracking search, which draws each cell's digits from these
+    /// candidates at every level, never tries them.
+    pub fn with_naked_subsets(mut self) -> Self {
+        logic::prune_naked_subsets(&mut self.candidates);
+        self
+    }
+
+    /// Memoizes solution counts of partial boards in a table of up to
+    /// `entries` states.
+    ///
+    /// The uniqueness check in `solve` and `count_solutions` then skip
+    /// sub-searches they have already done. Two partial boards leave the same
+    /// sub-problem when every empty cell has the same digits free, whatever
+    /// was placed to get there, so pathological and many-solution boards
+    /// revisit states often. Each entry takes about 200 bytes.
+    pub fn with_transposition_table(mut self, entries: usize) -> Self {
+        self.transposition_entries = Some(entries);
+        self
+    }
+
+    /// Runs the search with `parallelism` rather than the process-wide
+    /// setting from `set_parallelism`
+    pub fn with_parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// Solves by racing the `portfolio` strategies and keeping the first to
+    /// finish, which bounds the time spent on puzzles built to defeat one
+    /// particular search order.
+    ///
+    /// The race works from the givens alone, so candidates narrowed by
+    /// `with_pencilmarks` or `with_naked_subsets` don't restrict it. Timeouts
+    /// and the uniqueness check apply as usual; `SolveStats::nodes` counts the
+    /// winner's placements.
+    pub fn with_portfolio(mut self) -> Self {
+        self.portfolio = true;
+        self
+    }
+
     /// Precompute valid candidates for each empty cell
     fn precompute_candidates(&mut self) {
+        span!(DEBUG, "precompute_candidates");
         for row in 0..9 {
             for col in 0..9 {
                 if self.board.is_empty_cell(row, col) {
@@ -140,13 +528,156 @@ impl Solver {
         impact
     }
 
-    pub fn solve(&mut self) -> Result<Vec<Vec<i32>>> {
+    /// Solves the puzzle, returning the completed board with its context.
+    ///
+    /// With `with_uniqueness_check`, a second search then looks for another
+    /// solution; otherwise `Solution::unique` is None.
+    pub fn solve(&mut self) -> Result<Solution> {
+        let start = Instant::now();
+        let puzzle = self.board.clone();
+        let empty_cells = puzzle.cells.iter().filter(|&&v| v == 0).count();
+        span!(DEBUG, "solve", empty_cells, nodes = tracing::field::Empty, unique = tracing::field::Empty);
+
+        // Conflicting givens would otherwise send the search through every
+        // completion of the remaining cells before giving up
+        if !puzzle.is_consistent() {
+            return Err(SudokuError::InvalidBoard);
+        }
+
+        // A timeout too long to represent is no deadline at all
+        self.deadline = self.timeout.and_then(|timeout| start.checked_add(timeout));
+        self.timed_out.store(false, Ordering::Relaxed);
+
+        let board = if self.portfolio {
+            span!(DEBUG, "portfolio");
+            let race = portfolio::race(&puzzle, &portfolio::Strategy::ALL, self.timeout)?;
+            self.nodes.fetch_add(race.nodes, Ordering::Relaxed);
+            self.board = race.solution.clone();
+            race.solution
+        } else {
+            span!(DEBUG, "search");
+            match self.search() {
+                Err(_) if self.timed_out.load(Ordering::Relaxed) => return Err(SudokuError::SolveTimeout),
+                result => result?,
+            }
+        };
+        check_if_paranoid(&puzzle, &board)?;
+        self.unique_solution = if self.check_uniqueness {
+            span!(DEBUG, "uniqueness_check");
+            Some(self.is_unique_by_deadline(&puzzle)?)
+        } else {
+            None
+        };
+        let duration = start.elapsed();
+        metrics::SOLVE_DURATION.observe(duration.as_secs_f64());
+        record!("nodes", self.nodes.load(Ordering::Relaxed));
+        record!("unique", self.unique_solution == Some(true));
+
+        Ok(Solution {
+            matches_reference: self.solution.as_ref().map(|solution| board == *solution),
+            puzzle,
+            board,
+            unique: self.unique_solution,
+            stats: SolveStats {
+                duration,
+                empty_cells,
+                nodes: self.nodes.load(Ordering::Relaxed),
+            },
+        })
+    }
+
+    /// Searches for a solution within `budget`, returning what it got instead
+    /// of running on.
+    ///
+    /// The single-threaded bitmask search keeps the fullest board it reaches,
+    /// which is returned with `solved: false` if the nodes or time run out
+    /// first. With budget to spare after the first solution it keeps looking
+    /// for a second, so `unique` is known. Like `solve_into`, it works from
+    /// the givens alone. Returns `InvalidBoard` if the givens conflict or the
+    /// search proves there is no solution.
+    pub fn solve_bounded(&mut self, budget: Budget) -> Result<BoundedSolution> {
+        let start = Instant::now();
+        let puzzle = self.board.clone();
+        let empty_cells = puzzle.cells.iter().filter(|&&v| v == 0).count();
+        span!(DEBUG, "solve_bounded", empty_cells, nodes = tracing::field::Empty);
+        let mut state = SearchState::new(&puzzle).ok_or(SudokuError::InvalidBoard)?;
+
+        let mut bounds = Bounds::new(&puzzle, budget.nodes, budget.time.and_then(|time| start.checked_add(time)));
+        state.search_bounded(&mut bounds);
+        record!("nodes", bounds.nodes);
+
+        let (board, solved, unique) = match bounds.solutions.first() {
+            Some(solution) => {
+                let unique = match (bounds.solutions.len(), bounds.ran_out) {
+                    (1, true) => None,
+                    (count, _) => Some(count == 1),
+                };
+                (solution.clone(), true, unique)
+            }
+            None if !bounds.ran_out => return Err(SudokuError::InvalidBoard),
+            None => (bounds.best, false, None),
+        };
+        if solved {
+            check_if_paranoid(&puzzle, &board)?;
+            self.board = board.clone();
+            self.unique_solution = unique;
+        }
+        Ok(BoundedSolution {
+            filled: if solved { empty_cells } else { bounds.best_filled },
+            board,
+            solved,
+            unique,
+            backtracks: bounds.backtracks,
+            forced: bounds.forced,
+            stats: SolveStats { duration: start.elapsed(), empty_cells, nodes: bounds.nodes },
+        })
+    }
+
+    /// Solves `board` in place without heap allocation.
+    ///
+    /// Runs a single-threaded bitmask search and never builds row vectors or
+    /// candidate tables, so it suits hot loops. On error `board` is unchanged.
+    pub fn solve_into(board: &mut Board) -> Result<()> {
+        span!(TRACE, "solve_into");
+        let mut state = SearchState::new(board).ok_or(SudokuError::InvalidBoard)?;
+        let mut found = None;
+        state.search(&mut |solution| {
+            found = Some(solution.clone());
+            true
+        });
+        let solution = found.ok_or(SudokuError::InvalidBoard)?;
+        check_if_paranoid(board, &solution)?;
+        *board = solution;
+        Ok(())
+    }
+
+    /// Solves the puzzle on Tokio's blocking pool, yielding each decision as
+    /// it is made, then `Solved` or `Unsolvable`. The search is the
+    /// logic-first one `Replay::record` uses rather than the parallel search,
+    /// so the events follow a person's solve. Dropping the stream stops the
+    /// search. Must be called from within a Tokio runtime.
+    pub fn solve_stream(&self) -> impl Stream<Item = SolveEvent> + Send + 'static {
+        let puzzle = self.board.clone();
+        let (tx, rx) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            let solution = replay::search(LogicSolver::new(&puzzle), &mut |event| tx.blocking_send(event).is_ok());
+            let last = match solution {
+                Some(board) => SolveEvent::Solved { board: board.to_line() },
+                None => SolveEvent::Unsolvable,
+            };
+            let _ = tx.blocking_send(last);
+        });
+        stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|event| (event, rx)) })
+    }
+
+    /// Runs the parallel backtracking search, leaving the result in `self.board`
+    fn search(&mut self) -> Result<Board> {
         let empty_cells = self.find_empty_cells();
         if empty_cells.is_empty() {
             if !SimdValidator::validate_solution(&self.board) {
                 return Err(SudokuError::InvalidBoard);
             }
-            return Ok(self.board.to_vec());
+            return Ok(self.board.clone());
         }
         
         // Take only the first empty cell with minimum candidates and maximum impact
@@ -158,13 +689,9 @@ impl Solver {
         }
 
         let board = self.board.clone();
-        let solution = self.solution.clone();
-        
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-        let simd_solver = self.simd_solver.clone();
+        let checker = self.checker.clone();
         
         let solution_found = Arc::new(AtomicBool::new(false));
-        let matches_api = Arc::new(AtomicBool::new(false));
         
         // Use bounded channel with a reasonable size
         let (tx, rx) = crossbeam::channel::bounded(1);
@@ -177,44 +704,45 @@ impl Solver {
             self.calculate_impact(row, col)
         });
 
-        sorted_candidates.into_par_iter()
-            .find_map_first(|num| {
-                if solution_found.load(Ordering::SeqCst) {
+        let try_value = |num: u8| {
+            if solution_found.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            let mut board_copy = board.clone();
+            let mut checker = checker.clone();
+            
+            if self.try_solve_with_value(row, col, num, &mut board_copy, &mut checker) {
+                if solution_found.fetch_or(true, Ordering::SeqCst) {
                     return None;
                 }
-
-                let mut board_copy = board.clone();
-                #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-                let simd_solver = simd_solver.clone();
                 
-                if self.try_solve_with_value(row, col, num, &mut board_copy, 
-                    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-                    simd_solver
-                ) {
-                    if board_copy == solution {
-                        matches_api.store(true, Ordering::SeqCst);
-                    }
-                    
-                    if solution_found.fetch_or(true, Ordering::SeqCst) {
-                        return None;
-                    }
-                    
-                    match tx.send_timeout(board_copy, Duration::from_secs(1)) {
-                        Ok(_) => Some(()),
-                        Err(_) => None,
-                    }
-                } else {
-                    None
+                match tx.send_timeout(board_copy, Duration::from_secs(1)) {
+                    Ok(_) => Some(()),
+                    Err(_) => None,
                 }
-            });
+            } else {
+                None
+            }
+        };
+
+        match self.parallelism.unwrap_or_else(parallelism) {
+            Parallelism::Global => {
+                sorted_candidates.into_par_iter().find_map_first(try_value);
+            }
+            Parallelism::Threads(threads) => {
+                pool(threads)?.install(|| sorted_candidates.into_par_iter().find_map_first(try_value));
+            }
+            Parallelism::Sequential => {
+                sorted_candidates.into_iter().find_map(try_value);
+            }
+        }
 
-        self.unique_solution = matches_api.load(Ordering::SeqCst);
-        
         if solution_found.load(Ordering::SeqCst) {
             match rx.recv_timeout(Duration::from_secs(1)) {
                 Ok(solved_board) => {
                     self.board = solved_board;
-                    return Ok(self.board.to_vec());
+                    return Ok(self.board.clone());
                 }
                 Err(_) => {
                     return Err(SudokuError::InvalidBoard);
@@ -225,56 +753,62 @@ impl Solver {
         Err(SudokuError::InvalidBoard)
     }
 
+    /// Places `value` and searches onward, undoing the placement on failure.
+    ///
+    /// `board` and `checker` are updated in place rather than cloned per
+    /// level; on success `board` holds the solution.
     fn try_solve_with_value(
-        &self, 
-        start_row: usize, 
-        start_col: usize, 
-        value: u8, 
+        &self,
+        start_row: usize,
+        start_col: usize,
+        value: u8,
         board: &mut Board,
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-        mut simd_solver: Option<SimdSolver>,
+        checker: &mut CandidateChecker,
     ) -> bool {
-        board.set(start_row, start_col, value);
-        
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-        if let Some(ref mut solver) = simd_solver {
-            unsafe {
-                solver.update_masks(start_row, start_col, value);
-            }
+        if self.out_of_time() {
+            return false;
         }
-        
-        
-        if let Some((next_row, next_col)) = self.find_next_empty(board) {
-            for num in 1..=9 {
-                #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-                let is_valid = if let Some(ref solver) = simd_solver {
-                    unsafe { solver.is_valid_candidate(next_row, next_col, num) }
-                } else {
-                    self.is_valid_placement(board, next_row, next_col, num)
-                };
-                
-                #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-                let is_valid = self.is_valid_placement(board, next_row, next_col, num);
-                
-                if is_valid {
-                    let mut new_board = board.clone();
-                    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-                    let new_simd_solver = simd_solver.clone();
-                    
-                    if self.try_solve_with_value(next_row, next_col, num, &mut new_board,
-                        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
-                        new_simd_solver
-                    ) {
-                        *board = new_board;
-                        return true;
+        board.set(start_row, start_col, value);
+        checker.update_masks(start_row, start_col, value);
+
+        let solved = match self.find_next_empty(board) {
+            Some((next_row, next_col)) => {
+                let allowed = self.candidates[next_row * 9 + next_col];
+                let mut solved = false;
+                for num in allowed.iter_candidates() {
+                    if checker.is_valid_candidate(next_row, next_col, num)
+                        && self.try_solve_with_value(next_row, next_col, num, board, checker)
+                    {
+                        solved = true;
+                        break;
                     }
                 }
+                solved
             }
-            false
-        } else {
             // No empty cells left, verify the solution
-            self.is_valid_solution(board)
+            None => self.is_valid_solution(board),
+        };
+
+        if !solved {
+            board.set(start_row, start_col, 0);
+            checker.clear_masks(start_row, start_col, value);
+        }
+        solved
+    }
+
+    /// Counts a search node and reports whether the deadline has passed.
+    ///
+    /// The clock is only read every `DEADLINE_CHECK_INTERVAL` nodes to keep
+    /// the check cheap.
+    fn out_of_time(&self) -> bool {
+        let node = self.nodes.fetch_add(1, Ordering::Relaxed);
+        let Some(deadline) = self.deadline else {
+            return false;
+        };
+        if node.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+            self.timed_out.store(true, Ordering::Relaxed);
         }
+        self.timed_out.load(Ordering::Relaxed)
     }
 
     fn is_valid_solution(&self, board: &Board) -> bool {
@@ -322,26 +856,616 @@ impl Solver {
         true
     }
 
+    /// Whether the board matches the grid's solution, or without one whether
+    /// it is a valid completed grid
     pub fn verify_solution(&self) -> bool {
-        self.board == self.solution
+        match &self.solution {
+            Some(solution) => self.board == *solution,
+            None => crate::validate_solution(&self.board),
+        }
     }
 
+    /// True once a solve `with_uniqueness_check` found exactly one solution
     pub fn has_unique_solution(&self) -> bool {
-        self.unique_solution
+        self.unique_solution == Some(true)
     }
 
     pub fn get_solution(&self) -> Vec<Vec<i32>> {
         self.board.to_vec()
     }
 
-    pub fn get_original_solution(&self) -> Vec<Vec<i32>> {
-        self.solution.to_vec()
+    pub fn get_original_solution(&self) -> Option<Vec<Vec<i32>>> {
+        self.solution.as_ref().map(Board::to_vec)
     }
+
+    /// What the solver considers possible in each cell of the current board,
+    /// indexed `[row][col]`.
+    ///
+    /// Filled cells hold just their value, as in `PencilMarks::from_board`;
+    /// empty cells hold their computed candidates, narrowed by any pencil
+    /// marks given to `with_pencilmarks`. `PencilMarks::from` turns the grid
+    /// into marks that print in the standard pencilmark layout.
+    pub fn candidate_grid(&self) -> [[CandidateSet; 9]; 9] {
+        let mut grid = [[CandidateSet::empty(); 9]; 9];
+        for (row, cells) in grid.iter_mut().enumerate() {
+            for (col, set) in cells.iter_mut().enumerate() {
+                match self.board.get(row, col) {
+                    0 => *set = self.candidates[row * 9 + col],
+                    value => set.add_candidate(value),
+                }
+            }
+        }
+        grid
+    }
+
+    /// Every cell of the current board that a naked or hidden single fills,
+    /// in row-major order, without placing any of them or searching.
+    ///
+    /// Singles only see the current candidates, so moves that become forced
+    /// after these are placed aren't included. On a board without a solution
+    /// the moves may contradict each other.
+    pub fn forced_moves(&self) -> Vec<Placement> {
+        let mut forced = [0u8; 81];
+        for (cell, candidates) in self.candidates.iter().enumerate() {
+            if self.board.cells[cell] == 0 && candidates.count_candidates() == 1 {
+                forced[cell] = candidates.iter_candidates().next().unwrap_or(0);
+            }
+        }
+        for unit in UNITS.iter() {
+            for value in 1..=9u8 {
+                let mut spots = unit
+                    .iter()
+                    .filter(|&&cell| self.board.cells[cell] == 0 && self.candidates[cell].has_candidate(value));
+                if let (Some(&cell), None) = (spots.next(), spots.next()) {
+                    if forced[cell] == 0 {
+                        forced[cell] = value;
+                    }
+                }
+            }
+        }
+        forced
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value != 0)
+            .map(|(cell, &value)| Placement { row: cell / 9, col: cell % 9, value })
+            .collect()
+    }
+
+    /// Explains why the board has no solution, or returns `None` if it has one.
+    ///
+    /// The trace lists the logical steps that lead to a contradiction, such as
+    /// a cell left with no candidates.
+    pub fn explain_unsolvable(&self) -> Option<ContradictionTrace> {
+        contradiction::trace(&self.board)
+    }
+
+    /// Returns a uniformly random solution of the puzzle.
+    ///
+    /// Unlike `solve`, whose answer depends on the search order, every solution
+    /// of a multi-solution puzzle is equally likely, exactly so for puzzles
+    /// with up to a thousand solutions and usually for a few thousand. Beyond
+    /// that the draw is biased: solution counts are capped at a thousand per
+    /// candidate, so a candidate leading to more solutions is picked no more
+    /// often than one leading to exactly a thousand.
+    pub fn sample_solution<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Board> {
+        sample_solution_of(&self.board, rng).ok_or(SudokuError::InvalidBoard)
+    }
+
+    /// Counts the solutions of the current board, stopping once `limit` is reached
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        self.count_board_solutions(&self.board, limit)
+    }
+
+    /// Whether `puzzle` has exactly one solution using only the solver's
+    /// candidates, so pencil marks count. With a deadline set the check stops
+    /// there and returns `SolveTimeout`, so it can't outlast the search it
+    /// follows.
+    fn is_unique_by_deadline(&self, puzzle: &Board) -> Result<bool> {
+        let mut state = SearchState::new(puzzle).ok_or(SudokuError::InvalidBoard)?;
+        state.restrict(&self.candidates);
+        if self.deadline.is_none() {
+            let count = match self.transposition_entries {
+                Some(entries) => state.count_memoized(2, &mut TranspositionTable::new(entries)),
+                None => {
+                    let mut count = 0;
+                    state.search(&mut |_| {
+                        count += 1;
+                        count >= 2
+                    });
+                    count
+                }
+            };
+            return Ok(count == 1);
+        }
+        let mut bounds = Bounds::new(puzzle, None, self.deadline);
+        state.search_bounded(&mut bounds);
+        if bounds.ran_out {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return Err(SudokuError::SolveTimeout);
+        }
+        Ok(bounds.solutions.len() == 1)
+    }
+
+    fn count_board_solutions(&self, board: &Board, limit: usize) -> usize {
+        match self.transposition_entries {
+            Some(entries) => count_solutions_memoized(board, limit, &mut TranspositionTable::new(entries)),
+            None => count_solutions_up_to(board, limit),
+        }
+    }
+}
+
+/// Solution counts of partial boards, keyed on the free digits of every cell
+struct TranspositionTable {
+    counts: HashMap<[u16; 81], usize>,
+    capacity: usize,
+    hits: u64,
+}
+
+impl TranspositionTable {
+    fn new(capacity: usize) -> Self {
+        Self { counts: HashMap::new(), capacity, hits: 0 }
+    }
+
+    fn get(&mut self, key: &[u16; 81]) -> Option<usize> {
+        let count = self.counts.get(key).copied();
+        self.hits += count.is_some() as u64;
+        count
+    }
+
+    /// Stores a count unless the table is full
+    fn insert(&mut self, key: [u16; 81], count: usize) {
+        if self.counts.len() < self.capacity {
+            self.counts.insert(key, count);
+        }
+    }
+}
+
+/// Limits and progress of `SearchState::search_bounded`
+struct Bounds {
+    max_nodes: Option<u64>,
+    deadline: Option<Instant>,
+    nodes: u64,
+    /// Cells reached with no candidate left
+    backtracks: u64,
+    /// Placements in cells with one candidate
+    forced: u64,
+    /// Set once a limit stops the search
+    ran_out: bool,
+    /// Cells filled on the current path, and the most on any path so far
+    filled: usize,
+    best: Board,
+    best_filled: usize,
+    /// Up to two solutions, enough to tell whether the first is unique
+    solutions: Vec<Board>,
+}
+
+impl Bounds {
+    fn new(puzzle: &Board, max_nodes: Option<u64>, deadline: Option<Instant>) -> Self {
+        Self {
+            max_nodes,
+            deadline,
+            nodes: 0,
+            backtracks: 0,
+            forced: 0,
+            ran_out: false,
+            filled: 0,
+            best: puzzle.clone(),
+            best_filled: 0,
+            solutions: Vec::new(),
+        }
+    }
+
+    /// Reports whether a limit has been reached. The clock is only read
+    /// every `DEADLINE_CHECK_INTERVAL` nodes.
+    fn exhausted(&mut self) -> bool {
+        let out_of_nodes = self.max_nodes.is_some_and(|max| self.nodes >= max);
+        let out_of_time = self.nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        self.ran_out = out_of_nodes || out_of_time;
+        self.ran_out
+    }
+}
+
+/// Row, column, and box digit masks used by the exhaustive search
+#[derive(Clone)]
+struct SearchState {
+    board: Board,
+    rows: [u16; 9],
+    cols: [u16; 9],
+    boxes: [u16; 9],
+    /// Digits each cell may take beyond the rules, all nine unless restricted
+    allowed: [u16; 81],
+}
+
+impl SearchState {
+    /// Builds the masks for a board, returning None if the givens conflict
+    fn new(board: &Board) -> Option<Self> {
+        let mut state = Self {
+            board: board.clone(),
+            rows: [0; 9],
+            cols: [0; 9],
+            boxes: [0; 9],
+            allowed: [0x1FF; 81],
+        };
+        for row in 0..9 {
+            for col in 0..9 {
+                let value = board.get(row, col);
+                if value == 0 {
+                    continue;
+                }
+                let bit = 1 << (value - 1);
+                let box_idx = Board::get_box_index(row, col);
+                if (state.rows[row] | state.cols[col] | state.boxes[box_idx]) & bit != 0 {
+                    return None;
+                }
+                state.rows[row] |= bit;
+                state.cols[col] |= bit;
+                state.boxes[box_idx] |= bit;
+            }
+        }
+        Some(state)
+    }
+
+    /// Limits each empty cell to its digits in `candidates`
+    fn restrict(&mut self, candidates: &[CandidateSet]) {
+        for (cell, set) in candidates.iter().enumerate() {
+            if self.board.cells[cell] == 0 {
+                self.allowed[cell] = set.0;
+            }
+        }
+    }
+
+    #[inline]
+    fn free_mask(&self, row: usize, col: usize) -> u16 {
+        !(self.rows[row] | self.cols[col] | self.boxes[Board::get_box_index(row, col)]) & self.allowed[row * 9 + col]
+    }
+
+    #[inline]
+    fn toggle(&mut self, row: usize, col: usize, bit: u16) {
+        self.rows[row] ^= bit;
+        self.cols[col] ^= bit;
+        self.boxes[Board::get_box_index(row, col)] ^= bit;
+    }
+
+    /// Returns the empty cell with the fewest candidates and its candidate mask.
+    ///
+    /// Stops early at a cell with no candidates, returning a mask of 0. Returns
+    /// None once the board is full.
+    fn most_constrained(&self) -> Option<(usize, usize, u16)> {
+        let mut best: Option<(usize, usize, u16)> = None;
+        for row in 0..9 {
+            for col in 0..9 {
+                if !self.board.is_empty_cell(row, col) {
+                    continue;
+                }
+                let free = self.free_mask(row, col);
+                if free == 0 {
+                    return Some((row, col, 0));
+                }
+                if best.is_none_or(|(_, _, b)| free.count_ones() < b.count_ones()) {
+                    best = Some((row, col, free));
+                }
+            }
+        }
+        best
+    }
+
+    /// Depth-first search choosing the most constrained cell at each level.
+    ///
+    /// Calls `visit` with each complete board; the search stops as soon as
+    /// `visit` returns true. Returns true if it was stopped that way.
+    fn search(&mut self, visit: &mut impl FnMut(&Board) -> bool) -> bool {
+        let Some((row, col, mut free)) = self.most_constrained() else {
+            return visit(&self.board);
+        };
+        if free == 0 {
+            return false;
+        }
+
+        while free != 0 {
+            let bit = free & free.wrapping_neg();
+            free &= free - 1;
+            self.board.set(row, col, bit.trailing_zeros() as u8 + 1);
+            self.toggle(row, col, bit);
+            let stop = self.search(visit);
+            self.toggle(row, col, bit);
+            self.board.set(row, col, 0);
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Depth-first search that stops at a second solution or when `bounds`
+    /// run out, returning true if it stopped early
+    fn search_bounded(&mut self, bounds: &mut Bounds) -> bool {
+        let Some((row, col, mut free)) = self.most_constrained() else {
+            bounds.solutions.push(self.board.clone());
+            return bounds.solutions.len() >= 2;
+        };
+        match free.count_ones() {
+            0 => bounds.backtracks += 1,
+            1 => bounds.forced += 1,
+            _ => {}
+        }
+
+        while free != 0 {
+            if bounds.exhausted() {
+                return true;
+            }
+            let bit = free & free.wrapping_neg();
+            free &= free - 1;
+            bounds.nodes += 1;
+            self.board.set(row, col, bit.trailing_zeros() as u8 + 1);
+            self.toggle(row, col, bit);
+            bounds.filled += 1;
+            if bounds.filled > bounds.best_filled {
+                bounds.best_filled = bounds.filled;
+                bounds.best = self.board.clone();
+            }
+            let stop = self.search_bounded(bounds);
+            bounds.filled -= 1;
+            self.toggle(row, col, bit);
+            self.board.set(row, col, 0);
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The empty cell `order` picks next and its candidate mask, or None once
+    /// the board is full
+    fn next_cell(&self, order: CellOrder) -> Option<(usize, usize, u16)> {
+        let cell = match order {
+            CellOrder::MostConstrained => return self.most_constrained(),
+            CellOrder::First => (0..81).find(|&cell| self.board.cells[cell] == 0)?,
+            CellOrder::Last => (0..81).rev().find(|&cell| self.board.cells[cell] == 0)?,
+        };
+        let (row, col) = (cell / 9, cell % 9);
+        Some((row, col, self.free_mask(row, col)))
+    }
+
+    /// Depth-first search for one solution, filling cells in `order` and
+    /// trying digits highest first when `descending`. Counts placements in
+    /// `nodes` and gives up once `stop` is set.
+    fn search_ordered(&mut self, order: CellOrder, descending: bool, stop: &AtomicBool, nodes: &mut u64) -> bool {
+        let Some((row, col, mut free)) = self.next_cell(order) else {
+            return true;
+        };
+        while free != 0 {
+            if stop.load(Ordering::Relaxed) {
+                return false;
+            }
+            let bit = if descending { 1 << (15 - free.leading_zeros()) } else { free & free.wrapping_neg() };
+            free &= !bit;
+            *nodes += 1;
+            self.board.set(row, col, bit.trailing_zeros() as u8 + 1);
+            self.toggle(row, col, bit);
+            if self.search_ordered(order, descending, stop, nodes) {
+                return true;
+            }
+            self.toggle(row, col, bit);
+            self.board.set(row, col, 0);
+        }
+        false
+    }
+
+    /// The sub-problem left to solve: each cell's free digits, 0 when filled
+    fn key(&self) -> [u16; 81] {
+        let mut key = [0; 81];
+        for (cell, free) in key.iter_mut().enumerate() {
+            if self.board.cells[cell] == 0 {
+                *free = self.free_mask(cell / 9, cell % 9);
+            }
+        }
+        key
+    }
+
+    /// Counts completions up to `limit`. Sub-counts are always taken up to
+    /// the full `limit`, not what is left of it, so every stored count is
+    /// exact or capped at `limit` and can be reused anywhere in the search.
+    fn count_memoized(&mut self, limit: usize, table: &mut TranspositionTable) -> usize {
+        let Some((row, col, mut free)) = self.most_constrained() else {
+            return 1;
+        };
+        if free == 0 {
+            return 0;
+        }
+        let key = self.key();
+        if let Some(count) = table.get(&key) {
+            return count;
+        }
+
+        let mut count = 0;
+        while free != 0 && count < limit {
+            let bit = free & free.wrapping_neg();
+            free &= free - 1;
+            self.board.set(row, col, bit.trailing_zeros() as u8 + 1);
+            self.toggle(row, col, bit);
+            count += self.count_memoized(limit, table);
+            self.toggle(row, col, bit);
+            self.board.set(row, col, 0);
+        }
+        let count = count.min(limit);
+        table.insert(key, count);
+        count
+    }
+
+    /// Walks the same tree as `search`, adding every placement tried to
+    /// `nodes`. Stops once `solutions` reaches `limit`, returning true then.
+    fn count_nodes(&mut self, nodes: &mut u64, solutions: &mut usize, limit: usize) -> bool {
+        let Some((row, col, mut free)) = self.most_constrained() else {
+            *solutions += 1;
+            return *solutions >= limit;
+        };
+
+        while free != 0 {
+            let bit = free & free.wrapping_neg();
+            free &= free - 1;
+            *nodes += 1;
+            self.board.set(row, col, bit.trailing_zeros() as u8 + 1);
+            self.toggle(row, col, bit);
+            let stop = self.count_nodes(nodes, solutions, limit);
+            self.toggle(row, col, bit);
+            self.board.set(row, col, 0);
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Counts the solutions of `board` up to `limit`, like `count_solutions_up_to`,
+/// looking up and recording sub-search results in `table`
+fn count_solutions_memoized(board: &Board, limit: usize, table: &mut TranspositionTable) -> usize {
+    match SearchState::new(board) {
+        Some(mut state) if limit > 0 => state.count_memoized(limit, table),
+        _ => 0,
+    }
+}
+
+/// Which empty cell `first_solution_ordered` fills next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CellOrder {
+    /// The one with the fewest candidates
+    MostConstrained,
+    /// The first in reading order
+    First,
+    /// The last in reading order
+    Last,
+}
+
+/// Searches for one solution of `board` with the given cell and digit
+/// ordering, returning it (None if there is none or `stop` was set) and the
+/// number of placements tried
+pub(crate) fn first_solution_ordered(
+    board: &Board,
+    order: CellOrder,
+    descending: bool,
+    stop: &AtomicBool,
+) -> (Option<Board>, u64) {
+    let mut nodes = 0;
+    let solution = SearchState::new(board)
+        .and_then(|mut state| state.search_ordered(order, descending, stop, &mut nodes).then_some(state.board));
+    (solution, nodes)
+}
+
+/// Number of placements a depth-first search tries before proving `board`
+/// has no second solution (or finding one). Returns 0 if the givens conflict.
+pub(crate) fn search_tree_size(board: &Board) -> u64 {
+    let mut nodes = 0;
+    if let Some(mut state) = SearchState::new(board) {
+        state.count_nodes(&mut nodes, &mut 0, 2);
+    }
+    nodes
+}
+
+/// Collects up to `limit` solutions of `board`.
+///
+/// Unlike `Solver::solve`, this is an exhaustive search, so it can prove that
+/// a puzzle has no solution or more than one.
+pub(crate) fn enumerate_solutions(board: &Board, limit: usize) -> Vec<Board> {
+    let mut solutions = Vec::new();
+    if limit == 0 {
+        return solutions;
+    }
+    if let Some(mut state) = SearchState::new(board) {
+        state.search(&mut |solution| {
+            solutions.push(solution.clone());
+            solutions.len() >= limit
+        });
+    }
+    solutions
+}
+
+/// Solution count up to which `sample_solution_of` samples exactly
+const EXACT_SAMPLE_LIMIT: usize = 1000;
+
+/// Picks a random solution of `board`, or None if it has none.
+///
+/// When the board has fewer than `EXACT_SAMPLE_LIMIT` solutions they are all
+/// enumerated and one is drawn uniformly (reservoir sampling). Otherwise the
+/// most constrained cell is fixed to a candidate chosen in proportion to how
+/// many solutions it leaves, and the process repeats.
+///
+/// Counts are capped at the limit, so the draw is only exact while every
+/// candidate weighed leaves fewer solutions than that. Candidates that reach
+/// the cap are weighed equally however many solutions they lead to, which
+/// biases very large spaces, such as an empty grid's, towards the solutions
+/// in smaller branches.
+pub(crate) fn sample_solution_of<R: Rng + ?Sized>(board: &Board, rng: &mut R) -> Option<Board> {
+    let mut state = SearchState::new(board)?;
+    loop {
+        let mut seen = 0;
+        let mut chosen = None;
+        state.clone().search(&mut |solution| {
+            seen += 1;
+            if rng.gen_range(0..seen) == 0 {
+                chosen = Some(solution.clone());
+            }
+            seen >= EXACT_SAMPLE_LIMIT
+        });
+        if seen < EXACT_SAMPLE_LIMIT {
+            return chosen;
+        }
+
+        // Too many to enumerate: descend one cell, weighting candidates by
+        // solution count. Forced cells don't change the count, so they are
+        // filled in on the way without counting.
+        let (row, col, free) = loop {
+            let (row, col, free) = state.most_constrained()?;
+            if free.count_ones() != 1 {
+                break (row, col, free);
+            }
+            state.board.set(row, col, free.trailing_zeros() as u8 + 1);
+            state.toggle(row, col, free);
+        };
+        let weights: Vec<(u8, usize)> = (1..=9u8)
+            .filter(|v| free & (1 << (v - 1)) != 0)
+            .map(|value| {
+                state.board.set(row, col, value);
+                let count = count_solutions_up_to(&state.board, EXACT_SAMPLE_LIMIT);
+                state.board.set(row, col, 0);
+                (value, count)
+            })
+            .collect();
+        let total: usize = weights.iter().map(|&(_, count)| count).sum();
+        let mut pick = rng.gen_range(0..total);
+        let value = weights
+            .iter()
+            .find(|&&(_, count)| {
+                let found = pick < count;
+                pick = pick.saturating_sub(count);
+                found
+            })
+            .map(|&(value, _)| value)?;
+        state.board.set(row, col, value);
+        state.toggle(row, col, 1 << (value - 1));
+    }
+}
+
+/// Counts the solutions of `board`, stopping at `limit`, without allocating
+pub(crate) fn count_solutions_up_to(board: &Board, limit: usize) -> usize {
+    let mut count = 0;
+    if limit == 0 {
+        return count;
+    }
+    if let Some(mut state) = SearchState::new(board) {
+        state.search(&mut |_| {
+            count += 1;
+            count >= limit
+        });
+    }
+    count
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::GridBuilder;
 
     #[test]
     fn test_solver_with_valid_board() {
@@ -357,7 +1481,7 @@ mod tests {
                 vec![0,0,0,4,1,9,0,0,5],
                 vec![0,0,0,0,8,0,0,7,9],
             ],
-            solution: vec![
+            solution: Some(vec![
                 vec![5,3,4,6,7,8,9,1,2],
                 vec![6,7,2,1,9,5,3,4,8],
                 vec![1,9,8,3,4,2,5,6,7],
@@ -367,14 +1491,21 @@ mod tests {
                 vec![9,6,1,5,3,7,2,8,4],
                 vec![2,8,7,4,1,9,6,3,5],
                 vec![3,4,5,2,8,6,1,7,9],
-            ],
+            ]),
             difficulty: "Medium".to_string(),
+            metadata: Default::default(),
         };
 
-        let mut solver = Solver::new(grid);
+        let mut solver = Solver::new(grid).with_uniqueness_check();
         let solution = solver.solve().unwrap();
         
         // Verify solution
+        assert_eq!(solution.puzzle.get(0, 2), 0);
+        assert_eq!(solution.unique, Some(true));
+        assert_eq!(solution.matches_reference, Some(true));
+        assert_eq!(solution.stats.empty_cells, 51);
+        assert!(solution.stats.nodes >= 51);
+        let solution = solution.to_vec();
         assert_eq!(solution.len(), 9);
         for row in solution.iter() {
             assert_eq!(row.len(), 9);
@@ -402,7 +1533,7 @@ mod tests {
                 vec![0,0,0,4,1,9,0,0,5],
                 vec![0,0,0,0,8,0,0,7,9],
             ],
-            solution: vec![
+            solution: Some(vec![
                 vec![5,3,4,6,7,8,9,1,2],
                 vec![6,7,2,1,9,5,3,4,8],
                 vec![1,9,8,3,4,2,5,6,7],
@@ -412,34 +1543,33 @@ mod tests {
                 vec![9,6,1,5,3,7,2,8,4],
                 vec![2,8,7,4,1,9,6,3,5],
                 vec![3,4,5,2,8,6,1,7,9],
-            ],
+            ]),
             difficulty: "Medium".to_string(),
+            metadata: Default::default(),
         };
 
-        let mut solver = Solver::new(grid);
+        let mut solver = Solver::new(grid).with_uniqueness_check();
         solver.solve().unwrap();
         assert!(solver.has_unique_solution(), "Solution should match API's solution");
     }
 
     #[test]
     fn test_invalid_board() {
-        let grid = Grid {
-            value: vec![
-                vec![5,5,0,0,7,0,0,0,0], // Invalid: duplicate 5 in first row
-                vec![6,0,0,1,9,5,0,0,0],
-                vec![0,9,8,0,0,0,0,6,0],
-                vec![8,0,0,0,6,0,0,0,3],
-                vec![4,0,0,8,0,3,0,0,1],
-                vec![7,0,0,0,2,0,0,0,6],
-                vec![0,6,0,0,0,0,2,8,0],
-                vec![0,0,0,4,1,9,0,0,5],
-                vec![0,0,0,0,8,0,0,7,9],
-            ],
-            solution: vec![vec![0; 9]; 9],
-            difficulty: "Medium".to_string(),
-        };
+        let value = vec![
+            vec![5,5,0,0,7,0,0,0,0], // Invalid: duplicate 5 in first row
+            vec![6,0,0,1,9,5,0,0,0],
+            vec![0,9,8,0,0,0,0,6,0],
+            vec![8,0,0,0,6,0,0,0,3],
+            vec![4,0,0,8,0,3,0,0,1],
+            vec![7,0,0,0,2,0,0,0,6],
+            vec![0,6,0,0,0,0,2,8,0],
+            vec![0,0,0,4,1,9,0,0,5],
+            vec![0,0,0,0,8,0,0,7,9],
+        ];
+        assert!(matches!(GridBuilder::new(value.clone()).build(), Err(SudokuError::RuleViolation { .. })));
 
-        let mut solver = Solver::new(grid);
+        // Grids built without the builder still reach the solver's own check
+        let mut solver = Solver::new(Grid::from(&Board::new(&value)));
         assert!(solver.solve().is_err(), "Should fail with invalid board");
     }
 
@@ -447,7 +1577,7 @@ mod tests {
     fn test_empty_board() {
         let grid = Grid {
             value: vec![vec![0; 9]; 9],
-            solution: vec![
+            solution: Some(vec![
                 vec![1,2,3,4,5,6,7,8,9],
                 vec![4,5,6,7,8,9,1,2,3],
                 vec![7,8,9,1,2,3,4,5,6],
@@ -457,15 +1587,16 @@ mod tests {
                 vec![3,1,2,6,4,5,9,7,8],
                 vec![6,4,5,9,7,8,3,1,2],
                 vec![9,7,8,3,1,2,6,4,5],
-            ],
+            ]),
             difficulty: "Easy".to_string(),
+            metadata: Default::default(),
         };
 
-        let mut solver = Solver::new(grid);
+        let mut solver = Solver::new(grid).with_uniqueness_check();
         let solution = solver.solve().unwrap();
         
         // Verify that we found a valid solution
-        assert!(solver.is_valid_solution(&Board::new(&solution)));
+        assert!(solver.is_valid_solution(&solution.board));
         
         // Note: The solution might not match the API's solution since an empty board
         // has multiple valid solutions
@@ -486,7 +1617,7 @@ mod tests {
                 vec![2,8,7,4,1,9,6,3,5],
                 vec![3,4,5,2,8,6,1,7,9],
             ],
-            solution: vec![
+            solution: Some(vec![
                 vec![5,3,4,6,7,8,9,1,2],
                 vec![6,7,2,1,9,5,3,4,8],
                 vec![1,9,8,3,4,2,5,6,7],
@@ -496,20 +1627,322 @@ mod tests {
                 vec![9,6,1,5,3,7,2,8,4],
                 vec![2,8,7,4,1,9,6,3,5],
                 vec![3,4,5,2,8,6,1,7,9],
-            ],
+            ]),
             difficulty: "Easy".to_string(),
+            metadata: Default::default(),
         };
 
-        let mut solver = Solver::new(grid);
+        let mut solver = Solver::new(grid).with_uniqueness_check();
         solver.solve().unwrap();
         assert!(solver.has_unique_solution(), "Board with one empty cell should have unique solution");
         assert!(solver.verify_solution(), "Solution should match API's solution");
     }
 
+    #[test]
+    fn test_solver_with_pencilmarks() {
+        let grid = GridBuilder::new(vec![vec![0; 9]; 9]).with_difficulty("Easy").build().unwrap();
+
+        // Only allow 9 in the top-left cell and 1 in the cell to its right
+        let mut marks = PencilMarks::all();
+        let mut nine = CandidateSet::empty();
+        nine.add_candidate(9);
+        let mut one = CandidateSet::empty();
+        one.add_candidate(1);
+        marks.set(0, 0, nine);
+        marks.set(0, 1, one);
+
+        let mut solver = Solver::new(grid).with_pencilmarks(&marks);
+        let solution = solver.solve().unwrap().board;
+        assert_eq!(solution.get(0, 0), 9);
+        assert_eq!(solution.get(0, 1), 1);
+        assert!(solver.is_valid_solution(&solution));
+    }
+
+    #[test]
+    fn test_uniqueness_check() {
+        // Two solutions, swapping 6 and 7 around a rectangle in rows 1 and 4
+        let grid = Grid::from(&crate::providers::fixture::get("deadly_rectangle").unwrap().board());
+        assert_eq!(Solver::new(grid.clone()).solve().unwrap().unique, None);
+        let mut solver = Solver::new(grid.clone()).with_uniqueness_check();
+        assert_eq!(solver.solve().unwrap().unique, Some(false));
+        assert!(!solver.has_unique_solution());
+
+        // Pencil marks that rule one of them out leave a unique solution
+        let mut marks = PencilMarks::all();
+        let mut six = CandidateSet::empty();
+        six.add_candidate(6);
+        marks.set(0, 3, six);
+        let mut solver = Solver::new(grid).with_pencilmarks(&marks).with_uniqueness_check();
+        let solution = solver.solve().unwrap();
+        assert_eq!((solution.board.get(0, 3), solution.unique), (6, Some(true)));
+        assert!(solver.has_unique_solution());
+    }
+
+    #[test]
+    fn test_solve_timeout() {
+        let puzzle =
+            Board::parse("8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..").unwrap();
+        let grid = GridBuilder::from_board(&puzzle).with_difficulty("Hard").build().unwrap();
+
+        let mut solver = Solver::new(grid.clone()).with_timeout(Duration::ZERO);
+        assert!(matches!(solver.solve(), Err(SudokuError::SolveTimeout)));
+
+        let mut solver = Solver::new(grid.clone()).with_timeout(Duration::from_secs(60));
+        // There is no reference solution to compare with
+        assert_eq!(solver.solve().unwrap().matches_reference, None);
+        assert!(solver.verify_solution());
+
+        // Too long to add to an Instant, so no deadline at all
+        let mut solver = Solver::new(grid).with_uniqueness_check().with_timeout(Duration::MAX);
+        assert_eq!(solver.solve().unwrap().unique, Some(true));
+
+        // The uniqueness check keeps to the deadline too
+        solver.deadline = Some(Instant::now());
+        assert!(matches!(solver.is_unique_by_deadline(&puzzle), Err(SudokuError::SolveTimeout)));
+    }
+
+    #[test]
+    fn test_sample_solution() {
+        use rand::{rngs::SmallRng, SeedableRng};
+        use std::collections::HashMap;
+
+        // Removing a full row from a solved grid leaves a handful of solutions
+        let mut puzzle = Board::parse(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        for col in 0..9 {
+            puzzle.set(8, col, 0);
+            puzzle.set(7, col, 0);
+        }
+        let solutions = enumerate_solutions(&puzzle, 100);
+        assert!(solutions.len() > 1);
+
+        let grid = Grid::from(&puzzle);
+        let solver = Solver::new(grid);
+        let mut rng = SmallRng::seed_from_u64(5);
+        let mut counts: HashMap<Board, usize> = HashMap::new();
+        let draws = 200 * solutions.len();
+        for _ in 0..draws {
+            *counts.entry(solver.sample_solution(&mut rng).unwrap()).or_default() += 1;
+        }
+        assert_eq!(counts.len(), solutions.len());
+        assert!(counts.values().all(|&n| n > 120 && n < 280), "{:?}", counts.values());
+
+        // Large solution spaces fall back to weighted descent
+        let empty = Solver::new(Grid::from(&Board::empty()));
+        assert!(SimdValidator::validate_solution(&empty.sample_solution(&mut rng).unwrap()));
+    }
+
+    #[test]
+    fn test_sample_solution_beyond_limit() {
+        use rand::{rngs::SmallRng, SeedableRng};
+        use std::collections::HashSet;
+
+        // Emptying the last four rows leaves 1284 solutions, more than are
+        // enumerated, and the descent splits them 732 / 552 on (5, 0)
+        let puzzle = Board::parse(
+            "534678912672195348198342567859761423426853791....................................",
+        )
+        .unwrap();
+        let solutions: HashSet<Board> = enumerate_solutions(&puzzle, 10_000).into_iter().collect();
+        assert_eq!(solutions.len(), 1284);
+        let threes = solutions.iter().filter(|s| s.get(5, 0) == 3).count();
+        assert_eq!(threes, 732);
+
+        // Every candidate weighed leaves fewer solutions than the cap, so
+        // the draw is uniform: the split follows the counts, and few draws repeat
+        let draws = 120;
+        let mut rng = SmallRng::seed_from_u64(7);
+        let samples: Vec<Board> = (0..draws).map(|_| sample_solution_of(&puzzle, &mut rng).unwrap()).collect();
+        assert!(samples.iter().all(|sample| solutions.contains(sample)));
+        let share = samples.iter().filter(|s| s.get(5, 0) == 3).count() as f64 / draws as f64;
+        assert!((share - 732.0 / 1284.0).abs() < 0.15, "{}", share);
+        // About 115 distinct are expected from 120 uniform draws of 1284
+        let distinct = samples.iter().collect::<HashSet<_>>().len();
+        assert!(distinct >= 105, "{}", distinct);
+    }
+
+    #[test]
+    fn test_candidate_grid() {
+        let puzzle = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let grid = Grid::from(&puzzle);
+        let marks = PencilMarks::from(Solver::new(grid.clone()).candidate_grid());
+        assert_eq!(marks, PencilMarks::from_board(&puzzle));
+
+        // Pencil marks given to the solver show up in the grid
+        let mut only_one = PencilMarks::all();
+        let mut one = CandidateSet::empty();
+        one.add_candidate(1);
+        only_one.set(0, 2, one);
+        let candidates = Solver::new(grid).with_pencilmarks(&only_one).candidate_grid();
+        assert_eq!(candidates[0][2], one);
+        assert_eq!(candidates[0][0].iter_candidates().collect::<Vec<_>>(), [5]);
+    }
+
+    #[test]
+    fn test_naked_subset_pruning() {
+        let puzzle = Board::parse(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        )
+        .unwrap();
+        let grid = Grid::from(&puzzle);
+        let plain = Solver::new(grid.clone());
+        let mut pruned = Solver::new(grid).with_naked_subsets().with_uniqueness_check();
+        let count = |solver: &Solver| solver.candidates.iter().map(|set| set.count_candidates()).sum::<u32>();
+        assert!(count(&pruned) < count(&plain));
+
+        let solution = pruned.solve().unwrap();
+        assert_eq!(solution.unique, Some(true));
+        assert!(SimdValidator::validate_solution(&solution.board));
+        for cell in 0..81 {
+            let value = solution.board.cells[cell];
+            assert!(puzzle.cells[cell] != 0 || pruned.candidates[cell].has_candidate(value));
+        }
+    }
+
+    #[test]
+    fn test_solve_bounded() {
+        let grid = |board: &Board| Grid::from(board);
+        let hard = Board::parse(
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+        )
+        .unwrap();
+
+        let bounded = Solver::new(grid(&hard)).solve_bounded(Budget::default()).unwrap();
+        assert!(bounded.solved);
+        assert_eq!(bounded.unique, Some(true));
+        assert_eq!(bounded.filled, bounded.stats.empty_cells);
+        assert!(SimdValidator::validate_solution(&bounded.board));
+        let needed = bounded.stats.nodes;
+        // This one takes guessing, so the search hits dead ends and not every placement is forced
+        assert!(bounded.backtracks > 0 && bounded.forced < needed);
+
+        // Out of nodes: a partial board that keeps the givens and breaks no rule
+        let partial = Solver::new(grid(&hard)).solve_bounded(Budget::nodes(needed / 2)).unwrap();
+        assert!(!partial.solved);
+        assert_eq!(partial.unique, None);
+        assert_eq!(partial.stats.nodes, needed / 2);
+        assert!(partial.filled > 0 && partial.filled < partial.stats.empty_cells);
+        assert!(partial.board.is_consistent());
+        assert!(hard.cells.iter().zip(&partial.board.cells).all(|(&g, &v)| g == 0 || g == v));
+
+        // Out of time
+        let timed = Solver::new(grid(&hard)).solve_bounded(Budget::time(Duration::ZERO).with_nodes(needed)).unwrap();
+        assert!(!timed.solved);
+        assert_eq!(timed.stats.nodes, 0);
+
+        // A budget too long to add to an Instant is no time limit
+        let huge = Solver::new(grid(&hard)).solve_bounded(Budget::time(Duration::MAX)).unwrap();
+        assert!(huge.solved);
+        assert_eq!(huge.unique, Some(true));
+
+        // Several solutions show up as not unique; none is an error
+        let empty = Solver::new(Grid::from(&Board::empty())).solve_bounded(Budget::nodes(10_000)).unwrap();
+        assert_eq!((empty.solved, empty.unique), (true, Some(false)));
+        let stuck = Board::parse(&format!("12345678.........9{}", ".".repeat(63))).unwrap();
+        assert!(matches!(Solver::new(grid(&stuck)).solve_bounded(Budget::default()), Err(SudokuError::InvalidBoard)));
+    }
+
+    #[test]
+    fn test_parallelism() {
+        let puzzle = Board::parse(
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+        )
+        .unwrap();
+        let grid = Grid::from(&puzzle);
+        let expected = enumerate_solutions(&puzzle, 1).pop();
+        for parallelism in [Parallelism::Global, Parallelism::threads(2), Parallelism::threads(1)] {
+            let solution = Solver::new(grid.clone()).with_parallelism(parallelism).solve().unwrap();
+            assert_eq!(Some(solution.board), expected, "{:?}", parallelism);
+        }
+        assert_eq!(Parallelism::threads(1), Parallelism::Sequential);
+        assert!(Arc::ptr_eq(&pool(2).unwrap(), &pool(2).unwrap()));
+        assert_eq!(pool(3).unwrap().current_num_threads(), 3);
+    }
+
+    #[test]
+    fn test_transposition_table() {
+        // Three empty rows leave many solutions, reached through many orders
+        let mut puzzle = Board::parse(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        for row in 0..3 {
+            for col in 0..9 {
+                puzzle.set(row, col, 0);
+            }
+        }
+        let exact = count_solutions_up_to(&puzzle, usize::MAX);
+        assert!(exact > 100);
+        let mut table = TranspositionTable::new(10_000);
+        assert_eq!(count_solutions_memoized(&puzzle, usize::MAX, &mut table), exact);
+        assert!(table.hits > 0);
+
+        // Capped counts agree too, even once the table is full
+        for (limit, entries) in [(1, 10_000), (50, 10_000), (exact + 1, 5)] {
+            let mut table = TranspositionTable::new(entries);
+            assert_eq!(count_solutions_memoized(&puzzle, limit, &mut table), exact.min(limit));
+        }
+
+        let grid = Grid::from(&puzzle);
+        let mut solver = Solver::new(grid).with_transposition_table(10_000).with_uniqueness_check();
+        assert_eq!(solver.count_solutions(50), 50);
+        assert_eq!(solver.solve().unwrap().unique, Some(false));
+    }
+
+    #[test]
+    fn test_forced_moves() {
+        let puzzle = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let solution = Board::parse(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        let grid = Grid::from(&puzzle);
+        let moves = Solver::new(grid.clone()).forced_moves();
+        assert!(!moves.is_empty());
+        for p in &moves {
+            assert_eq!(puzzle.get(p.row, p.col), 0);
+            assert_eq!(solution.get(p.row, p.col), p.value);
+        }
+        // Every single the logic solver would find first is among them
+        let step = crate::logic::LogicSolver::new(&puzzle).find_step().unwrap();
+        assert!(moves.contains(&step.placements[0]));
+
+        // Nothing is forced on a finished board or one with no givens
+        let mut solver = Solver::new(grid);
+        solver.solve().unwrap();
+        assert!(solver.forced_moves().is_empty());
+        let empty = Grid::from(&Board::empty());
+        assert!(Solver::new(empty).forced_moves().is_empty());
+    }
+
+    #[test]
+    fn test_solve_into() {
+        let mut board = Board::parse(
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+        )
+        .unwrap();
+        Solver::solve_into(&mut board).unwrap();
+        assert!(SimdValidator::validate_solution(&board));
+        assert_eq!(board.get(0, 0), 8);
+
+        let mut conflicting = Board::empty();
+        conflicting.set(0, 0, 5);
+        conflicting.set(0, 1, 5);
+        assert!(Solver::solve_into(&mut conflicting).is_err());
+        assert_eq!(conflicting.get(0, 2), 0);
+    }
+
     #[test]
     fn test_simd_solution_validation() {
-        let grid = Grid {
-            value: vec![
+        let grid = GridBuilder::new(vec![
                 vec![5,3,4,6,7,8,9,1,2],
                 vec![6,7,2,1,9,5,3,4,8],
                 vec![1,9,8,3,4,2,5,6,7],
@@ -519,12 +1952,84 @@ mod tests {
                 vec![9,6,1,5,3,7,2,8,4],
                 vec![2,8,7,4,1,9,6,3,5],
                 vec![3,4,5,2,8,6,1,7,9],
-            ],
-            solution: vec![vec![0; 9]; 9],  // Not needed for this test
-            difficulty: "Test".to_string(),
-        };
+            ])
+            .with_difficulty("Test")
+            .build()
+            .unwrap();
 
         let board = Board::new(&grid.value);
         assert!(SimdValidator::validate_solution(&board));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_warm_start() {
+        let fixture = crate::providers::fixture::get("hard").unwrap();
+        let grid = fixture.grid().unwrap();
+        let puzzle = Board::new(&grid.value);
+        let solution = grid.solution_board().unwrap();
+        let (row, col) = (0..81).map(|i| (i / 9, i % 9)).find(|&(r, c)| puzzle.get(r, c) == 0).unwrap();
+
+        // Candidates left by a placement match those computed from scratch
+        let mut state = Solver::new(grid.clone()).with_uniqueness_check().state();
+        assert!(state.place(row, col, puzzle.get(row, (0..9).find(|&c| puzzle.get(row, c) != 0).unwrap())).is_err());
+        state.place(row, col, solution.get(row, col)).unwrap();
+        assert!(state.place(row, col, solution.get(row, col)).is_err());
+        let mut placed = puzzle.clone();
+        placed.set(row, col, solution.get(row, col));
+        let fresh = Solver::new(Grid { value: placed.to_vec(), ..grid.clone() });
+        let mut resumed = Solver::from_state(state);
+        assert_eq!(resumed.candidate_grid(), fresh.candidate_grid());
+        assert_eq!(resumed.forced_moves(), fresh.forced_moves());
+        let solved = resumed.solve().unwrap();
+        assert!(solved.matches_reference == Some(true) && solved.unique == Some(true));
+
+        // Eliminations carry over, and so does the work done
+        let mut marks = PencilMarks::from_board(&puzzle);
+        let (r, c) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| puzzle.get(r, c) == 0 && marks.get(r, c).count_candidates() > 1)
+            .unwrap();
+        let wrong = marks.get(r, c).iter_candidates().find(|&v| v != solution.get(r, c)).unwrap();
+        let mut narrowed = marks.get(r, c);
+        narrowed.remove_candidate(wrong);
+        marks.set(r, c, narrowed);
+        let mut solver = Solver::new(grid).with_pencilmarks(&marks).with_timeout(Duration::from_secs(30));
+        let state = solver.state();
+        assert!(!state.candidates(r, c).has_candidate(wrong));
+        let resumed = Solver::from_state(state);
+        assert!(!resumed.candidate_grid()[r][c].has_candidate(wrong));
+        assert_eq!(resumed.timeout, Some(Duration::from_secs(30)));
+        let nodes = solver.solve().unwrap().stats.nodes;
+        assert_eq!(Solver::from_state(solver.state()).state().nodes, nodes);
+    }
+
+    #[tokio::test]
+    async fn test_solve_stream() {
+        use futures_util::StreamExt;
+
+        let mut sparse = Board::empty();
+        sparse.set(0, 0, 1);
+        sparse.set(0, 1, 2);
+        let events: Vec<SolveEvent> = Solver::new(Grid::from(&sparse)).solve_stream().collect().await;
+        let guesses = events.iter().filter(|e| matches!(e, SolveEvent::Guess { .. })).count();
+        assert!(guesses > 0);
+        // The stream follows the same search a replay records
+        assert_eq!(crate::replay::Replay::record(&sparse).guesses(), guesses);
+        let Some(SolveEvent::Solved { board }) = events.last() else {
+            panic!("expected a solution last, got {:?}", events.last());
+        };
+        let solved = Board::parse(board).unwrap();
+        assert!(crate::validate_solution(&solved) && solved.get(0, 1) == 2);
+
+        let mut conflicting = Board::empty();
+        conflicting.set(0, 0, 5);
+        conflicting.set(0, 1, 5);
+        let events: Vec<SolveEvent> = Solver::new(Grid::from(&conflicting)).solve_stream().collect().await;
+        assert_eq!(events, vec![SolveEvent::Unsolvable]);
+
+        // Dropping the stream part way stops the search
+        let mut stream = Box::pin(Solver::new(Grid::from(&Board::empty())).solve_stream());
+        assert!(stream.next().await.is_some());
+        drop(stream);
+    }
+}
@@ -0,0 +1,222 @@
+//! Statistics over a puzzle collection.
+//!
+//! `analyze` reads a collection in any of the `formats` and reports how the
+//! puzzles are spread over clue counts, difficulties and hardest techniques,
+//! how many are the same puzzle in disguise (equal `symmetry::canonical_form`),
+//! and which break the one-solution rule. Puzzles are analyzed in parallel a
+//! chunk at a time, so memory stays bounded apart from one hash per distinct
+//! puzzle.
+
+use crate::{
+    formats::{self, Format},
+    logic::Technique,
+    rating, solver, symmetry, Board, Result,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+use std::time::{Duration, Instant};
+
+/// Puzzles analyzed at a time
+const CHUNK_SIZE: usize = 4096;
+
+/// Widest bar `CollectionStats::print` draws
+const BAR_WIDTH: usize = 40;
+
+/// A puzzle without exactly one solution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UniquenessViolation {
+    /// Position of the puzzle in the collection, from 1
+    pub entry: usize,
+    /// The puzzle as an 81-character line
+    pub puzzle: String,
+    /// 0, or 2 for two or more
+    pub solutions: usize,
+}
+
+/// What `analyze` found
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CollectionStats {
+    pub puzzles: usize,
+    /// Entries that aren't puzzles; they are skipped
+    pub invalid: usize,
+    /// Puzzles per number of givens
+    pub clues: BTreeMap<usize, usize>,
+    /// Puzzles per difficulty label; puzzles whose givens clash aren't rated
+    pub difficulties: BTreeMap<&'static str, usize>,
+    /// Puzzles per hardest technique needed; logic-free puzzles aren't counted
+    pub hardest: BTreeMap<Technique, usize>,
+    /// Puzzles logic alone couldn't finish
+    pub unsolved_by_logic: usize,
+    /// Distinct puzzles up to relabelling, reflection and row or column swaps
+    pub distinct: usize,
+    /// Puzzles equivalent to one earlier in the collection
+    pub duplicates: usize,
+    /// Puzzles with no solution or several, in collection order
+    pub violations: Vec<UniquenessViolation>,
+    pub elapsed: Duration,
+}
+
+/// What is learned from one puzzle
+struct Analysis {
+    clues: usize,
+    /// None when the givens clash
+    rating: Option<rating::Rating>,
+    class: u64,
+    solutions: usize,
+}
+
+fn analyze_board(board: &Board) -> Analysis {
+    let consistent = board.is_consistent();
+    Analysis {
+        clues: board.cells.iter().filter(|&&value| value != 0).count(),
+        rating: consistent.then(|| rating::rate(board)),
+        class: symmetry::canonical_hash(board),
+        solutions: if consistent { solver::count_solutions_up_to(board, 2) } else { 0 },
+    }
+}
+
+/// Analyzes every puzzle in `reader`, guessing the format unless `format` is
+/// given. Malformed entries are counted as invalid; read errors end the run.
+pub fn analyze<R: BufRead>(reader: R, format: Option<Format>) -> Result<CollectionStats> {
+    let start = Instant::now();
+    let mut stats = CollectionStats::default();
+    let mut classes: HashMap<u64, usize> = HashMap::new();
+    let mut records = formats::read(reader, format);
+    // Each puzzle with its position in the collection
+    let mut chunk: Vec<(usize, Board)> = Vec::with_capacity(CHUNK_SIZE);
+    let mut entries = 0;
+    loop {
+        chunk.clear();
+        for record in records.by_ref() {
+            entries += 1;
+            match record {
+                Ok(grid) => chunk.push((entries, Board::new(&grid.value))),
+                Err(e) if e.is_invalid_input() => stats.invalid += 1,
+                Err(e) => return Err(e),
+            }
+            if chunk.len() == CHUNK_SIZE {
+                break;
+            }
+        }
+        if chunk.is_empty() {
+            break;
+        }
+
+        let analyses: Vec<Analysis> = chunk.par_iter().map(|(_, board)| analyze_board(board)).collect();
+        for ((entry, board), analysis) in chunk.iter().zip(analyses) {
+            stats.puzzles += 1;
+            *stats.clues.entry(analysis.clues).or_insert(0) += 1;
+            if let Some(rating) = analysis.rating {
+                *stats.difficulties.entry(rating.difficulty()).or_insert(0) += 1;
+                if let Some(technique) = rating.hardest {
+                    *stats.hardest.entry(technique).or_insert(0) += 1;
+                }
+                if !rating.solved {
+                    stats.unsolved_by_logic += 1;
+                }
+            }
+            let seen = classes.entry(analysis.class).or_insert(0);
+            *seen += 1;
+            if *seen > 1 {
+                stats.duplicates += 1;
+            }
+            if analysis.solutions != 1 {
+                stats.violations.push(UniquenessViolation {
+                    entry: *entry,
+                    puzzle: board.to_line(),
+                    solutions: analysis.solutions,
+                });
+            }
+        }
+    }
+    stats.distinct = classes.len();
+    stats.elapsed = start.elapsed();
+    Ok(stats)
+}
+
+impl CollectionStats {
+    /// Puzzles with exactly one solution
+    pub fn unique(&self) -> usize {
+        self.puzzles - self.violations.len()
+    }
+
+    pub fn print(&self) {
+        let percent = |count: usize| 100.0 * count as f64 / self.puzzles.max(1) as f64;
+        let bar = |count: usize, most: usize| match (count * BAR_WIDTH).div_ceil(most.max(1)) {
+            0 => String::new(),
+            width => format!(" {}", "#".repeat(width)),
+        };
+        println!("\n{} puzzles analyzed in {:.2?}", self.puzzles, self.elapsed);
+        if self.invalid > 0 {
+            println!("  Invalid entries skipped: {}", self.invalid);
+        }
+
+        println!("\n  Clues:");
+        let most = self.clues.values().copied().max().unwrap_or(0);
+        for (&clues, &count) in &self.clues {
+            println!("    {:>2} {:>8} ({:5.1}%){}", clues, count, percent(count), bar(count, most));
+        }
+
+        println!("\n  By difficulty:");
+        let most = self.difficulties.values().copied().max().unwrap_or(0);
+        for label in ["Easy", "Medium", "Hard", "Expert"] {
+            let count = self.difficulties.get(label).copied().unwrap_or(0);
+            println!("    {:<18} {:>8} ({:5.1}%){}", label, count, percent(count), bar(count, most));
+        }
+        println!("  By hardest technique:");
+        let most = self.hardest.values().copied().max().unwrap_or(0);
+        for (technique, &count) in &self.hardest {
+            println!("    {:<18} {:>8} ({:5.1}%){}", technique.name(), count, percent(count), bar(count, most));
+        }
+        let unsolved = self.unsolved_by_logic;
+        println!("  {:<21}{:>8} ({:5.1}%)", "Unsolved by logic:", unsolved, percent(unsolved));
+
+        println!("\n  {:<21}{:>8}", "Distinct puzzles:", self.distinct);
+        println!("  {:<21}{:>8} ({:5.1}%)", "Duplicates:", self.duplicates, percent(self.duplicates));
+        println!("  {:<21}{:>8} ({:5.1}%)", "Unique solution:", self.unique(), percent(self.unique()));
+        for violation in &self.violations {
+            let solutions = if violation.solutions == 0 { "no solution" } else { "several solutions" };
+            println!("    #{:<8} {} {}", violation.entry, violation.puzzle, solutions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+    use crate::symmetry::Transform;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_analyze() {
+        let easy = fixture::get("easy").unwrap().board();
+        let hard = fixture::get("hard").unwrap().board();
+        let disguised = Transform::random(&mut StdRng::seed_from_u64(3)).apply(&easy);
+        let input = [
+            easy.to_line(),
+            "not a puzzle".to_string(),
+            hard.to_line(),
+            disguised.to_line(),
+            fixture::get("duplicate_in_row").unwrap().puzzle.to_string(),
+            fixture::get("empty").unwrap().puzzle.to_string(),
+        ]
+        .join("\n");
+        let stats = analyze(input.as_bytes(), None).unwrap();
+
+        assert_eq!((stats.puzzles, stats.invalid), (5, 1));
+        let clues = |board: &Board| board.cells.iter().filter(|&&v| v != 0).count();
+        assert_eq!(stats.clues.get(&clues(&easy)), Some(&2));
+        assert_eq!(stats.clues.values().sum::<usize>(), 5);
+        // The clashing puzzle isn't rated
+        assert_eq!(stats.difficulties.values().sum::<usize>(), 4);
+        assert_eq!(stats.difficulties.get("Easy"), Some(&2));
+        assert_eq!((stats.distinct, stats.duplicates), (4, 1));
+
+        let violations: Vec<(usize, usize)> = stats.violations.iter().map(|v| (v.entry, v.solutions)).collect();
+        assert_eq!(violations, [(5, 0), (6, 2)]);
+        assert_eq!(stats.unique(), 3);
+    }
+}
@@ -0,0 +1,268 @@
+//! Validity-preserving transformations of Sudoku grids.
+//!
+//! Relabelling digits, permuting bands and stacks, permuting rows within a
+//! band or columns within a stack, and transposing all map valid grids to
+//! valid grids (and puzzles to puzzles with the same number of solutions).
+
+use crate::Board;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// One element of the Sudoku symmetry group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transform {
+    /// `digits[d - 1]` is the digit that `d` becomes
+    pub digits: [u8; 9],
+    /// Row `i` of the result is row `rows[i]` of the source
+    pub rows: [usize; 9],
+    /// Column `j` of the result is column `cols[j]` of the source
+    pub cols: [usize; 9],
+    /// Swap rows and columns before reordering them
+    pub transpose: bool,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    /// The transform that leaves every grid unchanged
+    pub fn identity() -> Self {
+        Self {
+            digits: [1, 2, 3, 4, 5, 6, 7, 8, 9],
+            rows: [0, 1, 2, 3, 4, 5, 6, 7, 8],
+            cols: [0, 1, 2, 3, 4, 5, 6, 7, 8],
+            transpose: false,
+        }
+    }
+
+    /// Picks a transform uniformly from the whole group
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut digits = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        digits.shuffle(rng);
+        Self {
+            digits,
+            rows: random_line_order(rng),
+            cols: random_line_order(rng),
+            transpose: rng.gen(),
+        }
+    }
+
+    /// Applies the transform; empty cells stay empty
+    pub fn apply(&self, board: &Board) -> Board {
+        let mut result = Board::empty();
+        for row in 0..9 {
+            for col in 0..9 {
+                let (src_row, src_col) = (self.rows[row], self.cols[col]);
+                let value = if self.transpose {
+                    board.get(src_col, src_row)
+                } else {
+                    board.get(src_row, src_col)
+                };
+                if value != 0 {
+                    result.set(row, col, self.digits[value as usize - 1]);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Every order of the nine rows (or columns) that keeps bands together: 6^4 = 1296
+fn all_line_orders() -> Vec<[usize; 9]> {
+    const PERMS: [[usize; 3]; 6] = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+    let mut orders = Vec::with_capacity(1296);
+    for bands in PERMS {
+        for a in PERMS {
+            for b in PERMS {
+                for c in PERMS {
+                    let mut order = [0; 9];
+                    for (i, lines) in [a, b, c].iter().enumerate() {
+                        for (j, &line) in lines.iter().enumerate() {
+                            order[i * 3 + j] = bands[i] * 3 + line;
+                        }
+                    }
+                    orders.push(order);
+                }
+            }
+        }
+    }
+    orders
+}
+
+/// Returns the canonical representative of a board's equivalence class.
+///
+/// Two boards have the same canonical form exactly when one can be turned
+/// into the other by a `Transform`. The form is the lexicographically
+/// smallest image (in row-major order, empty cells first) with digits
+/// relabelled in order of first appearance. For each transposition and
+/// column order the rows are picked greedily, branching only on ties and
+/// abandoning the column order once it can't beat the best form so far.
+pub fn canonical_form(board: &Board) -> Board {
+    let mut search = CanonicalSearch { best: [u8::MAX; 81], candidate: [0; 81], source: [0; 81], rows: [0; 9] };
+    for transpose in [false, true] {
+        for cols in all_line_orders() {
+            for row in 0..9 {
+                for (c, &src_col) in cols.iter().enumerate() {
+                    search.source[row * 9 + c] = if transpose {
+                        board.cells[src_col * 9 + row]
+                    } else {
+                        board.cells[row * 9 + src_col]
+                    };
+                }
+            }
+            search.place_row(0, 0, [0; 10], 1);
+        }
+    }
+    Board { cells: search.best }
+}
+
+/// Stable 64-bit hash of a board's canonical form, equal for equivalent boards
+pub fn canonical_hash(board: &Board) -> u64 {
+    crate::fnv1a(&canonical_form(board).cells)
+}
+
+/// Row-by-row search behind `canonical_form`
+struct CanonicalSearch {
+    best: [u8; 81],
+    candidate: [u8; 81],
+    /// The board with its columns already reordered
+    source: [u8; 81],
+    /// Source row placed at each result row so far
+    rows: [usize; 9],
+}
+
+impl CanonicalSearch {
+    fn place_row(&mut self, pos: usize, used: u16, labels: [u8; 10], next_label: u8) {
+        let prefix = pos * 9;
+        if self.candidate[..prefix] > self.best[..prefix] {
+            return;
+        }
+        if pos == 9 {
+            self.best = self.candidate;
+            return;
+        }
+
+        let mut options = Vec::with_capacity(9);
+        for row in self.allowed_rows(pos, used) {
+            let mut labels = labels;
+            let mut next_label = next_label;
+            let mut line = [0u8; 9];
+            for (cell, &value) in line.iter_mut().zip(&self.source[row * 9..row * 9 + 9]) {
+                if value != 0 {
+                    if labels[value as usize] == 0 {
+                        labels[value as usize] = next_label;
+                        next_label += 1;
+                    }
+                    *cell = labels[value as usize];
+                }
+            }
+            options.push((line, row, labels, next_label));
+        }
+        let Some(min) = options.iter().map(|option| option.0).min() else {
+            return;
+        };
+        // Only worth going on if this row can match or beat the best one
+        if self.candidate[..prefix] == self.best[..prefix] && min[..] > self.best[prefix..prefix + 9] {
+            return;
+        }
+
+        // Empty rows of one band are interchangeable, so try only one of them
+        let mut empty_bands_tried = 0u8;
+        for (line, row, labels, next_label) in options {
+            if line == min {
+                if line == [0; 9] {
+                    if empty_bands_tried & (1 << (row / 3)) != 0 {
+                        continue;
+                    }
+                    empty_bands_tried |= 1 << (row / 3);
+                }
+                self.candidate[prefix..prefix + 9].copy_from_slice(&line);
+                self.rows[pos] = row;
+                self.place_row(pos + 1, used | 1 << row, labels, next_label);
+            }
+        }
+    }
+
+    /// Source rows that may go at result row `pos`, keeping bands together
+    fn allowed_rows(&self, pos: usize, used: u16) -> impl Iterator<Item = usize> {
+        let band = (!pos.is_multiple_of(3)).then(|| self.rows[pos - 1] / 3);
+        (0..9).filter(move |&row| {
+            let unused = used & (1 << row) == 0;
+            match band {
+                // Later rows of a band come from the same source band
+                Some(band) => unused && row / 3 == band,
+                // A new band starts from a band with no rows used yet
+                None => used & (0b111 << (row / 3 * 3)) == 0,
+            }
+        })
+    }
+}
+
+/// Random order of the nine rows (or columns) that keeps bands together
+fn random_line_order<R: Rng + ?Sized>(rng: &mut R) -> [usize; 9] {
+    let mut bands = [0, 1, 2];
+    bands.shuffle(rng);
+    let mut order = [0; 9];
+    for (i, &band) in bands.iter().enumerate() {
+        let mut lines = [0, 1, 2];
+        lines.shuffle(rng);
+        for (j, &line) in lines.iter().enumerate() {
+            order[i * 3 + j] = band * 3 + line;
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::SimdValidator;
+    use crate::solver::count_solutions_up_to;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn test_canonical_form() {
+        let puzzle = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let canonical = canonical_form(&puzzle);
+        let mut rng = SmallRng::seed_from_u64(2);
+        for _ in 0..3 {
+            let moved = Transform::random(&mut rng).apply(&puzzle);
+            assert_eq!(canonical_form(&moved), canonical);
+        }
+        assert_eq!(count_solutions_up_to(&canonical, 2), 1);
+
+        let other = Board::parse(
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+        )
+        .unwrap();
+        assert_ne!(canonical_form(&other), canonical);
+        assert_ne!(canonical_hash(&other), canonical_hash(&puzzle));
+        assert_eq!(canonical_form(&Board::empty()), Board::empty());
+    }
+
+    #[test]
+    fn test_transforms_preserve_validity() {
+        let mut solution = Board::parse(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let puzzle = solution.clone();
+        crate::solver::Solver::solve_into(&mut solution).unwrap();
+        assert_eq!(Transform::identity().apply(&solution), solution);
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let transform = Transform::random(&mut rng);
+            assert!(SimdValidator::validate_solution(&transform.apply(&solution)));
+            let moved = transform.apply(&puzzle);
+            assert_eq!(moved.cells.iter().filter(|&&v| v != 0).count(), 30);
+            assert_eq!(count_solutions_up_to(&moved, 2), 1);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! OpenTelemetry trace export (feature `otlp`).
+//!
+//! `otlp_layer` builds a `tracing-subscriber` layer that ships spans over
+//! OTLP/HTTP to a collector such as Jaeger or Tempo. Together with the `trace`
+//! spans this covers server requests, API calls, benchmark boards and the
+//! solver phases underneath them.
+//!
+//! ```ignore
+//! let (layer, telemetry) = telemetry::otlp_layer(&OtlpConfig::default())?;
+//! tracing_subscriber::registry().with(layer).with(fmt::layer()).init();
+//! // ... run; dropping `telemetry` flushes what is left
+//! drop(telemetry);
+//! ```
+
+use crate::Result;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use tracing_opentelemetry::OpenTelemetryLayer;
+
+/// Path OTLP/HTTP collectors accept traces on
+const TRACES_PATH: &str = "/v1/traces";
+
+/// Where and as what to export traces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtlpConfig {
+    /// Collector base URL, e.g. `http://localhost:4318`. When unset, the
+    /// standard `OTEL_EXPORTER_OTLP_*` environment variables apply.
+    pub endpoint: Option<String>,
+    /// `service.name` reported with every span
+    pub service_name: String,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            service_name: std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "sudoku".to_string()),
+        }
+    }
+}
+
+impl OtlpConfig {
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+
+    /// Full traces URL for an explicit endpoint
+    fn traces_url(&self) -> Option<String> {
+        self.endpoint.as_ref().map(|base| {
+            let base = base.trim_end_matches('/');
+            if base.ends_with(TRACES_PATH) {
+                base.to_string()
+            } else {
+                format!("{}{}", base, TRACES_PATH)
+            }
+        })
+    }
+}
+
+/// Keeps the exporter running; dropping it flushes buffered spans and stops it
+#[derive(Debug)]
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+}
+
+/// Builds a layer exporting spans as configured. Spans are batched and sent
+/// from a background thread, so export never blocks the traced code.
+pub fn otlp_layer<S>(config: &OtlpConfig) -> Result<(OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>, Telemetry)>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    let mut exporter = SpanExporter::builder().with_http();
+    if let Some(url) = config.traces_url() {
+        exporter = exporter.with_endpoint(url);
+    }
+    let exporter = exporter.build()?;
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(config.service_name.clone()).build())
+        .build();
+    let tracer = provider.tracer("sudoku");
+    Ok((tracing_opentelemetry::layer().with_tracer(tracer), Telemetry { provider }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_url() {
+        let config = |endpoint: &str| OtlpConfig::default().with_endpoint(endpoint);
+        assert_eq!(config("http://localhost:4318").traces_url().unwrap(), "http://localhost:4318/v1/traces");
+        assert_eq!(config("http://tempo:4318/").traces_url().unwrap(), "http://tempo:4318/v1/traces");
+        assert_eq!(config("http://tempo:4318/v1/traces").traces_url().unwrap(), "http://tempo:4318/v1/traces");
+        assert_eq!(OtlpConfig::default().traces_url(), None);
+    }
+}
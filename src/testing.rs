@@ -0,0 +1,231 @@
+//! Property-testing support (feature `testing`).
+//!
+//! `Arbitrary` impls for `Board`, `Grid` and `CandidateSet`, plus checks for
+//! the invariants every puzzle and solution must satisfy. Use them from
+//! `proptest!` blocks:
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn solves(grid: Grid) {
+//!         let mut board = Board::new(&grid.value);
+//!         Solver::solve_into(&mut board).unwrap();
+//!         check_solves(&Board::new(&grid.value), &board)?;
+//!     }
+//! }
+//! ```
+
+use crate::{diff::SideBySide, generator::BoardGenerator, Board, CandidateSet, Grid};
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+/// Complete valid grids, shrinking towards the grid of seed 0
+pub fn solutions() -> impl Strategy<Value = Board> {
+    any::<u64>().prop_map(|seed| BoardGenerator::with_seed(seed).random_solution())
+}
+
+/// Conflict-free puzzles: a valid grid with a random subset of cells emptied.
+/// They always have a solution but need not have a unique one. Shrinks
+/// towards fewer givens.
+pub fn puzzles() -> impl Strategy<Value = (Board, Board)> {
+    (solutions(), proptest::collection::vec(any::<bool>(), 81)).prop_map(|(solution, keep)| {
+        let mut puzzle = solution.clone();
+        for (cell, keep) in puzzle.cells.iter_mut().zip(keep) {
+            if !keep {
+                *cell = 0;
+            }
+        }
+        (puzzle, solution)
+    })
+}
+
+/// Boards with every cell drawn independently from 0-9, so givens often conflict
+pub fn any_cells() -> impl Strategy<Value = Board> {
+    proptest::collection::vec(0u8..=9, 81).prop_map(|values| {
+        let mut cells = [0u8; 81];
+        cells.copy_from_slice(&values);
+        Board { cells }
+    })
+}
+
+impl Arbitrary for Board {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Conflict-free puzzles, as from `puzzles`
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        puzzles().prop_map(|(puzzle, _)| puzzle).boxed()
+    }
+}
+
+impl Arbitrary for Grid {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Solvable grids whose `solution` is one solution of `value`
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        puzzles()
+            .prop_map(|(puzzle, solution)| Grid {
+                value: puzzle.to_vec(),
+                solution: Some(solution.to_vec()),
+                difficulty: "Unknown".to_string(),
+                metadata: Default::default(),
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for CandidateSet {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (0u16..=0x1FF).prop_map(CandidateSet).boxed()
+    }
+}
+
+/// Returns true if no two givens in a row, column or box share a digit
+pub fn is_consistent(board: &Board) -> bool {
+    board.is_consistent()
+}
+
+/// Checks that `board` has no conflicting givens
+pub fn check_consistent(board: &Board) -> Result<(), TestCaseError> {
+    if is_consistent(board) {
+        Ok(())
+    } else {
+        Err(TestCaseError::fail(format!("givens conflict: {}", board.to_line())))
+    }
+}
+
+/// Checks that `solution` is complete, valid, and keeps every given of `puzzle`
+pub fn check_solves(puzzle: &Board, solution: &Board) -> Result<(), TestCaseError> {
+    if solution.cells.contains(&0) || !is_consistent(solution) {
+        return Err(TestCaseError::fail(format!("not a valid solution: {}", solution.to_line())));
+    }
+    let changed = puzzle
+        .cells
+        .iter()
+        .zip(&solution.cells)
+        .position(|(&given, &value)| given != 0 && given != value);
+    match changed {
+        Some(cell) => Err(TestCaseError::fail(format!("solution changes the given at cell {}", cell))),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `actual` equals `expected`, failing with both boards printed
+/// side by side and the differing cells marked
+pub fn check_same(actual: &Board, expected: &Board) -> Result<(), TestCaseError> {
+    match actual.diff(expected).len() {
+        0 => Ok(()),
+        n => Err(TestCaseError::fail(format!(
+            "boards differ in {} cells:\n{}",
+            n,
+            SideBySide::new(actual, expected).with_labels("actual", "expected")
+        ))),
+    }
+}
+
+/// Checks that `puzzle` has exactly one solution
+pub fn check_unique(puzzle: &Board) -> Result<(), TestCaseError> {
+    match crate::solver::count_solutions_up_to(puzzle, 2) {
+        1 => Ok(()),
+        n => Err(TestCaseError::fail(format!("expected 1 solution, found {}{}", n, if n == 2 { "+" } else { "" }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_and_solve, solver::{count_solutions_up_to, Solver}, symmetry::Transform, validate_bytes, SudokuError};
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn solver_solves_any_solvable_grid(grid: Grid) {
+            let puzzle = Board::new(&grid.value);
+            let mut solution = puzzle.clone();
+            Solver::solve_into(&mut solution).unwrap();
+            check_solves(&puzzle, &solution)?;
+        }
+
+        #[test]
+        fn solver_rejects_inconsistent_boards(board in any_cells()) {
+            let mut solved = board.clone();
+            if Solver::solve_into(&mut solved).is_ok() {
+                check_consistent(&board)?;
+                check_solves(&board, &solved)?;
+            }
+        }
+
+        #[test]
+        fn transforms_preserve_validity(solution in solutions(), seed: u64) {
+            let moved = Transform::random(&mut SmallRng::seed_from_u64(seed)).apply(&solution);
+            check_solves(&Board::empty(), &moved)?;
+        }
+
+        #[test]
+        fn untrusted_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..200)) {
+            let _ = validate_bytes(&bytes);
+            let _ = parse_and_solve(&bytes);
+        }
+
+        #[test]
+        fn parse_and_solve_solves_any_line(board in any_cells()) {
+            match parse_and_solve(board.to_line().as_bytes()) {
+                Ok(solution) => check_solves(&board, &solution)?,
+                Err(_) => prop_assert!(!is_consistent(&board) || count_solutions_up_to(&board, 1) == 0),
+            }
+        }
+
+        #[test]
+        fn candidate_count_matches_iteration(set: CandidateSet) {
+            prop_assert_eq!(set.iter_candidates().count() as u32, set.count_candidates());
+            prop_assert_eq!(set.is_empty(), set.count_candidates() == 0);
+        }
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        let rows = |n: usize, len: usize, value: i32| serde_json::to_vec(&vec![vec![value; len]; n]).unwrap();
+        assert!(matches!(validate_bytes(&rows(10, 9, 0)), Err(SudokuError::ParseError(_))));
+        assert!(matches!(validate_bytes(&rows(9, 12, 0)), Err(SudokuError::ParseError(_))));
+        assert!(matches!(validate_bytes(&rows(9, 9, 300)), Err(SudokuError::InvalidValue { value: 300, .. })));
+        assert!(matches!(validate_bytes(&rows(9, 9, -1)), Err(SudokuError::InvalidValue { value: -1, .. })));
+        assert!(matches!(validate_bytes(&rows(9, 9, 1)), Err(SudokuError::InvalidBoard)));
+        assert!(validate_bytes(&rows(9, 9, 0)).is_ok());
+        assert!(matches!(validate_bytes(b"\xff\xfe"), Err(SudokuError::ParseError(_))));
+        assert!(matches!(validate_bytes(br#"{"value": [[1]]}"#), Err(SudokuError::Json(_))));
+
+        // Errors from other libraries stay reachable as the source
+        let err = validate_bytes(b"[[1, 2,").unwrap_err();
+        assert!(err.is_invalid_input() && !err.is_timeout());
+        let source = std::error::Error::source(&err).unwrap();
+        assert!(source.downcast_ref::<serde_json::Error>().is_some());
+        assert_eq!(err.to_string(), "Invalid JSON");
+        assert_eq!(err.report(), format!("Invalid JSON: {}", source));
+
+        let easy = crate::providers::fixture::get("easy").unwrap();
+        let json = serde_json::to_vec(&easy.grid().unwrap()).unwrap();
+        assert_eq!(parse_and_solve(&json).unwrap(), parse_and_solve(easy.puzzle.as_bytes()).unwrap());
+        assert!(matches!(parse_and_solve(crate::providers::fixture::get("no_candidates").unwrap().puzzle.as_bytes()), Err(SudokuError::InvalidBoard)));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn generated_puzzles_are_unique(seed: u64) {
+            let grid = BoardGenerator::with_seed(seed).generate().unwrap();
+            let puzzle = Board::new(&grid.value);
+            check_unique(&puzzle)?;
+            check_solves(&puzzle, &grid.solution_board().unwrap())?;
+            let mut solved = puzzle.clone();
+            Solver::solve_into(&mut solved).unwrap();
+            check_same(&solved, &grid.solution_board().unwrap())?;
+        }
+    }
+}
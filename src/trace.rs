@@ -0,0 +1,85 @@
+//! Optional `tracing` instrumentation (feature `trace`).
+//!
+//! With the feature enabled, solving, candidate precomputation, each technique
+//! attempt and the generator phases run inside spans, and counts such as
+//! search nodes, steps and removed clues are recorded as span fields. Attach
+//! any `tracing` subscriber (`tracing-subscriber`, an OTLP layer, ...) to see
+//! where the time goes. Without the feature the macros expand to nothing.
+//!
+//! Levels: whole solves and generator runs are `DEBUG`; per-technique and
+//! in-place searches, which run in hot loops, are `TRACE`.
+
+/// Enters a span until the end of the enclosing block.
+///
+/// `span!(DEBUG, "name", field = value, other = tracing::field::Empty)`
+macro_rules! span {
+    ($level:ident, $name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "trace")]
+        let _span = tracing::span!(tracing::Level::$level, $name $(, $($fields)*)?).entered();
+    };
+}
+
+/// Records a field declared as `Empty` on the current span. Without the
+/// feature the value is type-checked but never evaluated.
+macro_rules! record {
+    ($field:literal, $value:expr) => {
+        #[cfg(feature = "trace")]
+        tracing::Span::current().record($field, $value);
+        #[cfg(not(feature = "trace"))]
+        if false {
+            let _ = $value;
+        }
+    };
+}
+
+#[cfg(all(test, feature = "trace"))]
+mod tests {
+    use crate::{generator::BoardGenerator, logic::LogicSolver, providers::fixture, solver::Solver};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+    /// Collects span names and the fields recorded on them
+    #[derive(Clone, Default)]
+    struct Spans(Arc<Mutex<Vec<String>>>);
+
+    struct FieldNames<'a>(&'a mut Vec<String>);
+
+    impl tracing::field::Visit for FieldNames<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, _: &dyn std::fmt::Debug) {
+            self.0.push(field.name().to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for Spans {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+            self.0.lock().push(attrs.metadata().name().to_string());
+        }
+
+        fn on_record(&self, _: &Id, values: &Record<'_>, _: Context<'_, S>) {
+            values.record(&mut FieldNames(&mut self.0.lock()));
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let spans = Spans::default();
+        let subscriber = tracing_subscriber::registry().with(spans.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let grid = fixture::get("easy").unwrap().grid().unwrap();
+            Solver::new(grid.clone()).solve().unwrap();
+            LogicSolver::new(&crate::Board::new(&grid.value)).solve();
+            BoardGenerator::with_seed(1).generate_with_difficulty("Easy").unwrap();
+        });
+
+        let seen = spans.0.lock();
+        for name in [
+            "precompute_candidates", "solve", "search", "uniqueness_check", "nodes", "unique",
+            "logic_solve", "technique", "found", "steps",
+            "generate", "fill_board", "remove_clues", "removed",
+        ] {
+            assert!(seen.iter().any(|s| s == name), "no {} span or field", name);
+        }
+    }
+}
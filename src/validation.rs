@@ -0,0 +1,278 @@
+//! Diagnosis of puzzle input.
+//!
+//! `diagnose` checks a puzzle in the order its problems matter: whether it
+//! parses at all, whether any givens clash, whether it has a solution, and
+//! whether that solution is unique. Each failed check comes with the detail
+//! needed to fix it: the clashing cells, the contradiction an unsolvable
+//! puzzle runs into, or two solutions of an ambiguous one and where they
+//! differ.
+
+use crate::{
+    contradiction::{self, ContradictionTrace},
+    logic::{Unit, UNITS},
+    output::Status,
+    solver::enumerate_solutions,
+    Board,
+};
+use serde::Serialize;
+use std::fmt;
+
+/// Fewest givens any uniquely solvable puzzle has
+pub const MIN_UNIQUE_GIVENS: usize = 17;
+
+/// A digit given more than once in a unit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    pub unit: Unit,
+    pub value: u8,
+    /// `(row, col)` of every given of `value` in `unit`
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells: Vec<String> = self.cells.iter().map(|(row, col)| format!("r{}c{}", row + 1, col + 1)).collect();
+        write!(f, "{} has {} at {}", self.unit, self.value, cells.join(", "))
+    }
+}
+
+/// What `validate_puzzle` found on a board
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    /// Cells holding a digit
+    pub filled: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
+    /// Every cell is filled and no digit repeats
+    pub solved: bool,
+}
+
+impl ValidationReport {
+    /// True if no digit repeats in a row, column or box
+    pub fn is_valid(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// How many solutions a puzzle has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Solvability {
+    Unique,
+    Multiple,
+    NoSolution,
+}
+
+/// Everything `diagnose` found out about a puzzle
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnosis {
+    /// The puzzle as an 81-character line, or the raw input if it didn't parse
+    pub puzzle: String,
+    /// Why the input isn't a puzzle; nothing else is checked then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub givens: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Conflict>,
+    /// Unset when the input didn't parse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solvability: Option<Solvability>,
+    /// The solution when unique; two of them when there are several
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub solutions: Vec<String>,
+    /// Cells where the two solutions of an ambiguous puzzle differ
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub differing_cells: Vec<(usize, usize)>,
+    /// Why a puzzle with consistent givens has no solution
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contradiction: Option<ContradictionTrace>,
+}
+
+impl Diagnosis {
+    fn unparsable(input: &str, error: String) -> Self {
+        Self {
+            puzzle: input.trim().to_string(),
+            error: Some(error),
+            givens: 0,
+            conflicts: Vec::new(),
+            solvability: None,
+            solutions: Vec::new(),
+            differing_cells: Vec::new(),
+            contradiction: None,
+        }
+    }
+
+    /// Exit status matching the diagnosis
+    pub fn status(&self) -> Status {
+        if self.error.is_some() || !self.conflicts.is_empty() {
+            return Status::InvalidInput;
+        }
+        match self.solvability {
+            Some(Solvability::Unique) => Status::Solved,
+            Some(Solvability::Multiple) => Status::MultipleSolutions,
+            _ => Status::Unsolvable,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("Puzzle: {}", self.puzzle);
+        if let Some(error) = &self.error {
+            println!("  Structure:    invalid, {}", error);
+            return;
+        }
+        println!("  Structure:    ok, 81 cells with {} givens", self.givens);
+
+        if self.conflicts.is_empty() {
+            println!("  Consistency:  ok");
+        } else {
+            println!("  Consistency:  {} conflicts between givens", self.conflicts.len());
+            for conflict in &self.conflicts {
+                println!("    {}", conflict);
+            }
+        }
+
+        match self.solvability {
+            Some(Solvability::Unique) => {
+                println!("  Solvability:  solvable");
+                println!("  Uniqueness:   unique solution {}", self.solutions[0]);
+            }
+            Some(Solvability::Multiple) => {
+                println!("  Solvability:  solvable");
+                println!("  Uniqueness:   multiple solutions, for example");
+                for solution in &self.solutions {
+                    println!("    {}", solution);
+                }
+                let cells: Vec<String> = self.differing_cells.iter().map(|(row, col)| format!("r{}c{}", row + 1, col + 1)).collect();
+                println!("    which differ at {}", cells.join(", "));
+                if self.givens < MIN_UNIQUE_GIVENS {
+                    println!("    No puzzle with fewer than {} givens has a unique solution", MIN_UNIQUE_GIVENS);
+                }
+            }
+            Some(Solvability::NoSolution) => {
+                println!("  Solvability:  no solution");
+                if let Some(trace) = &self.contradiction {
+                    for line in trace.to_string().lines() {
+                        println!("    {}", line);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// Finds every digit given more than once in a row, column or box
+pub fn conflicts(board: &Board) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for (index, unit) in UNITS.iter().enumerate() {
+        for value in 1..=9 {
+            let cells: Vec<(usize, usize)> = unit
+                .iter()
+                .filter(|&&cell| board.cells[cell] == value)
+                .map(|&cell| (cell / 9, cell % 9))
+                .collect();
+            if cells.len() > 1 {
+                conflicts.push(Conflict { unit: Unit::from_index(index), value, cells });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Checks a parsed puzzle for conflicts, solvability and uniqueness
+pub fn diagnose(board: &Board) -> Diagnosis {
+    let mut diagnosis = Diagnosis {
+        puzzle: board.to_line(),
+        error: None,
+        givens: board.cells.iter().filter(|&&v| v != 0).count(),
+        conflicts: conflicts(board),
+        solvability: Some(Solvability::NoSolution),
+        solutions: Vec::new(),
+        differing_cells: Vec::new(),
+        contradiction: None,
+    };
+    if !diagnosis.conflicts.is_empty() {
+        return diagnosis;
+    }
+
+    let solutions = enumerate_solutions(board, 2);
+    match solutions.as_slice() {
+        [] => diagnosis.contradiction = contradiction::trace(board),
+        [unique] => {
+            diagnosis.solvability = Some(Solvability::Unique);
+            diagnosis.solutions.push(unique.to_line());
+        }
+        [first, second, ..] => {
+            diagnosis.solvability = Some(Solvability::Multiple);
+            diagnosis.solutions = vec![first.to_line(), second.to_line()];
+            diagnosis.differing_cells = (0..81)
+                .filter(|&cell| first.cells[cell] != second.cells[cell])
+                .map(|cell| (cell / 9, cell % 9))
+                .collect();
+        }
+    }
+    diagnosis
+}
+
+/// Parses untrusted input in any format `validate_bytes` accepts and
+/// diagnoses it; input that doesn't parse is diagnosed as such
+pub fn diagnose_bytes(bytes: &[u8]) -> Diagnosis {
+    match crate::parse_bytes(bytes) {
+        Ok(board) => diagnose(&board),
+        Err(e) => Diagnosis::unparsable(&String::from_utf8_lossy(bytes), e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture;
+
+    #[test]
+    fn test_validate_puzzle() {
+        let easy = fixture::get("easy").unwrap().board();
+        let report = crate::validate_puzzle(&easy);
+        assert!(report.is_valid() && !report.solved);
+        assert_eq!(report.filled, 30);
+
+        let solved = fixture::get("solved").unwrap().board();
+        assert!(crate::validate_puzzle(&solved).solved && crate::validate_solution(&solved));
+        assert!(!crate::validate_solution(&easy));
+
+        let duplicate = fixture::get("duplicate_in_row").unwrap().board();
+        let clashing = crate::validate_puzzle(&duplicate);
+        assert!(!clashing.is_valid() && !clashing.solved);
+        assert_eq!(clashing.conflicts, conflicts(&duplicate));
+    }
+
+    fn diagnose_fixture(name: &str) -> Diagnosis {
+        diagnose(&fixture::get(name).unwrap().board())
+    }
+
+    #[test]
+    fn test_diagnoses() {
+        let easy = diagnose_fixture("easy");
+        assert_eq!(easy.status(), Status::Solved);
+        assert_eq!(easy.givens, 30);
+        assert_eq!(easy.solutions.len(), 1);
+
+        let ambiguous = diagnose_fixture("deadly_rectangle");
+        assert_eq!(ambiguous.status(), Status::MultipleSolutions);
+        assert_eq!(ambiguous.differing_cells, vec![(0, 3), (0, 4), (3, 3), (3, 4)]);
+
+        let unsolvable = diagnose_fixture("no_candidates");
+        assert_eq!(unsolvable.status(), Status::Unsolvable);
+        assert!(unsolvable.contradiction.is_some());
+
+        let conflicting = diagnose_fixture("duplicate_in_row");
+        assert_eq!(conflicting.status(), Status::InvalidInput);
+        // The pair clashes in their row and their box
+        let clash = |unit| Conflict { unit, value: 1, cells: vec![(0, 0), (0, 1)] };
+        assert_eq!(conflicting.conflicts, vec![clash(Unit::Row(0)), clash(Unit::Box(0))]);
+        assert_eq!(conflicting.conflicts[0].to_string(), "row 1 has 1 at r1c1, r1c2");
+
+        let malformed = diagnose_bytes(b"123");
+        assert_eq!(malformed.status(), Status::InvalidInput);
+        assert!(malformed.error.unwrap().contains("expected 81 cells"));
+    }
+}
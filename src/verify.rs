@@ -0,0 +1,309 @@
+//! Differential verification of the solver backends.
+//!
+//! Every input is run through the candidate-table `Solver`, the bitmask
+//! search behind `Solver::solve_into`, the dancing-links solver (and with the
+//! `sat` feature, the SAT backend) and the `LogicSolver`, and their verdicts
+//! are compared: the same number of solutions (none, one, or several), the
+//! same solution when it is unique, and logical placements that every
+//! solution agrees with. Inputs are random
+//! grids with random cells emptied and the odd given corrupted, so they cover
+//! unique, ambiguous, unsolvable and conflicting puzzles.
+//!
+//! `Solver` can take a very long time on some sparse ambiguous puzzles, so it
+//! gets a time limit per puzzle; running out is counted, not treated as a
+//! mismatch.
+
+use crate::{
+    dlx,
+    generator::BoardGenerator,
+    logic::LogicSolver,
+    simd::SimdValidator,
+    solver::{enumerate_solutions, Solver},
+    Board, Grid, SudokuError,
+};
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Solution count every backend is asked for; enough to tell unique from ambiguous
+const SOLUTION_LIMIT: usize = 2;
+
+/// Time `Solver` gets per puzzle
+const SOLVER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many solutions a puzzle has, as far as the verifier cares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    NoSolution,
+    Unique,
+    Multiple,
+}
+
+impl Verdict {
+    fn from_count(count: usize) -> Self {
+        match count {
+            0 => Verdict::NoSolution,
+            1 => Verdict::Unique,
+            _ => Verdict::Multiple,
+        }
+    }
+}
+
+/// Agreed result for one puzzle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub verdict: Verdict,
+    /// `Solver` ran out of time, so only the other backends were compared
+    pub solver_timed_out: bool,
+}
+
+/// A puzzle on which the backends disagreed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mismatch {
+    /// 81-character puzzle line
+    pub puzzle: String,
+    pub detail: String,
+}
+
+/// Outcome of a verification run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub unique: usize,
+    pub multiple: usize,
+    pub unsolvable: usize,
+    pub solver_timeouts: usize,
+    pub mismatches: Vec<Mismatch>,
+    pub elapsed: Duration,
+}
+
+impl VerifyReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!("\nVerified {} puzzles in {:.2?}", self.checked, self.elapsed);
+        println!("  Unique:      {}", self.unique);
+        println!("  Ambiguous:   {}", self.multiple);
+        println!("  Unsolvable:  {}", self.unsolvable);
+        println!("  Timeouts:    {} (Solver over {:?})", self.solver_timeouts, SOLVER_TIMEOUT);
+        println!("  Mismatches:  {}", self.mismatches.len());
+        for mismatch in self.mismatches.iter().take(10) {
+            println!("    {}  {}", mismatch.puzzle, mismatch.detail);
+        }
+        if self.mismatches.len() > 10 {
+            println!("    ... and {} more", self.mismatches.len() - 10);
+        }
+    }
+}
+
+/// Builds the `index`th input of a run; inputs depend only on the seed and index
+pub fn random_input(seed: u64, index: usize) -> Board {
+    let mut rng = SmallRng::seed_from_u64(seed.wrapping_add(index as u64));
+    let mut board = BoardGenerator::with_seed(rng.gen()).random_solution();
+
+    // Around 30 givens random puzzles go from mostly ambiguous to mostly unique
+    let givens = rng.gen_range(20..=50);
+    let mut cells: Vec<usize> = (0..81).collect();
+    cells.shuffle(&mut rng);
+    for &cell in &cells[givens..] {
+        board.cells[cell] = 0;
+    }
+
+    // Now and then change a given, which may make the puzzle unsolvable or conflicting
+    if rng.gen_bool(0.1) {
+        let cell = cells[rng.gen_range(0..givens)];
+        board.cells[cell] = rng.gen_range(1..=9);
+    }
+    board
+}
+
+/// Runs every backend on `puzzle`, returning the agreed verdict or what went wrong
+pub fn check(puzzle: &Board) -> Result<Outcome, String> {
+    let search = enumerate_solutions(puzzle, SOLUTION_LIMIT);
+    let links = dlx::solve(puzzle, SOLUTION_LIMIT);
+    let verdict = Verdict::from_count(search.len());
+    if Verdict::from_count(links.len()) != verdict {
+        return Err(format!("bitmask search found {:?} but DLX found {:?}", verdict, Verdict::from_count(links.len())));
+    }
+
+    for solution in search.iter().chain(&links) {
+        if !solves(puzzle, solution) {
+            return Err(format!("invalid solution {}", solution.to_line()));
+        }
+    }
+    if verdict == Verdict::Unique && search[0] != links[0] {
+        return Err(format!("bitmask search solved to {} but DLX to {}", search[0].to_line(), links[0].to_line()));
+    }
+
+    #[cfg(feature = "sat")]
+    {
+        let models = crate::sat::solve(puzzle, SOLUTION_LIMIT);
+        if Verdict::from_count(models.len()) != verdict {
+            return Err(format!("bitmask search found {:?} but SAT found {:?}", verdict, Verdict::from_count(models.len())));
+        }
+        if let Some(model) = models.iter().find(|model| !solves(puzzle, model)) {
+            return Err(format!("SAT returned invalid solution {}", model.to_line()));
+        }
+        if verdict == Verdict::Unique && search[0] != models[0] {
+            return Err(format!("bitmask search solved to {} but SAT to {}", search[0].to_line(), models[0].to_line()));
+        }
+    }
+
+    let grid = Grid::from(puzzle);
+    let mut solver_timed_out = false;
+    match (Solver::new(grid).with_uniqueness_check().with_timeout(SOLVER_TIMEOUT).solve(), verdict) {
+        (Err(SudokuError::SolveTimeout), _) => solver_timed_out = true,
+        (Err(_), Verdict::NoSolution) => {}
+        (Err(e), _) => return Err(format!("Solver failed on a solvable puzzle: {}", e)),
+        (Ok(solution), Verdict::NoSolution) => {
+            return Err(format!("Solver solved an unsolvable puzzle to {}", solution.board.to_line()))
+        }
+        (Ok(solution), _) => {
+            if !solves(puzzle, &solution.board) {
+                return Err(format!("Solver returned invalid solution {}", solution.board.to_line()));
+            }
+            if solution.unique != Some(verdict == Verdict::Unique) {
+                return Err(format!("Solver reported unique = {:?} for a {:?} puzzle", solution.unique, verdict));
+            }
+            if verdict == Verdict::Unique && solution.board != search[0] {
+                return Err(format!("Solver solved to {} but search to {}", solution.board.to_line(), search[0].to_line()));
+            }
+        }
+    }
+
+    // Logical deductions are forced, so every solution must agree with them
+    if verdict != Verdict::NoSolution {
+        let mut logic = LogicSolver::new(puzzle);
+        logic.solve();
+        if logic.has_contradiction() {
+            return Err("logic solver hit a contradiction on a solvable puzzle".to_string());
+        }
+        for solution in search.iter().chain(&links) {
+            let disagrees = logic
+                .board()
+                .cells
+                .iter()
+                .zip(&solution.cells)
+                .position(|(&placed, &value)| placed != 0 && placed != value);
+            if let Some(cell) = disagrees {
+                return Err(format!("logic solver placed {} at cell {} but a solution has {}", logic.board().cells[cell], cell, solution.cells[cell]));
+            }
+        }
+        if logic.is_solved() && verdict != Verdict::Unique {
+            return Err("logic solver completed an ambiguous puzzle".to_string());
+        }
+    }
+    Ok(Outcome { verdict, solver_timed_out })
+}
+
+/// Checks `count` random inputs derived from `seed` in parallel
+pub fn run(count: usize, seed: u64) -> VerifyReport {
+    let start = Instant::now();
+    let results: Vec<(Board, Result<Outcome, String>)> = (0..count)
+        .into_par_iter()
+        .map(|index| {
+            let puzzle = random_input(seed, index);
+            let result = check(&puzzle);
+            (puzzle, result)
+        })
+        .collect();
+
+    let mut report = VerifyReport { checked: count, ..Default::default() };
+    for (puzzle, result) in results {
+        let outcome = match result {
+            Ok(outcome) => outcome,
+            Err(detail) => {
+                report.mismatches.push(Mismatch { puzzle: puzzle.to_line(), detail });
+                continue;
+            }
+        };
+        match outcome.verdict {
+            Verdict::Unique => report.unique += 1,
+            Verdict::Multiple => report.multiple += 1,
+            Verdict::NoSolution => report.unsolvable += 1,
+        }
+        if outcome.solver_timed_out {
+            report.solver_timeouts += 1;
+        }
+    }
+    report.elapsed = start.elapsed();
+    report
+}
+
+/// Checks a solver's answer independently of how it was found: the scalar
+/// validator has to accept `solution`, the SIMD validator has to agree with
+/// it, and every given of `puzzle` has to be kept. Run after every solve in
+/// paranoid mode (`solver::set_paranoid`).
+pub fn check_solution(puzzle: &Board, solution: &Board) -> crate::Result<()> {
+    let scalar = SimdValidator::validate_solution_scalar(solution);
+    let simd = SimdValidator::validate_solution(solution);
+    let failed = |detail: String| Err(SudokuError::VerificationFailed(format!("{} in {}", detail, solution.to_line())));
+    if scalar != simd {
+        return failed(format!("SIMD validator says {}, scalar validator says {}", simd, scalar));
+    }
+    if !scalar {
+        return failed("not a valid solution".to_string());
+    }
+    let changed = puzzle.cells.iter().zip(&solution.cells).position(|(&given, &value)| given != 0 && given != value);
+    if let Some(cell) = changed {
+        let (given, value) = (puzzle.cells[cell], solution.cells[cell]);
+        return failed(format!("given {} at r{}c{} changed to {}", given, cell / 9 + 1, cell % 9 + 1, value));
+    }
+    Ok(())
+}
+
+/// Returns true if `solution` is complete, valid, and keeps the givens of `puzzle`
+fn solves(puzzle: &Board, solution: &Board) -> bool {
+    crate::validate_solution(solution)
+        && puzzle.cells.iter().zip(&solution.cells).all(|(&given, &value)| given == 0 || given == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture::{FixtureKind, FIXTURES};
+
+    #[test]
+    fn test_fixture_verdicts() {
+        for fixture in FIXTURES {
+            let expected = match fixture.kind {
+                FixtureKind::Unique | FixtureKind::Solved => Verdict::Unique,
+                FixtureKind::MultipleSolutions => Verdict::Multiple,
+                FixtureKind::NoSolution | FixtureKind::Conflicting => Verdict::NoSolution,
+            };
+            assert_eq!(check(&fixture.board()).map(|outcome| outcome.verdict), Ok(expected), "{}", fixture.name);
+        }
+    }
+
+    #[test]
+    fn test_check_solution() {
+        let easy = crate::providers::fixture::get("easy").unwrap().board();
+        let mut solution = easy.clone();
+        Solver::solve_into(&mut solution).unwrap();
+        assert!(check_solution(&easy, &solution).is_ok());
+
+        // A valid grid that doesn't keep the givens
+        let relabelled = Board { cells: solution.cells.map(|v| v % 9 + 1) };
+        let err = check_solution(&easy, &relabelled).unwrap_err();
+        let detail = err.to_string();
+        assert!(matches!(err, SudokuError::VerificationFailed(_)) && detail.contains("given 5 at r1c1"), "{}", detail);
+
+        let mut broken = solution.clone();
+        broken.set(8, 8, 0);
+        assert!(matches!(check_solution(&easy, &broken), Err(SudokuError::VerificationFailed(_))));
+    }
+
+    #[test]
+    fn test_random_run() {
+        let report = run(100, 1);
+        assert!(report.passed(), "{:?}", report.mismatches);
+        assert_eq!(report.unique + report.multiple + report.unsolvable, 100);
+        // The input mix covers every verdict
+        assert!(report.unique > 0 && report.multiple > 0 && report.unsolvable > 0, "{:?}", report);
+        assert_eq!(random_input(1, 7), random_input(1, 7));
+    }
+}
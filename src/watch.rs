@@ -0,0 +1,250 @@
+//! Live dashboard for soak-testing the fetch, generate and solve path.
+//!
+//! `run` fetches puzzles from an `ApiClient` and solves them in a loop until
+//! stopped, handing a `Snapshot` of the rolling statistics to a callback at a
+//! fixed interval. Throughput and averages cover a sliding window, so the
+//! effect of a change shows up within seconds instead of being diluted by the
+//! whole run.
+
+use crate::{
+    api::{ApiClient, BoardProvider},
+    solver::Solver,
+    Result,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Longest a single solve may take before it counts as a failure
+const SOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How a watch session runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// Time between dashboard refreshes
+    pub interval: Duration,
+    /// Span of the rolling statistics
+    pub window: Duration,
+    /// Stop after this long; otherwise runs until interrupted
+    pub duration: Option<Duration>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            window: Duration::from_secs(10),
+            duration: None,
+        }
+    }
+}
+
+/// One solved puzzle
+#[derive(Debug, Clone)]
+struct Sample {
+    at: Instant,
+    nodes: u64,
+    solve_time: Duration,
+}
+
+/// Rolling and running statistics of a watch session
+#[derive(Debug)]
+pub struct Dashboard {
+    window: Duration,
+    started: Instant,
+    /// Samples inside the window, oldest first
+    recent: VecDeque<Sample>,
+    solved: usize,
+    failures: usize,
+    difficulties: BTreeMap<String, usize>,
+}
+
+/// The dashboard at one moment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub elapsed: Duration,
+    pub solved: usize,
+    /// Fetches or solves that failed
+    pub failures: usize,
+    /// Puzzles solved per second over the window
+    pub throughput: f64,
+    /// Mean search nodes per puzzle over the window
+    pub avg_nodes: f64,
+    /// Mean solve time per puzzle over the window
+    pub avg_solve_ms: f64,
+    /// Puzzles per difficulty label over the whole session
+    pub difficulties: BTreeMap<String, usize>,
+    pub cache_len: usize,
+    pub cache_capacity: usize,
+}
+
+impl Dashboard {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            started: Instant::now(),
+            recent: VecDeque::new(),
+            solved: 0,
+            failures: 0,
+            difficulties: BTreeMap::new(),
+        }
+    }
+
+    /// Records a solved puzzle
+    pub fn record(&mut self, difficulty: &str, nodes: u64, solve_time: Duration) {
+        self.solved += 1;
+        *self.difficulties.entry(difficulty.to_string()).or_insert(0) += 1;
+        self.recent.push_back(Sample { at: Instant::now(), nodes, solve_time });
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failures += 1;
+    }
+
+    /// Statistics as of now, with the cache holding `cache_len` of `cache_capacity` boards
+    pub fn snapshot(&mut self, cache_len: usize, cache_capacity: usize) -> Snapshot {
+        let now = Instant::now();
+        while self.recent.front().is_some_and(|sample| now.duration_since(sample.at) > self.window) {
+            self.recent.pop_front();
+        }
+
+        // Early on the window isn't full yet; rate over the time actually covered
+        let span = self.window.min(now.duration_since(self.started)).as_secs_f64().max(f64::EPSILON);
+        let count = self.recent.len().max(1) as f64;
+        Snapshot {
+            elapsed: now.duration_since(self.started),
+            solved: self.solved,
+            failures: self.failures,
+            throughput: self.recent.len() as f64 / span,
+            avg_nodes: self.recent.iter().map(|sample| sample.nodes as f64).sum::<f64>() / count,
+            avg_solve_ms: self.recent.iter().map(|sample| sample.solve_time.as_secs_f64() * 1000.0).sum::<f64>() / count,
+            difficulties: self.difficulties.clone(),
+            cache_len,
+            cache_capacity,
+        }
+    }
+}
+
+impl Snapshot {
+    /// The snapshot on one line, for logs and non-terminal output
+    pub fn line(&self) -> String {
+        let mix: Vec<String> = self.difficulties.iter().map(|(label, count)| format!("{}={}", label, count)).collect();
+        format!(
+            "t={:.0}s solved={} failures={} rate={:.1}/s nodes={:.0} solve={:.2}ms cache={}/{} mix=[{}]",
+            self.elapsed.as_secs_f64(),
+            self.solved,
+            self.failures,
+            self.throughput,
+            self.avg_nodes,
+            self.avg_solve_ms,
+            self.cache_len,
+            self.cache_capacity,
+            mix.join(" ")
+        )
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sudoku watch — {:.0}s elapsed", self.elapsed.as_secs_f64())?;
+        writeln!(f)?;
+        writeln!(f, "  Solved:       {} ({} failures)", self.solved, self.failures)?;
+        writeln!(f, "  Throughput:   {:.1} puzzles/s", self.throughput)?;
+        writeln!(f, "  Avg nodes:    {:.0}", self.avg_nodes)?;
+        writeln!(f, "  Avg solve:    {:.2} ms", self.avg_solve_ms)?;
+        let fill = self.cache_len as f64 / self.cache_capacity.max(1) as f64;
+        writeln!(f, "  Cache:        {}/{} {}", self.cache_len, self.cache_capacity, bar(fill))?;
+        writeln!(f)?;
+        writeln!(f, "  Difficulty mix:")?;
+        for (label, &count) in &self.difficulties {
+            let share = count as f64 / self.solved.max(1) as f64;
+            writeln!(f, "    {:<10} {:>7} {:5.1}% {}", label, count, share * 100.0, bar(share))?;
+        }
+        Ok(())
+    }
+}
+
+/// A 20-character bar filled to `fraction`
+fn bar(fraction: f64) -> String {
+    let filled = (fraction.clamp(0.0, 1.0) * 20.0).round() as usize;
+    format!("[{}{}]", "#".repeat(filled), " ".repeat(20 - filled))
+}
+
+/// Fetches and solves puzzles from `client` until `options.duration` is up or
+/// the process is interrupted, calling `on_refresh` every `options.interval`.
+/// Returns the final snapshot.
+pub async fn run<P: BoardProvider>(client: &ApiClient<P>, options: &WatchOptions, mut on_refresh: impl FnMut(&Snapshot)) -> Snapshot {
+    let mut dashboard = Dashboard::new(options.window);
+    let deadline = options.duration.map(|duration| Instant::now() + duration);
+    let mut next_refresh = Instant::now() + options.interval;
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+
+    loop {
+        tokio::select! {
+            _ = &mut interrupted => break,
+            result = fetch_and_solve(client) => match result {
+                Ok((difficulty, nodes, solve_time)) => dashboard.record(&difficulty, nodes, solve_time),
+                Err(_) => dashboard.record_failure(),
+            },
+        }
+
+        let now = Instant::now();
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            break;
+        }
+        if now >= next_refresh {
+            let (len, capacity) = client.cache_level();
+            on_refresh(&dashboard.snapshot(len, capacity));
+            next_refresh = now + options.interval;
+        }
+    }
+    let (len, capacity) = client.cache_level();
+    dashboard.snapshot(len, capacity)
+}
+
+/// Fetches one puzzle and solves it off the async executor
+async fn fetch_and_solve<P: BoardProvider>(client: &ApiClient<P>) -> Result<(String, u64, Duration)> {
+    let grid = client.fetch_new_board().await?;
+    let difficulty = grid.difficulty.clone();
+    let solution = tokio::task::spawn_blocking(move || Solver::new(grid).with_timeout(SOLVE_TIMEOUT).solve())
+        .await??;
+    Ok((difficulty, solution.stats.nodes, solution.stats.duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::fixture::FixtureProvider;
+
+    #[test]
+    fn test_dashboard_window() {
+        let mut dashboard = Dashboard::new(Duration::from_millis(50));
+        dashboard.record("Easy", 10, Duration::from_millis(2));
+        dashboard.record("Hard", 30, Duration::from_millis(4));
+        dashboard.record_failure();
+        let snapshot = dashboard.snapshot(3, 10);
+        assert_eq!((snapshot.solved, snapshot.failures), (2, 1));
+        assert_eq!(snapshot.avg_nodes, 20.0);
+        assert!((snapshot.avg_solve_ms - 3.0).abs() < 1e-9);
+        assert!(snapshot.to_string().contains("Cache:        3/10"));
+        assert!(snapshot.line().contains("mix=[Easy=1 Hard=1]"));
+
+        // Samples age out of the window, running totals don't
+        std::thread::sleep(Duration::from_millis(60));
+        let snapshot = dashboard.snapshot(3, 10);
+        assert_eq!((snapshot.solved, snapshot.throughput, snapshot.avg_nodes), (2, 0.0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_run_for_duration() {
+        let client = ApiClient::new(FixtureProvider::default()).with_warm_up(0).with_min_request_interval(Duration::ZERO);
+        let options = WatchOptions { interval: Duration::from_millis(20), duration: Some(Duration::from_millis(200)), ..Default::default() };
+        let mut refreshes = 0;
+        let snapshot = run(&client, &options, |_| refreshes += 1).await;
+        assert!(snapshot.solved > 0);
+        assert_eq!(snapshot.failures, 0);
+        assert!(refreshes > 0);
+    }
+}